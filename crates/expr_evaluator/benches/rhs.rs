@@ -0,0 +1,102 @@
+//! Right hand side of a model of viral infection, evaluated the way
+//! `OdeSystem` used to (cloning the context into every equation and looking
+//! variables up by name) and from compiled expressions.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use expr_evaluator::{
+    compiled::Slots,
+    expr::{ExprContext, Expression},
+};
+
+const POPULATIONS: [(&str, &str); 4] = [
+    ("E", "(- beta*T*V) + rho*E*(1 - E/Emax)"),
+    ("I", "k*E - delta*I/(Kd + I) - mu*I"),
+    ("T", "- beta*T*V"),
+    ("V", "p*I - c*V - beta*T*V + pow(V, 2)/(K + exp(-V))"),
+];
+
+const PARAMETERS: [(&str, f64); 11] = [
+    ("beta", 1.4e-4),
+    ("rho", 0.1),
+    ("Emax", 1e6),
+    ("k", 4.0),
+    ("delta", 0.6),
+    ("Kd", 1e3),
+    ("mu", 0.01),
+    ("p", 2.1e-2),
+    ("c", 3.0),
+    ("K", 1e8),
+    ("unused", 0.0),
+];
+
+const STATE: [f64; 4] = [10.0, 0.0, 4e8, 25.0];
+
+fn context() -> ExprContext {
+    let mut context = ExprContext::new();
+    for (name, value) in PARAMETERS {
+        context.set_var(name.to_owned(), value);
+    }
+    for ((name, _), value) in POPULATIONS.iter().zip(STATE) {
+        context.set_var((*name).to_owned(), value);
+    }
+    context
+}
+
+fn equations() -> Vec<Expression> {
+    POPULATIONS
+        .iter()
+        .map(|(_, text)| {
+            let mut equation = Expression::new();
+            equation.parse_expr((*text).to_owned()).unwrap();
+            equation
+        })
+        .collect()
+}
+
+fn rhs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rhs");
+    let mut dydt = [0.0; POPULATIONS.len()];
+
+    let mut context = context();
+    let mut trees = equations();
+    group.bench_function("tree", |b| {
+        b.iter(|| {
+            for ((name, _), value) in POPULATIONS.iter().zip(black_box(STATE)) {
+                context.set_var((*name).to_owned(), value);
+            }
+            for (dy, equation) in dydt.iter_mut().zip(&mut trees) {
+                equation.set_context(context.clone());
+                *dy = equation.eval().unwrap();
+            }
+            black_box(&dydt);
+        })
+    });
+
+    let mut slots = Slots::new();
+    for (name, _) in POPULATIONS {
+        slots.insert(name);
+    }
+    for name in context.vars.keys() {
+        slots.insert(name);
+    }
+    let compiled: Vec<_> = equations()
+        .iter()
+        .map(|equation| equation.compile(&slots).unwrap())
+        .collect();
+    let mut buffer = slots.buffer(&context);
+    let mut stack = Vec::new();
+    group.bench_function("compiled", |b| {
+        b.iter(|| {
+            buffer[..POPULATIONS.len()].copy_from_slice(&black_box(STATE));
+            for (dy, equation) in dydt.iter_mut().zip(&compiled) {
+                *dy = equation.eval(&buffer, &mut stack).unwrap();
+            }
+            black_box(&dydt);
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, rhs);
+criterion_main!(benches);
@@ -0,0 +1,213 @@
+//! Expressions compiled to the bytecode of a stack machine, with every variable
+//! resolved to a slot of a flat buffer and every function looked up once.
+//! Evaluating them looks nothing up by name and, once the stack has grown to
+//! the size it needs, allocates nothing, not even in the calls to functions
+//! defined with [`ExprContext::define_function`], whose stacks are reused.
+
+use std::collections::HashMap;
+
+use crate::expr::{ExprContext, ExprError, Expression, Func, Node, NodeType, Operator, Result};
+
+/// Variables of compiled expressions, each read from its index of the buffer
+/// they're evaluated with.
+#[derive(Debug, Clone, Default)]
+pub struct Slots {
+    names: Vec<String>,
+    indices: HashMap<String, usize>,
+}
+
+impl Slots {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index of `name`, which is given the next one if it had none.
+    pub fn insert(&mut self, name: &str) -> usize {
+        if let Some(&index) = self.indices.get(name) {
+            return index;
+        }
+
+        let index = self.names.len();
+        self.names.push(name.to_owned());
+        self.indices.insert(name.to_owned(), index);
+        index
+    }
+
+    pub fn get(&self, name: &str) -> Option<usize> {
+        self.indices.get(name).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Names of the variables, in the order of their indices.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// A buffer holding the value of every variable in `context`, or zero for
+    /// those it doesn't define.
+    pub fn buffer(&self, context: &ExprContext) -> Vec<f64> {
+        self.names
+            .iter()
+            .map(|name| context.vars.get(name).copied().unwrap_or_default())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Instruction {
+    Constant(f64),
+    Load(usize),
+    Negate,
+    /// Replaces the two values on top of the stack by the operator of them
+    Binary(Operator),
+    /// Pops the value on top of the stack and, if it's 0, goes to the
+    /// instruction at the index
+    JumpIfZero(usize),
+    Jump(usize),
+    /// Calls the function at the index of [`Compiled::functions`] with the
+    /// `arity` values on top of the stack
+    Call {
+        function: usize,
+        arity: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Compiled {
+    instructions: Vec<Instruction>,
+    functions: Vec<Func>,
+}
+
+impl Compiled {
+    /// Compiles `node`, reading its variables from `slots` and its functions
+    /// from `context`. Fails if any of them is undefined.
+    pub fn new(node: &Node, slots: &Slots, context: &ExprContext) -> Result<Self> {
+        let mut compiled = Self {
+            instructions: Vec::new(),
+            functions: Vec::new(),
+        };
+        compiled.emit(node, slots, context)?;
+        Ok(compiled)
+    }
+
+    fn emit(&mut self, node: &Node, slots: &Slots, context: &ExprContext) -> Result<()> {
+        match node {
+            Node::Leaf(leaf) => match leaf.node_type {
+                NodeType::Constant => self.instructions.push(Instruction::Constant(leaf.value)),
+                NodeType::Var => {
+                    let slot = slots
+                        .get(&leaf.name)
+                        .ok_or_else(|| ExprError::UndefinedVarError(leaf.name.clone()))?;
+                    self.instructions.push(Instruction::Load(slot));
+                }
+                NodeType::Function => {
+                    self.functions
+                        .push(context.get_function(leaf.name.clone())?);
+                    let function = self.functions.len() - 1;
+                    for arg in &leaf.args {
+                        self.emit(arg, slots, context)?;
+                    }
+                    self.instructions.push(Instruction::Call {
+                        function,
+                        arity: leaf.args.len(),
+                    });
+                }
+            },
+            Node::UnaryExpr { op, expr } => {
+                self.emit(expr, slots, context)?;
+                if let Operator::Minus = op {
+                    self.instructions.push(Instruction::Negate);
+                }
+            }
+            Node::BinaryExpr {
+                op,
+                left_expr,
+                right_expr,
+            } => {
+                self.emit(left_expr, slots, context)?;
+                self.emit(right_expr, slots, context)?;
+                self.instructions.push(Instruction::Binary(*op));
+            }
+            Node::Conditional {
+                condition,
+                if_true,
+                if_false,
+            } => {
+                self.emit(condition, slots, context)?;
+                let jump_to_false = self.instructions.len();
+                self.instructions.push(Instruction::JumpIfZero(0));
+
+                self.emit(if_true, slots, context)?;
+                let jump_to_end = self.instructions.len();
+                self.instructions.push(Instruction::Jump(0));
+
+                self.instructions[jump_to_false] = Instruction::JumpIfZero(self.instructions.len());
+                self.emit(if_false, slots, context)?;
+                self.instructions[jump_to_end] = Instruction::Jump(self.instructions.len());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Evaluates the expression with its variables read from `buffer`, which
+    /// must be as long as the [`Slots`] it was compiled with. `stack` is
+    /// scratch space, which can be reused between evaluations to avoid
+    /// allocating.
+    pub fn eval(&self, buffer: &[f64], stack: &mut Vec<f64>) -> Result<f64> {
+        stack.clear();
+
+        let mut next = 0;
+        while let Some(&instruction) = self.instructions.get(next) {
+            next += 1;
+            match instruction {
+                Instruction::Constant(value) => stack.push(value),
+                Instruction::Load(slot) => stack.push(buffer[slot]),
+                Instruction::Negate => {
+                    let value = top(stack);
+                    *value = -*value;
+                }
+                Instruction::Binary(op) => {
+                    let right = stack.pop().expect("compiled expressions are well formed");
+                    let left = top(stack);
+                    *left = op.apply(*left, right)?;
+                }
+                Instruction::JumpIfZero(target) => {
+                    if stack.pop().expect("compiled expressions are well formed") == 0.0 {
+                        next = target;
+                    }
+                }
+                Instruction::Jump(target) => next = target,
+                Instruction::Call { function, arity } => {
+                    let start = stack.len() - arity;
+                    let value = self.functions[function].call(&stack[start..])?;
+                    stack.truncate(start);
+                    stack.push(value);
+                }
+            }
+        }
+
+        Ok(*top(stack))
+    }
+}
+
+fn top(stack: &mut [f64]) -> &mut f64 {
+    stack
+        .last_mut()
+        .expect("compiled expressions are well formed")
+}
+
+impl Expression {
+    /// Compiles the parsed expression, with the functions of its context.
+    pub fn compile(&self, slots: &Slots) -> Result<Compiled> {
+        let ast = self.ast.as_deref().ok_or(ExprError::UndefinedAST)?;
+        Compiled::new(ast, slots, &self.context)
+    }
+}
@@ -0,0 +1,432 @@
+//! Symbolic differentiation of expressions, for the Jacobians used by
+//! implicit solvers.
+//!
+//! Expressions are simplified as they're built, folding constants, cancelling
+//! terms out of sums and dropping terms multiplied by zero, so the derivatives
+//! of the sparse equations of most models are mostly zero and stay small
+//! otherwise.
+
+use std::f64::consts::{LN_10, LN_2};
+
+use crate::expr::{ExprError, LeafNode, Node, NodeType, Operator, Result};
+
+impl Node {
+    /// Derivative of the expression with respect to the variable `var`.
+    /// Functions are taken to be the builtins of
+    /// [`ExprContext::new`](crate::expr::ExprContext::new), so the derivative
+    /// fails on any other function whose arguments depend on `var`, as
+    /// nothing is known about it.
+    pub fn derivative(&self, var: &str) -> Result<Box<Node>> {
+        match self {
+            Node::Leaf(leaf) => match leaf.node_type {
+                NodeType::Constant => Ok(Node::constant(0.0)),
+                NodeType::Var => Ok(Node::constant(if leaf.name == var { 1.0 } else { 0.0 })),
+                NodeType::Function => call_derivative(&leaf.name, &leaf.args, var),
+            },
+            Node::UnaryExpr { op, expr } => {
+                let derivative = expr.derivative(var)?;
+                Ok(match op {
+                    Operator::Minus => negate(*derivative),
+                    _ => derivative,
+                })
+            }
+            Node::BinaryExpr {
+                op,
+                left_expr,
+                right_expr,
+            } => binary_derivative(*op, left_expr, right_expr, var),
+            Node::Conditional {
+                condition,
+                if_true,
+                if_false,
+            } => Ok(conditional(
+                condition.clone(),
+                if_true.derivative(var)?,
+                if_false.derivative(var)?,
+            )),
+        }
+    }
+
+    /// The expression with its constants folded and the identities of sums,
+    /// products and powers removed.
+    pub fn simplify(&self) -> Box<Node> {
+        match self {
+            Node::Leaf(leaf) => match leaf.node_type {
+                NodeType::Function => Node::call(
+                    &leaf.name,
+                    leaf.args.iter().map(|arg| arg.simplify()).collect(),
+                ),
+                _ => Box::new(self.clone()),
+            },
+            Node::UnaryExpr { op, expr } => match op {
+                Operator::Minus => negate(*expr.simplify()),
+                _ => expr.simplify(),
+            },
+            Node::BinaryExpr {
+                op,
+                left_expr,
+                right_expr,
+            } => binary(*op, left_expr.simplify(), right_expr.simplify()),
+            Node::Conditional {
+                condition,
+                if_true,
+                if_false,
+            } => conditional(
+                condition.simplify(),
+                if_true.simplify(),
+                if_false.simplify(),
+            ),
+        }
+    }
+
+    /// Whether the expression is the constant `value`.
+    pub fn is_constant(&self, value: f64) -> bool {
+        as_constant(self) == Some(value)
+    }
+}
+
+fn binary_derivative(op: Operator, left: &Node, right: &Node, var: &str) -> Result<Box<Node>> {
+    let (left_derivative, right_derivative) = (left.derivative(var)?, right.derivative(var)?);
+    let (left, right) = (Box::new(left.clone()), Box::new(right.clone()));
+
+    Ok(match op {
+        Operator::Plus => add(left_derivative, right_derivative),
+        Operator::Minus => subtract(left_derivative, right_derivative),
+        Operator::Mult => add(
+            multiply(left_derivative, right),
+            multiply(left, right_derivative),
+        ),
+        Operator::Div if right_derivative.is_constant(0.0) => divide(left_derivative, right),
+        Operator::Div => divide(
+            subtract(
+                multiply(left_derivative, right.clone()),
+                multiply(left, right_derivative),
+            ),
+            power(right, Node::constant(2.0)),
+        ),
+        Operator::Pow => power_derivative(left, right, left_derivative, right_derivative),
+        // Comparisons are constant wherever they're differentiable
+        _ => Node::constant(0.0),
+    })
+}
+
+/// Derivative of `base ^ exponent`, given the derivatives of both.
+fn power_derivative(
+    base: Box<Node>,
+    exponent: Box<Node>,
+    base_derivative: Box<Node>,
+    exponent_derivative: Box<Node>,
+) -> Box<Node> {
+    if exponent_derivative.is_constant(0.0) {
+        let lowered = subtract(exponent.clone(), Node::constant(1.0));
+        return multiply(multiply(exponent, power(base, lowered)), base_derivative);
+    }
+
+    // a^b * (b' * ln(a) + b * a' / a)
+    let logarithm = Node::call("log", vec![base.clone()]);
+    multiply(
+        power(base.clone(), exponent.clone()),
+        add(
+            multiply(exponent_derivative, logarithm),
+            divide(multiply(exponent, base_derivative), base),
+        ),
+    )
+}
+
+/// Derivative of a call to the builtin `function` by the chain rule.
+fn call_derivative(function: &str, args: &[Box<Node>], var: &str) -> Result<Box<Node>> {
+    let derivatives = args
+        .iter()
+        .map(|arg| arg.derivative(var).map(|derivative| *derivative))
+        .collect::<Result<Vec<_>>>()?;
+    if derivatives
+        .iter()
+        .all(|derivative| derivative.is_constant(0.0))
+    {
+        return Ok(Node::constant(0.0));
+    }
+
+    let arg = |idx: usize| args[idx].clone();
+    let derivative = |idx: usize| Box::new(derivatives[idx].clone());
+    let square = |node: Box<Node>| power(node, Node::constant(2.0));
+
+    match function {
+        "pow" | "power" => {
+            check_arity(function, args, 2)?;
+            Ok(power_derivative(
+                arg(0),
+                arg(1),
+                derivative(0),
+                derivative(1),
+            ))
+        }
+        "arctan2" | "atan2" => {
+            check_arity(function, args, 2)?;
+            // (x * y' - y * x') / (x^2 + y^2), for arctan2(y, x)
+            let (y, x) = (arg(0), arg(1));
+            Ok(divide(
+                subtract(
+                    multiply(x.clone(), derivative(0)),
+                    multiply(y.clone(), derivative(1)),
+                ),
+                add(square(x), square(y)),
+            ))
+        }
+        "hypot" => {
+            check_arity(function, args, 2)?;
+            Ok(divide(
+                add(
+                    multiply(arg(0), derivative(0)),
+                    multiply(arg(1), derivative(1)),
+                ),
+                Node::call("hypot", vec![arg(0), arg(1)]),
+            ))
+        }
+        "min" | "minimum" => Ok(extremum_derivative(
+            function,
+            args,
+            derivatives,
+            Operator::Le,
+        )),
+        "max" | "maximum" => Ok(extremum_derivative(
+            function,
+            args,
+            derivatives,
+            Operator::Ge,
+        )),
+        _ => {
+            let outer = args
+                .first()
+                .and_then(|arg| outer_derivative(function, arg.clone()))
+                .ok_or_else(|| ExprError::NotDifferentiable(function.to_owned()))?;
+            check_arity(function, args, 1)?;
+            Ok(multiply(outer, derivative(0)))
+        }
+    }
+}
+
+/// Derivative of the builtin `function` of one argument at `arg`, or `None`
+/// if it isn't one.
+fn outer_derivative(function: &str, arg: Box<Node>) -> Option<Box<Node>> {
+    let square = |node: Box<Node>| power(node, Node::constant(2.0));
+    let reciprocal = |node: Box<Node>| divide(Node::constant(1.0), node);
+
+    Some(match function {
+        "sqrt" => divide(Node::constant(0.5), Node::call("sqrt", vec![arg])),
+        "cbrt" => reciprocal(multiply(
+            Node::constant(3.0),
+            square(Node::call("cbrt", vec![arg])),
+        )),
+        "exp" | "expm1" => Node::call("exp", vec![arg]),
+        "exp2" => multiply(Node::constant(LN_2), Node::call("exp2", vec![arg])),
+        "log" | "ln" => reciprocal(arg),
+        "log2" => reciprocal(multiply(Node::constant(LN_2), arg)),
+        "log10" => reciprocal(multiply(Node::constant(LN_10), arg)),
+        "log1p" => reciprocal(add(Node::constant(1.0), arg)),
+        "sin" => Node::call("cos", vec![arg]),
+        "cos" => negate(*Node::call("sin", vec![arg])),
+        "tan" => reciprocal(square(Node::call("cos", vec![arg]))),
+        "arcsin" | "asin" => reciprocal(Node::call(
+            "sqrt",
+            vec![subtract(Node::constant(1.0), square(arg))],
+        )),
+        "arccos" | "acos" => negate(*reciprocal(Node::call(
+            "sqrt",
+            vec![subtract(Node::constant(1.0), square(arg))],
+        ))),
+        "arctan" | "atan" => reciprocal(add(Node::constant(1.0), square(arg))),
+        "sinh" => Node::call("cosh", vec![arg]),
+        "cosh" => Node::call("sinh", vec![arg]),
+        "tanh" => reciprocal(square(Node::call("cosh", vec![arg]))),
+        "arcsinh" | "asinh" => reciprocal(Node::call(
+            "sqrt",
+            vec![add(square(arg), Node::constant(1.0))],
+        )),
+        "arccosh" | "acosh" => reciprocal(Node::call(
+            "sqrt",
+            vec![subtract(square(arg), Node::constant(1.0))],
+        )),
+        "arctanh" | "atanh" => reciprocal(subtract(Node::constant(1.0), square(arg))),
+        "abs" | "fabs" => Node::call("sign", vec![arg]),
+        // Piecewise constant
+        "floor" | "ceil" | "trunc" | "round" | "sign" => Node::constant(0.0),
+        _ => return None,
+    })
+}
+
+/// Derivative of the smallest or largest of `args`, which is that of the
+/// argument taken, compared with `op` to the others. Ties are broken towards
+/// the first one.
+fn extremum_derivative(
+    function: &str,
+    args: &[Box<Node>],
+    mut derivatives: Vec<Node>,
+    op: Operator,
+) -> Box<Node> {
+    let (Some(last), Some(last_derivative)) = (args.last(), derivatives.pop()) else {
+        return Node::constant(0.0);
+    };
+    if args.len() == 1 {
+        return Box::new(last_derivative);
+    }
+
+    let rest = &args[..args.len() - 1];
+    let extremum_of_rest = match rest {
+        [only] => only.clone(),
+        _ => Node::call(function, rest.to_vec()),
+    };
+    conditional(
+        binary(op, extremum_of_rest, last.clone()),
+        extremum_derivative(function, rest, derivatives, op),
+        Box::new(last_derivative),
+    )
+}
+
+fn check_arity(function: &str, args: &[Box<Node>], expected: usize) -> Result<()> {
+    if args.len() == expected {
+        Ok(())
+    } else {
+        Err(ExprError::ArgumentCount {
+            function: function.to_owned(),
+            expected,
+            found: args.len(),
+        })
+    }
+}
+
+fn as_constant(node: &Node) -> Option<f64> {
+    match node {
+        Node::Leaf(LeafNode {
+            node_type: NodeType::Constant,
+            value,
+            ..
+        }) => Some(*value),
+        _ => None,
+    }
+}
+
+fn negate(node: Node) -> Box<Node> {
+    match node {
+        Node::UnaryExpr {
+            op: Operator::Minus,
+            expr,
+        } => expr,
+        node => match as_constant(&node) {
+            Some(value) => Node::constant(-value),
+            None => Node::negated(Box::new(node)),
+        },
+    }
+}
+
+fn conditional(condition: Box<Node>, if_true: Box<Node>, if_false: Box<Node>) -> Box<Node> {
+    match as_constant(&condition) {
+        Some(value) if value != 0.0 => if_true,
+        Some(_) => if_false,
+        None if as_constant(&if_true).is_some_and(|value| if_false.is_constant(value)) => if_true,
+        None => Box::new(Node::Conditional {
+            condition,
+            if_true,
+            if_false,
+        }),
+    }
+}
+
+fn add(left: Box<Node>, right: Box<Node>) -> Box<Node> {
+    binary(Operator::Plus, left, right)
+}
+
+fn subtract(left: Box<Node>, right: Box<Node>) -> Box<Node> {
+    binary(Operator::Minus, left, right)
+}
+
+fn multiply(left: Box<Node>, right: Box<Node>) -> Box<Node> {
+    binary(Operator::Mult, left, right)
+}
+
+fn divide(left: Box<Node>, right: Box<Node>) -> Box<Node> {
+    binary(Operator::Div, left, right)
+}
+
+fn power(base: Box<Node>, exponent: Box<Node>) -> Box<Node> {
+    binary(Operator::Pow, base, exponent)
+}
+
+/// `left op right`, simplified.
+fn binary(op: Operator, left: Box<Node>, right: Box<Node>) -> Box<Node> {
+    let (left_value, right_value) = (as_constant(&left), as_constant(&right));
+    if let (Some(left_value), Some(right_value)) = (left_value, right_value) {
+        // Errors such as divisions by zero are left for the evaluation
+        if let Ok(value) = op.apply(left_value, right_value) {
+            return Node::constant(value);
+        }
+    }
+
+    let cancelled = match op {
+        Operator::Plus => without_term(&left, &right, false),
+        Operator::Minus => without_term(&left, &right, true),
+        _ => None,
+    };
+    if let Some(rest) = cancelled {
+        return rest;
+    }
+
+    match (op, left_value, right_value) {
+        (Operator::Plus, Some(0.0), _) => right,
+        (Operator::Plus | Operator::Minus, _, Some(0.0)) => left,
+        (Operator::Minus, Some(0.0), _) => negate(*right),
+        (Operator::Plus, _, _) if is_negation(&right) => subtract(left, negate(*right)),
+        (Operator::Minus, _, _) if is_negation(&right) => add(left, negate(*right)),
+        (Operator::Mult, Some(0.0), _) | (Operator::Mult, _, Some(0.0)) => Node::constant(0.0),
+        (Operator::Div, Some(0.0), _) => Node::constant(0.0),
+        (Operator::Mult, Some(1.0), _) => right,
+        (Operator::Mult | Operator::Div, _, Some(1.0)) => left,
+        (Operator::Mult, Some(-1.0), _) => negate(*right),
+        (Operator::Mult | Operator::Div, _, Some(-1.0)) => negate(*left),
+        (Operator::Pow, _, Some(0.0)) => Node::constant(1.0),
+        (Operator::Pow, _, Some(1.0)) => left,
+        // Constants go first, and signs out of products
+        (Operator::Mult, None, Some(_)) => multiply(right, left),
+        (Operator::Mult | Operator::Div, _, _) if is_negation(&left) => {
+            negate(*binary(op, negate(*left), right))
+        }
+        (Operator::Mult | Operator::Div, _, _) if is_negation(&right) => {
+            negate(*binary(op, left, negate(*right)))
+        }
+        _ => Node::binary(op, left, right),
+    }
+}
+
+/// `sum` without a term equal to `term`, added to it if `added` or subtracted
+/// otherwise, if it has one. Sums and differences are looked into, as they
+/// are built from the left, so `a + b - a` cancels as much as `a - a` does.
+fn without_term(sum: &Node, term: &Node, added: bool) -> Option<Box<Node>> {
+    if added && sum == term {
+        return Some(Node::constant(0.0));
+    }
+
+    match sum {
+        Node::BinaryExpr {
+            op: op @ (Operator::Plus | Operator::Minus),
+            left_expr,
+            right_expr,
+        } => {
+            if let Some(left) = without_term(left_expr, term, added) {
+                return Some(binary(*op, left, right_expr.clone()));
+            }
+            let added = added == (*op == Operator::Plus);
+            without_term(right_expr, term, added).map(|right| binary(*op, left_expr.clone(), right))
+        }
+        _ => None,
+    }
+}
+
+/// Whether the expression is a negation or a negative constant.
+fn is_negation(node: &Node) -> bool {
+    match node {
+        Node::UnaryExpr {
+            op: Operator::Minus,
+            ..
+        } => true,
+        node => as_constant(node).is_some_and(|value| value < 0.0),
+    }
+}
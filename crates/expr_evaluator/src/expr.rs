@@ -1,52 +1,120 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::sync::Arc;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Operator {
     Plus,
     Minus,
     Mult,
     Div,
+    Pow,
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+}
+
+impl Operator {
+    /// Applies the operator as a binary one. Comparisons are 1 when they
+    /// hold and 0 otherwise, as in Python.
+    pub fn apply(self, left: f64, right: f64) -> Result<f64> {
+        let holds = |condition: bool| Ok(if condition { 1.0 } else { 0.0 });
+        match self {
+            Operator::Plus => Ok(left + right),
+            Operator::Minus => Ok(left - right),
+            Operator::Mult => Ok(left * right),
+            Operator::Div => {
+                if right == 0.0 {
+                    Err(ExprError::DivisionByZero)
+                } else {
+                    Ok(left / right)
+                }
+            }
+            Operator::Pow => Ok(left.powf(right)),
+            Operator::Eq => holds(left == right),
+            Operator::Ne => holds(left != right),
+            Operator::Lt => holds(left < right),
+            Operator::Gt => holds(left > right),
+            Operator::Le => holds(left <= right),
+            Operator::Ge => holds(left >= right),
+        }
+    }
 }
 
 use std::fmt;
 
+use crate::compiled::{Compiled, Slots};
 use crate::lexer;
 use crate::parser::Parser;
+pub use crate::parser::{ParserError, Span};
 
-pub(crate) type Result<T> = std::result::Result<T, ExprError>;
+pub type Result<T> = std::result::Result<T, ExprError>;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ExprError {
     UndefinedAST,
-    EvaluationError,
+    Parse(ParserError),
+    DivisionByZero,
     UndefinedVarError(String),
     UndefinedFunctionError(String),
+    ArgumentCount {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    /// A function whose derivative isn't known
+    NotDifferentiable(String),
+    /// A function defined with the same parameter twice
+    DuplicateParameter(String),
 }
 
 impl fmt::Display for ExprError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             ExprError::UndefinedAST => write!(f, "The abstract syntax tree is not defined!"),
-            ExprError::EvaluationError => write!(
-                f,
-                "Evaluation error ocurred! Error evaluating an expression."
-            ),
+            ExprError::Parse(err) => write!(f, "{err}"),
+            ExprError::DivisionByZero => write!(f, "Division by zero"),
             ExprError::UndefinedVarError(var) => write!(f, "The var {} was not defined", var),
             ExprError::UndefinedFunctionError(func) => {
                 write!(f, "The function {} was not defined", func)
             }
+            ExprError::ArgumentCount {
+                function,
+                expected,
+                found,
+            } => write!(
+                f,
+                "The function {function} takes {expected} argument(s), but was given {found}"
+            ),
+            ExprError::NotDifferentiable(func) => {
+                write!(f, "The derivative of the function {func} is not known")
+            }
+            ExprError::DuplicateParameter(func) => {
+                write!(f, "The function {func} has the same parameter twice")
+            }
         }
     }
 }
 
-#[derive(Debug, Clone)]
+impl std::error::Error for ExprError {}
+
+impl From<ParserError> for ExprError {
+    fn from(err: ParserError) -> Self {
+        ExprError::Parse(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum NodeType {
     Constant,
     Var,
     Function,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct LeafNode {
     pub node_type: NodeType,
     pub name: String,
@@ -65,7 +133,7 @@ impl LeafNode {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Node {
     Leaf(LeafNode), //constant or population
     UnaryExpr {
@@ -77,6 +145,259 @@ pub enum Node {
         left_expr: Box<Node>,
         right_expr: Box<Node>,
     },
+    /// `if_true` when `condition` isn't 0, `if_false` otherwise. Only the
+    /// branch taken is evaluated.
+    Conditional {
+        condition: Box<Node>,
+        if_true: Box<Node>,
+        if_false: Box<Node>,
+    },
+}
+
+/// How tightly an expression binds, from the loosest, used to write it with
+/// no more parentheses than needed. Python orders its operators the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Precedence {
+    Conditional,
+    Comparison,
+    Sum,
+    Product,
+    Negation,
+    Power,
+    Atom,
+}
+
+impl Operator {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Operator::Plus => "+",
+            Operator::Minus => "-",
+            Operator::Mult => "*",
+            Operator::Div => "/",
+            Operator::Pow => "^",
+            Operator::Eq => "==",
+            Operator::Ne => "!=",
+            Operator::Lt => "<",
+            Operator::Gt => ">",
+            Operator::Le => "<=",
+            Operator::Ge => ">=",
+        }
+    }
+
+    pub fn precedence(self) -> Precedence {
+        match self {
+            Operator::Plus | Operator::Minus => Precedence::Sum,
+            Operator::Mult | Operator::Div => Precedence::Product,
+            Operator::Pow => Precedence::Power,
+            _ => Precedence::Comparison,
+        }
+    }
+}
+
+impl Node {
+    pub fn constant(value: f64) -> Box<Node> {
+        Box::new(Node::Leaf(LeafNode {
+            node_type: NodeType::Constant,
+            name: value.to_string(),
+            value,
+            args: vec![],
+        }))
+    }
+
+    pub fn var(name: &str) -> Box<Node> {
+        Box::new(Node::Leaf(LeafNode::new(NodeType::Var, name.to_owned())))
+    }
+
+    pub fn call(function: &str, args: Vec<Box<Node>>) -> Box<Node> {
+        let mut leaf = LeafNode::new(NodeType::Function, function.to_owned());
+        leaf.args = args;
+        Box::new(Node::Leaf(leaf))
+    }
+
+    pub fn negated(expr: Box<Node>) -> Box<Node> {
+        Box::new(Node::UnaryExpr {
+            op: Operator::Minus,
+            expr,
+        })
+    }
+
+    pub fn binary(op: Operator, left_expr: Box<Node>, right_expr: Box<Node>) -> Box<Node> {
+        Box::new(Node::BinaryExpr {
+            op,
+            left_expr,
+            right_expr,
+        })
+    }
+}
+
+impl Node {
+    /// The expression with every call to `name` replaced by `body`, whose
+    /// `parameters` are replaced by the arguments of the call. This is how
+    /// functions defined by models are differentiated, as the derivative only
+    /// knows about the builtins.
+    pub fn inline(&self, name: &str, parameters: &[String], body: &Node) -> Result<Box<Node>> {
+        match self {
+            Node::Leaf(leaf) if matches!(leaf.node_type, NodeType::Function) => {
+                let args = leaf
+                    .args
+                    .iter()
+                    .map(|arg| arg.inline(name, parameters, body))
+                    .collect::<Result<Vec<_>>>()?;
+                if leaf.name != name {
+                    return Ok(Node::call(&leaf.name, args));
+                }
+                if args.len() != parameters.len() {
+                    return Err(ExprError::ArgumentCount {
+                        function: name.to_owned(),
+                        expected: parameters.len(),
+                        found: args.len(),
+                    });
+                }
+
+                let values = parameters.iter().map(String::as_str).zip(args).collect();
+                Ok(body.substitute(&values))
+            }
+            _ => self.map_operands(|operand| operand.inline(name, parameters, body)),
+        }
+    }
+
+    /// The expression with the variables in `values` replaced by their
+    /// expressions.
+    pub fn substitute(&self, values: &HashMap<&str, Box<Node>>) -> Box<Node> {
+        match self {
+            Node::Leaf(leaf) if matches!(leaf.node_type, NodeType::Var) => values
+                .get(leaf.name.as_str())
+                .cloned()
+                .unwrap_or_else(|| Box::new(self.clone())),
+            Node::Leaf(leaf) if matches!(leaf.node_type, NodeType::Function) => Node::call(
+                &leaf.name,
+                leaf.args.iter().map(|arg| arg.substitute(values)).collect(),
+            ),
+            _ => self
+                .map_operands(|operand| Ok(operand.substitute(values)))
+                .expect("substituting never fails"),
+        }
+    }
+
+    /// The node with `f` applied to its operands. Leaves are cloned as they
+    /// are, arguments of calls included.
+    pub fn map_operands(&self, mut f: impl FnMut(&Node) -> Result<Box<Node>>) -> Result<Box<Node>> {
+        Ok(match self {
+            Node::Leaf(_) => Box::new(self.clone()),
+            Node::UnaryExpr { op, expr } => Box::new(Node::UnaryExpr {
+                op: *op,
+                expr: f(expr)?,
+            }),
+            Node::BinaryExpr {
+                op,
+                left_expr,
+                right_expr,
+            } => Node::binary(*op, f(left_expr)?, f(right_expr)?),
+            Node::Conditional {
+                condition,
+                if_true,
+                if_false,
+            } => Box::new(Node::Conditional {
+                condition: f(condition)?,
+                if_true: f(if_true)?,
+                if_false: f(if_false)?,
+            }),
+        })
+    }
+}
+
+impl Node {
+    pub fn precedence(&self) -> Precedence {
+        match self {
+            Node::Leaf(leaf) => match leaf.node_type {
+                NodeType::Constant if leaf.value < 0.0 => Precedence::Negation,
+                _ => Precedence::Atom,
+            },
+            Node::UnaryExpr { .. } => Precedence::Negation,
+            Node::BinaryExpr { op, .. } => op.precedence(),
+            Node::Conditional { .. } => Precedence::Conditional,
+        }
+    }
+
+    /// Whether the operands of a binary expression need parentheses to be
+    /// read back as they are. Sums and products group to the left, powers to
+    /// the right, and comparisons don't chain.
+    pub fn parenthesize_operands(op: Operator, left: &Node, right: &Node) -> (bool, bool) {
+        let precedence = op.precedence();
+        match precedence {
+            Precedence::Power => (
+                left.precedence() <= precedence,
+                right.precedence() < Precedence::Negation,
+            ),
+            Precedence::Comparison => (
+                left.precedence() <= precedence,
+                right.precedence() <= precedence,
+            ),
+            _ => (
+                left.precedence() < precedence,
+                right.precedence() <= precedence,
+            ),
+        }
+    }
+}
+
+/// Writes `node`, in parentheses if `parenthesized`.
+fn write_operand(f: &mut fmt::Formatter, node: &Node, parenthesized: bool) -> fmt::Result {
+    if parenthesized {
+        write!(f, "({node})")
+    } else {
+        write!(f, "{node}")
+    }
+}
+
+/// Written the way the parser reads it.
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Node::Leaf(leaf) => match leaf.node_type {
+                NodeType::Constant => write!(f, "{}", leaf.value),
+                NodeType::Var => write!(f, "{}", leaf.name),
+                NodeType::Function => {
+                    write!(f, "{}(", leaf.name)?;
+                    for (idx, arg) in leaf.args.iter().enumerate() {
+                        if idx > 0 {
+                            write!(f, ", ")?;
+                        }
+                        write!(f, "{arg}")?;
+                    }
+                    write!(f, ")")
+                }
+            },
+            Node::UnaryExpr { op, expr } => {
+                if let Operator::Minus = op {
+                    write!(f, "-")?;
+                }
+                write_operand(f, expr, expr.precedence() < Precedence::Negation)
+            }
+            Node::BinaryExpr {
+                op,
+                left_expr,
+                right_expr,
+            } => {
+                let (left, right) = Node::parenthesize_operands(*op, left_expr, right_expr);
+                write_operand(f, left_expr, left)?;
+                write!(f, " {} ", op.symbol())?;
+                write_operand(f, right_expr, right)
+            }
+            Node::Conditional {
+                condition,
+                if_true,
+                if_false,
+            } => {
+                write_operand(
+                    f,
+                    condition,
+                    condition.precedence() <= Precedence::Conditional,
+                )?;
+                write!(f, " ? {if_true} : {if_false}")
+            }
+        }
+    }
 }
 
 impl Node {
@@ -85,32 +406,21 @@ impl Node {
             Node::Leaf(n) => match n.node_type {
                 NodeType::Constant => Ok(n.value),
                 NodeType::Var => context.get_var(n.name.clone()),
-                NodeType::Function => match context.get_function(n.name.clone()) {
-                    Ok(f_ptr) => {
-                        let mut f_args: Vec<f64> = vec![];
-                        for arg in n.args.iter() {
-                            match arg.eval(context) {
-                                Ok(v) => f_args.push(v),
-                                Err(_) => (),
-                            }
-                        }
-                        f_ptr(f_args)
-                    }
-                    Err(e) => {
-                        println!("Error: {}", e);
-                        Err(e)
-                    }
-                },
+                NodeType::Function => {
+                    let function = context.get_function(n.name.clone())?;
+                    let f_args = n
+                        .args
+                        .iter()
+                        .map(|arg| arg.eval(context))
+                        .collect::<Result<Vec<f64>>>()?;
+                    function.call(&f_args)
+                }
             },
             Node::UnaryExpr { op, expr } => {
-                let value = expr.eval(context);
+                let value = expr.eval(context)?;
                 match op {
-                    Operator::Plus => value,
-                    Operator::Minus => match value {
-                        Ok(v) => Ok(-v),
-                        Err(_) => Err(ExprError::EvaluationError),
-                    },
-                    _ => value,
+                    Operator::Minus => Ok(-value),
+                    _ => Ok(value),
                 }
             }
             Node::BinaryExpr {
@@ -118,38 +428,209 @@ impl Node {
                 left_expr,
                 right_expr,
             } => {
-                let left_expr_value: f64 = left_expr.eval(context).unwrap();
-                let right_expr_value: f64 = right_expr.eval(context).unwrap();
-
-                match op {
-                    Operator::Plus => Ok(left_expr_value + right_expr_value),
-                    Operator::Minus => Ok(left_expr_value - right_expr_value),
-                    Operator::Mult => Ok(left_expr_value * right_expr_value),
-                    Operator::Div => {
-                        if right_expr_value == 0.0 {
-                            Err(ExprError::EvaluationError)
-                        } else {
-                            return Ok(left_expr_value / right_expr_value);
-                        }
-                    }
+                let left_expr_value: f64 = left_expr.eval(context)?;
+                let right_expr_value: f64 = right_expr.eval(context)?;
+                op.apply(left_expr_value, right_expr_value)
+            }
+            Node::Conditional {
+                condition,
+                if_true,
+                if_false,
+            } => {
+                if condition.eval(context)? != 0.0 {
+                    if_true.eval(context)
+                } else {
+                    if_false.eval(context)
                 }
             }
         }
     }
 }
 
-type Func = fn(Vec<f64>) -> Result<f64>;
+/// A function of an [`ExprContext`]. Unlike a plain function pointer, it may
+/// capture data, such as an interpolation table or the compiled body of a
+/// function declared by a model.
+#[derive(Clone)]
+pub struct Func(Arc<Closure>);
 
-pub fn sqrt(values: Vec<f64>) -> Result<f64> {
-    return Ok(f64::sqrt(values[0]));
+type Closure = dyn Fn(&[f64]) -> Result<f64> + Send + Sync;
+
+impl Func {
+    pub fn new(function: impl Fn(&[f64]) -> Result<f64> + Send + Sync + 'static) -> Self {
+        Self(Arc::new(function))
+    }
+
+    pub fn call(&self, values: &[f64]) -> Result<f64> {
+        (self.0)(values)
+    }
 }
 
-pub fn exp(values: Vec<f64>) -> Result<f64> {
-    return Ok(f64::exp(values[0]));
+impl fmt::Debug for Func {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Func")
+    }
 }
 
-pub fn pow(values: Vec<f64>) -> Result<f64> {
-    return Ok(f64::powf(values[0], values[1]));
+type Builtin = fn(&[f64]) -> Result<f64>;
+
+/// Checks that `function` was given as many arguments as it takes.
+fn check_arity(function: &str, values: &[f64], expected: usize) -> Result<()> {
+    if values.len() == expected {
+        Ok(())
+    } else {
+        Err(ExprError::ArgumentCount {
+            function: function.to_owned(),
+            expected,
+            found: values.len(),
+        })
+    }
+}
+
+/// Defines builtins taking one argument.
+macro_rules! unary_functions {
+    ($($name:ident => $f:expr;)*) => {
+        $(
+            pub fn $name(values: &[f64]) -> Result<f64> {
+                check_arity(stringify!($name), values, 1)?;
+                Ok($f(values[0]))
+            }
+        )*
+    };
+}
+
+/// Defines builtins taking two arguments.
+macro_rules! binary_functions {
+    ($($name:ident => $f:expr;)*) => {
+        $(
+            pub fn $name(values: &[f64]) -> Result<f64> {
+                check_arity(stringify!($name), values, 2)?;
+                Ok($f(values[0], values[1]))
+            }
+        )*
+    };
+}
+
+unary_functions! {
+    sqrt => f64::sqrt;
+    cbrt => f64::cbrt;
+    exp => f64::exp;
+    exp2 => f64::exp2;
+    expm1 => f64::exp_m1;
+    log => f64::ln;
+    log2 => f64::log2;
+    log10 => f64::log10;
+    log1p => f64::ln_1p;
+    sin => f64::sin;
+    cos => f64::cos;
+    tan => f64::tan;
+    arcsin => f64::asin;
+    arccos => f64::acos;
+    arctan => f64::atan;
+    sinh => f64::sinh;
+    cosh => f64::cosh;
+    tanh => f64::tanh;
+    arcsinh => f64::asinh;
+    arccosh => f64::acosh;
+    arctanh => f64::atanh;
+    abs => f64::abs;
+    floor => f64::floor;
+    ceil => f64::ceil;
+    trunc => f64::trunc;
+    round => f64::round_ties_even;
+    sign => sign_of;
+}
+
+binary_functions! {
+    pow => f64::powf;
+    arctan2 => f64::atan2;
+    hypot => f64::hypot;
+}
+
+/// Like numpy's `sign`, which is 0 at 0 unlike [`f64::signum`].
+fn sign_of(value: f64) -> f64 {
+    if value == 0.0 {
+        0.0
+    } else {
+        value.signum()
+    }
+}
+
+/// Smallest of one or more values.
+pub fn min(values: &[f64]) -> Result<f64> {
+    fold("min", values, f64::min)
+}
+
+/// Largest of one or more values.
+pub fn max(values: &[f64]) -> Result<f64> {
+    fold("max", values, f64::max)
+}
+
+fn fold(function: &str, values: &[f64], f: fn(f64, f64) -> f64) -> Result<f64> {
+    match values.split_first() {
+        Some((&first, rest)) => Ok(rest.iter().copied().fold(first, f)),
+        None => Err(ExprError::ArgumentCount {
+            function: function.to_owned(),
+            expected: 1,
+            found: 0,
+        }),
+    }
+}
+
+/// Functions every context starts with, named as in numpy and Python's
+/// `math`, so that the expressions of generated code evaluate the same.
+const BUILTINS: &[(&str, Builtin)] = &[
+    ("sqrt", sqrt),
+    ("cbrt", cbrt),
+    ("exp", exp),
+    ("exp2", exp2),
+    ("expm1", expm1),
+    ("log", log),
+    ("ln", log),
+    ("log2", log2),
+    ("log10", log10),
+    ("log1p", log1p),
+    ("sin", sin),
+    ("cos", cos),
+    ("tan", tan),
+    ("arcsin", arcsin),
+    ("asin", arcsin),
+    ("arccos", arccos),
+    ("acos", arccos),
+    ("arctan", arctan),
+    ("atan", arctan),
+    ("sinh", sinh),
+    ("cosh", cosh),
+    ("tanh", tanh),
+    ("arcsinh", arcsinh),
+    ("asinh", arcsinh),
+    ("arccosh", arccosh),
+    ("acosh", arccosh),
+    ("arctanh", arctanh),
+    ("atanh", arctanh),
+    ("abs", abs),
+    ("fabs", abs),
+    ("floor", floor),
+    ("ceil", ceil),
+    ("trunc", trunc),
+    ("round", round),
+    ("sign", sign),
+    ("pow", pow),
+    ("power", pow),
+    ("arctan2", arctan2),
+    ("atan2", arctan2),
+    ("hypot", hypot),
+    ("min", min),
+    ("minimum", min),
+    ("max", max),
+    ("maximum", max),
+];
+
+thread_local! {
+    /// Stacks of the bodies of the functions defined with
+    /// [`ExprContext::define_function`], kept between calls so that they
+    /// allocate nothing either. Each call takes one of its own, as a body may
+    /// call other functions defined that way.
+    static FUNCTION_STACKS: RefCell<Vec<Vec<f64>>> = const { RefCell::new(Vec::new()) };
 }
 
 #[derive(Debug, Clone)]
@@ -162,13 +643,10 @@ impl ExprContext {
     pub fn new() -> Self {
         Self {
             vars: HashMap::new(),
-            funcs: {
-                let mut functions: HashMap<String, Func> = HashMap::new();
-                functions.insert(String::from("sqrt"), sqrt);
-                functions.insert(String::from("exp"), exp);
-                functions.insert(String::from("pow"), pow);
-                functions
-            },
+            funcs: BUILTINS
+                .iter()
+                .map(|&(name, function)| (String::from(name), Func::new(function)))
+                .collect(),
         }
     }
 
@@ -190,8 +668,44 @@ impl ExprContext {
         }
     }
 
-    pub fn set_func(&mut self, name: String, f: Func) {
-        self.funcs.insert(name, f);
+    pub fn set_func(
+        &mut self,
+        name: String,
+        f: impl Fn(&[f64]) -> Result<f64> + Send + Sync + 'static,
+    ) {
+        self.funcs.insert(name, Func::new(f));
+    }
+
+    /// Defines `name(parameters...)` as `body`, which is compiled right away,
+    /// so it may only call the functions already in the context, and may only
+    /// refer to its parameters.
+    pub fn define_function(
+        &mut self,
+        name: &str,
+        parameters: &[String],
+        body: &Node,
+    ) -> Result<()> {
+        let mut slots = Slots::new();
+        for parameter in parameters {
+            slots.insert(parameter);
+        }
+        if slots.len() != parameters.len() {
+            return Err(ExprError::DuplicateParameter(name.to_owned()));
+        }
+
+        let body = Compiled::new(body, &slots, self)?;
+        let function = name.to_owned();
+        let arity = parameters.len();
+        self.set_func(name.to_owned(), move |values: &[f64]| {
+            check_arity(&function, values, arity)?;
+            let mut stack = FUNCTION_STACKS
+                .with_borrow_mut(Vec::pop)
+                .unwrap_or_default();
+            let value = body.eval(values, &mut stack);
+            FUNCTION_STACKS.with_borrow_mut(|stacks| stacks.push(stack));
+            value
+        });
+        Ok(())
     }
 }
 
@@ -213,13 +727,14 @@ impl Expression {
         self.context = ctx;
     }
 
-    pub fn parse_expr(&mut self, text: String) -> Result<bool> {
+    pub fn parse_expr(&mut self, text: String) -> Result<()> {
         //parse the expression and creates the ast tree
         let tokens = lexer::tokenize_string(text);
         let mut parser = Parser::new(tokens);
-        self.ast = Some(parser.parse());
+        self.ast = None;
+        self.ast = Some(parser.parse()?);
 
-        Ok(true)
+        Ok(())
     }
 
     pub fn eval(&self) -> Result<f64> {
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::fs::File;
 use std::io;
 use std::io::{prelude::*, BufWriter};
@@ -36,6 +37,7 @@ pub enum Punctuation {
     RBrace,
     LParen,
     RParen,
+    Question,
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -51,6 +53,7 @@ pub enum Operators {
     Minus,
     Multiplication,
     Division,
+    Power,
     Arrow,
 }
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -66,6 +69,69 @@ pub enum TokenKind {
     Error(String),
 }
 
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TokenKind::Identifier(name) => write!(f, "{name}"),
+            TokenKind::IntConst(value) => write!(f, "{value}"),
+            TokenKind::FloatConst(value) => write!(f, "{value}"),
+            TokenKind::CharConst(value) => write!(f, "'{value}'"),
+            TokenKind::FormattedString(value) => write!(f, "\"{value}\""),
+            TokenKind::ReservedWords(word) => {
+                let word = match word {
+                    ReservedWords::Function => "fn",
+                    ReservedWords::Main => "main",
+                    ReservedWords::Let => "let",
+                    ReservedWords::Int => "int",
+                    ReservedWords::Float => "float",
+                    ReservedWords::Char => "char",
+                    ReservedWords::If => "if",
+                    ReservedWords::Else => "else",
+                    ReservedWords::While => "while",
+                    ReservedWords::Print => "print",
+                    ReservedWords::Println => "println",
+                    ReservedWords::Return => "return",
+                };
+                write!(f, "{word}")
+            }
+            TokenKind::Punctuation(punctuation) => {
+                let punctuation = match punctuation {
+                    Punctuation::Arrow => "->",
+                    Punctuation::Dot => ".",
+                    Punctuation::Colon => ":",
+                    Punctuation::Semicolon => ";",
+                    Punctuation::Comma => ",",
+                    Punctuation::LBrace => "{",
+                    Punctuation::RBrace => "}",
+                    Punctuation::LParen => "(",
+                    Punctuation::RParen => ")",
+                    Punctuation::Question => "?",
+                };
+                write!(f, "{punctuation}")
+            }
+            TokenKind::Operators(operator) => {
+                let operator = match operator {
+                    Operators::Assign => "=",
+                    Operators::Equal => "==",
+                    Operators::NotEqual => "!=",
+                    Operators::GreaterThan => ">",
+                    Operators::GreaterThanEqual => ">=",
+                    Operators::LessThan => "<",
+                    Operators::LessThanEqual => "<=",
+                    Operators::Plus => "+",
+                    Operators::Minus => "-",
+                    Operators::Multiplication => "*",
+                    Operators::Division => "/",
+                    Operators::Power => "^",
+                    Operators::Arrow => "->",
+                };
+                write!(f, "{operator}")
+            }
+            TokenKind::Error(message) => write!(f, "{message}"),
+        }
+    }
+}
+
 impl From<String> for TokenKind {
     fn from(other: String) -> TokenKind {
         TokenKind::Identifier(other)
@@ -208,7 +274,7 @@ fn tokenize_ident_reservedword(data: &str) -> Result<(TokenKind, usize), usize>
 fn tokenize_number(data: &str) -> Result<(TokenKind, usize), usize> {
     let mut seen_dot = false;
 
-    let (decimal, bytes_read) = execute_predicate(data, |c| {
+    let (_, bytes_read) = execute_predicate(data, |c| {
         if c.is_digit(10) {
             true
         } else if c == '.' {
@@ -223,7 +289,29 @@ fn tokenize_number(data: &str) -> Result<(TokenKind, usize), usize> {
         }
     })?;
 
-    let n: f64 = decimal.parse::<f64>().unwrap();
+    // Scientific notation, as in `1.5e-3`, if the exponent has digits
+    let exponent = &data[bytes_read..];
+    let mut exponent_chars = exponent.char_indices();
+    let bytes_read = match exponent_chars.next() {
+        Some((_, 'e' | 'E')) => {
+            let digits = match exponent_chars.next() {
+                Some((idx, '+' | '-')) => idx + 1,
+                _ => 1,
+            };
+            let num_digits = exponent[digits..]
+                .chars()
+                .take_while(|c| c.is_ascii_digit())
+                .count();
+            if num_digits > 0 {
+                bytes_read + digits + num_digits
+            } else {
+                bytes_read
+            }
+        }
+        _ => bytes_read,
+    };
+
+    let n: f64 = data[..bytes_read].parse::<f64>().unwrap();
     Ok((TokenKind::FloatConst(n), bytes_read))
 }
 
@@ -332,7 +420,12 @@ pub fn tokenize_char(data: &str) -> Result<(TokenKind, usize), usize> {
         }
     })?;
 
-    Ok((TokenKind::CharConst(str[1..2].to_string()), bytes_read + 1))
+    // The closing quote is missing
+    if !data[bytes_read..].starts_with('\'') {
+        return Err(bytes_read);
+    }
+
+    Ok((TokenKind::CharConst(str[1..].to_string()), bytes_read + 1))
 }
 
 pub fn tokenize_formatted_string(data: &str) -> Result<(TokenKind, usize), usize> {
@@ -376,16 +469,27 @@ pub fn tokenize_single_token(data: &str) -> Result<(TokenKind, usize), usize> {
         '-' => tokenize_minus_or_arrow(data).expect("Couldn't tokenize minus or arrow!"),
         '*' => (TokenKind::Operators(Operators::Multiplication), 1),
         '/' => (TokenKind::Operators(Operators::Division), 1),
+        '^' => (TokenKind::Operators(Operators::Power), 1),
+        '?' => (TokenKind::Punctuation(Punctuation::Question), 1),
+        '!' if data.starts_with("!=") => (TokenKind::Operators(Operators::NotEqual), 2),
         '<' => tokenize_less_or_lessthan(data).expect("Couldn't tokenize the less than operator"),
         '>' => tokenize_greater_or_greaterthan(data)
             .expect("Couldn't tokenize the greater than operator"),
-        '\'' => tokenize_char(data).expect("Couldn't tokenize a char const"),
+        '\'' => tokenize_char(data).unwrap_or_else(|bytes_read| {
+            (
+                TokenKind::Error(String::from("Unterminated char constant")),
+                bytes_read,
+            )
+        }),
         '0'..='9' => tokenize_number(data).expect("Couldn't tokenize a number"),
         '=' => tokenize_assing_or_equal(data).expect("Couldn't tokenize assign or equal!"),
         '"' => tokenize_formatted_string(data).expect("Couldn't tokenize a formatted string"),
         c @ '_' | c if c.is_alphabetic() => tokenize_ident_reservedword(data)
             .expect("Couldn't tokenize an identifier or reserved word"),
-        other => (TokenKind::Error(format!("Unkown char {}", other)), 1),
+        other => (
+            TokenKind::Error(format!("Unknown character `{}`", other)),
+            other.len_utf8(),
+        ),
     };
 
     Ok((tok, length))
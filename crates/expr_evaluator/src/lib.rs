@@ -1,3 +1,5 @@
+pub mod compiled;
+pub mod derivative;
 pub mod expr;
 mod lexer;
 mod parser;
@@ -5,7 +7,8 @@ mod parser;
 #[cfg(test)]
 mod tests {
     use super::*;
-    use expr::{ExprContext, Expression, LeafNode, Node, NodeType};
+    use compiled::{Compiled, Slots};
+    use expr::{ExprContext, ExprError, Expression, LeafNode, Node, NodeType, ParserError, Span};
 
     #[test]
     fn test1() {
@@ -21,7 +24,7 @@ mod tests {
         }
     }
 
-    pub fn sum(values: Vec<f64>) -> expr::Result<f64> {
+    pub fn sum(values: &[f64]) -> expr::Result<f64> {
         let mut sum = 0.0;
         for v in values.iter() {
             sum += v;
@@ -155,6 +158,431 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_errors_have_positions() {
+        let parse_error = |text: &str| match Expression::new().parse_expr(String::from(text)) {
+            Err(ExprError::Parse(err)) => err,
+            other => panic!("`{text}` should not parse, got {other:?}"),
+        };
+        let span = |start, end| Span {
+            line: 1,
+            start,
+            end,
+        };
+
+        assert_eq!(
+            parse_error("(x + 2"),
+            ParserError::UnexpectedEnd {
+                expected: "`)`",
+                span: span(6, 6),
+            }
+        );
+        assert_eq!(
+            parse_error("x y"),
+            ParserError::UnexpectedToken {
+                expected: "an operator",
+                found: String::from("y"),
+                span: span(2, 3),
+            }
+        );
+        assert_eq!(
+            parse_error("sum(x, )"),
+            ParserError::UnexpectedToken {
+                expected: "a name, a number or `(`",
+                found: String::from(")"),
+                span: span(7, 8),
+            }
+        );
+        assert_eq!(
+            parse_error("2 * $"),
+            ParserError::InvalidToken {
+                message: String::from("Unknown character `$`"),
+                span: span(4, 5),
+            }
+        );
+        assert_eq!(
+            parse_error("x > 0 ? 1"),
+            ParserError::UnexpectedEnd {
+                expected: "`:`",
+                span: span(9, 9),
+            }
+        );
+        assert_eq!(
+            parse_error("1 + piecewise(1, x > 0)"),
+            ParserError::Piecewise {
+                found: 2,
+                span: span(4, 13),
+            }
+        );
+        assert_eq!(
+            parse_error("x ! y"),
+            ParserError::InvalidToken {
+                message: String::from("Unknown character `!`"),
+                span: span(2, 3),
+            }
+        );
+        assert!(matches!(parse_error(""), ParserError::UnexpectedEnd { .. }));
+    }
+
+    #[test]
+    fn evaluation_errors_are_returned() {
+        let mut ctx = ExprContext::new();
+        ctx.set_var(String::from("x"), 2.0);
+        ctx.set_func(String::from("sum"), sum);
+
+        let eval = |text: &str| {
+            let mut expr = Expression::new();
+            expr.parse_expr(String::from(text)).unwrap();
+            expr.set_context(ctx.clone());
+            expr.eval()
+        };
+
+        assert_eq!(
+            eval("x * (1 + y)"),
+            Err(ExprError::UndefinedVarError(String::from("y")))
+        );
+        assert_eq!(
+            eval("sum(x, y)"),
+            Err(ExprError::UndefinedVarError(String::from("y")))
+        );
+        assert_eq!(
+            eval("-hill(x)"),
+            Err(ExprError::UndefinedFunctionError(String::from("hill")))
+        );
+        assert_eq!(eval("x / (x - 2)"), Err(ExprError::DivisionByZero));
+        assert_eq!(
+            eval("pow(x)"),
+            Err(ExprError::ArgumentCount {
+                function: String::from("pow"),
+                expected: 2,
+                found: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn operators_follow_python() {
+        let mut ctx = ExprContext::new();
+        ctx.set_var(String::from("x"), 2.0);
+        ctx.set_var(String::from("y"), 3.0);
+
+        let eval = |text: &str| {
+            let mut expr = Expression::new();
+            expr.parse_expr(String::from(text)).unwrap();
+            expr.set_context(ctx.clone());
+            expr.eval().unwrap()
+        };
+
+        assert_eq!(eval("x ^ y"), 8.0);
+        assert_eq!(eval("2 ^ 3 ^ 2"), 512.0);
+        assert_eq!(eval("-x ^ 2"), -4.0);
+        assert_eq!(eval("x ^ -1 * 4"), 2.0);
+        assert_eq!(eval("1.5e-1 * 2E1"), 3.0);
+        assert_eq!(eval("x < y"), 1.0);
+        assert_eq!(eval("x + 1 != y"), 0.0);
+        assert_eq!(eval("x == 2 >= 1"), 1.0);
+        assert_eq!(eval("1 < x < y <= 3"), 1.0);
+        assert_eq!(eval("1 < y < x"), 0.0);
+        assert_eq!(eval("x > y ? 1 : y > 2 ? 2 : 3"), 2.0);
+        assert_eq!(eval("(x < y ? x : y) * 10"), 20.0);
+        // Only the branch taken is evaluated
+        assert_eq!(eval("x > 0 ? y : 1 / 0"), 3.0);
+        assert_eq!(eval("piecewise(10, x > y, 20, x == 2, 30)"), 20.0);
+        assert_eq!(eval("piecewise(10, x > y, 30)"), 30.0);
+        assert_eq!(eval("piecewise(30)"), 30.0);
+    }
+
+    #[test]
+    fn builtins_follow_numpy() {
+        let mut ctx = ExprContext::new();
+        ctx.set_var(String::from("x"), -2.5);
+
+        let eval = |text: &str| {
+            let mut expr = Expression::new();
+            expr.parse_expr(String::from(text)).unwrap();
+            expr.set_context(ctx.clone());
+            expr.eval()
+        };
+
+        assert_eq!(eval("abs(x)"), Ok(2.5));
+        assert_eq!(eval("floor(x)"), Ok(-3.0));
+        assert_eq!(eval("ceil(x)"), Ok(-2.0));
+        assert_eq!(eval("round(x)"), Ok(-2.0));
+        assert_eq!(eval("sign(x) + sign(0)"), Ok(-1.0));
+        assert_eq!(eval("min(3, x, 1)"), Ok(-2.5));
+        assert_eq!(eval("maximum(x, 1)"), Ok(1.0));
+        assert_eq!(eval("ln(exp(2))"), eval("log(exp(2))"));
+        assert_eq!(eval("log10(1000)"), Ok(3.0));
+        assert_eq!(eval("log2(8)"), Ok(3.0));
+        assert_eq!(eval("sin(0) + cos(0) + tanh(0)"), Ok(1.0));
+        assert_eq!(eval("arctan2(1, 1)"), eval("atan(1)"));
+        assert_eq!(eval("hypot(3, 4)"), Ok(5.0));
+        assert_eq!(eval("power(2, 10)"), Ok(1024.0));
+        assert_eq!(
+            eval("max()"),
+            Err(ExprError::ArgumentCount {
+                function: String::from("max"),
+                expected: 1,
+                found: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn compiled_expressions_match_the_tree() {
+        let mut ctx = ExprContext::new();
+        ctx.set_var(String::from("x"), 5.0);
+        ctx.set_var(String::from("y"), 13.0);
+        ctx.set_func(String::from("sum"), sum);
+
+        let mut slots = Slots::new();
+        for name in ["y", "unused", "x"] {
+            slots.insert(name);
+        }
+        let buffer = slots.buffer(&ctx);
+        let mut stack = Vec::new();
+
+        for text in [
+            "5 + 4 * 2 / 16 - 1",
+            "- x - y + sum(x, y, 3)",
+            "sum(x, y) / 2 * 7",
+            "-(x - y) * sqrt(pow(x, 2))",
+            "exp(-x / y)",
+            "-x ^ 2 / y ^ -0.5",
+            "1 < x <= y != 4",
+            "x > y ? 1 / (x - 5) : piecewise(x, y < 0, -y ^ 2, x == 5, 0)",
+            "max(x, y < 10 ? y : 10) * arctan(x)",
+        ] {
+            let mut expr = Expression::new();
+            expr.parse_expr(String::from(text)).unwrap();
+            expr.set_context(ctx.clone());
+
+            let compiled = expr.compile(&slots).unwrap();
+            assert_eq!(compiled.eval(&buffer, &mut stack), expr.eval(), "{text}");
+        }
+    }
+
+    #[test]
+    fn compiled_expressions_report_errors() {
+        let ctx = ExprContext::new();
+        let mut slots = Slots::new();
+        slots.insert("x");
+
+        let compile = |text: &str| {
+            let mut expr = Expression::new();
+            expr.parse_expr(String::from(text)).unwrap();
+            Compiled::new(expr.ast.as_deref().unwrap(), &slots, &ctx)
+        };
+
+        assert_eq!(
+            compile("x * y").unwrap_err(),
+            ExprError::UndefinedVarError(String::from("y"))
+        );
+        assert_eq!(
+            compile("hill(x)").unwrap_err(),
+            ExprError::UndefinedFunctionError(String::from("hill"))
+        );
+
+        let compiled = compile("1 / x").unwrap();
+        assert_eq!(
+            compiled.eval(&[0.0], &mut Vec::new()),
+            Err(ExprError::DivisionByZero)
+        );
+        assert_eq!(compiled.eval(&[4.0], &mut Vec::new()), Ok(0.25));
+    }
+
+    fn parse(text: &str) -> Box<Node> {
+        let mut expr = Expression::new();
+        expr.parse_expr(String::from(text)).unwrap();
+        expr.ast.unwrap()
+    }
+
+    #[test]
+    fn expressions_are_written_as_parsed() {
+        for text in [
+            "-x ^ 2",
+            "(-x) ^ 2",
+            "2 ^ 3 ^ x",
+            "(2 ^ 3) ^ x",
+            "x ^ -y",
+            "a - (b - c) / (d * e)",
+            "-(a + b) * sum(a, -b)",
+            "(a < b) < c",
+            "a < b ? 1 : c > 2 ? a + b : 0",
+            "(a ? b : c) ? d : e",
+        ] {
+            assert_eq!(parse(text).to_string(), text);
+        }
+    }
+
+    #[test]
+    fn derivatives_are_simplified() {
+        let derivative = |text: &str| parse(text).derivative("x").unwrap().to_string();
+
+        assert_eq!(derivative("k * y"), "0");
+        assert_eq!(derivative("3 * x + 1"), "3");
+        assert_eq!(derivative("-beta * x * y"), "-(beta * y)");
+        assert_eq!(derivative("x ^ 3"), "3 * x ^ 2");
+        assert_eq!(derivative("y / x"), "-(y / x ^ 2)");
+        assert_eq!(derivative("x / (K + x)"), "K / (K + x) ^ 2");
+        assert_eq!(derivative("k * x - k * x / x"), "k");
+        assert_eq!(derivative("exp(-x)"), "-exp(-x)");
+        assert_eq!(derivative("sin(2 * x)"), "2 * cos(2 * x)");
+        assert_eq!(derivative("x > 1 ? x * y : 2"), "x > 1 ? y : 0");
+        assert_eq!(derivative("max(x, 0)"), "x >= 0 ? 1 : 0");
+        assert_eq!(derivative("hill(y)"), "0");
+        assert_eq!(
+            parse("hill(x)").derivative("x").unwrap_err(),
+            ExprError::NotDifferentiable(String::from("hill"))
+        );
+
+        assert_eq!(parse("0 * x + 1 * (y - 0) / 1").simplify().to_string(), "y");
+        assert_eq!(parse("x * y - x * y + 0 / x").simplify().to_string(), "0");
+        assert_eq!(parse("a - b + c + b - a").simplify().to_string(), "c");
+    }
+
+    #[test]
+    fn derivatives_match_finite_differences() {
+        let mut ctx = ExprContext::new();
+        ctx.set_var(String::from("y"), 0.7);
+
+        let eval = |node: &Node, x: f64| {
+            let mut ctx = ctx.clone();
+            ctx.set_var(String::from("x"), x);
+            node.eval(&ctx).unwrap()
+        };
+
+        for text in [
+            "x * y / (1 + x ^ 2) - exp(-x * y)",
+            "sqrt(x) + cbrt(x) + ln(x) + log2(x) + log10(x) + log1p(x)",
+            "sin(x) * cos(y) + tan(x) + arcsin(x / 2) + acos(x / 2) + atan(x)",
+            "sinh(x) - cosh(x) + tanh(x) + asinh(x) + acosh(x + 1) + atanh(x / 2)",
+            "abs(x - 2) + floor(x) + pow(x, y) + y ^ x + x ^ x",
+            "arctan2(x, y) + hypot(x, y) + exp2(x) + expm1(x)",
+            "min(x, y, 1) + max(y, x) + piecewise(x, x > 1, -x)",
+        ] {
+            let expr = parse(text);
+            let derivative = expr.derivative("x").unwrap();
+            for x in [0.3, 0.9, 1.2] {
+                let h = 1e-6;
+                let expected = (eval(&expr, x + h) - eval(&expr, x - h)) / (2.0 * h);
+                let found = eval(&derivative, x);
+                assert!(
+                    (found - expected).abs() < 1e-5 * expected.abs().max(1.0),
+                    "{text} at {x}: {found} != {expected}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn functions_may_capture_data() {
+        // Linear interpolation over a table
+        let table = [(0.0, 1.0), (1.0, 3.0), (2.0, 2.0)];
+        let mut ctx = ExprContext::new();
+        ctx.set_var(String::from("x"), 0.5);
+        ctx.set_func(String::from("lookup"), move |values: &[f64]| {
+            let x = values[0];
+            let upper = table
+                .iter()
+                .position(|&(at, _)| at >= x)
+                .unwrap_or(0)
+                .max(1);
+            let ((x0, y0), (x1, y1)) = (table[upper - 1], table[upper]);
+            Ok(y0 + (y1 - y0) * (x - x0) / (x1 - x0))
+        });
+
+        let mut expr = Expression::new();
+        expr.parse_expr(String::from("lookup(x) + lookup(1.5)"))
+            .unwrap();
+        expr.set_context(ctx.clone());
+        assert_eq!(expr.eval(), Ok(4.5));
+
+        let mut slots = Slots::new();
+        slots.insert("x");
+        let compiled = Compiled::new(expr.ast.as_ref().unwrap(), &slots, &ctx).unwrap();
+        assert_eq!(compiled.eval(&[0.5], &mut Vec::new()), Ok(4.5));
+    }
+
+    #[test]
+    fn functions_are_defined_by_expressions() {
+        let mut ctx = ExprContext::new();
+        let parameters = |names: &[&str]| {
+            names
+                .iter()
+                .map(|&name| String::from(name))
+                .collect::<Vec<_>>()
+        };
+        ctx.define_function(
+            "hill",
+            &parameters(&["x", "k", "n"]),
+            &parse("x ^ n / (k ^ n + x ^ n)"),
+        )
+        .unwrap();
+        ctx.define_function("half", &parameters(&["k"]), &parse("hill(k, k, 2)"))
+            .unwrap();
+        ctx.set_var(String::from("x"), 2.0);
+
+        let eval = |text: &str| {
+            let mut expr = Expression::new();
+            expr.parse_expr(String::from(text)).unwrap();
+            expr.set_context(ctx.clone());
+            expr.eval()
+        };
+
+        assert_eq!(eval("hill(x, 2, 1) + half(3)"), Ok(1.0));
+        assert_eq!(
+            eval("hill(x, 2)"),
+            Err(ExprError::ArgumentCount {
+                function: String::from("hill"),
+                expected: 3,
+                found: 2,
+            })
+        );
+
+        // Bodies only see their parameters, and functions defined before them
+        assert_eq!(
+            ctx.clone()
+                .define_function("scaled", &parameters(&["k"]), &parse("k * x")),
+            Err(ExprError::UndefinedVarError(String::from("x")))
+        );
+        assert_eq!(
+            ctx.clone()
+                .define_function("twice", &parameters(&["k"]), &parse("2 * later(k)")),
+            Err(ExprError::UndefinedFunctionError(String::from("later")))
+        );
+        assert_eq!(
+            ctx.clone()
+                .define_function("f", &parameters(&["k", "k"]), &parse("k")),
+            Err(ExprError::DuplicateParameter(String::from("f")))
+        );
+    }
+
+    #[test]
+    fn inlined_functions_are_differentiated() {
+        let parameters = [String::from("x"), String::from("k")];
+        let body = parse("x / (k + x)");
+
+        let inlined = parse("sat(y, K) * sat(2 * x, K)")
+            .inline("sat", &parameters, &body)
+            .unwrap();
+        assert_eq!(inlined.to_string(), "y / (K + y) * (2 * x / (K + 2 * x))");
+        assert_eq!(
+            inlined.derivative("y").unwrap().to_string(),
+            "K / (K + y) ^ 2 * (2 * x / (K + 2 * x))"
+        );
+        assert_eq!(
+            parse("sat(y)")
+                .inline("sat", &parameters, &body)
+                .unwrap_err(),
+            ExprError::ArgumentCount {
+                function: String::from("sat"),
+                expected: 2,
+                found: 1,
+            }
+        );
+    }
+
     #[test]
     fn test_parallel() {
         std::thread::scope(|scope| {
@@ -1,244 +1,366 @@
+use std::fmt;
+
 use crate::expr::Operator::*;
 use crate::{
     expr::{LeafNode, Node, NodeType, Operator},
     lexer::*,
 };
 
+/// Where a token is in the text of an expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    /// Line of the token, starting at 1
+    pub line: usize,
+    /// Byte offset of the start of the token in its line
+    pub start: usize,
+    /// Byte offset right after the end of the token in its line
+    pub end: usize,
+}
+
+impl From<&Token> for Span {
+    fn from(token: &Token) -> Self {
+        Self {
+            line: token.line_number,
+            start: token.start,
+            end: token.end,
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.start + 1)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum ParserError {
-    FunctionNameNotFoundError,
+    /// A token that can't be where it was found
+    UnexpectedToken {
+        expected: &'static str,
+        found: String,
+        span: Span,
+    },
+    /// The expression ended while `expected` was still missing
+    UnexpectedEnd { expected: &'static str, span: Span },
+    /// Text the lexer couldn't read
+    InvalidToken { message: String, span: Span },
+    /// A call to `piecewise` without a value for when no condition holds
+    Piecewise { found: usize, span: Span },
+}
+
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParserError::UnexpectedToken {
+                expected,
+                found,
+                span,
+            } => write!(f, "Expected {expected} but found `{found}` at {span}"),
+            ParserError::UnexpectedEnd { expected, span } => {
+                write!(f, "Expected {expected} at {span}, but the expression ended")
+            }
+            ParserError::InvalidToken { message, span } => write!(f, "{message} at {span}"),
+            ParserError::Piecewise { found, span } => write!(
+                f,
+                "`piecewise` takes pairs of a value and a condition followed by a value \
+                 otherwise, but was given {found} argument(s) at {span}"
+            ),
+        }
+    }
 }
 
+impl std::error::Error for ParserError {}
+
+type Result<T> = std::result::Result<T, ParserError>;
+
 #[derive(Clone, Debug)]
 pub struct Parser {
     tokens: Vec<Token>,
-    c_token: Token,
-    last_token: Token,
     index: usize,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self {
-            tokens,
-            c_token: Token::new(0, 0, 0, TokenKind::Error(String::from(""))),
-            last_token: Token::new(0, 0, 0, TokenKind::Error(String::from(""))),
-            index: 0,
-        }
+        Self { tokens, index: 0 }
     }
 
-    fn next_token(&mut self) -> Token {
-        if self.index < self.tokens.len() {
-            self.last_token = self.tokens.get(self.index).unwrap().clone();
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.index).map(|token| &token.token_type)
+    }
+
+    /// Consumes the next token if it's `kind`.
+    fn accept(&mut self, kind: &TokenKind) -> bool {
+        let accepted = self.peek() == Some(kind);
+        if accepted {
             self.index += 1;
         }
-        return self.last_token.clone();
+        accepted
     }
 
-    fn back_token(&mut self) {
-        self.index -= 1;
+    fn expect(&mut self, kind: TokenKind, expected: &'static str) -> Result<()> {
+        if self.accept(&kind) {
+            Ok(())
+        } else {
+            Err(self.unexpected(expected))
+        }
     }
 
-    fn expr(&mut self) -> Box<Node> {
-        let node: Box<Node> = self.termo();
-        return self.adicao_opc(node);
+    /// Error for the next token, which isn't what was `expected`.
+    fn unexpected(&self, expected: &'static str) -> ParserError {
+        match self.tokens.get(self.index) {
+            Some(token) => match &token.token_type {
+                TokenKind::Error(message) => ParserError::InvalidToken {
+                    message: message.clone(),
+                    span: token.into(),
+                },
+                found => ParserError::UnexpectedToken {
+                    expected,
+                    found: found.to_string(),
+                    span: token.into(),
+                },
+            },
+            None => ParserError::UnexpectedEnd {
+                expected,
+                span: self.end_span(),
+            },
+        }
     }
 
-    fn termo(&mut self) -> Box<Node> {
-        let node = self.fator();
-        return self.termo_opc(node);
+    /// Span right after the last token, where a missing one would be.
+    fn end_span(&self) -> Span {
+        match self.tokens.last() {
+            Some(token) => Span {
+                line: token.line_number,
+                start: token.end,
+                end: token.end,
+            },
+            None => Span {
+                line: 1,
+                start: 0,
+                end: 0,
+            },
+        }
     }
 
-    fn adicao_opc(&mut self, node: Box<Node>) -> Box<Node> {
-        self.c_token = self.next_token();
+    fn expr(&mut self) -> Result<Box<Node>> {
+        self.condicional()
+    }
 
-        match self.c_token.token_type.clone() {
-            TokenKind::Operators(Operators::Plus) => {
-                let right_node = self.termo();
-                let binary_node = Box::new(Node::BinaryExpr {
-                    op: Operator::Plus,
-                    left_expr: node,
-                    right_expr: right_node,
-                });
-                return self.adicao_opc(binary_node);
-            }
-            TokenKind::Operators(Operators::Minus) => {
-                let right_node = self.termo();
-                let binary_node = Box::new(Node::BinaryExpr {
-                    op: Operator::Minus,
-                    left_expr: node,
-                    right_expr: right_node,
-                });
-                return self.adicao_opc(binary_node);
-            }
-            _ => {
-                self.back_token();
-                return node;
-            }
+    /// `condition ? if_true : if_false`, which nests to the right.
+    fn condicional(&mut self) -> Result<Box<Node>> {
+        let condition = self.comparacao()?;
+        if !self.accept(&TokenKind::Punctuation(Punctuation::Question)) {
+            return Ok(condition);
         }
+
+        let if_true = self.condicional()?;
+        self.expect(TokenKind::Punctuation(Punctuation::Colon), "`:`")?;
+        let if_false = self.condicional()?;
+        Ok(Box::new(Node::Conditional {
+            condition,
+            if_true,
+            if_false,
+        }))
     }
 
-    fn termo_opc(&mut self, node: Box<Node>) -> Box<Node> {
-        self.c_token = self.next_token();
-        match self.c_token.token_type.clone() {
-            TokenKind::Operators(Operators::Multiplication) => {
-                let right_node = self.fator();
-                let binary_node = Box::new(Node::BinaryExpr {
-                    op: Operator::Mult,
-                    left_expr: node,
-                    right_expr: right_node,
-                });
-                return self.termo_opc(binary_node);
-            }
-            TokenKind::Operators(Operators::Division) => {
-                let right_node = self.fator();
-                let binary_node = Box::new(Node::BinaryExpr {
-                    op: Operator::Div,
-                    left_expr: node,
-                    right_expr: right_node,
-                });
-                return self.termo_opc(binary_node);
-            }
-            _ => {
-                self.back_token();
-                return node;
-            }
+    /// Comparisons chain as in Python: `a < b <= c` holds when both `a < b`
+    /// and `b <= c` do.
+    fn comparacao(&mut self) -> Result<Box<Node>> {
+        let mut left = self.soma()?;
+        let mut chain: Option<Box<Node>> = None;
+
+        loop {
+            let op = match self.peek() {
+                Some(TokenKind::Operators(Operators::Equal)) => Operator::Eq,
+                Some(TokenKind::Operators(Operators::NotEqual)) => Operator::Ne,
+                Some(TokenKind::Operators(Operators::LessThan)) => Operator::Lt,
+                Some(TokenKind::Operators(Operators::GreaterThan)) => Operator::Gt,
+                Some(TokenKind::Operators(Operators::LessThanEqual)) => Operator::Le,
+                Some(TokenKind::Operators(Operators::GreaterThanEqual)) => Operator::Ge,
+                _ => break,
+            };
+            self.index += 1;
+
+            let right = self.soma()?;
+            let comparison = Box::new(Node::BinaryExpr {
+                op,
+                left_expr: left,
+                right_expr: right.clone(),
+            });
+            chain = Some(match chain {
+                Some(previous) => Box::new(Node::BinaryExpr {
+                    op: Mult,
+                    left_expr: previous,
+                    right_expr: comparison,
+                }),
+                None => comparison,
+            });
+            left = right;
         }
+
+        Ok(chain.unwrap_or(left))
     }
 
-    fn fator(&mut self) -> Box<Node> {
-        let mut is_unary: bool = false;
-        let mut is_minus: bool = false;
-        self.c_token = self.next_token();
+    fn soma(&mut self) -> Result<Box<Node>> {
+        let node: Box<Node> = self.termo()?;
+        self.adicao_opc(node)
+    }
 
-        match self.c_token.token_type.clone() {
-            TokenKind::Operators(Operators::Minus) => {
-                is_unary = true;
-                is_minus = true;
-            }
-            TokenKind::Operators(Operators::Plus) => {
-                is_unary = true;
-            }
-            _ => (),
-        }
+    fn termo(&mut self) -> Result<Box<Node>> {
+        let node = self.fator()?;
+        self.termo_opc(node)
+    }
+
+    fn adicao_opc(&mut self, node: Box<Node>) -> Result<Box<Node>> {
+        let op = match self.peek() {
+            Some(TokenKind::Operators(Operators::Plus)) => Operator::Plus,
+            Some(TokenKind::Operators(Operators::Minus)) => Operator::Minus,
+            _ => return Ok(node),
+        };
+        self.index += 1;
+
+        let right_node = self.termo()?;
+        let binary_node = Box::new(Node::BinaryExpr {
+            op,
+            left_expr: node,
+            right_expr: right_node,
+        });
+        self.adicao_opc(binary_node)
+    }
+
+    fn termo_opc(&mut self, node: Box<Node>) -> Result<Box<Node>> {
+        let op = match self.peek() {
+            Some(TokenKind::Operators(Operators::Multiplication)) => Operator::Mult,
+            Some(TokenKind::Operators(Operators::Division)) => Operator::Div,
+            _ => return Ok(node),
+        };
+        self.index += 1;
 
-        let node: Box<Node> = self.fator2(is_unary);
-        if is_minus {
-            return Box::new(Node::UnaryExpr {
+        let right_node = self.fator()?;
+        let binary_node = Box::new(Node::BinaryExpr {
+            op,
+            left_expr: node,
+            right_expr: right_node,
+        });
+        self.termo_opc(binary_node)
+    }
+
+    /// Signs apply after powers, so `-x^2` is `-(x^2)`.
+    fn fator(&mut self) -> Result<Box<Node>> {
+        if self.accept(&TokenKind::Operators(Operators::Minus)) {
+            let node = self.fator()?;
+            return Ok(Box::new(Node::UnaryExpr {
                 op: Minus,
                 expr: node,
-            });
-        } else {
-            return node;
-        }
-    }
-
-    fn fator2(&mut self, is_unary: bool) -> Box<Node> {
-        if !is_unary {
-            self.back_token();
-        }
-        self.c_token = self.next_token();
-
-        match self.c_token.token_type.clone() {
-            TokenKind::Identifier(lexeme) => {
-                self.c_token = self.next_token();
-                match self.c_token.token_type.clone() {
-                    TokenKind::Punctuation(Punctuation::LParen) => {
-                        return self.chamada_funcao(lexeme);
-                    }
-                    _ => {
-                        self.back_token();
-                        return Box::new(Node::Leaf(LeafNode {
-                            node_type: NodeType::Var,
-                            name: lexeme,
-                            value: 0.0,
-                            args: vec![],
-                        }));
-                    }
+            }));
+        }
+
+        if self.accept(&TokenKind::Operators(Operators::Plus)) {
+            return self.fator();
+        }
+        self.potencia()
+    }
+
+    /// `base ^ exponent`, which nests to the right like Python's `**`.
+    fn potencia(&mut self) -> Result<Box<Node>> {
+        let base = self.fator2()?;
+        if !self.accept(&TokenKind::Operators(Operators::Power)) {
+            return Ok(base);
+        }
+
+        let exponent = self.fator()?;
+        Ok(Box::new(Node::BinaryExpr {
+            op: Pow,
+            left_expr: base,
+            right_expr: exponent,
+        }))
+    }
+
+    fn fator2(&mut self) -> Result<Box<Node>> {
+        match self.peek().cloned() {
+            Some(TokenKind::Identifier(lexeme)) => {
+                let span = Span::from(&self.tokens[self.index]);
+                self.index += 1;
+                if self.accept(&TokenKind::Punctuation(Punctuation::LParen)) {
+                    return self.chamada_funcao(lexeme, span);
                 }
+                Ok(Box::new(Node::Leaf(LeafNode::new(NodeType::Var, lexeme))))
             }
-            TokenKind::FloatConst(value) => {
-                return Box::new(Node::Leaf(LeafNode {
+            Some(TokenKind::FloatConst(value)) => {
+                self.index += 1;
+                Ok(Box::new(Node::Leaf(LeafNode {
                     node_type: NodeType::Constant,
                     name: value.to_string(),
-                    value: value,
+                    value,
                     args: vec![],
-                }))
-            }
-            TokenKind::Punctuation(Punctuation::LParen) => {
-                let node = self.expr();
-                self.c_token = self.next_token();
-                if self.c_token.token_type != TokenKind::Punctuation(Punctuation::RParen) {
-                    println!(
-                        "Erro sintatico na linha {}. ) esperado na entrada.",
-                        self.c_token.line_number
-                    );
-                }
-                return node;
+                })))
             }
-            _ => {
-                println!(
-                    "Erro sintatico na linha {}. Id, ( ou constante float esperados na entrada.",
-                    self.c_token.line_number
-                );
-                return Box::new(Node::Leaf(LeafNode::new(
-                    NodeType::Var,
-                    String::from("Error"),
-                )));
+            Some(TokenKind::Punctuation(Punctuation::LParen)) => {
+                self.index += 1;
+                let node = self.expr()?;
+                self.expect(TokenKind::Punctuation(Punctuation::RParen), "`)`")?;
+                Ok(node)
             }
+            _ => Err(self.unexpected("a name, a number or `(`")),
         }
     }
 
-    fn chamada_funcao(&mut self, function_name: String) -> Box<Node> {
-        let mut function_node = LeafNode::new(NodeType::Function, function_name);
-        let args = self.lista_args();
-        function_node.args = args;
+    fn chamada_funcao(&mut self, function_name: String, span: Span) -> Result<Box<Node>> {
+        let args = self.lista_args()?;
+
+        self.expect(TokenKind::Punctuation(Punctuation::RParen), "`,` or `)`")?;
 
-        if self.c_token.token_type != TokenKind::Punctuation(Punctuation::RParen) {
-            println!(
-                "Erro sintatico na linha {}. ) esperado na entrada.",
-                self.c_token.line_number
-            );
+        if function_name == "piecewise" {
+            return piecewise(args, span);
         }
 
-        return Box::new(Node::Leaf(function_node));
+        let mut function_node = LeafNode::new(NodeType::Function, function_name);
+        function_node.args = args;
+        Ok(Box::new(Node::Leaf(function_node)))
     }
 
-    fn lista_args(&mut self) -> Vec<Box<Node>> {
+    fn lista_args(&mut self) -> Result<Vec<Box<Node>>> {
         let mut args: Vec<Box<Node>> = vec![];
-        self.c_token = self.next_token();
-
-        match self.c_token.token_type.clone() {
-            TokenKind::Operators(Operators::Plus)
-            | TokenKind::Operators(Operators::Minus)
-            | TokenKind::Identifier(_)
-            | TokenKind::FloatConst(_)
-            | TokenKind::Punctuation(Punctuation::LParen) => {
-                self.back_token();
-                let node = self.expr();
-                args.push(node);
-                args = self.lista_args2(args);
-            }
-            _ => (),
+        if self.peek() == Some(&TokenKind::Punctuation(Punctuation::RParen)) {
+            return Ok(args);
         }
 
-        return args;
-    }
+        args.push(self.expr()?);
+        while self.accept(&TokenKind::Punctuation(Punctuation::Comma)) {
+            args.push(self.expr()?);
+        }
 
-    fn lista_args2(&mut self, mut args: Vec<Box<Node>>) -> Vec<Box<Node>> {
-        self.c_token = self.next_token();
+        Ok(args)
+    }
 
-        match self.c_token.token_type.clone() {
-            TokenKind::Punctuation(Punctuation::Comma) => {
-                let node = self.expr();
-                args.push(node);
-                args = self.lista_args2(args);
-            }
-            _ => (),
+    /// Parses the whole expression, which must not be followed by anything.
+    pub fn parse(&mut self) -> Result<Box<Node>> {
+        let node = self.expr()?;
+        if self.index < self.tokens.len() {
+            return Err(self.unexpected("an operator"));
         }
-        return args;
+        Ok(node)
+    }
+}
+
+/// `piecewise(value1, condition1, value2, condition2, ..., otherwise)`, as in
+/// SBML, is the first value whose condition holds, or `otherwise` if none
+/// does. It's kept as nested conditionals, so only the value taken is
+/// evaluated.
+fn piecewise(mut args: Vec<Box<Node>>, span: Span) -> Result<Box<Node>> {
+    let found = args.len();
+    if found.is_multiple_of(2) {
+        return Err(ParserError::Piecewise { found, span });
     }
 
-    pub fn parse(&mut self) -> Box<Node> {
-        return self.expr();
+    let mut node = args.pop().expect("an odd number of arguments");
+    while let (Some(condition), Some(if_true)) = (args.pop(), args.pop()) {
+        node = Box::new(Node::Conditional {
+            condition,
+            if_true,
+            if_false: node,
+        });
     }
+    Ok(node)
 }
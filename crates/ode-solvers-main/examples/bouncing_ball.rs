@@ -70,7 +70,7 @@ fn main() {
 struct BouncingBall;
 
 impl ode_solvers::System<Time, State> for BouncingBall {
-    fn system(&self, _t: Time, y: &State, dy: &mut State) {
+    fn system(&mut self, _t: Time, y: &State, dy: &mut State) {
         dy[0] = y[1]; // location is changed by v
         dy[1] = -G as f32; // v is changed by acc of gravity
     }
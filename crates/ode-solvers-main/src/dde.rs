@@ -0,0 +1,348 @@
+//! Method of steps for delay differential equations (DDEs), with fixed step size.
+//!
+//! Each step is a step of the classic Runge-Kutta method of order 4. The past states the system
+//! depends on are read from the steps already taken, interpolated with cubic Hermite polynomials,
+//! which are as accurate as the steps themselves. Before the initial time, the state is the
+//! initial value. Steps are never longer than the shortest delay, so every past state needed by a
+//! step has already been computed.
+
+use crate::dop_shared::{FloatNumber, IntegrationError, SolverResult, Stats};
+
+use nalgebra::{allocator::Allocator, DefaultAllocator, Dim, OVector};
+
+/// Trait needed to be implemented by systems of delay differential equations.
+pub trait DelaySystem<T, V>
+where
+    T: FloatNumber,
+{
+    /// Delays of the system, which must all be positive.
+    fn delays(&self) -> Vec<T>;
+    /// System of delay differential equations, where `lagged[i]` is the state at
+    /// `x - delays()[i]`.
+    fn system(&mut self, x: T, y: &V, lagged: &[V], dy: &mut V);
+}
+
+/// Structure containing the parameters for the numerical integration.
+pub struct Dde<T, V, F>
+where
+    F: DelaySystem<T, V>,
+    T: FloatNumber,
+{
+    f: F,
+    x: T,
+    y: V,
+    x_end: T,
+    step_size: T,
+    delays: Vec<T>,
+    // Every step taken, along with the derivative there, for the dense output
+    past_x: Vec<T>,
+    past_y: Vec<V>,
+    past_dy: Vec<V>,
+    results: SolverResult<T, V>,
+    stats: Stats,
+}
+
+impl<T, D: Dim, F> Dde<T, OVector<T, D>, F>
+where
+    T: FloatNumber,
+    F: DelaySystem<T, OVector<T, D>>,
+    OVector<T, D>: std::ops::Mul<T, Output = OVector<T, D>>,
+    DefaultAllocator: Allocator<T, D>,
+{
+    /// Default initializer for the structure
+    ///
+    /// # Arguments
+    ///
+    /// * `f`           - Structure implementing the DelaySystem<V> trait
+    /// * `x`           - Initial value of the independent variable (usually time)
+    /// * `y`           - Initial value of the dependent variable(s), also used before `x`
+    /// * `x_end`       - Final value of the independent variable
+    /// * `step_size`   - Interval between outputs, and longest step taken
+    ///
+    pub fn new(f: F, x: T, y: OVector<T, D>, x_end: T, step_size: T) -> Self {
+        // Cheaper push while solving due to usage of Vec::with_capacity
+        let num_steps = (((x_end - x) / step_size).ceil()).to_usize().unwrap();
+        let delays = f.delays();
+
+        Dde {
+            f,
+            x,
+            y,
+            x_end,
+            step_size,
+            delays,
+            past_x: Vec::with_capacity(num_steps),
+            past_y: Vec::with_capacity(num_steps),
+            past_dy: Vec::with_capacity(num_steps),
+            results: SolverResult::with_capacity(num_steps),
+            stats: Stats::new(),
+        }
+    }
+
+    /// Core integration method.
+    pub fn integrate(&mut self) -> Result<Stats, IntegrationError> {
+        if let Some(&delay) = self.delays.iter().find(|&&delay| delay <= T::zero()) {
+            return Err(IntegrationError::NonPositiveDelay {
+                delay: delay.to_f64().unwrap(),
+            });
+        }
+
+        let max_step = self
+            .delays
+            .iter()
+            .fold(self.step_size, |max_step, &delay| max_step.min(delay));
+
+        // Save initial values
+        let y = self.y.clone();
+        let dy = self.derivative(self.x, &y);
+        self.push_step(self.x, y, dy);
+        self.results.push(self.x, self.y.clone());
+
+        let x_start = self.x;
+        let num_steps = (((self.x_end - self.x) / self.step_size).ceil())
+            .to_usize()
+            .unwrap();
+
+        for output in 1..=num_steps {
+            let x_output = x_start + T::from(output).unwrap() * self.step_size;
+
+            // Equal steps, no longer than the shortest delay but for rounding errors
+            let remaining = x_output - self.x;
+            let num_substeps = (remaining / max_step * T::from(1. - 1.0E-9).unwrap())
+                .ceil()
+                .max(T::one());
+            let h = remaining / num_substeps;
+
+            for substep in 1..=num_substeps.to_usize().unwrap() {
+                let x_new = if T::from(substep).unwrap() == num_substeps {
+                    x_output
+                } else {
+                    self.x + h
+                };
+                self.step(h, x_new);
+            }
+
+            self.results.push(self.x, self.y.clone());
+        }
+        Ok(self.stats)
+    }
+
+    /// Performs one step of length `h` of the Runge-Kutta 4 method, ending at `x_new`.
+    fn step(&mut self, h: T, x_new: T) {
+        let half_step = h / T::from(2.).unwrap();
+        let k1 = self.past_dy.last().unwrap().clone();
+
+        let buffer = self.y.clone() + k1.clone() * half_step;
+        let k2 = self.derivative(self.x + half_step, &buffer);
+
+        let buffer = self.y.clone() + k2.clone() * half_step;
+        let k3 = self.derivative(self.x + half_step, &buffer);
+
+        let buffer = self.y.clone() + k3.clone() * h;
+        let k4 = self.derivative(x_new, &buffer);
+
+        let two = T::from(2.).unwrap();
+        let y_new = self.y.clone() + (k1 + k2 * two + k3 * two + k4) * (h / T::from(6.).unwrap());
+
+        // Also the first stage of the next step
+        let dy_new = self.derivative(x_new, &y_new);
+        self.push_step(x_new, y_new, dy_new);
+
+        self.stats.accepted_steps += 1;
+    }
+
+    fn push_step(&mut self, x: T, y: OVector<T, D>, dy: OVector<T, D>) {
+        self.x = x;
+        self.y = y.clone();
+        self.past_x.push(x);
+        self.past_y.push(y);
+        self.past_dy.push(dy);
+    }
+
+    /// Evaluates the system at `(x, y)`, looking up the lagged states.
+    fn derivative(&mut self, x: T, y: &OVector<T, D>) -> OVector<T, D> {
+        let lagged: Vec<_> = self
+            .delays
+            .iter()
+            .map(|&delay| self.dense_output(x - delay))
+            .collect();
+
+        let (rows, cols) = y.shape_generic();
+        let mut dy = OVector::zeros_generic(rows, cols);
+        self.f.system(x, y, &lagged, &mut dy);
+        self.stats.num_eval += 1;
+
+        dy
+    }
+
+    /// State at `x`, interpolated between the steps already taken. Before them, it's the initial
+    /// value.
+    fn dense_output(&self, x: T) -> OVector<T, D> {
+        let (Some(&first_x), Some(&last_x)) = (self.past_x.first(), self.past_x.last()) else {
+            return self.y.clone();
+        };
+
+        if x <= first_x {
+            return self.past_y[0].clone();
+        }
+        if x >= last_x {
+            // Only off by rounding errors, as steps are no longer than the delays
+            let last = self.past_x.len() - 1;
+            return self.past_y[last].clone() + self.past_dy[last].clone() * (x - last_x);
+        }
+
+        let idx = self.past_x.partition_point(|&past_x| past_x < x);
+        let (x0, x1) = (self.past_x[idx - 1], self.past_x[idx]);
+        let h = x1 - x0;
+        let s = (x - x0) / h;
+
+        let one = T::one();
+        let two = T::from(2.).unwrap();
+        let three = T::from(3.).unwrap();
+        let h00 = (one + two * s) * (one - s) * (one - s);
+        let h10 = s * (one - s) * (one - s);
+        let h01 = s * s * (three - two * s);
+        let h11 = s * s * (s - one);
+
+        self.past_y[idx - 1].clone() * h00
+            + self.past_dy[idx - 1].clone() * (h * h10)
+            + self.past_y[idx].clone() * h01
+            + self.past_dy[idx].clone() * (h * h11)
+    }
+
+    /// Getter for the independent variable's output.
+    pub fn x_out(&self) -> &Vec<T> {
+        self.results.get().0
+    }
+
+    /// Getter for the dependent variables' output.
+    pub fn y_out(&self) -> &Vec<OVector<T, D>> {
+        self.results.get().1
+    }
+
+    /// Getter for the results type, a pair of independent and dependent variables
+    pub fn results(&self) -> &SolverResult<T, OVector<T, D>> {
+        &self.results
+    }
+
+    /// Getter for the system, as left by the integration
+    pub fn system(&self) -> &F {
+        &self.f
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dde::{Dde, DelaySystem};
+    use crate::dop_shared::IntegrationError;
+    use crate::{DVector, OVector, Vector1};
+    use nalgebra::{allocator::Allocator, DefaultAllocator, Dim};
+
+    // y'(x) = -y(x - delay)
+    struct Test1 {
+        delay: f64,
+    }
+    impl<D: Dim> DelaySystem<f64, OVector<f64, D>> for Test1
+    where
+        DefaultAllocator: Allocator<f64, D>,
+    {
+        fn delays(&self) -> Vec<f64> {
+            vec![self.delay]
+        }
+
+        fn system(
+            &mut self,
+            _x: f64,
+            _y: &OVector<f64, D>,
+            lagged: &[OVector<f64, D>],
+            dy: &mut OVector<f64, D>,
+        ) {
+            dy[0] = -lagged[0][0];
+        }
+    }
+
+    // y0'(x) = y1(x - 1), y1'(x) = -y0(x - 0.5)
+    struct Test2 {}
+    impl<D: Dim> DelaySystem<f64, OVector<f64, D>> for Test2
+    where
+        DefaultAllocator: Allocator<f64, D>,
+    {
+        fn delays(&self) -> Vec<f64> {
+            vec![1., 0.5]
+        }
+
+        fn system(
+            &mut self,
+            _x: f64,
+            _y: &OVector<f64, D>,
+            lagged: &[OVector<f64, D>],
+            dy: &mut OVector<f64, D>,
+        ) {
+            dy[0] = lagged[0][1];
+            dy[1] = -lagged[1][0];
+        }
+    }
+
+    #[test]
+    fn test_integrate_test1_svector() {
+        // The exact solution is a polynomial on each interval of length 1, of degree up to 3
+        // on [0, 3], which the method integrates exactly
+        let system = Test1 { delay: 1. };
+        let mut stepper = Dde::new(system, 0., Vector1::new(1.), 3., 0.1);
+        let _ = stepper.integrate();
+        let x_out = stepper.x_out();
+        let y_out = stepper.y_out();
+        assert_eq!(x_out.len(), 31);
+        assert!((*x_out.last().unwrap() - 3.).abs() < 1.0E-9);
+        assert!((&y_out[5][0] - 0.5).abs() < 1.0E-9);
+        assert!((&y_out[20][0] + 0.5).abs() < 1.0E-9);
+        assert!((&y_out[30][0] + 1. / 6.).abs() < 1.0E-9);
+    }
+
+    #[test]
+    fn test_integrate_test1_dvector() {
+        let system = Test1 { delay: 1. };
+        let mut stepper = Dde::new(system, 0., DVector::from(vec![1.]), 2., 0.1);
+        let _ = stepper.integrate();
+        let y_out = stepper.y_out();
+        assert!((&y_out[15][0] + 0.375).abs() < 1.0E-9);
+        assert!((&y_out[20][0] + 0.5).abs() < 1.0E-9);
+    }
+
+    #[test]
+    fn test_steps_shorter_than_output() {
+        // With a delay shorter than the output interval, the outputs are still every 0.1
+        let system = Test1 { delay: 0.025 };
+        let mut stepper = Dde::new(system, 0., Vector1::new(1.), 1., 0.1);
+        let stats = stepper.integrate().unwrap();
+        let x_out = stepper.x_out();
+        assert_eq!(x_out.len(), 11);
+        assert_eq!(stats.accepted_steps, 40);
+        assert!((x_out[4] - 0.4).abs() < 1.0E-9);
+
+        // y(x) = 1 - x on the first interval
+        let y_out = stepper.y_out();
+        assert!(y_out[10][0] < y_out[0][0]);
+    }
+
+    #[test]
+    fn test_integrate_test2_dvector() {
+        // y0 increases and y1 decreases linearly until x = 0.5
+        let system = Test2 {};
+        let mut stepper = Dde::new(system, 0., DVector::from(vec![1., 1.]), 1., 0.1);
+        let _ = stepper.integrate();
+        let y_out = stepper.y_out();
+        assert!((&y_out[5][0] - 1.5).abs() < 1.0E-9);
+        assert!((&y_out[5][1] - 0.5).abs() < 1.0E-9);
+    }
+
+    #[test]
+    fn test_non_positive_delay() {
+        let system = Test1 { delay: 0. };
+        let mut stepper = Dde::new(system, 0., Vector1::new(1.), 1., 0.1);
+        assert!(matches!(
+            stepper.integrate(),
+            Err(IntegrationError::NonPositiveDelay { .. })
+        ));
+    }
+}
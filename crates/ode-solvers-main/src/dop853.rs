@@ -276,6 +276,17 @@ where
         self.f.system(self.x, &self.y, &mut k[0]);
         self.stats.num_eval += 1;
 
+        // Event functions
+        let n_events = self.f.n_events();
+        let mut g_old = vec![T::zero(); n_events];
+        let mut g_new = vec![T::zero(); n_events];
+        if n_events > 0 {
+            self.f.events(self.x, &self.y, &mut g_old);
+            self.stats.num_eval += 1;
+        }
+        // Events are located on the dense output, so it's needed even for a sparse output
+        let dense = self.out_type == OutputType::Dense || n_events > 0;
+
         // Main loop
         while !last {
             // Check if step number is within allowed range
@@ -394,7 +405,7 @@ where
                     }
                 }
 
-                if self.out_type == OutputType::Dense {
+                if dense {
                     let h = self.h;
 
                     self.rcont[0] = self.y.clone();
@@ -484,7 +495,31 @@ where
                 self.x += self.h;
                 self.h_old = self.h;
 
-                self.solution_output(k[4].clone());
+                let event = if n_events > 0 {
+                    self.detect_events(&mut g_old, &mut g_new)
+                } else {
+                    None
+                };
+
+                // The step is truncated when an event is found
+                self.solution_output(self.y.clone());
+
+                if let Some((action, y_event)) = event {
+                    if action == EventAction::Terminate {
+                        self.h_old = posneg * h_new;
+                        return Ok(self.stats);
+                    }
+
+                    // Restart from the modified state
+                    self.y = y_event;
+                    self.f.system(self.x, &self.y, &mut k[0]);
+                    self.f.events(self.x, &self.y, &mut g_old);
+                    self.stats.num_eval += 2;
+                    if self.out_type == OutputType::Sparse {
+                        self.results.push(self.x, self.y.clone());
+                    }
+                    last = false;
+                }
 
                 // Early abortion check
                 if self
@@ -510,6 +545,45 @@ where
         Ok(self.stats)
     }
 
+    /// Looks for events in the last accepted step. When one of them terminates the integration or
+    /// modifies the state, the step is truncated at its location and the action is returned along
+    /// with the state to restart from.
+    fn detect_events(
+        &mut self,
+        g_old: &mut [T],
+        g_new: &mut [T],
+    ) -> Option<(EventAction, OVector<T, D>)> {
+        self.f.events(self.x, &self.y, g_new);
+        self.stats.num_eval += 1;
+
+        let (x_old, h_old) = (self.x_old, self.h_old);
+        let rcont = &self.rcont;
+        let f = &mut self.f;
+        let located = locate_events(g_old, g_new, x_old, self.x, |x, g| {
+            f.events(x, &dense_output(rcont, x_old, h_old, x), g)
+        });
+        g_old.copy_from_slice(g_new);
+
+        for (index, x) in located {
+            let y = dense_output(&self.rcont, x_old, h_old, x);
+            let mut y_event = y.clone();
+            let action = self.f.on_event(index, x, &mut y_event);
+            self.results.push_event(EventRecord {
+                index,
+                x,
+                y: y.clone(),
+                action,
+            });
+
+            if action != EventAction::Continue {
+                self.x = x;
+                self.y = y;
+                return Some((action, y_event));
+            }
+        }
+        None
+    }
+
     /// If a dense output is required, computes the solution and pushes it into the output vector. Else, pushes the solution into the output vector.
     fn solution_output(&mut self, y_next: OVector<T, D>) {
         if self.out_type == OutputType::Dense {
@@ -519,29 +593,14 @@ where
             } else {
                 while self.xd.abs() <= self.x.abs() {
                     if self.x_old.abs() <= self.xd.abs() && self.x.abs() >= self.xd.abs() {
-                        let theta = (self.xd - self.x_old) / self.h_old;
-                        let theta1 = T::one() - theta;
-
-                        let y_out = &self.rcont[0]
-                            + (&self.rcont[1]
-                                + (&self.rcont[2]
-                                    + (&self.rcont[3]
-                                        + (&self.rcont[4]
-                                            + (&self.rcont[5]
-                                                + (&self.rcont[6] + &self.rcont[7] * theta)
-                                                    * theta1)
-                                                * theta)
-                                            * theta1)
-                                        * theta)
-                                    * theta1)
-                                * theta;
+                        let y_out = dense_output(&self.rcont, self.x_old, self.h_old, self.xd);
                         self.results.push(self.xd, y_out);
                         self.xd += self.dx;
                     }
                 }
             }
         } else {
-            self.results.push(self.x0, y_next);
+            self.results.push(self.x, y_next);
         }
     }
 
@@ -555,10 +614,20 @@ where
         &self.results.get().1
     }
 
+    /// Getter for the events located during the integration.
+    pub fn events_out(&self) -> &Vec<EventRecord<T, OVector<T, D>>> {
+        self.results.events()
+    }
+
     /// Getter for the results type, a pair of independent and dependent variables
     pub fn results(&self) -> &SolverResult<T, OVector<T, D>> {
         &self.results
     }
+
+    /// Getter for the system, as left by the integration
+    pub fn system(&self) -> &F {
+        &self.f
+    }
 }
 
 impl<T, D: Dim, F> Into<SolverResult<T, OVector<T, D>>> for Dop853<T, OVector<T, D>, F>
@@ -572,6 +641,26 @@ where
     }
 }
 
+/// Evaluates the continuous extension of the last accepted step at `x`.
+fn dense_output<T, D: Dim>(rcont: &[OVector<T, D>; 8], x_old: T, h_old: T, x: T) -> OVector<T, D>
+where
+    T: FloatNumber,
+    DefaultAllocator: Allocator<T, D>,
+{
+    let theta = (x - x_old) / h_old;
+    let theta1 = T::one() - theta;
+    &rcont[0]
+        + (&rcont[1]
+            + (&rcont[2]
+                + (&rcont[3]
+                    + (&rcont[4]
+                        + (&rcont[5] + (&rcont[6] + &rcont[7] * theta) * theta1) * theta)
+                        * theta1)
+                    * theta)
+                * theta1)
+            * theta
+}
+
 fn sign<T: FloatNumber>(a: T, b: T) -> T {
     if b > T::zero() {
         a.abs()
@@ -583,7 +672,7 @@ fn sign<T: FloatNumber>(a: T, b: T) -> T {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{OVector, System, Vector1};
+    use crate::{OVector, System, Vector1, Vector2};
     use nalgebra::{allocator::Allocator, DefaultAllocator, Dim};
 
     // Same as Test3 from rk4.rs, but aborts after x is greater/equal than 0.5
@@ -613,4 +702,123 @@ mod tests {
         let out = stepper.y_out();
         assert!((&out[5][0] - 0.912968195).abs() < 1.0E-9);
     }
+
+    // Free fall from 10 m, bouncing back with part of its speed when hitting the ground
+    struct BouncingBall {
+        bounces: u32,
+    }
+    impl System<f64, Vector2<f64>> for BouncingBall {
+        fn system(&mut self, _x: f64, y: &Vector2<f64>, dy: &mut Vector2<f64>) {
+            dy[0] = y[1];
+            dy[1] = -9.81;
+        }
+
+        fn n_events(&self) -> usize {
+            1
+        }
+
+        fn events(&mut self, _x: f64, y: &Vector2<f64>, g: &mut [f64]) {
+            g[0] = y[0];
+        }
+
+        fn on_event(&mut self, _index: usize, _x: f64, y: &mut Vector2<f64>) -> EventAction {
+            self.bounces += 1;
+            if self.bounces == 3 {
+                return EventAction::Terminate;
+            }
+            y[0] = 0.0;
+            y[1] = -0.5 * y[1];
+            EventAction::ModifyState
+        }
+    }
+
+    // Exponential decay with a dose at x = 10, also recording when y crosses 0.5
+    struct Dosing;
+    impl System<f64, Vector1<f64>> for Dosing {
+        fn system(&mut self, _x: f64, y: &Vector1<f64>, dy: &mut Vector1<f64>) {
+            dy[0] = -0.1 * y[0];
+        }
+
+        fn n_events(&self) -> usize {
+            2
+        }
+
+        fn events(&mut self, x: f64, y: &Vector1<f64>, g: &mut [f64]) {
+            g[0] = x - 10.0;
+            g[1] = y[0] - 0.5;
+        }
+
+        fn on_event(&mut self, index: usize, _x: f64, y: &mut Vector1<f64>) -> EventAction {
+            if index == 0 {
+                y[0] += 1.0;
+                EventAction::ModifyState
+            } else {
+                EventAction::Continue
+            }
+        }
+    }
+
+    #[test]
+    fn test_events_bouncing_ball() {
+        let system = BouncingBall { bounces: 0 };
+        let mut stepper = Dop853::new(system, 0., 20., 0.01, Vector2::new(10., 0.), 1e-10, 1e-10);
+        stepper.integrate().unwrap();
+
+        // Time to fall from 10 m, then each bounce takes twice the time to reach the top at half
+        // the speed
+        let t_fall = (2. * 10. / 9.81_f64).sqrt();
+        let events = stepper.events_out();
+        assert_eq!(events.len(), 3);
+        assert!((events[0].x - t_fall).abs() < 1.0E-8);
+        assert!((events[1].x - 2. * t_fall).abs() < 1.0E-8);
+        assert!((events[2].x - 2.5 * t_fall).abs() < 1.0E-8);
+        assert_eq!(events[2].action, EventAction::Terminate);
+
+        // The integration stops at the third bounce
+        assert!(*stepper.x_out().last().unwrap() <= events[2].x);
+        assert!(stepper.y_out().iter().all(|y| y[0] > -1.0E-8));
+    }
+
+    #[test]
+    fn test_events_dose_sparse() {
+        let mut stepper = Dop853::from_param(
+            Dosing,
+            0.,
+            20.,
+            0.,
+            Vector1::new(1.),
+            1e-10,
+            1e-10,
+            0.9,
+            0.,
+            0.333,
+            6.,
+            20.,
+            0.,
+            100000,
+            1000,
+            OutputType::Sparse,
+        );
+        stepper.integrate().unwrap();
+
+        let events = stepper.events_out();
+        assert_eq!(events.len(), 2);
+
+        // y = 0.5 at x = 10 ln(2)
+        assert_eq!(events[0].index, 1);
+        assert!((events[0].x - 10. * 2_f64.ln()).abs() < 1.0E-8);
+
+        assert_eq!(events[1].index, 0);
+        assert!((events[1].x - 10.).abs() < 1.0E-8);
+        assert!((events[1].y[0] - (-1_f64).exp()).abs() < 1.0E-8);
+
+        // The output holds the state before and after the dose
+        let x_out = stepper.x_out();
+        let i = x_out.iter().position(|x| *x == events[1].x).unwrap();
+        assert_eq!(x_out[i + 1], x_out[i]);
+        assert!((stepper.y_out()[i + 1][0] - stepper.y_out()[i][0] - 1.).abs() < 1.0E-12);
+
+        let y_end = stepper.y_out().last().unwrap()[0];
+        assert!((y_end - ((-1_f64).exp() + 1.) * (-1_f64).exp()).abs() < 1.0E-8);
+    }
 }
@@ -1,6 +1,6 @@
-//! Shared traits and structures for dopri5 and dop853.
+//! Shared traits and structures for dopri5, dop853 and rosenbrock.
 
-use nalgebra::Scalar;
+use nalgebra::{DMatrix, Scalar};
 use num_traits::{Float, FromPrimitive, NumCast, One, Zero};
 use simba::scalar::{ClosedAdd, ClosedDiv, ClosedMul, ClosedNeg, ClosedSub, SubsetOf};
 use std::fmt;
@@ -12,7 +12,7 @@ use thiserror::Error;
 /// internally to allow generic code.
 ///
 /// The type parameter V is a state vector. To have an easy start it is recommend to use [nalgebra] vectors.
-/// ```rust
+/// ```ignore
 /// // A predefined type for a vector (works from 1..6)
 /// type Precision = f64
 /// type State = Vector3<Precision>;
@@ -32,11 +32,55 @@ where
     fn solout(&mut self, _x: T, _y: &V, _dy: &V) -> bool {
         false
     }
+    /// Jacobian of the system with respect to y, used by the implicit solvers. Implementations
+    /// should fill `jac` and return true; the default returns false, in which case the Jacobian is
+    /// approximated with finite differences.
+    fn jacobian(&mut self, _x: T, _y: &V, _jac: &mut DMatrix<T>) -> bool {
+        false
+    }
+    /// Number of event functions evaluated by [System::events]. Default is 0, which disables
+    /// event detection.
+    fn n_events(&self) -> usize {
+        0
+    }
+    /// Event functions, evaluated at (x, y) into `g`. An event is triggered whenever one of them
+    /// changes sign during a step, and its location is then refined on the dense output.
+    fn events(&mut self, _x: T, _y: &V, _g: &mut [T]) {}
+    /// Called once the event `index` has been located at `x`, with `y` holding the state at that
+    /// point. The returned [EventAction] decides how the integration proceeds; changes made to `y`
+    /// are only taken into account with [EventAction::ModifyState].
+    fn on_event(&mut self, _index: usize, _x: T, _y: &mut V) -> EventAction {
+        EventAction::Terminate
+    }
+}
+
+/// What to do once an event has been located.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventAction {
+    /// Stops the integration at the event.
+    Terminate,
+    /// Only records the event and carries on with the integration.
+    Continue,
+    /// Restarts the integration at the event from the state modified by [System::on_event].
+    ModifyState,
+}
+
+/// An event located during the integration.
+#[derive(Debug, Clone)]
+pub struct EventRecord<T, V> {
+    /// Index of the event function that triggered it.
+    pub index: usize,
+    /// Location of the event.
+    pub x: T,
+    /// State at the event, before any modification.
+    pub y: V,
+    /// Action taken for this event.
+    pub action: EventAction,
 }
 
 /// A struct that holds the result of a solver/stepper run
 #[derive(Debug, Clone)]
-pub struct SolverResult<T, V>(Vec<T>, Vec<V>);
+pub struct SolverResult<T, V>(Vec<T>, Vec<V>, Vec<EventRecord<T, V>>);
 
 /// This trait combines several traits that are useful
 /// when writing generic code that shall work in f32 and f64
@@ -67,13 +111,18 @@ impl FloatNumber for f64 {}
 
 impl<T, V> SolverResult<T, V> {
     pub fn new(x: Vec<T>, y: Vec<V>) -> Self {
-        SolverResult { 0: x, 1: y }
+        SolverResult {
+            0: x,
+            1: y,
+            2: Vec::new(),
+        }
     }
 
     pub fn with_capacity(n: usize) -> Self {
         SolverResult {
             0: Vec::with_capacity(n),
             1: Vec::with_capacity(n),
+            2: Vec::new(),
         }
     }
 
@@ -85,12 +134,22 @@ impl<T, V> SolverResult<T, V> {
     pub fn append(&mut self, mut other: SolverResult<T, V>) {
         self.0.append(&mut other.0);
         self.1.append(&mut other.1);
+        self.2.append(&mut other.2);
+    }
+
+    pub fn push_event(&mut self, event: EventRecord<T, V>) {
+        self.2.push(event);
     }
 
     /// Returns a pair that contains references to the internal vectors
     pub fn get(&self) -> (&Vec<T>, &Vec<V>) {
         (&self.0, &self.1)
     }
+
+    /// Returns the events located during the integration, in the order they happened
+    pub fn events(&self) -> &Vec<EventRecord<T, V>> {
+        &self.2
+    }
 }
 
 /// default implementation starts with empty vectors for x and y
@@ -99,10 +158,75 @@ impl<T, V> Default for SolverResult<T, V> {
         Self {
             0: Default::default(),
             1: Default::default(),
+            2: Default::default(),
         }
     }
 }
 
+/// Locates the events whose functions changed sign between `g_old`, evaluated at `x_old`, and
+/// `g_new`, evaluated at `x_new`. Each crossing is refined by bisection, with `g_at` evaluating the
+/// event functions on the dense output. Returns the index of each event along with the end of its
+/// final bracket, so that the event function has already changed sign there, sorted by location.
+pub(crate) fn locate_events<T: FloatNumber>(
+    g_old: &[T],
+    g_new: &[T],
+    x_old: T,
+    x_new: T,
+    mut g_at: impl FnMut(T, &mut [T]),
+) -> Vec<(usize, T)> {
+    let crossed =
+        |old: T, new: T| old != T::zero() && (new == T::zero() || old.signum() != new.signum());
+
+    // A function that is zero at the start of the step, e.g. right after the state was modified
+    // by an event, takes its sign from just after it.
+    let mut g_start = g_old.to_vec();
+    let x_start = x_old + (x_new - x_old) * T::epsilon().sqrt();
+    if g_old.iter().any(|g| *g == T::zero()) {
+        g_at(x_start, &mut g_start);
+    }
+
+    let mut g = g_old.to_vec();
+    let tol = T::from(4.0).unwrap() * T::epsilon() * x_old.abs().max(x_new.abs()).max(T::one());
+
+    let mut located: Vec<(usize, T)> = g_old
+        .iter()
+        .zip(&g_start)
+        .map(|(&old, &start)| {
+            if old == T::zero() {
+                (start, x_start)
+            } else {
+                (old, x_old)
+            }
+        })
+        .zip(g_new)
+        .enumerate()
+        .filter(|(_, ((old, _), &new))| crossed(*old, new))
+        .map(|(index, ((old, mut a), _))| {
+            let mut b = x_new;
+            for _ in 0..100 {
+                if (b - a).abs() <= tol {
+                    break;
+                }
+                let mid = a + (b - a) / T::from(2.0).unwrap();
+                g_at(mid, &mut g);
+                if crossed(old, g[index]) {
+                    b = mid;
+                } else {
+                    a = mid;
+                }
+            }
+            (index, b)
+        })
+        .collect();
+
+    located.sort_by(|(_, a), (_, b)| {
+        ((*a - x_old).abs())
+            .partial_cmp(&(*b - x_old).abs())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    located
+}
+
 /// Enumeration of the types of the integration output.
 #[derive(PartialEq, Eq)]
 pub enum OutputType {
@@ -119,6 +243,8 @@ pub enum IntegrationError {
     StepSizeUnderflow { x: f64 },
     #[error("The problem seems to become stiff at x = {x}.")]
     StiffnessDetected { x: f64 },
+    #[error("Delays must be positive, but one is {delay}.")]
+    NonPositiveDelay { delay: f64 },
 }
 
 /// Contains some statistics of the integration.
@@ -269,6 +269,17 @@ where
         self.f.system(self.x, &self.y, &mut k[0]);
         self.stats.num_eval += 1;
 
+        // Event functions
+        let n_events = self.f.n_events();
+        let mut g_old = vec![T::zero(); n_events];
+        let mut g_new = vec![T::zero(); n_events];
+        if n_events > 0 {
+            self.f.events(self.x, &self.y, &mut g_old);
+            self.stats.num_eval += 1;
+        }
+        // Events are located on the dense output, so it's needed even for a sparse output
+        let dense = self.out_type == OutputType::Dense || n_events > 0;
+
         // Main loop
         while !last {
             // Check if step number is within allowed range
@@ -314,7 +325,7 @@ where
             self.stats.num_eval += 6;
 
             // Prepare dense output
-            if self.out_type == OutputType::Dense {
+            if dense {
                 self.rcont[4] = (&k[0] * dopri54::d::<T>(1)
                     + &k[2] * dopri54::d::<T>(3)
                     + &k[3] * dopri54::d::<T>(4)
@@ -376,7 +387,7 @@ where
                 }
 
                 // Prepare dense output
-                if self.out_type == OutputType::Dense {
+                if dense {
                     let h = self.h;
 
                     let ydiff = &y_next - &self.y;
@@ -393,8 +404,35 @@ where
                 self.x += self.h;
                 self.h_old = self.h;
 
+                let event = if n_events > 0 {
+                    self.detect_events(&mut g_old, &mut g_new)
+                } else {
+                    None
+                };
+                if event.is_some() {
+                    // The step was truncated at the event
+                    y_next = self.y.clone();
+                }
+
                 self.solution_output(y_next, &k);
 
+                if let Some((action, y_event)) = event {
+                    if action == EventAction::Terminate {
+                        self.h_old = posneg * h_new;
+                        return Ok(self.stats);
+                    }
+
+                    // Restart from the modified state
+                    self.y = y_event;
+                    self.f.system(self.x, &self.y, &mut k[0]);
+                    self.f.events(self.x, &self.y, &mut g_old);
+                    self.stats.num_eval += 2;
+                    if self.out_type == OutputType::Sparse {
+                        self.results.push(self.x, self.y.clone());
+                    }
+                    last = false;
+                }
+
                 if self
                     .f
                     .solout(self.x, self.results.get().1.last().unwrap(), &k[0])
@@ -418,18 +456,50 @@ where
         Ok(self.stats)
     }
 
+    /// Looks for events in the last accepted step. When one of them terminates the integration or
+    /// modifies the state, the step is truncated at its location and the action is returned along
+    /// with the state to restart from.
+    fn detect_events(
+        &mut self,
+        g_old: &mut [T],
+        g_new: &mut [T],
+    ) -> Option<(EventAction, OVector<T, D>)> {
+        self.f.events(self.x, &self.y, g_new);
+        self.stats.num_eval += 1;
+
+        let (x_old, h_old) = (self.x_old, self.h_old);
+        let rcont = &self.rcont;
+        let f = &mut self.f;
+        let located = locate_events(g_old, g_new, x_old, self.x, |x, g| {
+            f.events(x, &dense_output(rcont, x_old, h_old, x), g)
+        });
+        g_old.copy_from_slice(g_new);
+
+        for (index, x) in located {
+            let y = dense_output(&self.rcont, x_old, h_old, x);
+            let mut y_event = y.clone();
+            let action = self.f.on_event(index, x, &mut y_event);
+            self.results.push_event(EventRecord {
+                index,
+                x,
+                y: y.clone(),
+                action,
+            });
+
+            if action != EventAction::Continue {
+                self.x = x;
+                self.y = y;
+                return Some((action, y_event));
+            }
+        }
+        None
+    }
+
     fn solution_output(&mut self, y_next: OVector<T, D>, _k: &[OVector<T, D>]) {
         if self.out_type == OutputType::Dense {
             while self.xd.abs() <= self.x.abs() {
                 if self.x_old.abs() <= self.xd.abs() && self.x.abs() >= self.xd.abs() {
-                    let theta = (self.xd - self.x_old) / self.h_old;
-                    let theta1 = T::one() - theta;
-                    let y_out = &self.rcont[0]
-                        + (&self.rcont[1]
-                            + (&self.rcont[2]
-                                + (&self.rcont[3] + &self.rcont[4] * theta1) * theta)
-                                * theta1)
-                            * theta;
+                    let y_out = dense_output(&self.rcont, self.x_old, self.h_old, self.xd);
                     self.results.push(self.xd, y_out);
                     self.xd += self.dx;
                 }
@@ -449,10 +519,20 @@ where
         &self.results.get().1
     }
 
+    /// Getter for the events located during the integration.
+    pub fn events_out(&self) -> &Vec<EventRecord<T, OVector<T, D>>> {
+        self.results.events()
+    }
+
     /// Getter for the results type, a pair of independent and dependent variables
     pub fn results(&self) -> &SolverResult<T, OVector<T, D>> {
         &self.results
     }
+
+    /// Getter for the system, as left by the integration
+    pub fn system(&self) -> &F {
+        &self.f
+    }
 }
 
 impl<T, D: Dim, F> Into<SolverResult<T, OVector<T, D>>> for Dopri5<T, OVector<T, D>, F>
@@ -466,6 +546,18 @@ where
     }
 }
 
+/// Evaluates the continuous extension of the last accepted step at `x`.
+fn dense_output<T, D: Dim>(rcont: &[OVector<T, D>; 5], x_old: T, h_old: T, x: T) -> OVector<T, D>
+where
+    T: FloatNumber,
+    DefaultAllocator: Allocator<T, D>,
+{
+    let theta = (x - x_old) / h_old;
+    let theta1 = T::one() - theta;
+    &rcont[0]
+        + (&rcont[1] + (&rcont[2] + (&rcont[3] + &rcont[4] * theta1) * theta) * theta1) * theta
+}
+
 fn sign<T: FloatNumber>(a: T, b: T) -> T {
     if b > T::zero() {
         a.abs()
@@ -486,7 +578,7 @@ mod tests {
     where
         DefaultAllocator: Allocator<f64, D>,
     {
-        fn system(&self, x: f64, y: &OVector<f64, D>, dy: &mut OVector<f64, D>) {
+        fn system(&mut self, x: f64, y: &OVector<f64, D>, dy: &mut OVector<f64, D>) {
             dy[0] = (5. * x * x - y[0]) / (x + y[0]).exp();
         }
 
@@ -507,4 +599,47 @@ mod tests {
         let out = stepper.y_out();
         assert!((&out[5][0] - 0.913059243).abs() < 1.0E-9);
     }
+
+    // Exponential decay with a dose at x = 10
+    struct Dosing;
+    impl System<f64, Vector1<f64>> for Dosing {
+        fn system(&mut self, _x: f64, y: &Vector1<f64>, dy: &mut Vector1<f64>) {
+            dy[0] = -0.1 * y[0];
+        }
+
+        fn n_events(&self) -> usize {
+            1
+        }
+
+        fn events(&mut self, x: f64, _y: &Vector1<f64>, g: &mut [f64]) {
+            g[0] = x - 10.0;
+        }
+
+        fn on_event(&mut self, _index: usize, _x: f64, y: &mut Vector1<f64>) -> EventAction {
+            y[0] += 1.0;
+            EventAction::ModifyState
+        }
+    }
+
+    #[test]
+    fn test_events_dose() {
+        let mut stepper = Dopri5::new(Dosing, 0., 20., 0.5, Vector1::new(1.), 1e-10, 1e-10);
+        stepper.integrate().unwrap();
+
+        let events = stepper.events_out();
+        assert_eq!(events.len(), 1);
+        assert!((events[0].x - 10.).abs() < 1.0E-8);
+
+        let x_out = stepper.x_out();
+        let y_out = stepper.y_out();
+        assert_eq!(x_out.len(), 41);
+        for (x, y) in x_out.iter().zip(y_out) {
+            let expected = if *x <= events[0].x {
+                (-0.1 * x).exp()
+            } else {
+                (-0.1 * x).exp() + (-0.1 * (x - 10.)).exp()
+            };
+            assert!((y[0] - expected).abs() < 1.0E-7);
+        }
+    }
 }
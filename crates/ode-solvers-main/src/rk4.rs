@@ -156,6 +156,11 @@ where
     pub fn results(&self) -> &SolverResult<T, OVector<T, D>> {
         &self.results
     }
+
+    /// Getter for the system, as left by the integration
+    pub fn system(&self) -> &F {
+        &self.f
+    }
 }
 
 impl<T, D: Dim, F> Into<SolverResult<T, OVector<T, D>>> for Rk4<T, OVector<T, D>, F>
@@ -180,7 +185,7 @@ mod tests {
     where
         DefaultAllocator: Allocator<f64, D>,
     {
-        fn system(&self, x: f64, y: &OVector<f64, D>, dy: &mut OVector<f64, D>) {
+        fn system(&mut self, x: f64, y: &OVector<f64, D>, dy: &mut OVector<f64, D>) {
             dy[0] = (x - y[0]) / 2.;
         }
     }
@@ -190,7 +195,7 @@ mod tests {
     where
         DefaultAllocator: Allocator<f64, D>,
     {
-        fn system(&self, x: f64, y: &OVector<f64, D>, dy: &mut OVector<f64, D>) {
+        fn system(&mut self, x: f64, y: &OVector<f64, D>, dy: &mut OVector<f64, D>) {
             dy[0] = -2. * x - y[0];
         }
     }
@@ -200,7 +205,7 @@ mod tests {
     where
         DefaultAllocator: Allocator<f64, D>,
     {
-        fn system(&self, x: f64, y: &OVector<f64, D>, dy: &mut OVector<f64, D>) {
+        fn system(&mut self, x: f64, y: &OVector<f64, D>, dy: &mut OVector<f64, D>) {
             dy[0] = (5. * x * x - y[0]) / (x + y[0]).exp();
         }
     }
@@ -211,7 +216,7 @@ mod tests {
     where
         DefaultAllocator: Allocator<f64, D>,
     {
-        fn system(&self, x: f64, y: &OVector<f64, D>, dy: &mut OVector<f64, D>) {
+        fn system(&mut self, x: f64, y: &OVector<f64, D>, dy: &mut OVector<f64, D>) {
             dy[0] = (5. * x * x - y[0]) / (x + y[0]).exp();
         }
 
@@ -0,0 +1,644 @@
+//! Linearly implicit Rosenbrock method of order 2(3) for stiff problems, with dense output of order 2.
+//!
+//! The coefficients are the ones of the Rosenbrock-W pair by Shampine and Reichelt, also used by
+//! MATLAB's `ode23s`. The Jacobian is obtained from [System::jacobian] when the system provides it,
+//! and approximated with forward finite differences otherwise.
+
+use crate::controller::Controller;
+use crate::dop_shared::*;
+
+use nalgebra::{allocator::Allocator, DMatrix, DefaultAllocator, Dim, OVector};
+
+trait DefaultController<T: FloatNumber> {
+    fn default(x: T, x_end: T) -> Self;
+}
+
+impl<T: FloatNumber> DefaultController<T> for Controller<T> {
+    fn default(x: T, x_end: T) -> Self {
+        Controller::new(
+            T::one() / T::from(3.0).unwrap(),
+            T::zero(),
+            T::from(5.0).unwrap(),
+            T::from(0.2).unwrap(),
+            x_end - x,
+            T::from(0.8).unwrap(),
+            sign(T::one(), x_end - x),
+        )
+    }
+}
+
+/// Structure containing the parameters for the numerical integration.
+pub struct Rosenbrock23<T, V, F>
+where
+    T: FloatNumber,
+    F: System<T, V>,
+{
+    f: F,
+    x: T,
+    x_old: T,
+    x_end: T,
+    xd: T,
+    dx: T,
+    y: V,
+    y_old: V,
+    rtol: T,
+    atol: T,
+    results: SolverResult<T, V>,
+    uround: T,
+    h: T,
+    h_old: T,
+    n_max: u32,
+    controller: Controller<T>,
+    out_type: OutputType,
+    rcont: [V; 2],
+    stats: Stats,
+}
+
+impl<T, D: Dim, F> Rosenbrock23<T, OVector<T, D>, F>
+where
+    f64: From<T>,
+    T: FloatNumber,
+    F: System<T, OVector<T, D>>,
+    OVector<T, D>: std::ops::Mul<T, Output = OVector<T, D>>,
+    DefaultAllocator: Allocator<T, D>,
+{
+    /// Default initializer for the structure
+    ///
+    /// # Arguments
+    ///
+    /// * `f`       - Structure implementing the System<V> trait
+    /// * `x`       - Initial value of the independent variable (usually time)
+    /// * `x_end`   - Final value of the independent variable
+    /// * `dx`      - Increment in the dense output. This argument has no effect if the output type is Sparse
+    /// * `y`       - Initial value of the dependent variable(s)
+    /// * `rtol`    - Relative tolerance used in the computation of the adaptive step size
+    /// * `atol`    - Absolute tolerance used in the computation of the adaptive step size
+    ///
+    pub fn new(f: F, x: T, x_end: T, dx: T, y: OVector<T, D>, rtol: T, atol: T) -> Self {
+        let (rows, cols) = y.shape_generic();
+        Self {
+            f,
+            x,
+            xd: x,
+            dx,
+            x_old: x,
+            x_end,
+            y_old: y.clone(),
+            y,
+            rtol,
+            atol,
+            results: SolverResult::default(),
+            uround: T::epsilon(),
+            h: T::zero(),
+            h_old: T::zero(),
+            n_max: 100000,
+            controller: Controller::default(x, x_end),
+            out_type: OutputType::Dense,
+            rcont: [
+                OVector::zeros_generic(rows, cols),
+                OVector::zeros_generic(rows, cols),
+            ],
+            stats: Stats::new(),
+        }
+    }
+
+    /// Advanced initializer for the structure.
+    ///
+    /// # Arguments
+    ///
+    /// * `f`       - Structure implementing the System<V> trait
+    /// * `x`       - Initial value of the independent variable (usually time)
+    /// * `x_end`   - Final value of the independent variable
+    /// * `dx`      - Increment in the dense output. This argument has no effect if the output type is Sparse
+    /// * `y`       - Initial value of the dependent variable(s)
+    /// * `rtol`    - Relative tolerance used in the computation of the adaptive step size
+    /// * `atol`    - Absolute tolerance used in the computation of the adaptive step size
+    /// * `safety_factor`   - Safety factor used in the computation of the adaptive step size. Default is 0.8
+    /// * `fac_min` - Minimum factor between two successive steps. Default is 0.2
+    /// * `fac_max` - Maximum factor between two successive steps. Default is 5.0
+    /// * `h_max`   - Maximum step size. Default is `x_end-x`
+    /// * `h`       - Initial value of the step size. If h = 0.0, the intial value of h is computed automatically
+    /// * `n_max`   - Maximum number of iterations. Default is 100000
+    /// * `out_type`    - Type of the output. Must be a variant of the OutputType enum. Default is Dense
+    ///
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_param(
+        f: F,
+        x: T,
+        x_end: T,
+        dx: T,
+        y: OVector<T, D>,
+        rtol: T,
+        atol: T,
+        safety_factor: T,
+        fac_min: T,
+        fac_max: T,
+        h_max: T,
+        h: T,
+        n_max: u32,
+        out_type: OutputType,
+    ) -> Self {
+        let (rows, cols) = y.shape_generic();
+        Self {
+            f,
+            x,
+            xd: x,
+            x_old: x,
+            x_end,
+            dx,
+            y_old: y.clone(),
+            y,
+            rtol,
+            atol,
+            results: SolverResult::default(),
+            uround: T::epsilon(),
+            h,
+            h_old: T::zero(),
+            n_max,
+            controller: Controller::new(
+                T::one() / T::from(3.0).unwrap(),
+                T::zero(),
+                fac_max,
+                fac_min,
+                h_max,
+                safety_factor,
+                sign(T::one(), x_end - x),
+            ),
+            out_type,
+            rcont: [
+                OVector::zeros_generic(rows, cols),
+                OVector::zeros_generic(rows, cols),
+            ],
+            stats: Stats::new(),
+        }
+    }
+
+    /// Compute the initial stepsize
+    fn hinit(&mut self, f0: &OVector<T, D>) -> T {
+        let (rows, cols) = self.y.shape_generic();
+        let posneg = sign(T::one(), self.x_end - self.x);
+
+        // Compute the norm of y0 and f0
+        let dim = rows.value();
+        let mut d0 = T::zero();
+        let mut d1 = T::zero();
+        for i in 0..dim {
+            let sci = self.atol + self.y[i].abs() * self.rtol;
+            d0 += (self.y[i] / sci) * (self.y[i] / sci);
+            d1 += (f0[i] / sci) * (f0[i] / sci);
+        }
+
+        // Compute h0
+        let tol = T::from(1.0E-10).unwrap();
+        let mut h0 = if d0 < tol || d1 < tol {
+            T::from(1.0E-6).unwrap()
+        } else {
+            T::from(0.01).unwrap() * (d0 / d1).sqrt()
+        };
+
+        h0 = h0.min(self.controller.h_max());
+        h0 = sign(h0, posneg);
+
+        let y1 = &self.y + f0 * h0;
+        let mut f1 = OVector::zeros_generic(rows, cols);
+        self.f.system(self.x + h0, &y1, &mut f1);
+
+        // Compute the norm of f1-f0 divided by h0
+        let mut d2 = T::zero();
+        for i in 0..dim {
+            let sci = self.atol + self.y[i].abs() * self.rtol;
+            d2 += ((f1[i] - f0[i]) / sci) * ((f1[i] - f0[i]) / sci);
+        }
+        d2 = d2.sqrt() / h0;
+
+        let h1 = if d1.sqrt().max(d2.abs()) <= T::from(1.0E-15).unwrap() {
+            T::from(1.0E-6_f64)
+                .unwrap()
+                .max(h0.abs() * T::from(1.0E-3).unwrap())
+        } else {
+            (T::from(0.01).unwrap() / (d1.sqrt().max(d2))).powf(T::one() / T::from(3.0).unwrap())
+        };
+
+        sign(
+            (T::from(100.0).unwrap() * h0.abs()).min(h1.min(self.controller.h_max())),
+            posneg,
+        )
+    }
+
+    /// Evaluates the Jacobian of the system with respect to y, and the partial derivative with
+    /// respect to x, at the current point. Both fall back to forward differences when the system
+    /// does not provide them.
+    fn jacobian(&mut self, f0: &OVector<T, D>, jac: &mut DMatrix<T>, dfdx: &mut OVector<T, D>) {
+        let (rows, cols) = self.y.shape_generic();
+        let dim = rows.value();
+        let mut f_pert = OVector::zeros_generic(rows, cols);
+
+        if !self.f.jacobian(self.x, &self.y, jac) {
+            let mut y_pert = self.y.clone();
+            for j in 0..dim {
+                let y_j = self.y[j];
+                let delta = (self.uround * y_j.abs().max(T::from(1.0E-5).unwrap())).sqrt();
+                y_pert[j] = y_j + delta;
+                self.f.system(self.x, &y_pert, &mut f_pert);
+                for i in 0..dim {
+                    jac[(i, j)] = (f_pert[i] - f0[i]) / delta;
+                }
+                y_pert[j] = y_j;
+            }
+            self.stats.num_eval += dim as u32;
+        }
+
+        let posneg = sign(T::one(), self.x_end - self.x);
+        let mut delta =
+            (self.uround.sqrt() * self.x.abs().max((self.x + self.h).abs())).min(self.h.abs());
+        if delta == T::zero() {
+            delta = self.uround.sqrt();
+        }
+        delta = sign(delta, posneg);
+
+        self.f.system(self.x + delta, &self.y, &mut f_pert);
+        self.stats.num_eval += 1;
+        *dfdx = (f_pert - f0) * (T::one() / delta);
+    }
+
+    /// Core integration method.
+    pub fn integrate(&mut self) -> Result<Stats, IntegrationError> {
+        // Initilization
+        let (rows, cols) = self.y.shape_generic();
+        self.x_old = self.x;
+        let mut n_step = 0;
+        let mut last = false;
+        let mut h_new = T::zero();
+        let dim = rows.value();
+        let posneg = sign(T::one(), self.x_end - self.x);
+
+        let d = gamma::<T>();
+        let e32 = T::from(6.0).unwrap() + T::from(2.0).unwrap().sqrt();
+
+        let mut f0 = OVector::zeros_generic(rows, cols);
+        self.f.system(self.x, &self.y, &mut f0);
+        self.stats.num_eval += 1;
+
+        if self.h == T::zero() {
+            self.h = self.hinit(&f0);
+            self.stats.num_eval += 1;
+        }
+        self.h_old = self.h;
+
+        // Save initial values
+        if self.out_type == OutputType::Sparse {
+            self.results.push(self.x, self.y.clone());
+        }
+
+        let mut jac = DMatrix::zeros(dim, dim);
+        let mut dfdx = OVector::zeros_generic(rows, cols);
+        let mut jac_is_current = false;
+
+        // Main loop
+        while !last {
+            // Check if step number is within allowed range
+            if n_step > self.n_max {
+                self.h_old = self.h;
+                return Err(IntegrationError::MaxNumStepReached {
+                    x: f64::from(self.x),
+                    n_step,
+                });
+            }
+
+            // Check for step size underflow
+            if T::from(0.1).unwrap() * self.h.abs() <= self.uround * self.x.abs() {
+                self.h_old = self.h;
+                return Err(IntegrationError::StepSizeUnderflow {
+                    x: f64::from(self.x),
+                });
+            }
+
+            // Check if it's the last iteration
+            if (self.x + T::from(1.01).unwrap() * self.h - self.x_end) * posneg > T::zero() {
+                self.h = self.x_end - self.x;
+                last = true;
+            }
+            n_step += 1;
+
+            // The Jacobian only changes after a step is accepted
+            if !jac_is_current {
+                self.jacobian(&f0, &mut jac, &mut dfdx);
+                jac_is_current = true;
+            }
+
+            let h = self.h;
+            let hd = h * d;
+
+            // Iteration matrix W = I - h*d*J
+            let mut w = DMatrix::zeros(dim, dim);
+            for i in 0..dim {
+                for j in 0..dim {
+                    w[(i, j)] = -hd * jac[(i, j)];
+                }
+                w[(i, i)] += T::one();
+            }
+            let Some(w) = Lu::factor(w) else {
+                // Singular iteration matrix, retry with a smaller step
+                last = false;
+                self.h = h * T::from(0.5).unwrap();
+                continue;
+            };
+
+            // 3 Stages
+            let mut k1 = &f0 + &dfdx * hd;
+            w.solve(&mut k1);
+
+            let y_mid = &self.y + &k1 * (h * T::from(0.5).unwrap());
+            let mut f1 = OVector::zeros_generic(rows, cols);
+            self.f
+                .system(self.x + h * T::from(0.5).unwrap(), &y_mid, &mut f1);
+
+            let mut k2 = &f1 - &k1;
+            w.solve(&mut k2);
+            k2 += &k1;
+
+            let y_next = &self.y + &k2 * h;
+            let mut f2 = OVector::zeros_generic(rows, cols);
+            self.f.system(self.x + h, &y_next, &mut f2);
+
+            let mut k3 = &f2 - (&k2 - &f1) * e32 - (&k1 - &f0) * T::from(2.0).unwrap() + &dfdx * hd;
+            w.solve(&mut k3);
+            self.stats.num_eval += 2;
+
+            // Compute error
+            let err_est = (&k1 - &k2 * T::from(2.0).unwrap() + &k3) * (h / T::from(6.0).unwrap());
+            let mut err = T::zero();
+            for i in 0..dim {
+                let sc_i: T = self.atol + self.y[i].abs().max(y_next[i].abs()) * self.rtol;
+                err += (err_est[i] / sc_i) * (err_est[i] / sc_i);
+            }
+            err = (err / T::from(dim).unwrap()).sqrt();
+
+            // Step size control
+            if err.is_finite() && self.controller.accept(err, self.h, &mut h_new) {
+                self.stats.accepted_steps += 1;
+
+                // Prepare dense output
+                self.rcont = [k1, k2];
+                self.y_old = std::mem::replace(&mut self.y, y_next.clone());
+                self.x_old = self.x;
+                self.x += self.h;
+                self.h_old = self.h;
+
+                f0 = f2;
+                jac_is_current = false;
+
+                self.solution_output(y_next);
+
+                if self.f.solout(self.x, &self.y, &f0) {
+                    last = true;
+                }
+
+                // Normal exit
+                if last {
+                    self.h_old = posneg * h_new;
+                    return Ok(self.stats);
+                }
+            } else {
+                if !err.is_finite() {
+                    h_new = h * T::from(0.2).unwrap();
+                }
+                last = false;
+                if self.stats.accepted_steps >= 1 {
+                    self.stats.rejected_steps += 1;
+                }
+            }
+            self.h = h_new;
+        }
+        Ok(self.stats)
+    }
+
+    fn solution_output(&mut self, y_next: OVector<T, D>) {
+        if self.out_type == OutputType::Dense {
+            let d = gamma::<T>();
+            let den = T::one() - T::from(2.0).unwrap() * d;
+            while self.xd.abs() <= self.x.abs() {
+                let s = (self.xd - self.x_old) / self.h_old;
+                let y_out = &self.y_old
+                    + (&self.rcont[0] * (s * (T::one() - s) / den)
+                        + &self.rcont[1] * (s * (s - T::from(2.0).unwrap() * d) / den))
+                        * self.h_old;
+                self.results.push(self.xd, y_out);
+                self.xd += self.dx;
+            }
+        } else {
+            self.results.push(self.x, y_next)
+        }
+    }
+
+    /// Getter for the independent variable's output.
+    pub fn x_out(&self) -> &Vec<T> {
+        self.results.get().0
+    }
+
+    /// Getter for the dependent variables' output.
+    pub fn y_out(&self) -> &Vec<OVector<T, D>> {
+        self.results.get().1
+    }
+
+    /// Getter for the results type, a pair of independent and dependent variables
+    pub fn results(&self) -> &SolverResult<T, OVector<T, D>> {
+        &self.results
+    }
+
+    /// Getter for the system, as left by the integration
+    pub fn system(&self) -> &F {
+        &self.f
+    }
+}
+
+impl<T, D: Dim, F> From<Rosenbrock23<T, OVector<T, D>, F>> for SolverResult<T, OVector<T, D>>
+where
+    T: FloatNumber,
+    F: System<T, OVector<T, D>>,
+    DefaultAllocator: Allocator<T, D>,
+{
+    fn from(stepper: Rosenbrock23<T, OVector<T, D>, F>) -> Self {
+        stepper.results
+    }
+}
+
+/// Diagonal coefficient of the method, d = 1 / (2 + sqrt(2)).
+fn gamma<T: FloatNumber>() -> T {
+    T::one() / (T::from(2.0).unwrap() + T::from(2.0).unwrap().sqrt())
+}
+
+fn sign<T: FloatNumber>(a: T, b: T) -> T {
+    if b > T::zero() {
+        a.abs()
+    } else {
+        -a.abs()
+    }
+}
+
+/// LU decomposition with partial pivoting of the iteration matrix.
+struct Lu<T> {
+    lu: DMatrix<T>,
+    perm: Vec<usize>,
+}
+
+impl<T: FloatNumber> Lu<T> {
+    /// Factorizes `a`, returning `None` if it is singular.
+    fn factor(mut a: DMatrix<T>) -> Option<Self> {
+        let n = a.nrows();
+        let mut perm: Vec<usize> = (0..n).collect();
+
+        for k in 0..n {
+            let mut p = k;
+            for i in k + 1..n {
+                if a[(i, k)].abs() > a[(p, k)].abs() {
+                    p = i;
+                }
+            }
+
+            let pivot = a[(p, k)];
+            if pivot == T::zero() || !pivot.is_finite() {
+                return None;
+            }
+            if p != k {
+                a.swap_rows(p, k);
+                perm.swap(p, k);
+            }
+
+            for i in k + 1..n {
+                let factor = a[(i, k)] / pivot;
+                a[(i, k)] = factor;
+                for j in k + 1..n {
+                    let a_kj = a[(k, j)];
+                    a[(i, j)] -= factor * a_kj;
+                }
+            }
+        }
+
+        Some(Self { lu: a, perm })
+    }
+
+    /// Solves `A x = b` in place.
+    fn solve<D: Dim>(&self, b: &mut OVector<T, D>)
+    where
+        DefaultAllocator: Allocator<T, D>,
+    {
+        let n = self.perm.len();
+        let mut x: Vec<T> = self.perm.iter().map(|&p| b[p]).collect();
+
+        for i in 0..n {
+            for j in 0..i {
+                let x_j = x[j];
+                x[i] -= self.lu[(i, j)] * x_j;
+            }
+        }
+
+        for i in (0..n).rev() {
+            for j in i + 1..n {
+                let x_j = x[j];
+                x[i] -= self.lu[(i, j)] * x_j;
+            }
+            x[i] /= self.lu[(i, i)];
+        }
+
+        for (i, x_i) in x.into_iter().enumerate() {
+            b[i] = x_i;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DVector, System, Vector1, Vector3};
+
+    // Chemical reaction of Robertson, a classic stiff problem.
+    struct Robertson;
+    impl System<f64, Vector3<f64>> for Robertson {
+        fn system(&mut self, _x: f64, y: &Vector3<f64>, dy: &mut Vector3<f64>) {
+            dy[0] = -0.04 * y[0] + 1.0E4 * y[1] * y[2];
+            dy[1] = 0.04 * y[0] - 1.0E4 * y[1] * y[2] - 3.0E7 * y[1] * y[1];
+            dy[2] = 3.0E7 * y[1] * y[1];
+        }
+    }
+
+    // y' = -50 (y - cos(x)), with an analytic Jacobian
+    struct Curtiss {
+        jacobian_calls: u32,
+    }
+    impl System<f64, DVector<f64>> for Curtiss {
+        fn system(&mut self, x: f64, y: &DVector<f64>, dy: &mut DVector<f64>) {
+            dy[0] = -50.0 * (y[0] - x.cos());
+        }
+
+        fn jacobian(&mut self, _x: f64, _y: &DVector<f64>, jac: &mut DMatrix<f64>) -> bool {
+            self.jacobian_calls += 1;
+            jac[(0, 0)] = -50.0;
+            true
+        }
+    }
+
+    #[test]
+    fn test_integrate_robertson() {
+        let mut stepper = Rosenbrock23::new(
+            Robertson,
+            0.,
+            40.,
+            40.,
+            Vector3::new(1., 0., 0.),
+            1e-6,
+            1e-10,
+        );
+        let stats = stepper.integrate().unwrap();
+
+        // Reference solution at x = 40 by Hairer & Wanner
+        let y = stepper.y_out().last().unwrap();
+        assert!((y[0] - 0.7158270687).abs() < 1.0E-4);
+        assert!((y[1] - 9.185534765E-6).abs() < 1.0E-8);
+        assert!((y[2] - 0.2841637457).abs() < 1.0E-4);
+
+        // Explicit methods need tens of thousands of steps here
+        assert!(stats.accepted_steps < 1000);
+    }
+
+    #[test]
+    fn test_integrate_analytic_jacobian() {
+        let system = Curtiss { jacobian_calls: 0 };
+        let mut stepper = Rosenbrock23::new(
+            system,
+            0.,
+            1.5,
+            0.5,
+            DVector::from_element(1, 0.),
+            1e-6,
+            1e-8,
+        );
+        stepper.integrate().unwrap();
+
+        assert_eq!(stepper.x_out().len(), 4);
+        let exact =
+            |x: f64| 2500. / 2501. * (x.cos() + x.sin() / 50.) - 2500. / 2501. * (-50. * x).exp();
+        for (x, y) in stepper.x_out().iter().zip(stepper.y_out()) {
+            assert!((y[0] - exact(*x)).abs() < 1.0E-4);
+        }
+        assert!(stepper.f.jacobian_calls > 0);
+    }
+
+    #[test]
+    fn test_dense_output_matches_steps() {
+        struct Decay;
+        impl System<f64, Vector1<f64>> for Decay {
+            fn system(&mut self, _x: f64, y: &Vector1<f64>, dy: &mut Vector1<f64>) {
+                dy[0] = -y[0];
+            }
+        }
+
+        let mut stepper = Rosenbrock23::new(Decay, 0., 2., 0.25, Vector1::new(1.), 1e-8, 1e-10);
+        stepper.integrate().unwrap();
+
+        assert_eq!(stepper.x_out().len(), 9);
+        for (x, y) in stepper.x_out().iter().zip(stepper.y_out()) {
+            assert!((y[0] - (-x).exp()).abs() < 1.0E-5);
+        }
+    }
+}
@@ -0,0 +1,3 @@
+//! Conversion of models written in other formats into `odeir` models.
+
+pub mod sbml;
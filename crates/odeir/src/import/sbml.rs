@@ -0,0 +1,968 @@
+//! Import of SBML (Levels 2 and 3) models.
+//!
+//! Species become populations, parameters and compartments become constants
+//! and kinetic laws are broken down into composite arguments, one per MathML
+//! `<apply>`. Each species then gets an equation summing the rates of the
+//! reactions it takes part in, weighted by its stoichiometry. Constructs that
+//! have no counterpart in an [`OdeModel`] are skipped and reported as
+//! [`Diagnostic`]s instead of failing the whole import.
+
+use std::collections::BTreeSet;
+
+use roxmltree::{Document, Node};
+
+use crate::{
+    models::{ode::Metadata, ode::OdeModel, CompositionStyle},
+    Argument, Component, Equation, Map, Position,
+};
+
+const COLUMN_WIDTH: f64 = 250.0;
+const ROW_HEIGHT: f64 = 100.0;
+const MARGIN: f64 = 50.0;
+
+#[derive(Debug)]
+pub enum SbmlError {
+    Xml(roxmltree::Error),
+    NotSbml,
+    MissingModel,
+}
+
+impl std::fmt::Display for SbmlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SbmlError::Xml(err) => write!(f, "Invalid XML: {err}"),
+            SbmlError::NotSbml => write!(f, "The document is not an SBML file"),
+            SbmlError::MissingModel => write!(f, "The SBML file does not contain a model"),
+        }
+    }
+}
+
+impl std::error::Error for SbmlError {}
+
+impl From<roxmltree::Error> for SbmlError {
+    fn from(value: roxmltree::Error) -> Self {
+        SbmlError::Xml(value)
+    }
+}
+
+/// Something in the SBML file that could not be represented and was left out
+/// of the imported model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// The id of the offending SBML element (or of the closest one enclosing
+    /// it), or its tag when there is none.
+    pub element: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}`: {}", self.element, self.message)
+    }
+}
+
+#[derive(Debug)]
+pub struct SbmlImport {
+    pub model: OdeModel,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Builds an [`OdeModel`] out of an SBML document. Only malformed documents
+/// are errors; everything else that can't be imported ends up in
+/// [`SbmlImport::diagnostics`].
+pub fn import_sbml(xml: &str) -> Result<SbmlImport, SbmlError> {
+    let document = Document::parse(xml)?;
+
+    let root = document.root_element();
+    if !root.has_tag_name("sbml") {
+        return Err(SbmlError::NotSbml);
+    }
+    let sbml_model = child(root, "model").ok_or(SbmlError::MissingModel)?;
+
+    let name = sbml_model
+        .attribute("name")
+        .or_else(|| sbml_model.attribute("id"))
+        .unwrap_or_default()
+        .to_owned();
+
+    let mut importer = Importer {
+        model: OdeModel::new(
+            name,
+            Metadata {
+                start_time: 0.0,
+                delta_time: 0.01,
+                end_time: 50.0,
+                time_unit: None,
+            },
+        ),
+        diagnostics: Vec::new(),
+        taken_names: BTreeSet::new(),
+        compartments: Map::new(),
+        species: Map::new(),
+        literals: Vec::new(),
+        pending: Vec::new(),
+        fluxes: Map::new(),
+    };
+
+    importer.read(sbml_model);
+    importer.layout();
+
+    Ok(SbmlImport {
+        model: importer.model,
+        diagnostics: importer.diagnostics,
+    })
+}
+
+struct Species {
+    compartment: Option<String>,
+    has_only_substance_units: bool,
+    /// Boundary and constant species are not changed by reactions.
+    fixed: bool,
+}
+
+struct Importer {
+    model: OdeModel,
+    diagnostics: Vec<Diagnostic>,
+    taken_names: BTreeSet<String>,
+    /// Compartment sizes. Compartments are only added to the model once
+    /// something refers to them.
+    compartments: Map<String, f64>,
+    species: Map<String, Species>,
+    literals: Vec<(f64, String)>,
+    /// Arguments built for the expression currently being converted, which
+    /// are only committed to the model if the whole expression is supported.
+    pending: Vec<Argument>,
+    /// For each species, the rate arguments that change it and their
+    /// stoichiometry.
+    fluxes: Map<String, Vec<(String, f64)>>,
+}
+
+/// Local parameters of a kinetic law, mapped to the constants they were
+/// renamed to.
+type Scope = Map<String, String>;
+
+impl Importer {
+    fn read(&mut self, sbml_model: Node) {
+        // SBML ids share a single namespace, so they are reserved upfront to
+        // keep generated names from clashing with them.
+        self.taken_names.extend(
+            sbml_model
+                .descendants()
+                .filter(|node| node.tag_name().namespace() != Some(MATHML_NS))
+                .filter_map(|node| node.attribute("id"))
+                .map(str::to_owned),
+        );
+
+        // SBML has no notion of a time span, but it may have been stored as a
+        // SED-ML time course, as done by `transformations::sbml`
+        if let Some(time_course) = child(sbml_model, "annotation")
+            .into_iter()
+            .flat_map(|annotation| annotation.descendants())
+            .find(|node| node.has_tag_name("uniformTimeCourse"))
+        {
+            self.read_time_course(time_course);
+        }
+
+        for (list, what) in [
+            ("listOfFunctionDefinitions", "function definitions"),
+            ("listOfInitialAssignments", "initial assignments"),
+            ("listOfConstraints", "constraints"),
+            ("listOfEvents", "events"),
+        ] {
+            for node in child(sbml_model, list).into_iter().flat_map(elements) {
+                self.diagnose(node, format!("{what} are not supported"));
+            }
+        }
+
+        for compartment in list(sbml_model, "listOfCompartments", "compartment") {
+            let Some(id) = compartment.attribute("id") else {
+                continue;
+            };
+            let size = number_attribute(compartment, "size")
+                .or_else(|| number_attribute(compartment, "volume"))
+                .unwrap_or(1.0);
+            self.compartments.insert(id.to_owned(), size);
+        }
+
+        for species in list(sbml_model, "listOfSpecies", "species") {
+            self.read_species(species);
+        }
+
+        for parameter in list(sbml_model, "listOfParameters", "parameter") {
+            let Some(id) = parameter.attribute("id") else {
+                continue;
+            };
+            let value = number_attribute(parameter, "value").unwrap_or_else(|| {
+                self.diagnose(parameter, "parameter has no value, defaulting to 0".into());
+                0.0
+            });
+            self.model.insert_argument(Argument::Value {
+                name: id.to_owned(),
+                value,
+                unit: None,
+                info: None,
+            });
+        }
+
+        if let Some(rules) = child(sbml_model, "listOfRules") {
+            for rule in elements(rules) {
+                self.read_rule(rule);
+            }
+        }
+
+        for reaction in list(sbml_model, "listOfReactions", "reaction") {
+            self.read_reaction(reaction);
+        }
+
+        self.build_species_equations();
+    }
+
+    fn read_time_course(&mut self, time_course: Node) {
+        let start = number_attribute(time_course, "outputStartTime")
+            .or_else(|| number_attribute(time_course, "initialTime"));
+        let end = number_attribute(time_course, "outputEndTime");
+        let steps = number_attribute(time_course, "numberOfSteps").filter(|&steps| steps > 0.0);
+
+        let (Some(start), Some(end), Some(steps)) = (start, end, steps) else {
+            self.diagnose(
+                time_course,
+                "incomplete time course, using the default time span".into(),
+            );
+            return;
+        };
+
+        self.model.metadata = Metadata {
+            start_time: start,
+            delta_time: (end - start) / steps,
+            end_time: end,
+            time_unit: None,
+        };
+    }
+
+    fn read_species(&mut self, species: Node) {
+        let Some(id) = species.attribute("id") else {
+            return;
+        };
+        let compartment = species.attribute("compartment").map(str::to_owned);
+        let has_only_substance_units = species.attribute("hasOnlySubstanceUnits") == Some("true");
+
+        if species.attribute("conversionFactor").is_some() {
+            self.diagnose(species, "conversion factors are not supported".into());
+        }
+
+        let size = compartment
+            .as_ref()
+            .and_then(|c| self.compartments.get(c))
+            .copied()
+            .unwrap_or(1.0);
+
+        // Species symbols stand for concentrations unless they are declared
+        // to only have substance units.
+        let value = match (
+            number_attribute(species, "initialConcentration"),
+            number_attribute(species, "initialAmount"),
+        ) {
+            (Some(concentration), _) if has_only_substance_units => concentration * size,
+            (Some(concentration), _) => concentration,
+            (None, Some(amount)) if has_only_substance_units => amount,
+            (None, Some(amount)) => amount / size,
+            (None, None) => {
+                self.diagnose(
+                    species,
+                    "species has no initial value, defaulting to 0".into(),
+                );
+                0.0
+            }
+        };
+
+        self.model.insert_argument(Argument::Value {
+            name: id.to_owned(),
+            value,
+            unit: None,
+            info: None,
+        });
+
+        self.species.insert(
+            id.to_owned(),
+            Species {
+                compartment,
+                has_only_substance_units,
+                fixed: species.attribute("boundaryCondition") == Some("true")
+                    || species.attribute("constant") == Some("true"),
+            },
+        );
+    }
+
+    fn read_rule(&mut self, rule: Node) {
+        if !rule.has_tag_name("rateRule") {
+            self.diagnose(
+                rule,
+                format!("<{}> is not supported", rule.tag_name().name()),
+            );
+            return;
+        }
+
+        let Some(variable) = rule.attribute("variable") else {
+            return;
+        };
+
+        let is_value = matches!(
+            self.model.arguments.get(variable),
+            Some(Argument::Value { .. })
+        );
+        if !is_value {
+            self.diagnose(
+                rule,
+                format!("rate rules can only change species or parameters, not `{variable}`"),
+            );
+            return;
+        }
+
+        let name = self.unique_name(&format!("{variable}_eq"));
+        let Some(argument) = self.convert_math(rule, &name, &Scope::new()) else {
+            return;
+        };
+
+        let argument = if argument == name {
+            argument
+        } else {
+            // Equations must point to a composite, so lone symbols are wrapped
+            self.model
+                .insert_argument(infixed(&name, "+", [(argument, '+')]));
+            name
+        };
+
+        self.insert_equation(variable, argument);
+    }
+
+    fn read_reaction(&mut self, reaction: Node) {
+        let Some(id) = reaction.attribute("id") else {
+            return;
+        };
+
+        let Some(kinetic_law) = child(reaction, "kineticLaw") else {
+            self.diagnose(reaction, "reaction has no kinetic law".into());
+            return;
+        };
+
+        // Local parameters become constants prefixed by the reaction id, as
+        // their ids are only unique within the kinetic law.
+        let mut scope = Scope::new();
+        let local_parameters = list(kinetic_law, "listOfLocalParameters", "localParameter")
+            .chain(list(kinetic_law, "listOfParameters", "parameter"));
+        for parameter in local_parameters {
+            let Some(local_id) = parameter.attribute("id") else {
+                continue;
+            };
+            let name = self.unique_name(&format!("{id}_{local_id}"));
+            let value = number_attribute(parameter, "value").unwrap_or_else(|| {
+                self.diagnose(parameter, "parameter has no value, defaulting to 0".into());
+                0.0
+            });
+            self.model.insert_argument(Argument::Value {
+                name: name.clone(),
+                value,
+                unit: None,
+                info: None,
+            });
+            scope.insert(local_id.to_owned(), name);
+        }
+
+        let Some(rate) = self.convert_math(kinetic_law, id, &scope) else {
+            return;
+        };
+
+        for (list_tag, sign) in [("listOfReactants", -1.0), ("listOfProducts", 1.0)] {
+            for reference in list(reaction, list_tag, "speciesReference") {
+                let Some(species) = reference.attribute("species") else {
+                    continue;
+                };
+                if reference.attribute("id").is_some()
+                    && reference.attribute("stoichiometry").is_none()
+                {
+                    self.diagnose(
+                        reference,
+                        "variable stoichiometries are not supported".into(),
+                    );
+                }
+                let stoichiometry = number_attribute(reference, "stoichiometry").unwrap_or(1.0);
+
+                let fluxes = self.fluxes.entry(species.to_owned()).or_default();
+                match fluxes.iter_mut().find(|(arg, _)| arg == &rate) {
+                    Some((_, total)) => *total += sign * stoichiometry,
+                    None => fluxes.push((rate.clone(), sign * stoichiometry)),
+                }
+            }
+        }
+    }
+
+    fn build_species_equations(&mut self) {
+        let fluxes = std::mem::take(&mut self.fluxes);
+
+        for (species_id, fluxes) in fluxes {
+            let Some(species) = self.species.get(&species_id) else {
+                continue;
+            };
+            if species.fixed {
+                continue;
+            }
+            let fluxes: Vec<_> = fluxes.into_iter().filter(|(_, s)| *s != 0.0).collect();
+            if fluxes.is_empty() {
+                continue;
+            }
+
+            let compartment = species
+                .compartment
+                .clone()
+                .filter(|_| !species.has_only_substance_units)
+                .filter(|c| self.compartments.get(c).is_some_and(|&size| size != 1.0));
+
+            let components: Vec<_> = fluxes
+                .into_iter()
+                .map(|(rate, stoichiometry)| {
+                    let contribution = if stoichiometry < 0.0 { '-' } else { '+' };
+                    let magnitude = stoichiometry.abs();
+                    if magnitude == 1.0 {
+                        return (rate, contribution);
+                    }
+                    let name = self.unique_name(&format!("{species_id}_{rate}"));
+                    let literal = self.literal(magnitude);
+                    self.commit_pending();
+                    self.model
+                        .insert_argument(infixed(&name, "*", [(literal, '+'), (rate, '+')]));
+                    (name, contribution)
+                })
+                .collect();
+
+            let name = self.unique_name(&format!("{species_id}_eq"));
+
+            match compartment {
+                // Reaction rates are given in amount per time, so they must be
+                // divided by the volume to get the change in concentration
+                Some(compartment) => {
+                    let flux = self.unique_name(&format!("{species_id}_flux"));
+                    self.model.insert_argument(infixed(&flux, "+", components));
+                    let compartment = self.compartment(&compartment);
+                    self.commit_pending();
+                    self.model.insert_argument(infixed(
+                        &name,
+                        "/",
+                        [(flux, '+'), (compartment, '+')],
+                    ));
+                }
+                None => self.model.insert_argument(infixed(&name, "+", components)),
+            }
+
+            self.insert_equation(&species_id, name);
+        }
+    }
+
+    fn insert_equation(&mut self, variable: &str, argument: String) {
+        let name = self.unique_name(&format!("d{variable}dt"));
+        self.model.insert_equation(Equation {
+            name,
+            operates_on: Some(variable.to_owned()),
+            argument,
+            contribution: '+',
+        });
+    }
+
+    /// Converts the `<math>` child of `parent`, naming its outermost composite
+    /// `name`. Returns the name of the resulting argument, or `None` after
+    /// reporting a diagnostic if the expression is not supported.
+    fn convert_math(&mut self, parent: Node, name: &str, scope: &Scope) -> Option<String> {
+        let Some(math) = child(parent, "math").and_then(|math| elements(math).next()) else {
+            self.diagnose(parent, "missing <math> element".into());
+            return None;
+        };
+
+        let mut counter = 0;
+        match self.convert(math, name, &mut counter, scope) {
+            Ok(argument) => {
+                self.commit_pending();
+                Some(argument)
+            }
+            Err(reason) => {
+                self.pending.clear();
+                self.diagnose(parent, format!("{reason}, so it was skipped"));
+                None
+            }
+        }
+    }
+
+    fn convert(
+        &mut self,
+        node: Node,
+        name: &str,
+        counter: &mut usize,
+        scope: &Scope,
+    ) -> Result<String, String> {
+        match node.tag_name().name() {
+            "ci" => self.symbol(node.text().unwrap_or_default().trim(), scope),
+            "cn" => Ok(self.literal(parse_cn(node)?)),
+            "pi" => Ok(self.literal(std::f64::consts::PI)),
+            "exponentiale" => Ok(self.literal(std::f64::consts::E)),
+            "apply" => self.convert_apply(node, name, counter, scope),
+            "csymbol" => Err(format!(
+                "the symbol `{}` is not supported",
+                node.text().unwrap_or_default().trim()
+            )),
+            other => Err(format!("<{other}> is not supported")),
+        }
+    }
+
+    fn convert_apply(
+        &mut self,
+        node: Node,
+        name: &str,
+        counter: &mut usize,
+        scope: &Scope,
+    ) -> Result<String, String> {
+        let mut children = elements(node);
+        let operator = children.next().ok_or("empty <apply>")?;
+        let operands: Vec<_> = children.collect();
+
+        // Negated operands become components with a `-` contribution instead
+        // of composites of their own
+        let mut convert_operands = |importer: &mut Self, operands: &[Node]| {
+            operands
+                .iter()
+                .map(|&operand| {
+                    let (operand, contribution) = match negated(operand) {
+                        Some(inner) => (inner, '-'),
+                        None => (operand, '+'),
+                    };
+                    let sub_name = if operand.has_tag_name("apply") {
+                        *counter += 1;
+                        importer.unique_name(&format!("{name}_{counter}"))
+                    } else {
+                        String::new()
+                    };
+                    importer
+                        .convert(operand, &sub_name, counter, scope)
+                        .map(|name| (name, contribution))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        let operator_name = operator.tag_name().name();
+        let (operation, components) = match (operator_name, operands.len()) {
+            ("plus", 1..) => ("+", convert_operands(self, &operands)?),
+            ("times", 1..) => ("*", convert_operands(self, &operands)?),
+            ("minus", 1) => {
+                let (operand, contribution) = convert_operands(self, &operands)?.remove(0);
+                let contribution = if contribution == '-' { '+' } else { '-' };
+                ("*", vec![(operand, contribution)])
+            }
+            ("minus", 2) => ("-", convert_operands(self, &operands)?),
+            ("divide", 2) => ("/", convert_operands(self, &operands)?),
+            ("power", 2) => {
+                // Only whole exponents can be written as products
+                let exponent = match operands[1].tag_name().name() {
+                    "cn" => parse_cn(operands[1]).ok(),
+                    _ => None,
+                }
+                .filter(|exponent| exponent.fract() == 0.0 && *exponent >= 1.0)
+                .ok_or("powers are only supported with positive integer exponents")?;
+
+                let base = convert_operands(self, &operands[..1])?.remove(0);
+                ("*", vec![base; exponent as usize])
+            }
+            ("ci", _) => {
+                return Err(format!(
+                    "calls to `{}` are not supported",
+                    operator.text().unwrap_or_default().trim()
+                ))
+            }
+            (other, _) => return Err(format!("<{other}> is not supported")),
+        };
+
+        if let [(single, '+')] = components.as_slice() {
+            return Ok(single.clone());
+        }
+
+        self.pending.push(infixed(name, operation, components));
+        Ok(name.to_owned())
+    }
+
+    fn symbol(&mut self, id: &str, scope: &Scope) -> Result<String, String> {
+        if let Some(local) = scope.get(id) {
+            return Ok(local.clone());
+        }
+        if self.model.arguments.contains_key(id) {
+            return Ok(id.to_owned());
+        }
+        if self.compartments.contains_key(id) {
+            return Ok(self.compartment(id));
+        }
+        Err(format!("the symbol `{id}` is not supported"))
+    }
+
+    /// Adds the compartment to the model, if it isn't already there.
+    fn compartment(&mut self, id: &str) -> String {
+        if !self.model.arguments.contains_key(id) {
+            self.pending.push(Argument::Value {
+                name: id.to_owned(),
+                value: self.compartments[id],
+                unit: None,
+                info: None,
+            });
+        }
+        id.to_owned()
+    }
+
+    /// Numbers in expressions become constants, shared among all the
+    /// expressions using the same value.
+    fn literal(&mut self, value: f64) -> String {
+        if let Some((_, name)) = self.literals.iter().find(|(v, _)| *v == value) {
+            return name.clone();
+        }
+        let base = format!("num_{value}").replace('.', "_").replace('-', "m");
+        let name = self.unique_name(&base);
+        self.literals.push((value, name.clone()));
+        self.pending.push(Argument::Value {
+            name: name.clone(),
+            value,
+            unit: None,
+            info: None,
+        });
+        name
+    }
+
+    fn commit_pending(&mut self) {
+        for argument in std::mem::take(&mut self.pending) {
+            self.model.insert_argument(argument);
+        }
+    }
+
+    fn unique_name(&mut self, base: &str) -> String {
+        let mut name = base.to_owned();
+        while self.taken_names.contains(&name) {
+            name.push('_');
+        }
+        self.taken_names.insert(name.clone());
+        name
+    }
+
+    fn diagnose(&mut self, node: Node, message: String) {
+        let element = node
+            .ancestors()
+            .find_map(|n| n.attribute("id").or_else(|| n.attribute("variable")))
+            .unwrap_or_else(|| node.tag_name().name())
+            .to_owned();
+        self.diagnostics.push(Diagnostic { element, message });
+    }
+
+    /// Places values on the first column, composites on the columns after the
+    /// deepest of their components and equations on the last one.
+    fn layout(&mut self) {
+        fn depth(
+            arguments: &Map<String, Argument>,
+            name: &str,
+            memo: &mut Map<String, usize>,
+        ) -> usize {
+            if let Some(&d) = memo.get(name) {
+                return d;
+            }
+            let d = match arguments.get(name) {
+                Some(Argument::Composite { composition, .. }) => {
+                    1 + composition
+                        .iter()
+                        .map(|c| depth(arguments, &c.name, memo))
+                        .max()
+                        .unwrap_or(0)
+                }
+                _ => 0,
+            };
+            memo.insert(name.to_owned(), d);
+            d
+        }
+
+        let mut memo = Map::new();
+        let mut columns: Vec<Vec<String>> = Vec::new();
+
+        // Populations go first so they sit at the top of the first column
+        let populations: Vec<_> = self
+            .model
+            .get_populations()
+            .map(|a| a.name().to_owned())
+            .collect();
+        let others: Vec<_> = self
+            .model
+            .arguments
+            .keys()
+            .filter(|name| !populations.contains(name))
+            .cloned()
+            .collect();
+
+        for name in populations.into_iter().chain(others) {
+            let column = depth(&self.model.arguments, &name, &mut memo);
+            if columns.len() <= column {
+                columns.resize(column + 1, Vec::new());
+            }
+            columns[column].push(name);
+        }
+
+        columns.push(
+            self.model
+                .equations
+                .iter()
+                .map(|eq| eq.name.clone())
+                .collect(),
+        );
+
+        for (column, names) in columns.into_iter().enumerate() {
+            for (row, name) in names.into_iter().enumerate() {
+                self.model.positions.insert(
+                    name,
+                    Position {
+                        x: MARGIN + column as f64 * COLUMN_WIDTH,
+                        y: MARGIN + row as f64 * ROW_HEIGHT,
+                    },
+                );
+            }
+        }
+    }
+}
+
+const MATHML_NS: &str = "http://www.w3.org/1998/Math/MathML";
+
+fn infixed(
+    name: &str,
+    operation: &str,
+    components: impl IntoIterator<Item = (String, char)>,
+) -> Argument {
+    Argument::Composite {
+        name: name.to_owned(),
+        operation: operation.to_owned(),
+        style: CompositionStyle::Infixed,
+        composition: components
+            .into_iter()
+            .map(|(name, contribution)| Component { name, contribution })
+            .collect(),
+    }
+}
+
+/// The operand of an unary `<minus/>`, if `node` is one.
+fn negated<'a, 'input>(node: Node<'a, 'input>) -> Option<Node<'a, 'input>> {
+    if !node.has_tag_name("apply") {
+        return None;
+    }
+    let mut children = elements(node);
+    match (children.next(), children.next(), children.next()) {
+        (Some(operator), Some(operand), None) if operator.has_tag_name("minus") => Some(operand),
+        _ => None,
+    }
+}
+
+fn parse_cn(node: Node) -> Result<f64, String> {
+    let invalid = || {
+        format!(
+            "invalid number in <cn>: `{}`",
+            node.text().unwrap_or_default()
+        )
+    };
+
+    // `e-notation` and `rational` numbers are split in two by a `<sep/>`
+    let mut parts = node
+        .children()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<f64>().map_err(|_| invalid()));
+
+    let first = parts.next().ok_or_else(invalid)??;
+
+    match node.attribute("type") {
+        Some("e-notation") => Ok(first * 10f64.powf(parts.next().ok_or_else(invalid)??)),
+        Some("rational") => Ok(first / parts.next().ok_or_else(invalid)??),
+        _ => Ok(first),
+    }
+}
+
+fn number_attribute(node: Node, attribute: &str) -> Option<f64> {
+    node.attribute(attribute)?.parse().ok()
+}
+
+fn elements<'a, 'input>(node: Node<'a, 'input>) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(Node::is_element)
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, tag: &str) -> Option<Node<'a, 'input>> {
+    elements(node).find(|n| n.has_tag_name(tag))
+}
+
+fn list<'a, 'input>(
+    node: Node<'a, 'input>,
+    list_tag: &str,
+    item_tag: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    child(node, list_tag)
+        .into_iter()
+        .flat_map(elements)
+        .filter(move |n| n.has_tag_name(item_tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENZYME_KINETICS: &str = include_str!("../../fixtures/enzyme-kinetics.xml");
+
+    fn composite<'a>(model: &'a OdeModel, name: &str) -> (&'a str, Vec<(&'a str, char)>) {
+        match &model.arguments[name] {
+            Argument::Composite {
+                operation,
+                composition,
+                ..
+            } => (
+                operation.as_str(),
+                composition
+                    .iter()
+                    .map(|c| (c.name.as_str(), c.contribution))
+                    .collect(),
+            ),
+            other => panic!("`{name}` is not a composite: {other:?}"),
+        }
+    }
+
+    fn value(model: &OdeModel, name: &str) -> f64 {
+        match &model.arguments[name] {
+            Argument::Value { value, .. } => *value,
+            other => panic!("`{name}` is not a value: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn import_enzyme_kinetics() {
+        let SbmlImport { model, diagnostics } = import_sbml(ENZYME_KINETICS).unwrap();
+
+        assert_eq!(model.name, "Enzyme kinetics");
+
+        let mut populations: Vec<_> = model.get_populations().map(Argument::name).collect();
+        populations.sort();
+        assert_eq!(populations, ["E", "ES", "P", "S"]);
+
+        assert_eq!(value(&model, "S"), 10.0);
+        assert_eq!(value(&model, "kon"), 1e-3);
+        // Local parameters are renamed after their reaction
+        assert_eq!(value(&model, "release_kcat"), 0.1);
+
+        // kon * E * S - koff * ES
+        assert_eq!(
+            composite(&model, "binding"),
+            ("-", vec![("binding_1", '+'), ("binding_2", '+')])
+        );
+        assert_eq!(
+            composite(&model, "binding_1"),
+            ("*", vec![("kon", '+'), ("E", '+'), ("S", '+')])
+        );
+
+        let equation = model
+            .equations
+            .iter()
+            .find(|eq| eq.operates_on.as_deref() == Some("ES"))
+            .unwrap();
+        assert_eq!(equation.name, "dESdt");
+        assert_eq!(
+            composite(&model, &equation.argument),
+            ("+", vec![("binding", '+'), ("release", '-')])
+        );
+
+        // The stoichiometry of 2 is kept as a constant
+        assert_eq!(composite(&model, "P_eq"), ("+", vec![("P_release", '+')]));
+        assert_eq!(
+            composite(&model, "P_release"),
+            ("*", vec![("num_2", '+'), ("release", '+')])
+        );
+        assert_eq!(value(&model, "num_2"), 2.0);
+
+        assert_eq!(
+            diagnostics,
+            [
+                Diagnostic {
+                    element: "ev1".into(),
+                    message: "events are not supported".into(),
+                },
+                Diagnostic {
+                    element: "decay".into(),
+                    message: "<sin> is not supported, so it was skipped".into(),
+                },
+            ]
+        );
+        assert!(!model.arguments.contains_key("decay_1"));
+
+        for name in model
+            .arguments
+            .keys()
+            .chain(model.equations.iter().map(|eq| &eq.name))
+        {
+            assert!(
+                model.positions.contains_key(name),
+                "`{name}` has no position"
+            );
+        }
+    }
+
+    #[test]
+    fn power_and_compartments() {
+        let sbml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<sbml xmlns="http://www.sbml.org/sbml/level3/version2/core" level="3" version="2">
+  <model id="dimer">
+    <listOfCompartments>
+      <compartment id="cell" size="2" constant="true"/>
+    </listOfCompartments>
+    <listOfSpecies>
+      <species id="A" compartment="cell" initialAmount="4" hasOnlySubstanceUnits="false" boundaryCondition="false" constant="false"/>
+    </listOfSpecies>
+    <listOfParameters>
+      <parameter id="k" value="0.5" constant="true"/>
+    </listOfParameters>
+    <listOfReactions>
+      <reaction id="dimerization" reversible="false">
+        <listOfReactants>
+          <speciesReference species="A" stoichiometry="1" constant="true"/>
+        </listOfReactants>
+        <kineticLaw>
+          <math xmlns="http://www.w3.org/1998/Math/MathML">
+            <apply><times/><ci>cell</ci><ci>k</ci>
+              <apply><power/><ci>A</ci><cn type="integer">2</cn></apply>
+            </apply>
+          </math>
+        </kineticLaw>
+      </reaction>
+    </listOfReactions>
+  </model>
+</sbml>"#;
+
+        let SbmlImport { model, diagnostics } = import_sbml(sbml).unwrap();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+
+        // Amounts are converted to concentrations
+        assert_eq!(value(&model, "A"), 2.0);
+        assert_eq!(value(&model, "cell"), 2.0);
+        assert_eq!(
+            composite(&model, "dimerization_1"),
+            ("*", vec![("A", '+'), ("A", '+')])
+        );
+        assert_eq!(
+            composite(&model, "A_eq"),
+            ("/", vec![("A_flux", '+'), ("cell", '+')])
+        );
+        assert_eq!(
+            composite(&model, "A_flux"),
+            ("+", vec![("dimerization", '-')])
+        );
+    }
+
+    #[test]
+    fn rejects_other_documents() {
+        assert!(matches!(import_sbml("<html/>"), Err(SbmlError::NotSbml)));
+        assert!(matches!(import_sbml("<sbml"), Err(SbmlError::Xml(_))));
+        assert!(matches!(
+            import_sbml("<sbml/>"),
+            Err(SbmlError::MissingModel)
+        ));
+    }
+}
@@ -1,52 +1,84 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    models::{self, cellular_automata::CaModel, ode::OdeModel, Argument, CoreModel, Equation},
+    models::{
+        self, cellular_automata::CaModel, ode::OdeModel, Argument, CoreModel, Delay, Equation,
+        Function, Reaction, Submodel,
+    },
     Map,
 };
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+pub mod migrations;
+
+pub use migrations::{FormatError, CURRENT_FORMAT_VERSION};
+
+/// The on-disk format of a model, in its current version. Files written by
+/// older versions are upgraded when read as a `Model`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[schemars(title = "ODE-Designer model")]
 pub struct Json {
     pub metadata: Metadata,
     pub arguments: Vec<Argument>,
     pub equations: Vec<Equation>,
+    /// Other model files embedded in this one.
+    pub submodels: Vec<Submodel>,
+    /// Reactions between populations, which are compiled into equations.
+    pub reactions: Vec<Reaction>,
+    /// Past values of populations, which expressions may refer to.
+    pub delays: Vec<Delay>,
+    /// Helper functions, which composites call by name.
+    pub functions: Vec<Function>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(tag = "type")]
 pub enum ModelMetadata {
     #[serde(rename = "ode")]
     ODE(models::ode::Metadata),
     #[serde(rename = "cellular-automata")]
-    CellularAutomata {},
+    CellularAutomata(models::cellular_automata::Metadata),
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct Metadata {
-    #[serde(default)]
+    pub format_version: u32,
     pub name: String,
     #[serde(flatten)]
     pub model_metadata: ModelMetadata,
-    #[serde(default)]
     pub positions: Map<String, Position>,
-    #[serde(default)]
     pub extension_files: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
 pub struct Position {
     pub x: f64,
     pub y: f64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(from = "Json")]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "serde_json::Value")]
 #[serde(into = "Json")]
 pub enum Model {
     ODE(OdeModel),
     CellularAutomata(CaModel),
 }
 
+/// JSON Schema of the current format, for validating model files with other
+/// tools.
+pub fn json_schema() -> schemars::schema::RootSchema {
+    schemars::schema_for!(Json)
+}
+
+impl TryFrom<serde_json::Value> for Model {
+    type Error = FormatError;
+
+    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
+        let json: Json = serde_json::from_value(migrations::migrate(value)?)?;
+        Ok(json.into())
+    }
+}
+
 impl From<Json> for Model {
     fn from(value: Json) -> Self {
         let core = CoreModel {
@@ -57,10 +89,19 @@ impl From<Json> for Model {
                 .collect(),
             equations: value.equations,
             positions: value.metadata.positions,
+            submodels: value.submodels,
+            reactions: value.reactions,
+            delays: value.delays,
+            functions: value.functions,
         };
         let name = value.metadata.name;
         match value.metadata.model_metadata {
-            ModelMetadata::CellularAutomata {} => Self::CellularAutomata(CaModel { name, core }),
+            ModelMetadata::CellularAutomata(metadata) => Self::CellularAutomata(CaModel {
+                name,
+                metadata,
+                core,
+                extension_files: value.metadata.extension_files,
+            }),
             ModelMetadata::ODE(metadata) => Self::ODE(OdeModel {
                 name,
                 core,
@@ -73,12 +114,12 @@ impl From<Json> for Model {
 
 impl From<Model> for Json {
     fn from(value: Model) -> Self {
-        let (equations, model_metadata, name, extension_files) = match value {
+        let (core, model_metadata, name, extension_files) = match value {
             Model::CellularAutomata(model) => (
                 model.core,
-                ModelMetadata::CellularAutomata {},
+                ModelMetadata::CellularAutomata(model.metadata),
                 model.name,
-                Vec::new(),
+                model.extension_files,
             ),
             Model::ODE(model) => (
                 model.core,
@@ -88,13 +129,18 @@ impl From<Model> for Json {
             ),
         };
         Self {
-            arguments: equations.arguments.values().cloned().collect(),
-            equations: equations.equations,
+            arguments: core.arguments.into_values().collect(),
+            equations: core.equations,
+            submodels: core.submodels,
+            reactions: core.reactions,
+            delays: core.delays,
+            functions: core.functions,
             metadata: Metadata {
+                format_version: CURRENT_FORMAT_VERSION,
                 name,
                 model_metadata,
                 extension_files,
-                positions: Map::new(),
+                positions: core.positions,
             },
         }
     }
@@ -102,237 +148,295 @@ impl From<Model> for Json {
 
 #[cfg(test)]
 mod test {
-    use assert_json_diff::assert_json_eq;
-
     use super::*;
 
-    use crate::models::Component;
+    /// Name and contents of every model in the `examples/` directory.
+    pub(crate) fn examples() -> impl Iterator<Item = (String, String)> {
+        let examples_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../examples");
 
-    const GAME_OF_LIFE: &str = include_str!("../fixtures/game-of-life.json");
+        std::fs::read_dir(examples_dir)
+            .expect("The examples directory should exist")
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .map(|path| {
+                let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                (name, std::fs::read_to_string(path).unwrap())
+            })
+    }
 
-    /* fn fixture_game_of_life() -> Json {
-        Json {
-            metadata: Metadata {
-                name: "Conway's Game of Life".into(),
-                model_metadata: ModelMetadata::CellularAutomata {},
-                positions: Map::new(),
-            },
-            arguments: vec![
-                Argument::Value {
-                    name: "dead".into(),
-                    value: 0.5,
-                },
-                Argument::Value {
-                    name: "alive".into(),
-                    value: 0.5,
-                },
-                Argument::Composite {
-                    name: "reproduction".into(),
-                    operation: "==".into(),
-                    composition: vec![
-                        Component::Argument {
-                            name: "alive".into(),
-                            contribution: '+',
-                        },
-                        Component::Constant {
-                            value: 3.0,
-                            contribution: '+',
-                        },
-                    ],
-                },
-                Argument::Composite {
-                    name: "overpopulation".into(),
-                    operation: ">".into(),
-                    composition: vec![
-                        Component::Argument {
-                            name: "alive".into(),
-                            contribution: '+',
-                        },
-                        Component::Constant {
-                            value: 3.0,
-                            contribution: '+',
-                        },
-                    ],
-                },
-                Argument::Composite {
-                    name: "underpopulation".into(),
-                    operation: "<".into(),
-                    composition: vec![
-                        Component::Argument {
-                            name: "alive".into(),
-                            contribution: '+',
-                        },
-                        Component::Constant {
-                            value: 2.0,
-                            contribution: '+',
-                        },
-                    ],
-                },
-                Argument::Composite {
-                    name: "dead_equation".into(),
-                    operation: "+".into(),
-                    composition: vec![
-                        Component::Argument {
-                            name: "overpopulation".into(),
-                            contribution: '+',
-                        },
-                        Component::Argument {
-                            name: "underpopulation".into(),
-                            contribution: '+',
-                        },
-                        Component::Argument {
-                            name: "reproduction".into(),
-                            contribution: '-',
-                        },
-                    ],
-                },
-                Argument::Composite {
-                    name: "alive_equation".into(),
-                    operation: "-".into(),
-                    composition: vec![
-                        Component {
-                            value: 1.0,
-                            contribution: '+',
-                        },
-                        Component {
-                            name: "dead_equation".into(),
-                            contribution: '+',
-                        },
-                    ],
+    mod strategies {
+        use proptest::{collection, option, prelude::*};
+
+        use crate::models::{
+            cellular_automata::{self, CaModel, Neighbourhood},
+            ode::{Metadata, OdeModel},
+            parameter::{Bounds, Distribution},
+            reaction::{RateLaw, Species},
+            submodel::Port,
+            Argument, Component, CompositionStyle, CoreModel, Delay, Equation, Function,
+            ParameterInfo, Reaction, Submodel,
+        };
+        use crate::{Model, Position};
+
+        fn name() -> impl Strategy<Value = String> {
+            "[a-zA-Z_][a-zA-Z0-9_*+/ -]{0,8}"
+        }
+
+        /// JSON has no representation for NaN or infinities
+        fn finite() -> impl Strategy<Value = f64> {
+            any::<f64>().prop_filter("finite", |value| value.is_finite())
+        }
+
+        fn info() -> impl Strategy<Value = ParameterInfo> {
+            let bounds = (finite(), finite()).prop_map(|(min, max)| Bounds { min, max });
+            let prior = prop_oneof![
+                (finite(), finite()).prop_map(|(min, max)| Distribution::Uniform { min, max }),
+                (finite(), finite())
+                    .prop_map(|(mean, std_dev)| Distribution::Normal { mean, std_dev }),
+                (finite(), finite()).prop_map(|(mu, sigma)| Distribution::LogNormal { mu, sigma }),
+            ];
+
+            (".*", ".*", option::of(bounds), option::of(prior)).prop_map(
+                |(description, reference, bounds, prior)| ParameterInfo {
+                    description,
+                    reference,
+                    bounds,
+                    prior,
                 },
-            ],
-            equations: [
-                Equation {
-                    name: "dead_equation".to_string(),
-                    operates_on: Some("dead".to_string()),
-                    argument: "dead_equation".to_string(),
-                    contribution: '+',
+            )
+        }
+
+        fn argument() -> impl Strategy<Value = Argument> {
+            let component = (name(), any::<char>())
+                .prop_map(|(name, contribution)| Component { name, contribution });
+            let style = prop_oneof![
+                Just(CompositionStyle::Infixed),
+                Just(CompositionStyle::Prefixed)
+            ];
+
+            prop_oneof![
+                (name(), finite(), option::of(".*"), option::of(info())).prop_map(
+                    |(name, value, unit, info)| Argument::Value {
+                        name,
+                        value,
+                        unit,
+                        info,
+                    }
+                ),
+                (name(), ".*", style, collection::vec(component, 0..4)).prop_map(
+                    |(name, operation, style, composition)| Argument::Composite {
+                        name,
+                        operation,
+                        style,
+                        composition,
+                    }
+                ),
+            ]
+        }
+
+        fn equation() -> impl Strategy<Value = Equation> {
+            (name(), option::of(name()), name(), any::<char>()).prop_map(
+                |(name, operates_on, argument, contribution)| Equation {
+                    name,
+                    operates_on,
+                    argument,
+                    contribution,
                 },
-                Equation {
-                    name: "alive_equation".to_string(),
-                    operates_on: Some("alive".to_string()),
-                    argument: "alive_equation".to_string(),
-                    contribution: '+',
+            )
+        }
+
+        fn submodel() -> impl Strategy<Value = Submodel> {
+            let port = (name(), option::of(name()))
+                .prop_map(|(name, connects_to)| Port { name, connects_to });
+
+            (name(), ".*", collection::vec(port, 0..3)).prop_map(|(name, path, ports)| Submodel {
+                name,
+                path,
+                ports,
+            })
+        }
+
+        fn species() -> impl Strategy<Value = Species> {
+            (name(), any::<u32>()).prop_map(|(name, stoichiometry)| Species {
+                name,
+                stoichiometry,
+            })
+        }
+
+        fn reaction() -> impl Strategy<Value = Reaction> {
+            let rate = prop_oneof![
+                name().prop_map(|k| RateLaw::MassAction { k }),
+                (name(), name()).prop_map(|(vmax, km)| RateLaw::MichaelisMenten { vmax, km }),
+                (name(), name(), any::<u32>()).prop_map(|(vmax, k, n)| RateLaw::Hill {
+                    vmax,
+                    k,
+                    n
+                }),
+            ];
+
+            (
+                name(),
+                collection::vec(species(), 0..3),
+                collection::vec(species(), 0..3),
+                rate,
+            )
+                .prop_map(|(name, reactants, products, rate)| Reaction {
+                    name,
+                    reactants,
+                    products,
+                    rate,
+                })
+        }
+
+        fn delay() -> impl Strategy<Value = Delay> {
+            (name(), name(), finite()).prop_map(|(name, population, tau)| Delay {
+                name,
+                population,
+                tau,
+            })
+        }
+
+        fn function() -> impl Strategy<Value = Function> {
+            (name(), collection::vec(name(), 0..3), ".*").prop_map(|(name, parameters, body)| {
+                Function {
+                    name,
+                    parameters,
+                    body,
+                }
+            })
+        }
+
+        fn core() -> impl Strategy<Value = CoreModel> {
+            let position = (finite(), finite()).prop_map(|(x, y)| Position { x, y });
+
+            (
+                collection::vec(argument(), 0..8),
+                collection::vec(equation(), 0..4),
+                collection::btree_map(name(), position, 0..8),
+                collection::vec(submodel(), 0..2),
+                collection::vec(reaction(), 0..2),
+                collection::vec(delay(), 0..2),
+                collection::vec(function(), 0..2),
+            )
+                .prop_map(
+                    |(arguments, equations, positions, submodels, reactions, delays, functions)| {
+                        CoreModel {
+                            arguments: arguments
+                                .into_iter()
+                                .map(|arg| (arg.name().to_owned(), arg))
+                                .collect(),
+                            equations,
+                            positions,
+                            submodels,
+                            reactions,
+                            delays,
+                            functions,
+                        }
+                    },
+                )
+        }
+
+        pub fn model() -> impl Strategy<Value = Model> {
+            let metadata = (finite(), finite(), finite(), option::of(".*")).prop_map(
+                |(start_time, delta_time, end_time, time_unit)| Metadata {
+                    start_time,
+                    delta_time,
+                    end_time,
+                    time_unit,
                 },
+            );
+            let extension_files = collection::vec(".*", 0..3);
+            let neighbourhood =
+                prop_oneof![Just(Neighbourhood::Moore), Just(Neighbourhood::VonNeumann)];
+            let ca_metadata = (
+                any::<usize>(),
+                any::<usize>(),
+                any::<usize>(),
+                neighbourhood,
+                any::<u64>(),
+            )
+                .prop_map(|(width, height, steps, neighbourhood, seed)| {
+                    cellular_automata::Metadata {
+                        width,
+                        height,
+                        steps,
+                        neighbourhood,
+                        seed,
+                    }
+                });
+
+            prop_oneof![
+                (".*", metadata, extension_files.clone(), core()).prop_map(
+                    |(name, metadata, extension_files, core)| Model::ODE(OdeModel {
+                        name,
+                        metadata,
+                        extension_files,
+                        core,
+                    })
+                ),
+                (".*", ca_metadata, extension_files, core()).prop_map(
+                    |(name, metadata, extension_files, core)| Model::CellularAutomata(CaModel {
+                        name,
+                        metadata,
+                        core,
+                        extension_files,
+                    })
+                ),
             ]
-            .to_vec()
         }
-    } */
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn model_round_trip(model in strategies::model()) {
+            let saved = serde_json::to_string(&model).unwrap();
+            let loaded: Model = serde_json::from_str(&saved).unwrap();
+
+            proptest::prop_assert_eq!(loaded, model);
+        }
+    }
+
+    /// Arguments are kept in a map by the model, so they may be saved in a
+    /// different order.
+    fn sorted_arguments(mut json: serde_json::Value) -> serde_json::Value {
+        if let Some(arguments) = json["arguments"].as_array_mut() {
+            arguments.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+        }
+        json
+    }
+
+    #[test]
+    fn examples_round_trip() {
+        for (name, content) in examples() {
+            let model: Model = serde_json::from_str(&content).unwrap();
+            let saved = serde_json::to_value(&model).unwrap();
+
+            let original = migrations::migrate(serde_json::from_str(&content).unwrap()).unwrap();
+            assert_eq!(
+                sorted_arguments(saved.clone()),
+                sorted_arguments(original),
+                "{name}"
+            );
 
-    /* #[test]
-    fn deserialize_game_of_life_json() {
-        let json = serde_json::from_str::<Json>(GAME_OF_LIFE).unwrap();
-        let expected = fixture_game_of_life();
-        assert_json_eq!(expected, json);
+            let loaded: Model = serde_json::from_value(saved).unwrap();
+            assert_eq!(loaded, model, "{name}");
+        }
     }
 
     #[test]
-    fn serialize_game_of_life_json() {
-        let serialized = serde_json::to_string(&fixture_game_of_life()).unwrap();
-        let expected = include_str!("../fixtures/game-of-life-serialized.json");
-        assert_eq!(serialized, expected);
-    } */
-
-    /* #[test]
-    fn deserialize_game_of_life_model() {
-        let model = serde_json::from_str::<Model>(GAME_OF_LIFE).unwrap();
-        let expected = crate::models::cellular_automata::Model {
-            name: "Conway's Game of Life".into(),
-            equations: Equations {
-                arguments: [
-                    Argument::Value {
-                        name: "dead".into(),
-                        value: 0.5,
-                    },
-                    Argument::Value {
-                        name: "alive".into(),
-                        value: 0.5,
-                    },
-                    Argument::Composite {
-                        name: "reproduction".into(),
-                        operation: "==".into(),
-                        composition: vec![
-                            Component::Argument {
-                                name: "alive".into(),
-                                contribution: '+',
-                            },
-                            Component::Constant {
-                                value: 3.0,
-                                contribution: '+',
-                            },
-                        ],
-                    },
-                    Argument::Composite {
-                        name: "overpopulation".into(),
-                        operation: ">".into(),
-                        composition: vec![
-                            Component::Argument {
-                                name: "alive".into(),
-                                contribution: '+',
-                            },
-                            Component::Constant {
-                                value: 3.0,
-                                contribution: '+',
-                            },
-                        ],
-                    },
-                    Argument::Composite {
-                        name: "underpopulation".into(),
-                        operation: "<".into(),
-                        composition: vec![
-                            Component::Argument {
-                                name: "alive".into(),
-                                contribution: '+',
-                            },
-                            Component::Constant {
-                                value: 2.0,
-                                contribution: '+',
-                            },
-                        ],
-                    },
-                    Argument::Composite {
-                        name: "dead_equation".into(),
-                        operation: "+".into(),
-                        composition: vec![
-                            Component::Argument {
-                                name: "overpopulation".into(),
-                                contribution: '+',
-                            },
-                            Component::Argument {
-                                name: "underpopulation".into(),
-                                contribution: '+',
-                            },
-                            Component::Argument {
-                                name: "reproduction".into(),
-                                contribution: '-',
-                            },
-                        ],
-                    },
-                    Argument::Composite {
-                        name: "alive_equation".into(),
-                        operation: "-".into(),
-                        composition: vec![
-                            Component::Constant {
-                                value: 1.0,
-                                contribution: '+',
-                            },
-                            Component::Argument {
-                                name: "dead_equation".into(),
-                                contribution: '+',
-                            },
-                        ],
-                    },
-                ]
-                .into_iter()
-                .map(|arg| (arg.name().to_owned(), arg))
-                .collect(),
-                equations: vec![("dead", "dead_equation"), ("alive", "alive_equation")]
-                    .into_iter()
-                    .map(|(k, v)| (k.to_string(), v.to_string()))
-                    .collect()
-            },
-        };
-        let expected = Json::from(Model::CellularAutomata(expected));
-        assert_json_eq!(model, expected);
-    } */
+    fn published_schema_is_up_to_date() {
+        let schema = serde_json::to_string_pretty(&json_schema()).unwrap() + "\n";
+        let path =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("schema/model.schema.json");
+
+        if std::env::var_os("UPDATE_SCHEMA").is_some() {
+            std::fs::write(&path, &schema).unwrap();
+        }
+
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap_or_default(),
+            schema,
+            "The schema is outdated, run this test with UPDATE_SCHEMA=1 to update it"
+        );
+    }
 }
@@ -0,0 +1,392 @@
+//! Upgrades model files written by older versions of the program to the
+//! current [`Json`](super::Json) format, one version at a time.
+//!
+//! Files from before `format_version` was introduced don't have it, so their
+//! version is guessed from their shape:
+//!
+//! - **0**: `equations` is an object mapping each population to the argument
+//!   it is defined by.
+//! - **1**: `equations` is a list, but `style`, `extension_files`, `name`
+//!   and `positions` may be missing. Up to this version, components may be
+//!   constants, written `{ "value": 3, "contribution": "+" }`.
+//! - **2**: every field is present, along with `format_version`.
+//! - **3**: models may embed other model files, listed in `submodels`.
+//! - **4**: cellular automata record the size of their grid, how many steps
+//!   to simulate, their neighbourhood and the seed of their initial grid.
+//! - **5**: models may list reactions between their populations, in
+//!   `reactions`.
+//! - **6**: models may refer to past values of their populations, listed in
+//!   `delays`.
+//! - **7**: models may declare helper functions of their own, listed in
+//!   `functions`.
+
+use std::fmt::Display;
+
+use serde_json::{json, Map, Value};
+
+use crate::models::cellular_automata;
+
+pub const CURRENT_FORMAT_VERSION: u32 = 7;
+
+type Migration = fn(&mut Map<String, Value>) -> Result<(), FormatError>;
+
+/// Each migration upgrades a file from the version matching its index to the
+/// next one.
+const MIGRATIONS: &[Migration] = &[
+    equations_as_list,
+    explicit_defaults,
+    no_submodels,
+    automaton_settings,
+    no_reactions,
+    no_delays,
+    no_functions,
+];
+
+#[derive(Debug)]
+pub enum FormatError {
+    /// The file was written by a newer version of the program.
+    NewerVersion(u32),
+    Malformed(String),
+    Invalid(serde_json::Error),
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormatError::NewerVersion(version) => write!(
+                f,
+                "the model uses format version {version}, but only versions up to \
+                 {CURRENT_FORMAT_VERSION} are supported"
+            ),
+            FormatError::Malformed(reason) => write!(f, "malformed model file: {reason}"),
+            FormatError::Invalid(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+impl From<serde_json::Error> for FormatError {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Invalid(value)
+    }
+}
+
+fn malformed(reason: impl Into<String>) -> FormatError {
+    FormatError::Malformed(reason.into())
+}
+
+/// The version a file was written with, guessing it for files that predate
+/// `format_version`.
+pub fn format_version(value: &Value) -> Result<u32, FormatError> {
+    let version = value
+        .get("metadata")
+        .and_then(|metadata| metadata.get("format_version"));
+
+    match version {
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| malformed("`format_version` is not a version number")),
+        None if value.get("equations").is_some_and(Value::is_object) => Ok(0),
+        None => Ok(1),
+    }
+}
+
+/// Upgrades a model file to [`CURRENT_FORMAT_VERSION`].
+pub fn migrate(mut value: Value) -> Result<Value, FormatError> {
+    let version = format_version(&value)?;
+
+    if version > CURRENT_FORMAT_VERSION {
+        return Err(FormatError::NewerVersion(version));
+    }
+
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| malformed("the model is not a JSON object"))?;
+
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(object)?;
+    }
+
+    object
+        .get_mut("metadata")
+        .and_then(Value::as_object_mut)
+        .ok_or_else(|| malformed("missing `metadata`"))?
+        .insert("format_version".into(), CURRENT_FORMAT_VERSION.into());
+
+    Ok(value)
+}
+
+/// 0 -> 1: equations used to be a `{ population: argument }` object.
+fn equations_as_list(model: &mut Map<String, Value>) -> Result<(), FormatError> {
+    let Some(Value::Object(equations)) = model.remove("equations") else {
+        return Err(malformed("`equations` is not an object"));
+    };
+
+    let equations = equations
+        .into_iter()
+        .map(|(population, argument)| {
+            let argument = argument.as_str().ok_or_else(|| {
+                malformed(format!("the equation of `{population}` is not a name"))
+            })?;
+
+            Ok(json!({
+                "name": format!("d{population}dt"),
+                "operates_on": population,
+                "argument": argument,
+                "contribution": "+",
+            }))
+        })
+        .collect::<Result<Vec<_>, FormatError>>()?;
+
+    model.insert("equations".into(), equations.into());
+    Ok(())
+}
+
+/// 1 -> 2: fields that used to be optional are written explicitly, and
+/// constant components refer to values added for them instead.
+fn explicit_defaults(model: &mut Map<String, Value>) -> Result<(), FormatError> {
+    let metadata = model
+        .get_mut("metadata")
+        .and_then(Value::as_object_mut)
+        .ok_or_else(|| malformed("missing `metadata`"))?;
+
+    metadata.entry("name").or_insert_with(|| "".into());
+    metadata.entry("positions").or_insert_with(|| json!({}));
+    metadata
+        .entry("extension_files")
+        .or_insert_with(|| json!([]));
+
+    let arguments = model
+        .get_mut("arguments")
+        .and_then(Value::as_array_mut)
+        .ok_or_else(|| malformed("`arguments` is not a list"))?;
+
+    for argument in arguments.iter_mut().filter_map(Value::as_object_mut) {
+        if argument.contains_key("composition") {
+            argument.entry("style").or_insert_with(|| "Infixed".into());
+        }
+    }
+
+    name_constant_components(arguments)
+}
+
+/// Replaces every constant component by a reference to a value holding the
+/// constant, named after it and shared by the components with the same one.
+fn name_constant_components(arguments: &mut Vec<Value>) -> Result<(), FormatError> {
+    let mut constants: Vec<(f64, String)> = Vec::new();
+    let mut names: Vec<String> = arguments
+        .iter()
+        .filter_map(|argument| argument.get("name")?.as_str().map(str::to_owned))
+        .collect();
+
+    for argument in arguments.iter_mut() {
+        let Some(composition) = argument
+            .get_mut("composition")
+            .and_then(Value::as_array_mut)
+        else {
+            continue;
+        };
+
+        for component in composition.iter_mut().filter_map(Value::as_object_mut) {
+            if component.contains_key("name") {
+                continue;
+            }
+            let value = component
+                .remove("value")
+                .and_then(|value| value.as_f64())
+                .ok_or_else(|| malformed("a component has neither a name nor a value"))?;
+
+            let name = match constants.iter().find(|(constant, _)| *constant == value) {
+                Some((_, name)) => name.clone(),
+                None => {
+                    let stem = format!("constant_{value}").replace(['.', '-'], "_");
+                    let mut name = stem.clone();
+                    let mut suffix = 1;
+                    while names.contains(&name) {
+                        suffix += 1;
+                        name = format!("{stem}_{suffix}");
+                    }
+                    names.push(name.clone());
+                    constants.push((value, name.clone()));
+                    name
+                }
+            };
+            component.insert("name".into(), name.into());
+        }
+    }
+
+    arguments.extend(
+        constants
+            .into_iter()
+            .map(|(value, name)| json!({ "name": name, "value": value })),
+    );
+    Ok(())
+}
+
+/// 2 -> 3: models couldn't embed other models.
+fn no_submodels(model: &mut Map<String, Value>) -> Result<(), FormatError> {
+    model.insert("submodels".into(), json!([]));
+    Ok(())
+}
+
+/// 3 -> 4: cellular automata couldn't be simulated, so they had no settings.
+fn automaton_settings(model: &mut Map<String, Value>) -> Result<(), FormatError> {
+    let metadata = model
+        .get_mut("metadata")
+        .and_then(Value::as_object_mut)
+        .ok_or_else(|| malformed("missing `metadata`"))?;
+
+    if metadata.get("type").and_then(Value::as_str) != Some("cellular-automata") {
+        return Ok(());
+    }
+
+    let Value::Object(defaults) = serde_json::to_value(cellular_automata::Metadata::default())?
+    else {
+        unreachable!("The metadata is a struct");
+    };
+    for (key, value) in defaults {
+        metadata.entry(key).or_insert(value);
+    }
+
+    Ok(())
+}
+
+/// 4 -> 5: models couldn't describe reactions.
+fn no_reactions(model: &mut Map<String, Value>) -> Result<(), FormatError> {
+    model.insert("reactions".into(), json!([]));
+    Ok(())
+}
+
+/// 5 -> 6: models couldn't refer to past values of their populations.
+fn no_delays(model: &mut Map<String, Value>) -> Result<(), FormatError> {
+    model.insert("delays".into(), json!([]));
+    Ok(())
+}
+
+/// 6 -> 7: models couldn't declare functions.
+fn no_functions(model: &mut Map<String, Value>) -> Result<(), FormatError> {
+    model.insert("functions".into(), json!([]));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{json::test::examples, Argument, Json, Model};
+
+    use super::*;
+
+    const LOTKA_VOLTERRA: &str = include_str!("../../fixtures/lotka-volterra.json");
+    const GAME_OF_LIFE: &str = include_str!("../../fixtures/game-of-life.json");
+    const GAME_OF_LIFE_SERIALIZED: &str =
+        include_str!("../../fixtures/game-of-life-serialized.json");
+
+    #[test]
+    fn examples_are_migrated() {
+        let mut count = 0;
+
+        for (name, content) in examples() {
+            let value: Value = serde_json::from_str(&content).unwrap();
+            let migrated = migrate(value).unwrap_or_else(|err| panic!("{name}: {err}"));
+
+            assert_eq!(format_version(&migrated).unwrap(), CURRENT_FORMAT_VERSION);
+
+            // The migrated file must be readable without any help from serde
+            // defaults, and saving it again must not change it
+            let json: Json = serde_json::from_value(migrated.clone())
+                .unwrap_or_else(|err| panic!("{name}: {err}"));
+            let model: Model = serde_json::from_str(&content).unwrap();
+            let saved = serde_json::to_value(Json::from(model)).unwrap();
+
+            assert_eq!(
+                json.arguments.len(),
+                saved["arguments"].as_array().unwrap().len()
+            );
+            assert_eq!(migrate(saved.clone()).unwrap(), saved, "{name}");
+
+            count += 1;
+        }
+
+        assert!(count > 0, "No examples were found");
+    }
+
+    #[test]
+    fn equations_used_to_be_an_object() {
+        let value: Value = serde_json::from_str(LOTKA_VOLTERRA).unwrap();
+        assert_eq!(format_version(&value).unwrap(), 0);
+
+        let Model::ODE(model) = serde_json::from_str(LOTKA_VOLTERRA).unwrap() else {
+            panic!("Lotka-Volterra is an ODE model");
+        };
+
+        let populations: Vec<_> = model
+            .equations
+            .iter()
+            .map(|eq| (eq.operates_on.as_deref().unwrap(), eq.argument.as_str()))
+            .collect();
+        assert_eq!(populations, [("x", "dx"), ("y", "dy")]);
+        assert!(model.extension_files.is_empty());
+    }
+
+    #[test]
+    fn constant_components_become_values() {
+        for fixture in [GAME_OF_LIFE, GAME_OF_LIFE_SERIALIZED] {
+            let value: Value = serde_json::from_str(fixture).unwrap();
+            assert_eq!(format_version(&value).unwrap(), 0);
+
+            let Model::CellularAutomata(model) = Model::try_from(value).unwrap() else {
+                panic!("The Game of Life is a cellular automaton");
+            };
+
+            let value = |name: &str| match model.arguments.get(name) {
+                Some(Argument::Value { value, .. }) => Some(*value),
+                _ => None,
+            };
+            assert_eq!(value("constant_3"), Some(3.0));
+            assert_eq!(value("constant_2"), Some(2.0));
+            assert_eq!(value("constant_1"), Some(1.0));
+
+            let Some(Argument::Composite { composition, .. }) =
+                model.arguments.get("overpopulation")
+            else {
+                panic!("`overpopulation` is a composite");
+            };
+            let names: Vec<_> = composition.iter().map(|comp| comp.name.as_str()).collect();
+            assert_eq!(names, ["alive", "constant_3"]);
+            assert!(model.automaton().is_ok());
+        }
+    }
+
+    #[test]
+    fn automata_get_default_settings() {
+        let value = json!({
+            "metadata": {
+                "format_version": 3,
+                "type": "cellular-automata",
+                "name": "",
+                "positions": {},
+                "extension_files": [],
+            },
+            "arguments": [],
+            "equations": [],
+            "submodels": [],
+        });
+
+        let Model::CellularAutomata(model) = Model::try_from(value).unwrap() else {
+            panic!("The model is a cellular automaton");
+        };
+        assert_eq!(model.metadata, cellular_automata::Metadata::default());
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        let value = json!({
+            "metadata": { "format_version": CURRENT_FORMAT_VERSION + 1 },
+            "arguments": [],
+            "equations": [],
+        });
+
+        assert!(matches!(migrate(value), Err(FormatError::NewerVersion(_))));
+    }
+}
@@ -1,10 +1,13 @@
 pub type Map<K, V> = std::collections::BTreeMap<K, V>;
 
+pub mod import;
 pub mod json;
 pub mod models;
 pub mod transformations;
+pub mod units;
+pub mod validation;
 
-pub use json::{Json, Metadata, Model, ModelMetadata, Position};
+pub use json::{json_schema, Json, Metadata, Model, ModelMetadata, Position};
 pub use models::{Argument, Component, CoreModel, Equation};
 
 #[cfg(test)]
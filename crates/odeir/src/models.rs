@@ -1,16 +1,36 @@
-
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::{Map, Position};
 
 pub mod cellular_automata;
+pub mod delay;
+pub mod function;
 pub mod ode;
+pub mod parameter;
+pub mod reaction;
+pub mod stochastic;
+pub mod submodel;
+
+pub use delay::Delay;
+pub use function::Function;
+pub use parameter::ParameterInfo;
+pub use reaction::Reaction;
+pub use submodel::Submodel;
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 pub struct CoreModel {
     pub arguments: Map<String, Argument>,
     pub equations: Vec<Equation>,
     pub positions: Map<String, Position>,
+    #[serde(default)]
+    pub submodels: Vec<Submodel>,
+    #[serde(default)]
+    pub reactions: Vec<Reaction>,
+    #[serde(default)]
+    pub delays: Vec<Delay>,
+    #[serde(default)]
+    pub functions: Vec<Function>,
 }
 
 impl CoreModel {
@@ -25,23 +45,27 @@ impl CoreModel {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum Argument {
     Value {
         name: String,
         value: f64,
+        /// Physical unit of the value, such as `cells/mL` or `1/day`.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        unit: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        info: Option<ParameterInfo>,
     },
     Composite {
         name: String,
         operation: String,
-        #[serde(default)]
         style: CompositionStyle,
         composition: Vec<Component>,
     },
 }
 
-#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum CompositionStyle {
     #[default]
     Infixed,
@@ -55,19 +79,35 @@ impl Argument {
             Argument::Composite { name, .. } => name,
         }
     }
+
+    /// The unit of a value, if it has one. Composites don't store a unit, as
+    /// it follows from their components.
+    pub fn unit(&self) -> Option<&str> {
+        match self {
+            Argument::Value { unit, .. } => unit.as_deref(),
+            Argument::Composite { .. } => None,
+        }
+    }
+
+    /// Description, provenance and uncertainty of a value.
+    pub fn info(&self) -> Option<&ParameterInfo> {
+        match self {
+            Argument::Value { info, .. } => info.as_ref(),
+            Argument::Composite { .. } => None,
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq, Default)]
 pub struct Component {
     pub name: String,
     pub contribution: char,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
 pub struct Equation {
     pub name: String,
     pub operates_on: Option<String>,
     pub argument: String,
     pub contribution: char,
 }
-
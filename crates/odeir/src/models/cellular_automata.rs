@@ -1,7 +1,761 @@
-use super::CoreModel;
+//! Cellular automata, simulated on a 2D grid whose cells are each in one of
+//! the model's states.
+//!
+//! The states are the values the equations operate on, and each value is the
+//! share of the cells that starts in that state. The composites make up the
+//! rules: while a cell is updated, the name of a state stands for how many of
+//! its neighbours are in that state, and comparisons (`==`, `!=`, `<`, `>`,
+//! `<=` and `>=`) are 1 when they hold and 0 otherwise. Each equation adds its
+//! argument to the score of the state it operates on, and the cell moves to
+//! the state with the highest positive score. When no score is positive, the
+//! cell keeps its state. Prefixed composites call the functions of the model,
+//! and are 0 where they fail to evaluate.
+//!
+//! The grid wraps around, so the cells on an edge neighbour the ones on the
+//! opposite edge.
 
-#[derive(Debug, Clone)]
+use std::fmt::Display;
+
+use expr_evaluator::expr::{ExprContext, Func};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::{Argument, CompositionStyle, CoreModel};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Metadata {
+    /// Number of columns of the grid.
+    pub width: usize,
+    /// Number of rows of the grid.
+    pub height: usize,
+    /// Number of generations simulated after the initial one.
+    pub steps: usize,
+    pub neighbourhood: Neighbourhood,
+    /// Seed of the random initial grid, so simulations can be repeated.
+    pub seed: u64,
+}
+
+impl Default for Metadata {
+    fn default() -> Self {
+        Self {
+            width: 64,
+            height: 64,
+            steps: 100,
+            neighbourhood: Neighbourhood::default(),
+            seed: 0,
+        }
+    }
+}
+
+/// Which cells count as the neighbours of a cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Neighbourhood {
+    /// The eight cells around it.
+    #[default]
+    Moore,
+    /// The four cells sharing an edge with it.
+    VonNeumann,
+}
+
+impl Neighbourhood {
+    pub const VARIANTS: &'static [Neighbourhood] =
+        &[Neighbourhood::Moore, Neighbourhood::VonNeumann];
+
+    fn offsets(self) -> &'static [(isize, isize)] {
+        match self {
+            Neighbourhood::Moore => &[
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (-1, 0),
+                (1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+            ],
+            Neighbourhood::VonNeumann => &[(0, -1), (-1, 0), (1, 0), (0, 1)],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct CaModel {
     pub name: String,
+    pub metadata: Metadata,
     pub core: CoreModel,
+    pub extension_files: Vec<String>,
+}
+
+impl std::ops::Deref for CaModel {
+    type Target = CoreModel;
+
+    fn deref(&self) -> &Self::Target {
+        &self.core
+    }
+}
+
+impl std::ops::DerefMut for CaModel {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.core
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaError {
+    /// No equation operates on a value, so there are no states to be in.
+    NoStates,
+    EmptyGrid,
+    UndefinedReference {
+        node: String,
+        name: String,
+    },
+    UnknownOperation {
+        node: String,
+        operation: String,
+    },
+    /// Extension functions are written in Python, so they can't be used by
+    /// the rules.
+    ExtensionFunction {
+        node: String,
+        function: String,
+    },
+    /// A composite that depends on itself.
+    Cycle {
+        node: String,
+    },
+    /// A function of the model that can't be defined.
+    InvalidFunction {
+        function: String,
+        reason: String,
+    },
+}
+
+impl Display for CaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CaError::NoStates => f.write_str("no equation operates on a state"),
+            CaError::EmptyGrid => f.write_str("the grid has no cells"),
+            CaError::UndefinedReference { node, name } => {
+                write!(f, "`{node}` refers to `{name}`, which does not exist")
+            }
+            CaError::UnknownOperation { node, operation } => {
+                write!(f, "`{node}` uses the unknown operation `{operation}`")
+            }
+            CaError::ExtensionFunction { node, function } => write!(
+                f,
+                "`{node}` uses the extension function `{function}`, which cellular automata \
+                 don't support"
+            ),
+            CaError::Cycle { node } => write!(f, "`{node}` depends on itself"),
+            CaError::InvalidFunction { function, reason } => {
+                write!(f, "`{function}` is not a valid function: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CaError {}
+
+/// The cells of a generation, each holding the index of its state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid {
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<usize>,
+}
+
+impl Grid {
+    /// A grid with every cell in `state`.
+    pub fn new(width: usize, height: usize, state: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![state; width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> usize {
+        self.cells[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, state: usize) {
+        self.cells[y * self.width + x] = state;
+    }
+
+    /// The states of the cells, row by row.
+    pub fn cells(&self) -> &[usize] {
+        &self.cells
+    }
+
+    /// How many cells are in each of the first `state_count` states.
+    pub fn count(&self, state_count: usize) -> Vec<usize> {
+        let mut counts = vec![0; state_count];
+        for &state in &self.cells {
+            counts[state] += 1;
+        }
+        counts
+    }
+
+    fn neighbour(&self, x: usize, y: usize, (dx, dy): (isize, isize)) -> usize {
+        let x = (x as isize + dx).rem_euclid(self.width as isize) as usize;
+        let y = (y as isize + dy).rem_euclid(self.height as isize) as usize;
+        self.get(x, y)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operation {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+}
+
+impl Operation {
+    fn parse(operation: &str) -> Option<Self> {
+        Some(match operation {
+            "+" => Self::Add,
+            "-" => Self::Sub,
+            "*" => Self::Mul,
+            "/" => Self::Div,
+            "==" => Self::Eq,
+            "!=" => Self::Ne,
+            "<" => Self::Lt,
+            ">" => Self::Gt,
+            "<=" => Self::Le,
+            ">=" => Self::Ge,
+            _ => return None,
+        })
+    }
+
+    /// Arithmetic is applied from left to right, while comparisons hold when
+    /// they hold for every pair of consecutive operands, as in `a < b < c`.
+    fn apply(self, operands: &[f64]) -> f64 {
+        let Some((&first, rest)) = operands.split_first() else {
+            return 0.0;
+        };
+
+        let compare = |holds: fn(f64, f64) -> bool| {
+            let all_hold = operands.windows(2).all(|pair| holds(pair[0], pair[1]));
+            if all_hold {
+                1.0
+            } else {
+                0.0
+            }
+        };
+
+        match self {
+            Operation::Add => rest.iter().fold(first, |acc, value| acc + value),
+            Operation::Sub => rest.iter().fold(first, |acc, value| acc - value),
+            Operation::Mul => rest.iter().fold(first, |acc, value| acc * value),
+            Operation::Div => rest.iter().fold(first, |acc, value| acc / value),
+            Operation::Eq => compare(|a, b| a == b),
+            Operation::Ne => compare(|a, b| a != b),
+            Operation::Lt => compare(|a, b| a < b),
+            Operation::Gt => compare(|a, b| a > b),
+            Operation::Le => compare(|a, b| a <= b),
+            Operation::Ge => compare(|a, b| a >= b),
+        }
+    }
+}
+
+/// A composite with every name resolved, so it can be evaluated for each
+/// cell without looking anything up.
+#[derive(Debug, Clone)]
+enum Rule {
+    Constant(f64),
+    /// How many neighbours are in the state with this index.
+    Neighbours(usize),
+    Negated(Box<Rule>),
+    Apply {
+        operation: Operation,
+        operands: Vec<Rule>,
+    },
+    Call {
+        function: Func,
+        operands: Vec<Rule>,
+    },
+}
+
+impl Rule {
+    fn eval(&self, neighbours: &[usize]) -> f64 {
+        match self {
+            Rule::Constant(value) => *value,
+            Rule::Neighbours(state) => neighbours[*state] as f64,
+            Rule::Negated(rule) => -rule.eval(neighbours),
+            Rule::Apply {
+                operation,
+                operands,
+            } => {
+                let operands: Vec<f64> =
+                    operands.iter().map(|rule| rule.eval(neighbours)).collect();
+                operation.apply(&operands)
+            }
+            Rule::Call { function, operands } => {
+                let operands: Vec<f64> =
+                    operands.iter().map(|rule| rule.eval(neighbours)).collect();
+                function.call(&operands).unwrap_or(0.0)
+            }
+        }
+    }
+
+    fn signed(self, contribution: char) -> Self {
+        match contribution {
+            '-' => Rule::Negated(Box::new(self)),
+            _ => self,
+        }
+    }
+}
+
+/// The rules of a [`CaModel`], ready to be applied to a [`Grid`].
+#[derive(Debug, Clone)]
+pub struct Automaton {
+    pub states: Vec<String>,
+    /// Share of the cells starting in each state, not normalized.
+    shares: Vec<f64>,
+    /// The rules adding up to the score of each state.
+    scores: Vec<Vec<Rule>>,
+    neighbourhood: Neighbourhood,
+}
+
+impl Automaton {
+    /// A grid whose cells are in each state with a probability proportional
+    /// to its share. If no share is positive, every state is equally likely.
+    pub fn initial_grid(&self, width: usize, height: usize, seed: u64) -> Grid {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let shares: Vec<f64> = if self.shares.iter().any(|&share| share > 0.0) {
+            self.shares.iter().map(|&share| share.max(0.0)).collect()
+        } else {
+            vec![1.0; self.states.len()]
+        };
+        let total: f64 = shares.iter().sum();
+
+        let mut grid = Grid::new(width, height, 0);
+        for cell in &mut grid.cells {
+            let mut drawn = rng.gen::<f64>() * total;
+            *cell = shares
+                .iter()
+                .position(|&share| {
+                    drawn -= share;
+                    drawn < 0.0
+                })
+                .unwrap_or(shares.len() - 1);
+        }
+        grid
+    }
+
+    /// Applies the rules to every cell at once.
+    pub fn step(&self, grid: &Grid) -> Grid {
+        let mut next = grid.clone();
+        let mut neighbours = vec![0; self.states.len()];
+
+        for y in 0..grid.height {
+            for x in 0..grid.width {
+                neighbours.fill(0);
+                for &offset in self.neighbourhood.offsets() {
+                    neighbours[grid.neighbour(x, y, offset)] += 1;
+                }
+
+                let mut best_score = 0.0;
+                for (state, rules) in self.scores.iter().enumerate() {
+                    let score: f64 = rules.iter().map(|rule| rule.eval(&neighbours)).sum();
+                    if score > best_score {
+                        best_score = score;
+                        next.set(x, y, state);
+                    }
+                }
+            }
+        }
+
+        next
+    }
+}
+
+/// Every generation of a simulated [`CaModel`], starting from the initial
+/// one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Simulation {
+    pub states: Vec<String>,
+    pub generations: Vec<Grid>,
+}
+
+impl Simulation {
+    /// How many cells are in each state over the generations, indexed by
+    /// state and then by generation.
+    pub fn counts(&self) -> Vec<Vec<usize>> {
+        let mut counts = vec![Vec::with_capacity(self.generations.len()); self.states.len()];
+        for grid in &self.generations {
+            for (state, count) in grid.count(self.states.len()).into_iter().enumerate() {
+                counts[state].push(count);
+            }
+        }
+        counts
+    }
+}
+
+impl CaModel {
+    pub fn new(name: String, metadata: Metadata) -> Self {
+        Self {
+            name,
+            metadata,
+            core: CoreModel::new(),
+            extension_files: Vec::new(),
+        }
+    }
+
+    /// The values the equations operate on, in the order of the equations.
+    pub fn states(&self) -> Vec<&str> {
+        let mut states = Vec::new();
+        for population in self
+            .equations
+            .iter()
+            .filter_map(|eq| eq.operates_on.as_deref())
+        {
+            let is_value = matches!(self.arguments.get(population), Some(Argument::Value { .. }));
+            if is_value && !states.contains(&population) {
+                states.push(population);
+            }
+        }
+        states
+    }
+
+    /// Resolves the rules of the model.
+    pub fn automaton(&self) -> Result<Automaton, CaError> {
+        let states = self.states();
+        if states.is_empty() {
+            return Err(CaError::NoStates);
+        }
+
+        let mut context = ExprContext::new();
+        self.define_functions(&mut context)
+            .map_err(|(function, err)| CaError::InvalidFunction {
+                function,
+                reason: err.to_string(),
+            })?;
+
+        let mut scores = vec![Vec::new(); states.len()];
+        for equation in &self.equations {
+            let Some(state) = equation
+                .operates_on
+                .as_deref()
+                .and_then(|population| states.iter().position(|&state| state == population))
+            else {
+                continue;
+            };
+
+            let rule = self.rule(
+                &equation.name,
+                &equation.argument,
+                &states,
+                &context,
+                &mut Vec::new(),
+            )?;
+            scores[state].push(rule.signed(equation.contribution));
+        }
+
+        let shares = states
+            .iter()
+            .map(|&state| match self.arguments.get(state) {
+                Some(Argument::Value { value, .. }) => *value,
+                _ => 0.0,
+            })
+            .collect();
+
+        Ok(Automaton {
+            states: states.into_iter().map(str::to_owned).collect(),
+            shares,
+            scores,
+            neighbourhood: self.metadata.neighbourhood,
+        })
+    }
+
+    /// Resolves the argument `name`, which `node` refers to, calling the
+    /// functions in `context`. `visiting` holds the composites being
+    /// resolved, to catch cycles.
+    fn rule<'a>(
+        &'a self,
+        node: &str,
+        name: &'a str,
+        states: &[&str],
+        context: &ExprContext,
+        visiting: &mut Vec<&'a str>,
+    ) -> Result<Rule, CaError> {
+        if let Some(state) = states.iter().position(|&state| state == name) {
+            return Ok(Rule::Neighbours(state));
+        }
+
+        match self.arguments.get(name) {
+            None => Err(CaError::UndefinedReference {
+                node: node.to_owned(),
+                name: name.to_owned(),
+            }),
+            Some(Argument::Value { value, .. }) => Ok(Rule::Constant(*value)),
+            Some(Argument::Composite {
+                operation,
+                style,
+                composition,
+                ..
+            }) => {
+                if visiting.contains(&name) {
+                    return Err(CaError::Cycle {
+                        node: name.to_owned(),
+                    });
+                }
+                let operands = |visiting: &mut Vec<&'a str>| {
+                    visiting.push(name);
+                    let operands = composition
+                        .iter()
+                        .map(|component| {
+                            self.rule(name, &component.name, states, context, visiting)
+                                .map(|rule| rule.signed(component.contribution))
+                        })
+                        .collect::<Result<_, _>>()?;
+                    visiting.pop();
+                    Ok(operands)
+                };
+
+                match style {
+                    CompositionStyle::Prefixed => {
+                        let function = context.get_function(operation.clone()).map_err(|_| {
+                            CaError::ExtensionFunction {
+                                node: name.to_owned(),
+                                function: operation.clone(),
+                            }
+                        })?;
+                        Ok(Rule::Call {
+                            function,
+                            operands: operands(visiting)?,
+                        })
+                    }
+                    CompositionStyle::Infixed => {
+                        let operation = Operation::parse(operation).ok_or_else(|| {
+                            CaError::UnknownOperation {
+                                node: name.to_owned(),
+                                operation: operation.clone(),
+                            }
+                        })?;
+                        Ok(Rule::Apply {
+                            operation,
+                            operands: operands(visiting)?,
+                        })
+                    }
+                }
+            }
+        }
+    }
+
+    /// Runs the automaton from a random grid, as set up in the metadata.
+    pub fn simulate(&self) -> Result<Simulation, CaError> {
+        let Metadata {
+            width,
+            height,
+            steps,
+            seed,
+            ..
+        } = self.metadata;
+        if width == 0 || height == 0 {
+            return Err(CaError::EmptyGrid);
+        }
+
+        let automaton = self.automaton()?;
+        let mut generations = Vec::with_capacity(steps + 1);
+        generations.push(automaton.initial_grid(width, height, seed));
+        for _ in 0..steps {
+            let next = automaton.step(generations.last().expect("There is an initial grid"));
+            generations.push(next);
+        }
+
+        Ok(Simulation {
+            states: automaton.states,
+            generations,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::{Component, Function},
+        Equation, Model,
+    };
+
+    const GAME_OF_LIFE: &str = include_str!("../../fixtures/game-of-life-grid.json");
+
+    fn game_of_life() -> CaModel {
+        let Model::CellularAutomata(model) = serde_json::from_str(GAME_OF_LIFE).unwrap() else {
+            panic!("The fixture is a cellular automaton");
+        };
+        model
+    }
+
+    /// Grid with the cells marked by `#` alive, in the fixture's states.
+    fn grid(rows: &[&str]) -> Grid {
+        let mut grid = Grid::new(rows[0].len(), rows.len(), 0);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, cell) in row.chars().enumerate() {
+                if cell == '#' {
+                    grid.set(x, y, 1);
+                }
+            }
+        }
+        grid
+    }
+
+    #[test]
+    fn game_of_life_blinker_oscillates() {
+        let automaton = game_of_life().automaton().unwrap();
+        assert_eq!(automaton.states, ["dead", "alive"]);
+
+        let horizontal = grid(&[".....", ".....", ".###.", ".....", "....."]);
+        let vertical = grid(&[".....", "..#..", "..#..", "..#..", "....."]);
+
+        assert_eq!(automaton.step(&horizontal), vertical);
+        assert_eq!(automaton.step(&vertical), horizontal);
+    }
+
+    #[test]
+    fn game_of_life_glider_wraps_around() {
+        let automaton = game_of_life().automaton().unwrap();
+        let glider = grid(&[".#....", "..#...", "###...", "......", "......", "......"]);
+
+        // A glider moves one cell diagonally every four generations
+        let mut moved = glider.clone();
+        for _ in 0..4 * 6 {
+            moved = automaton.step(&moved);
+        }
+
+        assert_eq!(moved, glider);
+    }
+
+    #[test]
+    fn simulation_is_reproducible() {
+        let mut model = game_of_life();
+        model.metadata = Metadata {
+            width: 16,
+            height: 8,
+            steps: 5,
+            neighbourhood: Neighbourhood::Moore,
+            seed: 42,
+        };
+
+        let simulation = model.simulate().unwrap();
+        assert_eq!(simulation.generations.len(), 6);
+        assert_eq!(simulation, model.simulate().unwrap());
+
+        let counts = simulation.counts();
+        assert_eq!(counts.len(), 2);
+        for (dead, alive) in counts[0].iter().zip(&counts[1]) {
+            assert_eq!(dead + alive, 16 * 8);
+        }
+    }
+
+    #[test]
+    fn initial_grid_follows_the_shares() {
+        let mut model = game_of_life();
+        model.insert_argument(Argument::Value {
+            name: "alive".into(),
+            value: 0.0,
+            unit: None,
+            info: None,
+        });
+
+        let grid = model.automaton().unwrap().initial_grid(10, 10, 7);
+        assert_eq!(grid.count(2), [100, 0]);
+    }
+
+    #[test]
+    fn broken_rules_are_reported() {
+        let mut model = CaModel::new("broken".into(), Metadata::default());
+        assert_eq!(model.simulate(), Err(CaError::NoStates));
+
+        model.insert_argument(Argument::Value {
+            name: "on".into(),
+            value: 1.0,
+            unit: None,
+            info: None,
+        });
+        model.insert_argument(Argument::Composite {
+            name: "rule".into(),
+            operation: "%".into(),
+            style: CompositionStyle::Infixed,
+            composition: vec![Component {
+                name: "on".into(),
+                contribution: '+',
+            }],
+        });
+        model.insert_equation(Equation {
+            name: "on_rule".into(),
+            operates_on: Some("on".into()),
+            argument: "rule".into(),
+            contribution: '+',
+        });
+        assert_eq!(
+            model.automaton().unwrap_err(),
+            CaError::UnknownOperation {
+                node: "rule".into(),
+                operation: "%".into()
+            }
+        );
+
+        model.insert_argument(Argument::Composite {
+            name: "rule".into(),
+            operation: "+".into(),
+            style: CompositionStyle::Infixed,
+            composition: vec![Component {
+                name: "rule".into(),
+                contribution: '+',
+            }],
+        });
+        assert_eq!(
+            model.automaton().unwrap_err(),
+            CaError::Cycle {
+                node: "rule".into()
+            }
+        );
+
+        model.metadata.width = 0;
+        assert_eq!(model.simulate(), Err(CaError::EmptyGrid));
+    }
+
+    #[test]
+    fn rules_may_call_functions() {
+        let mut model = game_of_life();
+        model.insert_argument(Argument::Composite {
+            name: "death".into(),
+            operation: "outside".into(),
+            style: CompositionStyle::Prefixed,
+            composition: ["alive", "two", "three"]
+                .into_iter()
+                .map(|name| Component {
+                    name: name.into(),
+                    contribution: '+',
+                })
+                .collect(),
+        });
+        model.functions.push(Function {
+            name: "outside".into(),
+            parameters: vec!["n".into(), "low".into(), "high".into()],
+            body: "(n < low) + (n > high)".into(),
+        });
+
+        let automaton = model.automaton().unwrap();
+        let horizontal = grid(&[".....", ".....", ".###.", ".....", "....."]);
+        let vertical = grid(&[".....", "..#..", "..#..", "..#..", "....."]);
+        assert_eq!(automaton.step(&horizontal), vertical);
+
+        model.functions[0].body = "n < lower".into();
+        assert!(matches!(
+            model.automaton().unwrap_err(),
+            CaError::InvalidFunction { function, .. } if function == "outside"
+        ));
+    }
 }
@@ -0,0 +1,176 @@
+//! Past values of populations, for delay differential equations such as
+//! `dX/dt = k * Y(t - tau)`.
+//!
+//! A delay is referred to by its name, like an argument, and stands for the
+//! value its population had `tau` units of time earlier. Before the start of
+//! the simulation, populations are taken to have always had their initial
+//! value. Unlike reactions, delays are kept when the model is flattened, as
+//! each code generator evaluates them in its own way.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::{Argument, CoreModel};
+use crate::validation::{Diagnostic, DiagnosticKind, Target};
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct Delay {
+    /// Name the delayed value is referred to by.
+    pub name: String,
+    /// The population whose past value is taken.
+    pub population: String,
+    /// How long ago the value is taken from, in units of time.
+    pub tau: f64,
+}
+
+impl Delay {
+    fn invalid(&self, reason: impl Into<String>) -> Diagnostic {
+        Diagnostic::new(
+            &self.name,
+            DiagnosticKind::InvalidDelay {
+                reason: reason.into(),
+            },
+        )
+    }
+}
+
+impl CoreModel {
+    pub fn get_delay(&self, name: &str) -> Option<&Delay> {
+        self.delays.iter().find(|delay| delay.name == name)
+    }
+
+    /// Whether `name` is an argument or a delay, which expressions may refer
+    /// to.
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.arguments.contains_key(name) || self.get_delay(name).is_some()
+    }
+
+    pub(crate) fn validate_delays(&self, target: Target, diagnostics: &mut Vec<Diagnostic>) {
+        for (idx, delay) in self.delays.iter().enumerate() {
+            if !target.is_valid_identifier(&delay.name) {
+                diagnostics.push(Diagnostic::new(
+                    &delay.name,
+                    DiagnosticKind::InvalidIdentifier { target },
+                ));
+            }
+
+            if self.arguments.contains_key(&delay.name)
+                || self.delays[..idx]
+                    .iter()
+                    .any(|other| other.name == delay.name)
+            {
+                diagnostics.push(delay.invalid(format!("`{}` is already defined", delay.name)));
+            }
+
+            let is_population = matches!(
+                self.arguments.get(&delay.population),
+                Some(Argument::Value { .. })
+            ) && self
+                .equations
+                .iter()
+                .any(|eq| eq.operates_on.as_ref() == Some(&delay.population));
+            if !is_population {
+                diagnostics
+                    .push(delay.invalid(format!("`{}` is not a population", delay.population)));
+            }
+
+            if !(delay.tau.is_finite() && delay.tau > 0.0) {
+                diagnostics.push(delay.invalid("its delay must be positive"));
+            }
+
+            // Only the generated Python and the native simulation look delays up
+            if target != Target::Python {
+                diagnostics.push(delay.invalid(format!("delays can't be generated in {target}")));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        models::{
+            ode::{Metadata, OdeModel},
+            submodel::{Flattened, Port, Submodel},
+        },
+        transformations::test_support::*,
+    };
+
+    use super::*;
+
+    fn delay(name: &str, population: &str, tau: f64) -> Delay {
+        Delay {
+            name: name.into(),
+            population: population.into(),
+            tau,
+        }
+    }
+
+    /// `dXdt = -k * X_lag`, where `X_lag` is `X` one unit of time earlier.
+    fn delayed_decay() -> OdeModel {
+        let mut model = OdeModel::new("delayed decay".into(), Metadata::default());
+        model.insert_argument(value("X", 1.0));
+        model.insert_argument(value("k", 0.5));
+        model.insert_argument(composite("decay", "*", [argument("k", '-'), arg("X_lag")]));
+        model.insert_equation(equation("dXdt", "X", arg("decay")));
+        model.delays.push(delay("X_lag", "X", 1.0));
+        model
+    }
+
+    #[test]
+    fn delays_can_be_referred_to() {
+        let model = delayed_decay();
+
+        assert!(model.is_defined("X_lag"));
+        assert_eq!(model.validate(Target::Python), []);
+        assert!(model
+            .validate(Target::Julia)
+            .iter()
+            .any(Diagnostic::is_error));
+    }
+
+    #[test]
+    fn broken_delays_are_reported() {
+        let mut model = delayed_decay();
+        model.delays.extend([
+            delay("k", "X", 1.0),
+            delay("from_constant", "k", 1.0),
+            delay("instant", "X", 0.0),
+        ]);
+
+        let diagnostics: Vec<_> = model
+            .validate(Target::Python)
+            .into_iter()
+            .map(|diagnostic| diagnostic.node)
+            .collect();
+        assert_eq!(diagnostics, ["k", "from_constant", "instant"]);
+    }
+
+    #[test]
+    fn delays_of_submodels_are_inlined() {
+        let mut parent = OdeModel::new("parent".into(), Metadata::default());
+        parent.insert_argument(value("Y", 2.0));
+        parent.submodels.push(Submodel {
+            name: "child".into(),
+            path: "child.json".into(),
+            ports: vec![Port {
+                name: "X".into(),
+                connects_to: Some("Y".into()),
+            }],
+        });
+
+        let flattened = parent.flatten_with(|_| {
+            Ok(Flattened {
+                model: delayed_decay(),
+                diagnostics: Vec::new(),
+            })
+        });
+
+        assert_eq!(flattened.diagnostics, []);
+        assert_eq!(flattened.model.delays, [delay("child_X_lag", "Y", 1.0)]);
+        assert!(matches!(
+            &flattened.model.arguments["child_decay"],
+            Argument::Composite { composition, .. } if composition[1].name == "child_X_lag"
+        ));
+    }
+}
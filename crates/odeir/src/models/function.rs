@@ -0,0 +1,258 @@
+//! Functions declared by a model in the expression language, such as
+//! `hill(x, k, n) = x ^ n / (k ^ n + x ^ n)`.
+//!
+//! Prefixed composites call them by name, as they do extension functions.
+//! Unlike extensions, which are Python code, their bodies are expressions of
+//! their parameters, so every code generator writes them in its own language
+//! and the native simulation evaluates them. A body may call the builtins and
+//! the functions declared before it, which rules out recursion.
+
+use expr_evaluator::expr::{ExprContext, ExprError, Expression, Node};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::{Argument, CompositionStyle, CoreModel};
+use crate::validation::{Diagnostic, DiagnosticKind, Target};
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct Function {
+    /// Name the function is called by.
+    pub name: String,
+    /// Names the arguments are referred to by in the body, in order.
+    pub parameters: Vec<String>,
+    /// Expression of the parameters the function evaluates to.
+    pub body: String,
+}
+
+impl Function {
+    pub fn parse_body(&self) -> Result<Box<Node>, ExprError> {
+        let mut expr = Expression::new();
+        expr.parse_expr(self.body.clone())?;
+        expr.ast.ok_or(ExprError::UndefinedAST)
+    }
+
+    fn invalid(&self, reason: impl Into<String>) -> Diagnostic {
+        Diagnostic::new(
+            &self.name,
+            DiagnosticKind::InvalidFunction {
+                reason: reason.into(),
+            },
+        )
+    }
+}
+
+impl CoreModel {
+    pub fn get_function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+
+    /// Defines the functions of the model in `context`, in the order they
+    /// were declared.
+    pub fn define_functions(&self, context: &mut ExprContext) -> Result<(), (String, ExprError)> {
+        for function in &self.functions {
+            function
+                .parse_body()
+                .and_then(|body| {
+                    context.define_function(&function.name, &function.parameters, &body)
+                })
+                .map_err(|error| (function.name.clone(), error))?;
+        }
+        Ok(())
+    }
+
+    /// The expression with the calls to the functions of the model replaced
+    /// by their bodies, so that it only calls builtins.
+    pub fn inline_functions(&self, node: &Node) -> Result<Box<Node>, ExprError> {
+        // Functions only call the ones before them, so inlining from the last
+        // one leaves no call behind
+        let mut node = Box::new(node.clone());
+        for function in self.functions.iter().rev() {
+            node = node.inline(
+                &function.name,
+                &function.parameters,
+                &*function.parse_body()?,
+            )?;
+        }
+        Ok(node)
+    }
+
+    pub(crate) fn validate_functions(&self, target: Target, diagnostics: &mut Vec<Diagnostic>) {
+        let mut context = ExprContext::new();
+
+        for function in &self.functions {
+            let names = std::iter::once(&function.name).chain(&function.parameters);
+            for name in names {
+                if !target.is_valid_identifier(name) {
+                    diagnostics.push(Diagnostic::new(
+                        name,
+                        DiagnosticKind::InvalidIdentifier { target },
+                    ));
+                }
+            }
+
+            // The generated code defines functions alongside the arguments
+            if self.is_defined(&function.name) || context.funcs.contains_key(&function.name) {
+                diagnostics
+                    .push(function.invalid(format!("`{}` is already defined", function.name)));
+                continue;
+            }
+
+            // Bodies are checked by compiling them as the native simulation
+            // does, which also defines them for the functions that follow
+            if let Err(error) = function.parse_body().and_then(|body| {
+                context.define_function(&function.name, &function.parameters, &body)
+            }) {
+                diagnostics.push(function.invalid(error.to_string()));
+            }
+        }
+
+        for arg in self.arguments.values() {
+            let Argument::Composite {
+                name,
+                operation,
+                style: CompositionStyle::Prefixed,
+                composition,
+            } = arg
+            else {
+                continue;
+            };
+            let Some(function) = self.get_function(operation) else {
+                continue;
+            };
+
+            if composition.len() != function.parameters.len() {
+                diagnostics.push(Diagnostic::new(
+                    name,
+                    DiagnosticKind::ArgumentCount {
+                        function: function.name.clone(),
+                        expected: function.parameters.len(),
+                        found: composition.len(),
+                    },
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        models::submodel::{Flattened, Submodel},
+        transformations::test_support::*,
+    };
+
+    use super::*;
+
+    fn function(name: &str, parameters: &[&str], body: &str) -> Function {
+        Function {
+            name: name.into(),
+            parameters: parameters
+                .iter()
+                .map(|&parameter| parameter.into())
+                .collect(),
+            body: body.into(),
+        }
+    }
+
+    #[test]
+    fn functions_are_defined_in_order() {
+        let mut model = hill_growth();
+        model.functions.push(function("half", &["k"], "hill(k, k)"));
+
+        assert_eq!(model.validate(Target::Python), []);
+        assert_eq!(model.validate(Target::C), []);
+
+        let mut context = ExprContext::new();
+        model.define_functions(&mut context).unwrap();
+        let half = context.get_function("half".into()).unwrap();
+        assert_eq!(half.call(&[4.0]).unwrap(), 0.5);
+
+        let inlined = model
+            .inline_functions(&Node::call("half", vec![Node::var("X")]))
+            .unwrap();
+        assert_eq!(inlined.to_string(), "X ^ 2 / (X ^ 2 + X ^ 2)");
+    }
+
+    #[test]
+    fn broken_functions_are_reported() {
+        let mut model = hill_growth();
+        model.functions.extend([
+            function("sqrt", &["x"], "x"),
+            function("K", &["x"], "x"),
+            function("free", &["x"], "x * K"),
+            function("early", &["x"], "later(x)"),
+            function("later", &["x"], "x +"),
+            function("twice", &["x", "x"], "x"),
+        ]);
+        model.insert_argument(Argument::Composite {
+            name: "partial".into(),
+            operation: "hill".into(),
+            style: CompositionStyle::Prefixed,
+            composition: vec![arg("X")],
+        });
+
+        let diagnostics = model.validate(Target::Python);
+        let nodes: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.node.as_str())
+            .collect();
+        assert_eq!(
+            nodes,
+            ["sqrt", "K", "free", "early", "later", "twice", "partial"]
+        );
+        assert_eq!(
+            diagnostics[2].to_string(),
+            "`free` is not a valid function: The var K was not defined"
+        );
+        assert_eq!(
+            diagnostics[6].to_string(),
+            "`partial` calls `hill` with 1 argument(s), but it takes 2"
+        );
+    }
+
+    #[test]
+    fn functions_of_submodels_are_inlined() {
+        let mut parent = hill_growth();
+        parent.submodels.push(Submodel {
+            name: "child".into(),
+            path: "child.json".into(),
+            ports: Vec::new(),
+        });
+
+        let flattened = parent.flatten_with(|_| {
+            let mut child = hill_growth();
+            child.functions.push(function("double", &["x"], "2 * x"));
+            Ok(Flattened {
+                model: child,
+                diagnostics: Vec::new(),
+            })
+        });
+
+        assert_eq!(flattened.diagnostics, []);
+        let names: Vec<_> = flattened
+            .model
+            .functions
+            .iter()
+            .map(|function| function.name.as_str())
+            .collect();
+        assert_eq!(names, ["hill", "double"]);
+
+        let clashing = parent.flatten_with(|_| {
+            let mut child = hill_growth();
+            child.functions[0].body = "x".into();
+            Ok(Flattened {
+                model: child,
+                diagnostics: Vec::new(),
+            })
+        });
+        assert_eq!(
+            clashing.diagnostics,
+            [Diagnostic::new(
+                "hill",
+                DiagnosticKind::NameClash {
+                    submodel: "child".into()
+                }
+            )]
+        );
+    }
+}
@@ -1,15 +1,20 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use super::CoreModel;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
 pub struct Metadata {
     pub start_time: f64,
     pub delta_time: f64,
     pub end_time: f64,
+    /// Unit the times are measured in, which the rate of change of every
+    /// population is checked against.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_unit: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct OdeModel {
     pub name: String,
     pub metadata: Metadata,
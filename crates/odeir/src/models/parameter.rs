@@ -0,0 +1,263 @@
+use rand::Rng;
+use rand_distr::{Distribution as _, LogNormal, Normal, Uniform};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::{Argument, CoreModel};
+use crate::Map;
+
+/// What is known about a value besides the value itself: what it means,
+/// where it was taken from and how uncertain it is.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Default)]
+pub struct ParameterInfo {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    /// Literature reference or DOI the value was taken from.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub reference: String,
+    /// Range of plausible values, used as the search range when estimating
+    /// the parameter.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bounds: Option<Bounds>,
+    /// Prior distribution of the value, for uncertainty analyses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prior: Option<Distribution>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Distribution {
+    Uniform {
+        min: f64,
+        max: f64,
+    },
+    Normal {
+        mean: f64,
+        std_dev: f64,
+    },
+    /// Distribution whose logarithm is normal with mean `mu` and standard
+    /// deviation `sigma`.
+    LogNormal {
+        mu: f64,
+        sigma: f64,
+    },
+}
+
+impl ParameterInfo {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Draws a value from the prior, or uniformly within the bounds when
+    /// there is no prior.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<f64> {
+        match (self.prior, self.bounds) {
+            (Some(prior), _) => prior.sample(rng),
+            (None, Some(Bounds { min, max })) => Distribution::Uniform { min, max }.sample(rng),
+            (None, None) => None,
+        }
+    }
+}
+
+impl Bounds {
+    pub fn is_valid(&self) -> bool {
+        self.min.is_finite() && self.max.is_finite() && self.min <= self.max
+    }
+
+    pub fn contains(&self, value: f64) -> bool {
+        (self.min..=self.max).contains(&value)
+    }
+}
+
+impl Distribution {
+    pub const VARIANTS: &'static [Distribution] = &[
+        Distribution::Uniform { min: 0.0, max: 1.0 },
+        Distribution::Normal {
+            mean: 0.0,
+            std_dev: 1.0,
+        },
+        Distribution::LogNormal {
+            mu: 0.0,
+            sigma: 1.0,
+        },
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Distribution::Uniform { .. } => "uniform",
+            Distribution::Normal { .. } => "normal",
+            Distribution::LogNormal { .. } => "log-normal",
+        }
+    }
+
+    /// The two numbers defining the distribution, in declaration order.
+    pub fn params_mut(&mut self) -> [&mut f64; 2] {
+        match self {
+            Distribution::Uniform { min, max } => [min, max],
+            Distribution::Normal { mean, std_dev } => [mean, std_dev],
+            Distribution::LogNormal { mu, sigma } => [mu, sigma],
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            Distribution::Uniform { min, max } => min.is_finite() && max.is_finite() && min < max,
+            Distribution::Normal { mean, std_dev } => mean.is_finite() && std_dev > 0.0,
+            Distribution::LogNormal { mu, sigma } => mu.is_finite() && sigma > 0.0,
+        }
+    }
+
+    /// Draws a value, or `None` if the distribution is not
+    /// [valid](Self::is_valid).
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<f64> {
+        if !self.is_valid() {
+            return None;
+        }
+
+        let value = match *self {
+            Distribution::Uniform { min, max } => Uniform::new(min, max).sample(rng),
+            Distribution::Normal { mean, std_dev } => Normal::new(mean, std_dev).ok()?.sample(rng),
+            Distribution::LogNormal { mu, sigma } => LogNormal::new(mu, sigma).ok()?.sample(rng),
+        };
+        Some(value)
+    }
+}
+
+impl CoreModel {
+    /// Draws a value for every argument that has a prior or bounds, keyed by
+    /// name, so the model can be simulated over its parameters' uncertainty.
+    pub fn sample_parameters<R: Rng + ?Sized>(&self, rng: &mut R) -> Map<String, f64> {
+        self.arguments
+            .values()
+            .filter_map(|arg| match arg {
+                Argument::Value {
+                    name,
+                    info: Some(info),
+                    ..
+                } => Some((name.clone(), info.sample(rng)?)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn value(name: &str, value: f64, info: ParameterInfo) -> Argument {
+        Argument::Value {
+            name: name.into(),
+            value,
+            unit: None,
+            info: Some(info),
+        }
+    }
+
+    #[test]
+    fn samples_follow_priors_and_bounds() {
+        let mut model = CoreModel::new();
+        model.insert_argument(value(
+            "beta",
+            0.008,
+            ParameterInfo {
+                prior: Some(Distribution::LogNormal {
+                    mu: 0.008_f64.ln(),
+                    sigma: 0.5,
+                }),
+                ..Default::default()
+            },
+        ));
+        model.insert_argument(value(
+            "gamma",
+            0.3,
+            ParameterInfo {
+                bounds: Some(Bounds { min: 0.1, max: 0.5 }),
+                ..Default::default()
+            },
+        ));
+        model.insert_argument(value(
+            "alpha",
+            0.1,
+            ParameterInfo {
+                description: "Recovery rate".into(),
+                ..Default::default()
+            },
+        ));
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let samples: Vec<_> = (0..1000)
+            .map(|_| model.sample_parameters(&mut rng))
+            .collect();
+
+        assert!(samples
+            .iter()
+            .all(|sample| sample.keys().eq(["beta", "gamma"])));
+        assert!(samples.iter().all(|sample| sample["beta"] > 0.0
+            && Bounds { min: 0.1, max: 0.5 }.contains(sample["gamma"])));
+
+        let mean_gamma = samples.iter().map(|sample| sample["gamma"]).sum::<f64>() / 1000.0;
+        assert!((mean_gamma - 0.3).abs() < 0.01, "{mean_gamma}");
+    }
+
+    #[test]
+    fn invalid_distributions_are_not_sampled() {
+        let mut rng = StdRng::seed_from_u64(42);
+
+        assert_eq!(
+            Distribution::Normal {
+                mean: 1.0,
+                std_dev: 0.0
+            }
+            .sample(&mut rng),
+            None
+        );
+        assert_eq!(
+            Distribution::Uniform { min: 1.0, max: 1.0 }.sample(&mut rng),
+            None
+        );
+    }
+
+    #[test]
+    fn info_is_stored_in_the_model() {
+        let arg: Argument = serde_json::from_str(
+            r#"{
+                "name": "beta",
+                "value": 0.008,
+                "info": {
+                    "reference": "doi:10.1098/rspa.1927.0118",
+                    "bounds": { "min": 0.001, "max": 0.01 },
+                    "prior": { "type": "log-normal", "mu": -4.8, "sigma": 0.5 }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let Argument::Value {
+            info: Some(info), ..
+        } = &arg
+        else {
+            panic!("Expected a value with info, got {arg:?}");
+        };
+        assert_eq!(info.reference, "doi:10.1098/rspa.1927.0118");
+        assert_eq!(
+            info.prior,
+            Some(Distribution::LogNormal {
+                mu: -4.8,
+                sigma: 0.5
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<Argument>(serde_json::to_value(&arg).unwrap()).unwrap(),
+            arg
+        );
+    }
+}
@@ -0,0 +1,547 @@
+//! Reactions between populations, such as `E + S -> ES`, which are compiled
+//! into the arguments and equations of an ODE model.
+//!
+//! Each reaction turns its reactants into its products at the rate given by
+//! its [`RateLaw`]. The rate is an argument named after the reaction, so other
+//! expressions may refer to it. Every species the reaction changes gets a
+//! term in its equation for each unit of stoichiometry, added for products
+//! and subtracted for reactants. Species on both sides, like enzymes, only
+//! change by the difference.
+//!
+//! Species that already have an equation keep it, and the reactions are
+//! added to it. Otherwise a `d{species}dt` equation is created. Like
+//! submodels, reactions are compiled by
+//! [`OdeModel::flatten`](super::ode::OdeModel::flatten), before any code is
+//! generated.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::{Argument, Component, CompositionStyle, CoreModel, Equation};
+use crate::{
+    validation::{Diagnostic, DiagnosticKind},
+    Map,
+};
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct Reaction {
+    /// Name of the reaction, which its rate is referred to by.
+    pub name: String,
+    pub reactants: Vec<Species>,
+    pub products: Vec<Species>,
+    pub rate: RateLaw,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+pub struct Species {
+    /// The population taking part in the reaction.
+    pub name: String,
+    /// How many of it the reaction consumes or produces.
+    pub stoichiometry: u32,
+}
+
+/// How fast a reaction happens. Parameters are the names of constants of the
+/// model.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "law", rename_all = "kebab-case")]
+pub enum RateLaw {
+    /// `k` times every reactant to the power of its stoichiometry.
+    MassAction { k: String },
+    /// `vmax * S / (km + S)`, where `S` is the first reactant.
+    MichaelisMenten { vmax: String, km: String },
+    /// `vmax * S^n / (k^n + S^n)`, where `S` is the first reactant.
+    Hill { vmax: String, k: String, n: u32 },
+}
+
+impl RateLaw {
+    /// Names of the parameters, in the same order as [`RateLaw::parameters`]
+    /// and [`RateLaw::parameters_mut`].
+    pub fn parameter_names(&self) -> &'static [&'static str] {
+        match self {
+            RateLaw::MassAction { .. } => &["k"],
+            RateLaw::MichaelisMenten { .. } => &["vmax", "km"],
+            RateLaw::Hill { .. } => &["vmax", "k"],
+        }
+    }
+
+    pub fn parameters(&self) -> Vec<&String> {
+        match self {
+            RateLaw::MassAction { k } => vec![k],
+            RateLaw::MichaelisMenten { vmax, km } => vec![vmax, km],
+            RateLaw::Hill { vmax, k, .. } => vec![vmax, k],
+        }
+    }
+    pub fn parameters_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            RateLaw::MassAction { k } => vec![k],
+            RateLaw::MichaelisMenten { vmax, km } => vec![vmax, km],
+            RateLaw::Hill { vmax, k, .. } => vec![vmax, k],
+        }
+    }
+}
+
+fn components(names: impl IntoIterator<Item = impl Into<String>>) -> Vec<Component> {
+    names
+        .into_iter()
+        .map(|name| Component {
+            name: name.into(),
+            contribution: '+',
+        })
+        .collect()
+}
+
+fn composite(name: impl Into<String>, operation: &str, composition: Vec<Component>) -> Argument {
+    Argument::Composite {
+        name: name.into(),
+        operation: operation.into(),
+        style: CompositionStyle::Infixed,
+        composition,
+    }
+}
+
+/// `name` repeated `times` times.
+fn repeated(name: &str, times: u32) -> impl Iterator<Item = &str> {
+    std::iter::repeat_n(name, times as usize)
+}
+
+impl Reaction {
+    fn invalid(&self, reason: impl Into<String>) -> Diagnostic {
+        Diagnostic::new(
+            &self.name,
+            DiagnosticKind::InvalidReaction {
+                reason: reason.into(),
+            },
+        )
+    }
+
+    /// Problems that keep the reaction from being compiled.
+    fn check(&self) -> Option<Diagnostic> {
+        if self.reactants.is_empty() && self.products.is_empty() {
+            return Some(Diagnostic::new(
+                &self.name,
+                DiagnosticKind::EmptyComposition,
+            ));
+        }
+        if let Some(species) = self
+            .reactants
+            .iter()
+            .chain(&self.products)
+            .find(|species| species.stoichiometry == 0)
+        {
+            return Some(self.invalid(format!("`{}` has no stoichiometry", species.name)));
+        }
+
+        match &self.rate {
+            RateLaw::MassAction { .. } => None,
+            RateLaw::Hill { n: 0, .. } => Some(self.invalid("the Hill coefficient is 0")),
+            RateLaw::MichaelisMenten { .. } | RateLaw::Hill { .. } if self.reactants.is_empty() => {
+                Some(self.invalid("its rate law needs a reactant to saturate"))
+            }
+            RateLaw::MichaelisMenten { .. } | RateLaw::Hill { .. } => None,
+        }
+    }
+
+    /// The arguments computing the rate of the reaction, the last one being
+    /// the rate itself.
+    fn rate_arguments(&self) -> Vec<Argument> {
+        let name = &self.name;
+        let (vmax, k, n) = match &self.rate {
+            RateLaw::MassAction { k } => {
+                let reactants = self
+                    .reactants
+                    .iter()
+                    .flat_map(|species| repeated(&species.name, species.stoichiometry));
+                let composition = components(std::iter::once(k.as_str()).chain(reactants));
+                return vec![composite(name, "*", composition)];
+            }
+            RateLaw::MichaelisMenten { vmax, km } => (vmax, km, 1),
+            RateLaw::Hill { vmax, k, n } => (vmax, k, *n),
+        };
+
+        let substrate = &self.reactants[0].name;
+        let mut arguments = Vec::new();
+        let mut power = |base: &str, suffix: &str| {
+            if n == 1 {
+                return base.to_owned();
+            }
+            let power_name = format!("{name}_{suffix}");
+            arguments.push(composite(&power_name, "*", components(repeated(base, n))));
+            power_name
+        };
+        let threshold = power(k, "threshold");
+        let occupancy = power(substrate, "occupancy");
+
+        let activity = format!("{name}_activity");
+        let saturation = format!("{name}_saturation");
+        arguments.push(composite(
+            &activity,
+            "*",
+            components([vmax.as_str(), occupancy.as_str()]),
+        ));
+        arguments.push(composite(
+            &saturation,
+            "+",
+            components([threshold, occupancy]),
+        ));
+        arguments.push(composite(name, "/", components([activity, saturation])));
+        arguments
+    }
+
+    /// How much each species changes every time the reaction happens.
+    fn net_change(&self) -> Map<&str, i64> {
+        let mut changes = Map::new();
+        for species in &self.reactants {
+            *changes.entry(species.name.as_str()).or_default() -= i64::from(species.stoichiometry);
+        }
+        for species in &self.products {
+            *changes.entry(species.name.as_str()).or_default() += i64::from(species.stoichiometry);
+        }
+        changes
+    }
+}
+
+/// The argument summing up what the reactions do to `species`.
+fn reactions_name(species: &str) -> String {
+    format!("{species}_reactions")
+}
+
+impl CoreModel {
+    /// Replaces the reactions with the arguments and equations they stand
+    /// for, returning the ones that couldn't be compiled.
+    pub(crate) fn compile_reactions(&mut self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        // Terms of the equation of each species, in the order they're found
+        let mut terms: Map<String, Vec<Component>> = Map::new();
+
+        for reaction in std::mem::take(&mut self.reactions) {
+            if let Some(diagnostic) = reaction.check() {
+                diagnostics.push(diagnostic);
+                continue;
+            }
+
+            let arguments = reaction.rate_arguments();
+            if let Some(clash) = arguments
+                .iter()
+                .find(|arg| self.arguments.contains_key(arg.name()))
+            {
+                diagnostics
+                    .push(reaction.invalid(format!("`{}` is already defined", clash.name())));
+                continue;
+            }
+            arguments
+                .into_iter()
+                .for_each(|arg| self.insert_argument(arg));
+
+            for (species, change) in reaction.net_change() {
+                let contribution = if change < 0 { '-' } else { '+' };
+                terms.entry(species.to_owned()).or_default().extend(
+                    repeated(&reaction.name, change.unsigned_abs() as u32).map(|name| Component {
+                        name: name.to_owned(),
+                        contribution,
+                    }),
+                );
+            }
+        }
+
+        for (species, mut composition) in terms {
+            if composition.is_empty() {
+                continue;
+            }
+
+            let name = reactions_name(&species);
+            if self.arguments.contains_key(&name) {
+                diagnostics.push(Diagnostic::new(
+                    &species,
+                    DiagnosticKind::InvalidReaction {
+                        reason: format!("`{name}` is already defined"),
+                    },
+                ));
+                continue;
+            }
+
+            match self
+                .equations
+                .iter_mut()
+                .find(|eq| eq.operates_on.as_deref() == Some(species.as_str()))
+            {
+                Some(eq) => {
+                    composition.insert(
+                        0,
+                        Component {
+                            name: std::mem::replace(&mut eq.argument, name.clone()),
+                            contribution: eq.contribution,
+                        },
+                    );
+                    eq.contribution = '+';
+                }
+                None => self.equations.push(Equation {
+                    name: format!("d{species}dt"),
+                    operates_on: Some(species.clone()),
+                    argument: name.clone(),
+                    contribution: '+',
+                }),
+            }
+
+            self.insert_argument(composite(name, "+", composition));
+        }
+
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        models::ode::{Metadata, OdeModel},
+        transformations::test_support::{argument, value},
+        validation::Target,
+    };
+
+    use super::*;
+
+    fn species(name: &str, stoichiometry: u32) -> Species {
+        Species {
+            name: name.into(),
+            stoichiometry,
+        }
+    }
+
+    fn signed(components: &[(&str, char)]) -> Vec<Component> {
+        components
+            .iter()
+            .map(|&(name, contribution)| argument(name, contribution))
+            .collect()
+    }
+
+    fn model(arguments: &[(&str, f64)], reactions: Vec<Reaction>) -> OdeModel {
+        let mut model = OdeModel::new("reactions".into(), Metadata::default());
+        for &(name, initial) in arguments {
+            model.insert_argument(value(name, initial));
+        }
+        model.reactions = reactions;
+        model
+    }
+
+    #[test]
+    fn mass_action_follows_stoichiometry() {
+        let mut model = model(
+            &[("A", 1.0), ("B", 2.0), ("C", 0.0), ("k", 0.1)],
+            vec![Reaction {
+                name: "binding".into(),
+                reactants: vec![species("A", 2), species("B", 1)],
+                products: vec![species("C", 1)],
+                rate: RateLaw::MassAction { k: "k".into() },
+            }],
+        );
+
+        assert_eq!(model.compile_reactions(), []);
+
+        assert_eq!(
+            model.arguments["binding"],
+            composite("binding", "*", components(["k", "A", "A", "B"]))
+        );
+        assert_eq!(
+            model.arguments["A_reactions"],
+            composite(
+                "A_reactions",
+                "+",
+                signed(&[("binding", '-'), ("binding", '-')])
+            )
+        );
+        assert_eq!(
+            model.arguments["C_reactions"],
+            composite("C_reactions", "+", signed(&[("binding", '+')]))
+        );
+        let equations: Vec<_> = model
+            .equations
+            .iter()
+            .map(|eq| (eq.name.as_str(), eq.argument.as_str()))
+            .collect();
+        assert_eq!(
+            equations,
+            [
+                ("dAdt", "A_reactions"),
+                ("dBdt", "B_reactions"),
+                ("dCdt", "C_reactions")
+            ]
+        );
+        assert!(model.reactions.is_empty());
+        assert!(model.validate(Target::Python).is_empty());
+    }
+
+    #[test]
+    fn enzymes_are_not_consumed() {
+        let mut model = model(
+            &[("E", 1.0), ("S", 10.0), ("P", 0.0), ("kcat", 2.0)],
+            vec![Reaction {
+                name: "catalysis".into(),
+                reactants: vec![species("E", 1), species("S", 1)],
+                products: vec![species("E", 1), species("P", 1)],
+                rate: RateLaw::MassAction { k: "kcat".into() },
+            }],
+        );
+
+        assert_eq!(model.compile_reactions(), []);
+
+        assert!(!model.arguments.contains_key("E_reactions"));
+        assert!(model
+            .equations
+            .iter()
+            .all(|eq| eq.operates_on.as_deref() != Some("E")));
+    }
+
+    #[test]
+    fn hill_kinetics_saturate() {
+        let mut model = model(
+            &[("S", 1.0), ("P", 0.0), ("vmax", 1.0), ("K", 0.5)],
+            vec![Reaction {
+                name: "uptake".into(),
+                reactants: vec![species("S", 1)],
+                products: vec![species("P", 1)],
+                rate: RateLaw::Hill {
+                    vmax: "vmax".into(),
+                    k: "K".into(),
+                    n: 2,
+                },
+            }],
+        );
+
+        assert_eq!(model.compile_reactions(), []);
+
+        assert_eq!(
+            model.arguments["uptake"],
+            composite(
+                "uptake",
+                "/",
+                components(["uptake_activity", "uptake_saturation"])
+            )
+        );
+        assert_eq!(
+            model.arguments["uptake_activity"],
+            composite(
+                "uptake_activity",
+                "*",
+                components(["vmax", "uptake_occupancy"])
+            )
+        );
+        assert_eq!(
+            model.arguments["uptake_saturation"],
+            composite(
+                "uptake_saturation",
+                "+",
+                components(["uptake_threshold", "uptake_occupancy"])
+            )
+        );
+        assert_eq!(
+            model.arguments["uptake_occupancy"],
+            composite("uptake_occupancy", "*", components(["S", "S"]))
+        );
+    }
+
+    #[test]
+    fn reactions_add_to_existing_equations() {
+        let mut model = model(
+            &[("X", 1.0), ("k", 0.1)],
+            vec![Reaction {
+                name: "decay".into(),
+                reactants: vec![species("X", 1)],
+                products: vec![],
+                rate: RateLaw::MassAction { k: "k".into() },
+            }],
+        );
+        model.insert_argument(composite("influx", "*", components(["k"])));
+        model.insert_equation(Equation {
+            name: "dXdt".into(),
+            operates_on: Some("X".into()),
+            argument: "influx".into(),
+            contribution: '-',
+        });
+
+        assert_eq!(model.compile_reactions(), []);
+
+        assert_eq!(
+            model.equations,
+            [Equation {
+                name: "dXdt".into(),
+                operates_on: Some("X".into()),
+                argument: "X_reactions".into(),
+                contribution: '+',
+            }]
+        );
+        assert_eq!(
+            model.arguments["X_reactions"],
+            composite(
+                "X_reactions",
+                "+",
+                signed(&[("influx", '-'), ("decay", '-')])
+            )
+        );
+    }
+
+    #[test]
+    fn enzymatic_example_is_compiled() {
+        let content = include_str!("../../../../examples/enzymatic-reactions.json");
+        let crate::Model::ODE(model) = serde_json::from_str(content).unwrap() else {
+            panic!("The example is an ODE model");
+        };
+
+        let flattened = model.flatten(Path::new(""));
+
+        assert!(flattened.validate(Target::Python).is_empty());
+        assert_eq!(
+            flattened.model.arguments["E_reactions"],
+            composite(
+                "E_reactions",
+                "+",
+                signed(&[
+                    ("binding", '-'),
+                    ("unbinding", '+'),
+                    ("catalysis", '+'),
+                    ("inhibition", '-'),
+                    ("release", '+'),
+                ])
+            )
+        );
+        assert_eq!(flattened.model.equations.len(), 6);
+    }
+
+    #[test]
+    fn broken_reactions_are_reported() {
+        let mut model = model(
+            &[("S", 1.0), ("vmax", 1.0), ("km", 1.0), ("k", 1.0)],
+            vec![
+                Reaction {
+                    name: "nothing".into(),
+                    reactants: vec![],
+                    products: vec![],
+                    rate: RateLaw::MassAction { k: "k".into() },
+                },
+                Reaction {
+                    name: "source".into(),
+                    reactants: vec![],
+                    products: vec![species("S", 1)],
+                    rate: RateLaw::MichaelisMenten {
+                        vmax: "vmax".into(),
+                        km: "km".into(),
+                    },
+                },
+                Reaction {
+                    name: "S".into(),
+                    reactants: vec![species("S", 1)],
+                    products: vec![],
+                    rate: RateLaw::MassAction { k: "k".into() },
+                },
+            ],
+        );
+
+        let nodes: Vec<_> = model
+            .compile_reactions()
+            .into_iter()
+            .map(|diagnostic| diagnostic.node)
+            .collect();
+        assert_eq!(nodes, ["nothing", "source", "S"]);
+        assert!(model.equations.is_empty());
+    }
+}
@@ -0,0 +1,254 @@
+//! Reaction channels of an ODE model, so it can be simulated stochastically.
+//!
+//! Every equation is read as a sum of terms, looking into the arguments that
+//! add or subtract others. Each distinct term is a channel, which fires at the
+//! rate the term gives and changes every population whose equation it appears
+//! in by one unit, up or down depending on its sign there. A term found in
+//! several equations, like an infection taking from one population and adding
+//! to another, is a single channel moving units between them. Compiled
+//! [reactions](super::reaction) are one channel each, changing their species
+//! by their stoichiometry.
+//!
+//! Rates are the terms themselves, so channels are only meaningful for models
+//! made of reactions or mass-action terms. A channel whose rate is negative
+//! fires backwards.
+
+use super::{Argument, CompositionStyle, CoreModel};
+use crate::Map;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Channel {
+    /// Name of the argument giving the rate.
+    pub name: String,
+    /// The rate, as an expression of the values of the model.
+    pub rate: String,
+    /// How much each population changes whenever the channel fires.
+    pub changes: Vec<(String, i64)>,
+}
+
+impl CoreModel {
+    /// Splits the equations into channels, ordered by name. Terms that
+    /// cancel out, changing no population, are left out.
+    pub fn channels(&self) -> Vec<Channel> {
+        // Change of each population, per term
+        let mut terms: Map<String, Map<String, i64>> = Map::new();
+
+        for equation in &self.equations {
+            let Some(population) = &equation.operates_on else {
+                continue;
+            };
+            let sign = if equation.contribution == '-' { -1 } else { 1 };
+
+            self.visit_terms(&equation.argument, sign, 0, &mut |term, sign| {
+                *terms
+                    .entry(term.to_owned())
+                    .or_default()
+                    .entry(population.clone())
+                    .or_default() += sign;
+            });
+        }
+
+        terms
+            .into_iter()
+            .filter_map(|(name, changes)| {
+                let changes: Vec<_> = changes
+                    .into_iter()
+                    .filter(|(_, change)| *change != 0)
+                    .collect();
+
+                (!changes.is_empty()).then(|| Channel {
+                    rate: self.render_rate(&name, 0),
+                    name,
+                    changes,
+                })
+            })
+            .collect()
+    }
+
+    /// Calls `visit` with every term `name` adds up, and its sign. `depth`
+    /// keeps cyclic arguments, which validation reports, from recursing
+    /// forever.
+    fn visit_terms(&self, name: &str, sign: i64, depth: usize, visit: &mut impl FnMut(&str, i64)) {
+        match self.arguments.get(name) {
+            Some(Argument::Composite {
+                operation,
+                style: CompositionStyle::Infixed,
+                composition,
+                ..
+            }) if (operation == "+" || operation == "-") && depth <= self.arguments.len() => {
+                for (idx, component) in composition.iter().enumerate() {
+                    // `a - b - c` subtracts everything but the first operand
+                    let subtracted = operation == "-" && idx > 0;
+                    let negated = (component.contribution == '-') != subtracted;
+
+                    let sign = if negated { -sign } else { sign };
+                    self.visit_terms(&component.name, sign, depth + 1, visit);
+                }
+            }
+            _ => visit(name, sign),
+        }
+    }
+
+    /// Writes the argument out as an expression of values, in the same
+    /// notation as [`render_txt_with_equations`](crate::transformations::ode::render_txt_with_equations).
+    fn render_rate(&self, name: &str, depth: usize) -> String {
+        match self.arguments.get(name) {
+            Some(Argument::Composite {
+                operation,
+                style,
+                composition,
+                ..
+            }) if depth <= self.arguments.len() => {
+                let operands: Vec<String> = composition
+                    .iter()
+                    .map(|component| {
+                        let operand = self.render_rate(&component.name, depth + 1);
+                        match component.contribution {
+                            '-' => format!("(- {operand})"),
+                            _ => operand,
+                        }
+                    })
+                    .collect();
+
+                match style {
+                    CompositionStyle::Infixed => format!("({})", operands.join(operation)),
+                    CompositionStyle::Prefixed => {
+                        format!("{operation}({})", operands.join(","))
+                    }
+                }
+            }
+            _ => name.to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        models::{
+            ode::{Metadata, OdeModel},
+            reaction::{RateLaw, Reaction, Species},
+        },
+        transformations::test_support::*,
+    };
+
+    use super::*;
+
+    fn changes(changes: &[(&str, i64)]) -> Vec<(String, i64)> {
+        changes
+            .iter()
+            .map(|&(population, change)| (population.into(), change))
+            .collect()
+    }
+
+    #[test]
+    fn shared_terms_move_units_between_populations() {
+        let mut model = OdeModel::new("sir".into(), Metadata::default());
+        for (name, initial) in [
+            ("S", 99.0),
+            ("I", 1.0),
+            ("R", 0.0),
+            ("beta", 0.3),
+            ("gamma", 0.1),
+        ] {
+            model.insert_argument(value(name, initial));
+        }
+        model.insert_argument(composite(
+            "infection",
+            "*",
+            [arg("beta"), arg("S"), arg("I")],
+        ));
+        model.insert_argument(composite("recovery", "*", [arg("gamma"), arg("I")]));
+        model.insert_argument(composite("I_eq", "-", [arg("infection"), arg("recovery")]));
+        model.insert_argument(composite("S_eq", "+", [argument("infection", '-')]));
+        model.insert_equation(equation("dSdt", "S", arg("S_eq")));
+        model.insert_equation(equation("dIdt", "I", arg("I_eq")));
+        model.insert_equation(equation("dRdt", "R", arg("recovery")));
+
+        assert_eq!(
+            model.channels(),
+            vec![
+                Channel {
+                    name: "infection".into(),
+                    rate: "(beta*S*I)".into(),
+                    changes: changes(&[("I", 1), ("S", -1)]),
+                },
+                Channel {
+                    name: "recovery".into(),
+                    rate: "(gamma*I)".into(),
+                    changes: changes(&[("I", -1), ("R", 1)]),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reactions_are_one_channel_each() {
+        let mut model = OdeModel::new("dimerization".into(), Metadata::default());
+        for (name, initial) in [("A", 10.0), ("A2", 0.0), ("k", 0.1)] {
+            model.insert_argument(value(name, initial));
+        }
+        model.reactions.push(Reaction {
+            name: "dimerization".into(),
+            reactants: vec![Species {
+                name: "A".into(),
+                stoichiometry: 2,
+            }],
+            products: vec![Species {
+                name: "A2".into(),
+                stoichiometry: 1,
+            }],
+            rate: RateLaw::MassAction { k: "k".into() },
+        });
+
+        let flattened = model.flatten(Path::new(""));
+
+        assert_eq!(
+            flattened.model.channels(),
+            vec![Channel {
+                name: "dimerization".into(),
+                rate: "(k*A*A)".into(),
+                changes: changes(&[("A", -2), ("A2", 1)]),
+            }]
+        );
+    }
+
+    #[test]
+    fn negated_terms_are_parenthesized() {
+        let mut model = OdeModel::new("decay".into(), Metadata::default());
+        for (name, initial) in [("X", 5.0), ("k", 0.5)] {
+            model.insert_argument(value(name, initial));
+        }
+        model.insert_argument(composite("decay", "*", [argument("k", '-'), arg("X")]));
+        model.insert_equation(equation("dXdt", "X", arg("decay")));
+
+        let channels = model.channels();
+
+        assert_eq!(channels.len(), 1);
+        assert_eq!(channels[0].rate, "((- k)*X)");
+        assert_eq!(channels[0].changes, changes(&[("X", 1)]));
+    }
+
+    #[test]
+    fn viral_infection_example_is_split() {
+        let content = include_str!("../../../../examples/viral-infection.json");
+        let crate::Model::ODE(model) = serde_json::from_str(content).unwrap() else {
+            panic!("The example is an ODE model");
+        };
+
+        let channels = model.channels();
+        let infection = channels
+            .iter()
+            .find(|channel| channel.name == "infection")
+            .unwrap();
+        let death = channels
+            .iter()
+            .find(|channel| channel.name == "I_death")
+            .unwrap();
+
+        assert_eq!(infection.changes, changes(&[("E", 1), ("I", -1)]));
+        assert_eq!(death.changes, changes(&[("I", -1), ("V", 1)]));
+    }
+}
@@ -0,0 +1,486 @@
+//! Models embedded in other models as namespaced modules.
+//!
+//! A [`Submodel`] refers to another model file and exposes some of its
+//! arguments as ports, which the parent model refers to as `namespace.port`.
+//! A port may also be connected to an argument of the parent, which then
+//! replaces the submodel's own definition of it, so the submodel's equations
+//! act on the parent's population.
+//!
+//! The code generators only understand flat models, so submodels are inlined
+//! by [`OdeModel::flatten`] first, and everything they define is renamed to
+//! `namespace_name`. Flattening also compiles the
+//! [reactions](super::reaction) of the model.
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::{ode::OdeModel, Argument, CoreModel, Delay, Equation};
+use crate::{
+    json::FormatError,
+    validation::{Diagnostic, DiagnosticKind, Target},
+    Map, Model,
+};
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct Submodel {
+    /// Namespace of the submodel, which its ports are referred to by.
+    pub name: String,
+    /// Path of the model file, relative to the file of the parent model.
+    pub path: String,
+    pub ports: Vec<Port>,
+}
+
+/// An argument of a submodel that the parent model can refer to.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct Port {
+    pub name: String,
+    /// Argument of the parent model that replaces the submodel's own.
+    pub connects_to: Option<String>,
+}
+
+/// A model with its submodels inlined, along with the problems found while
+/// inlining them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Flattened {
+    pub model: OdeModel,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug)]
+pub enum SubmodelError {
+    Io(std::io::Error),
+    Format(FormatError),
+    NotOde,
+    /// The model includes itself, directly or through other submodels.
+    Cycle,
+}
+
+impl Display for SubmodelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubmodelError::Io(err) => err.fmt(f),
+            SubmodelError::Format(err) => err.fmt(f),
+            SubmodelError::NotOde => f.write_str("only ODE models can be used as submodels"),
+            SubmodelError::Cycle => f.write_str("the model includes itself"),
+        }
+    }
+}
+
+impl std::error::Error for SubmodelError {}
+
+impl From<std::io::Error> for SubmodelError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<FormatError> for SubmodelError {
+    fn from(value: FormatError) -> Self {
+        Self::Format(value)
+    }
+}
+
+/// How the parent model refers to the port `name` of `namespace`.
+pub fn qualified_name(namespace: &str, name: &str) -> String {
+    format!("{namespace}.{name}")
+}
+
+/// The name an argument of a submodel is given once inlined.
+pub fn inlined_name(namespace: &str, name: &str) -> String {
+    format!("{namespace}_{name}")
+}
+
+impl Submodel {
+    /// Reads the model the submodel refers to, relative to `dir`, and
+    /// flattens it.
+    pub fn load(&self, dir: &Path) -> Result<Flattened, SubmodelError> {
+        load(&dir.join(&self.path), &mut Vec::new())
+    }
+}
+
+/// `including` holds the files whose submodels are being loaded, to catch
+/// models that include themselves.
+fn load(path: &Path, including: &mut Vec<PathBuf>) -> Result<Flattened, SubmodelError> {
+    let path = path.canonicalize()?;
+    if including.contains(&path) {
+        return Err(SubmodelError::Cycle);
+    }
+
+    let content = std::fs::read_to_string(&path)?;
+    let value: serde_json::Value = serde_json::from_str(&content).map_err(FormatError::from)?;
+    let Model::ODE(model) = Model::try_from(value)? else {
+        return Err(SubmodelError::NotOde);
+    };
+
+    let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    including.push(path);
+    let flattened = model.flatten_with(|submodel| {
+        load(&dir.join(&submodel.path), including).map_err(|err| err.to_string())
+    });
+    including.pop();
+
+    Ok(flattened)
+}
+
+impl Flattened {
+    /// Validates the flat model, along with the problems found while
+    /// flattening it.
+    pub fn validate(&self, target: Target) -> Vec<Diagnostic> {
+        let mut diagnostics = self.diagnostics.clone();
+        diagnostics.extend(self.model.validate(target));
+
+        diagnostics.sort_by_key(|diagnostic| std::cmp::Reverse(diagnostic.severity()));
+        diagnostics
+    }
+}
+
+fn renamed(arg: &Argument, rename: impl Fn(&str) -> String) -> Argument {
+    let mut arg = arg.clone();
+    match &mut arg {
+        Argument::Value { name, .. } => *name = rename(name),
+        Argument::Composite {
+            name, composition, ..
+        } => {
+            *name = rename(name);
+            for component in composition {
+                component.name = rename(&component.name);
+            }
+        }
+    }
+    arg
+}
+
+impl OdeModel {
+    /// Inlines the submodels, reading their files relative to `dir`.
+    pub fn flatten(&self, dir: &Path) -> Flattened {
+        self.flatten_with(|submodel| submodel.load(dir).map_err(|err| err.to_string()))
+    }
+
+    /// Inlines the submodels, which `resolve` provides already flattened, or
+    /// the reason they couldn't be loaded. Reactions are compiled into
+    /// equations as well.
+    pub fn flatten_with(
+        &self,
+        mut resolve: impl FnMut(&Submodel) -> Result<Flattened, String>,
+    ) -> Flattened {
+        let mut model = OdeModel {
+            name: self.name.clone(),
+            metadata: self.metadata.clone(),
+            extension_files: self.extension_files.clone(),
+            core: CoreModel {
+                arguments: self.arguments.clone(),
+                equations: self.equations.clone(),
+                positions: self.positions.clone(),
+                submodels: Vec::new(),
+                reactions: self.reactions.clone(),
+                delays: self.delays.clone(),
+                functions: self.functions.clone(),
+            },
+        };
+        let mut diagnostics = Vec::new();
+        // What each `namespace.port` stands for in the flat model
+        let mut ports: Map<String, String> = Map::new();
+
+        for submodel in &self.submodels {
+            let namespace = submodel.name.as_str();
+            let child = match resolve(submodel) {
+                Ok(child) => child,
+                Err(reason) => {
+                    diagnostics.push(Diagnostic::new(
+                        namespace,
+                        DiagnosticKind::InvalidSubmodel {
+                            path: submodel.path.clone(),
+                            reason,
+                        },
+                    ));
+                    continue;
+                }
+            };
+
+            diagnostics.extend(child.diagnostics.into_iter().map(|diagnostic| Diagnostic {
+                node: qualified_name(namespace, &diagnostic.node),
+                ..diagnostic
+            }));
+            let child = child.model;
+
+            let connections: Map<&str, &str> = submodel
+                .ports
+                .iter()
+                .filter_map(|port| Some((port.name.as_str(), port.connects_to.as_deref()?)))
+                .collect();
+            let rename = |name: &str| match connections.get(name) {
+                Some(connects_to) => (*connects_to).to_owned(),
+                None => inlined_name(namespace, name),
+            };
+
+            for port in &submodel.ports {
+                if child.arguments.contains_key(&port.name) {
+                    ports.insert(qualified_name(namespace, &port.name), rename(&port.name));
+                } else {
+                    diagnostics.push(Diagnostic::new(
+                        namespace,
+                        DiagnosticKind::UndefinedReference {
+                            name: qualified_name(namespace, &port.name),
+                        },
+                    ));
+                }
+            }
+
+            for arg in child.arguments.values() {
+                if connections.contains_key(arg.name()) {
+                    continue;
+                }
+
+                let arg = renamed(arg, rename);
+                if model.arguments.contains_key(arg.name()) {
+                    diagnostics.push(Diagnostic::new(
+                        arg.name(),
+                        DiagnosticKind::NameClash {
+                            submodel: namespace.to_owned(),
+                        },
+                    ));
+                } else {
+                    model.insert_argument(arg);
+                }
+            }
+
+            model
+                .equations
+                .extend(child.equations.iter().map(|eq| Equation {
+                    name: inlined_name(namespace, &eq.name),
+                    operates_on: eq.operates_on.as_deref().map(rename),
+                    argument: rename(&eq.argument),
+                    contribution: eq.contribution,
+                }));
+
+            for delay in &child.delays {
+                let delay = Delay {
+                    name: rename(&delay.name),
+                    population: rename(&delay.population),
+                    tau: delay.tau,
+                };
+                if model.is_defined(&delay.name) {
+                    diagnostics.push(Diagnostic::new(
+                        &delay.name,
+                        DiagnosticKind::NameClash {
+                            submodel: namespace.to_owned(),
+                        },
+                    ));
+                } else {
+                    model.delays.push(delay);
+                }
+            }
+
+            // Functions only refer to their parameters, so they keep their
+            // names, and the ones shared with the parent are merged
+            for function in &child.functions {
+                match model.get_function(&function.name) {
+                    Some(other) if other == function => {}
+                    Some(_) => diagnostics.push(Diagnostic::new(
+                        &function.name,
+                        DiagnosticKind::NameClash {
+                            submodel: namespace.to_owned(),
+                        },
+                    )),
+                    None => model.functions.push(function.clone()),
+                }
+            }
+
+            // Extension files are relative to the submodel's file
+            let dir = Path::new(&submodel.path).parent().unwrap_or(Path::new(""));
+            for file in &child.extension_files {
+                let file = dir.join(file).to_string_lossy().into_owned();
+                if !model.extension_files.contains(&file) {
+                    model.extension_files.push(file);
+                }
+            }
+        }
+
+        // A port may be connected to the port of another submodel, so names
+        // are followed until they leave the namespaces
+        let resolve_port = |name: &mut String| {
+            for _ in 0..ports.len() {
+                match ports.get(name.as_str()) {
+                    Some(target) => *name = target.clone(),
+                    None => break,
+                }
+            }
+        };
+
+        for arg in model.arguments.values_mut() {
+            if let Argument::Composite { composition, .. } = arg {
+                composition
+                    .iter_mut()
+                    .for_each(|component| resolve_port(&mut component.name));
+            }
+        }
+        for eq in &mut model.equations {
+            resolve_port(&mut eq.argument);
+            if let Some(population) = &mut eq.operates_on {
+                resolve_port(population);
+            }
+        }
+        for reaction in &mut model.reactions {
+            reaction
+                .reactants
+                .iter_mut()
+                .chain(&mut reaction.products)
+                .for_each(|species| resolve_port(&mut species.name));
+            reaction
+                .rate
+                .parameters_mut()
+                .into_iter()
+                .for_each(resolve_port);
+        }
+
+        for delay in &mut model.delays {
+            resolve_port(&mut delay.population);
+        }
+
+        diagnostics.extend(model.compile_reactions());
+
+        Flattened { model, diagnostics }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::ode::Metadata;
+    use crate::transformations::test_support::*;
+
+    use super::*;
+
+    fn fixtures() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/submodels")
+    }
+
+    fn load_fixture(name: &str) -> OdeModel {
+        let content = std::fs::read_to_string(fixtures().join(name)).unwrap();
+        let Model::ODE(model) = serde_json::from_str(&content).unwrap() else {
+            panic!("{name} is an ODE model");
+        };
+        model
+    }
+
+    fn port(name: &str, connects_to: Option<&str>) -> Port {
+        Port {
+            name: name.into(),
+            connects_to: connects_to.map(Into::into),
+        }
+    }
+
+    fn names(model: &OdeModel) -> Vec<&str> {
+        model.arguments.keys().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn ports_are_connected_and_namespaced() {
+        let mut growth = OdeModel::new("growth".into(), Metadata::default());
+        growth.insert_argument(value("N", 1.0));
+        growth.insert_argument(value("r", 0.5));
+        growth.insert_argument(composite("dN", "*", [arg("r"), arg("N")]));
+        growth.insert_equation(equation("dNdt", "N", arg("dN")));
+
+        let mut parent = OdeModel::new("parent".into(), Metadata::default());
+        parent.insert_argument(value("X", 10.0));
+        parent.insert_argument(composite("loss", "*", [arg("first.r"), arg("second.N")]));
+        parent.insert_equation(equation("dXdt", "X", arg("loss")));
+        parent.submodels = vec![
+            Submodel {
+                name: "first".into(),
+                path: "growth.json".into(),
+                ports: vec![port("N", Some("X")), port("r", None)],
+            },
+            Submodel {
+                name: "second".into(),
+                path: "growth.json".into(),
+                ports: vec![port("N", None)],
+            },
+        ];
+
+        let Flattened { model, diagnostics } = parent.flatten_with(|_| {
+            Ok(Flattened {
+                model: growth.clone(),
+                diagnostics: Vec::new(),
+            })
+        });
+
+        assert_eq!(diagnostics, []);
+        assert_eq!(
+            names(&model),
+            [
+                "X",
+                "first_dN",
+                "first_r",
+                "loss",
+                "second_N",
+                "second_dN",
+                "second_r"
+            ]
+        );
+        assert_eq!(
+            model.arguments["first_dN"],
+            composite("first_dN", "*", [arg("first_r"), arg("X")])
+        );
+        assert_eq!(
+            model.arguments["loss"],
+            composite("loss", "*", [arg("first_r"), arg("second_N")])
+        );
+        assert_eq!(
+            model.equations,
+            [
+                equation("dXdt", "X", arg("loss")),
+                equation("first_dNdt", "X", arg("first_dN")),
+                equation("second_dNdt", "second_N", arg("second_dN")),
+            ]
+        );
+        assert!(model.submodels.is_empty());
+    }
+
+    #[test]
+    fn submodels_are_read_from_files() {
+        let Flattened { model, diagnostics } = load_fixture("host.json").flatten(&fixtures());
+
+        assert_eq!(diagnostics, []);
+        assert!(!model.arguments.contains_key("immune_P"));
+        assert_eq!(
+            model.arguments["clearance"],
+            composite("clearance", "*", [arg("c"), arg("V"), arg("immune_M1")])
+        );
+        assert_eq!(
+            model.arguments["immune_activation"],
+            composite("immune_activation", "*", [arg("immune_k"), arg("V")])
+        );
+        assert!(model.validate(Target::Python).is_empty());
+    }
+
+    #[test]
+    fn broken_submodels_are_reported() {
+        let Flattened { diagnostics, .. } = load_fixture("broken.json").flatten(&fixtures());
+        let kinds: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.node.as_str(), &diagnostic.kind))
+            .collect();
+
+        assert!(
+            matches!(
+                kinds[..],
+                [
+                    ("missing", DiagnosticKind::InvalidSubmodel { .. }),
+                    ("loop.again", DiagnosticKind::InvalidSubmodel { .. }),
+                    (
+                        "immune",
+                        DiagnosticKind::UndefinedReference { name }
+                    ),
+                    ("immune_k", DiagnosticKind::NameClash { .. }),
+                ] if name == "immune.Q"
+            ),
+            "{kinds:?}"
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use minijinja::{context, Environment};
+
+use crate::{models::ode::OdeModel, validation::Target, Map};
+
+use super::expression;
+
+const C_TEMPLATE: &str = include_str!("../../templates/ode.h.jinja");
+
+/// Renders the model as a C header, also usable from C++, to embed it in
+/// other simulators. It holds the `rhs` function with index constants, the
+/// initial state and the default parameters, plus a fixed-step RK4 driver if
+/// `with_driver` is set.
+pub fn render_c_header(model: &OdeModel, with_driver: bool) -> String {
+    let env = Environment::new();
+
+    let populations = model.get_populations().collect::<Vec<_>>();
+    let constants = model.get_constants().collect::<Vec<_>>();
+    let equations = model
+        .equations
+        .iter()
+        .cloned()
+        .filter_map(|eq| Some((eq.operates_on.clone()?, eq)))
+        .collect::<Map<_, _>>();
+
+    // Extensions are Python code, so they must be provided by the user
+    let has_prefixed = expression::uses_extensions(model);
+
+    let mut ctx = context! {
+        model => model,
+        metadata => model.metadata,
+        equations => equations,
+        populations => populations,
+        constants => constants,
+        has_prefixed => has_prefixed,
+        functions => expression::definitions(model, Target::C),
+        driver => with_driver,
+        guard => include_guard(&model.name),
+    };
+
+    env.render_str(C_TEMPLATE, &mut ctx).unwrap()
+}
+
+fn include_guard(model_name: &str) -> String {
+    let name: String = model_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if name.is_empty() {
+        "ODE_MODEL_H".to_owned()
+    } else {
+        format!("ODE_{name}_H")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::ode::Metadata;
+    use crate::transformations::test_support::*;
+
+    use super::*;
+
+    #[test]
+    fn render_simple() {
+        let mut model = OdeModel::new(
+            "abc".into(),
+            Metadata {
+                start_time: 0.0,
+                delta_time: 0.1,
+                end_time: 10.0,
+                time_unit: None,
+            },
+        );
+
+        model.insert_argument(value("A", 10_f64));
+        model.insert_argument(value("B", 20_f64));
+        model.insert_argument(value("k", 0.5));
+
+        model.insert_argument(composite("A+B", "+", [arg("A"), arg("B")]));
+        model.insert_equation(equation("dA/dt", "A", arg("A+B")));
+
+        model.insert_argument(composite("(A+B)*k", "*", [arg("A+B"), argument("k", '-')]));
+        model.insert_equation(equation("dB/dt", "B", argument("(A+B)*k", '-')));
+
+        let header = render_c_header(&model, true);
+
+        const EXPECTED: &str = include_str!("fixtures/abc_ode.h");
+
+        assert_eq!(header, EXPECTED);
+    }
+
+    #[test]
+    fn driver_is_optional() {
+        let mut model = OdeModel::new("".into(), Metadata::default());
+        model.insert_argument(value("A", 1.0));
+        model.insert_argument(composite("-A", "*", [argument("A", '-')]));
+        model.insert_equation(equation("dA/dt", "A", arg("-A")));
+
+        let header = render_c_header(&model, false);
+
+        assert!(header.starts_with("/* Generated by ODE-Designer. */\n#ifndef ODE_MODEL_H\n"));
+        assert!(header.contains("dydt[POP_A] = - A;"));
+        assert!(!header.contains("PARAM_"));
+        assert!(!header.contains("ode_rk4_step"));
+    }
+
+    #[test]
+    fn constants_only() {
+        let mut model = OdeModel::new("".into(), Metadata::default());
+        model.insert_argument(value("k", 0.5));
+
+        let header = render_c_header(&model, true);
+
+        // Empty enums and zero-length arrays aren't valid C
+        assert!(header.contains("#include <math.h>"));
+        assert!(header.contains("PARAM_k = 0,"));
+        assert!(!header.contains("POP_"));
+        assert!(!header.contains("[ODE_N_POPULATIONS]"));
+        assert!(!header.contains("ode_rk4_step"));
+    }
+
+    #[test]
+    fn render_functions() {
+        let header = render_c_header(&hill_growth(), false);
+
+        assert!(header.contains("#include <math.h>"));
+        assert!(header.contains("static inline double hill(double x, double k)\n{\n    return pow(x, 2.0) / (pow(k, 2.0) + pow(x, 2.0));\n}"));
+        assert!(header.contains("dydt[POP_X] = hill(X, K);"));
+        assert!(!header.contains("extension functions"));
+    }
+}
@@ -0,0 +1,307 @@
+//! Expressions written in the languages code is generated in, such as the
+//! bodies of the functions a model declares and the entries of its Jacobian.
+//!
+//! The builtins of expressions are named as in numpy, some of them under
+//! several names, so each language gets them under its own name, or spelled
+//! out when it has no equivalent. Any other call, to a function of the model
+//! or to an extension, is written as it is.
+
+use expr_evaluator::expr::{Node, NodeType, Operator, Precedence};
+use minijinja::{context, Value};
+
+use crate::{
+    models::{Argument, CompositionStyle, CoreModel},
+    validation::Target,
+};
+
+/// Builtins, by the name they are written by.
+const BUILTINS: &[&str] = &[
+    "sqrt", "cbrt", "exp", "exp2", "expm1", "log", "log2", "log10", "log1p", "sin", "cos", "tan",
+    "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh", "arcsinh", "arccosh", "arctanh", "abs",
+    "floor", "ceil", "trunc", "round", "sign", "pow", "arctan2", "hypot", "min", "max",
+];
+
+/// Other names of the builtins, with the name they are written by.
+const ALIASES: &[(&str, &str)] = &[
+    ("ln", "log"),
+    ("asin", "arcsin"),
+    ("acos", "arccos"),
+    ("atan", "arctan"),
+    ("asinh", "arcsinh"),
+    ("acosh", "arccosh"),
+    ("atanh", "arctanh"),
+    ("fabs", "abs"),
+    ("power", "pow"),
+    ("atan2", "arctan2"),
+    ("minimum", "min"),
+    ("maximum", "max"),
+];
+
+/// Writes `node` in the language of `target`.
+pub fn write(node: &Node, target: Target) -> String {
+    print(&translate(node, target), target)
+}
+
+/// The functions of the model, in the order they are declared, with their
+/// `name`, `parameters` and `body` written in the language of `target`.
+/// Bodies that don't parse are left as they are, validation reports them.
+pub fn definitions(model: &CoreModel, target: Target) -> Vec<Value> {
+    model
+        .functions
+        .iter()
+        .map(|function| {
+            let body = match function.parse_body() {
+                Ok(body) => write(&body, target),
+                Err(_) => function.body.clone(),
+            };
+            context! {
+                name => function.name,
+                parameters => function.parameters,
+                body => body,
+            }
+        })
+        .collect()
+}
+
+/// The name the builtin `name` is written by, or `None` if it isn't one.
+pub fn builtin(name: &str) -> Option<&'static str> {
+    let name = ALIASES
+        .iter()
+        .find(|&&(alias, _)| alias == name)
+        .map_or(name, |&(_, name)| name);
+    BUILTINS.iter().copied().find(|&builtin| builtin == name)
+}
+
+/// Whether a composite calls a function the model doesn't declare, which is
+/// an extension function written in Python.
+pub fn uses_extensions(model: &CoreModel) -> bool {
+    model.arguments.values().any(|arg| {
+        matches!(
+            arg,
+            Argument::Composite {
+                operation,
+                style: CompositionStyle::Prefixed,
+                ..
+            } if model.get_function(operation).is_none()
+        )
+    })
+}
+
+/// Replaces the builtins by what they are in the language of `target`, so
+/// that what is left can be written operator by operator.
+fn translate(node: &Node, target: Target) -> Box<Node> {
+    let Node::Leaf(leaf) = node else {
+        let translated = node.map_operands(|operand| Ok(translate(operand, target)));
+        return translated.expect("translating never fails");
+    };
+    if !matches!(leaf.node_type, NodeType::Function) {
+        return Box::new(node.clone());
+    }
+
+    let mut args: Vec<_> = leaf.args.iter().map(|arg| translate(arg, target)).collect();
+    let Some(name) = builtin(&leaf.name) else {
+        return Node::call(&leaf.name, args);
+    };
+
+    let arg = |idx: usize, args: &[Box<Node>]| {
+        args.get(idx)
+            .cloned()
+            .unwrap_or_else(|| Node::constant(f64::NAN))
+    };
+    match (target, name) {
+        (Target::Python, "min" | "max") => Node::call(name, args),
+        (Target::Python, "pow") => Node::call("np.power", args),
+        (Target::Python, name) => Node::call(&format!("np.{name}"), args),
+
+        (Target::Julia | Target::R, "pow") => {
+            Node::binary(Operator::Pow, arg(0, &args), arg(1, &args))
+        }
+        (Target::Julia, "arctan2") => Node::call("atan", args),
+        (Target::R, "ceil") => Node::call("ceiling", args),
+        (Target::R, "exp2") => Node::binary(Operator::Pow, Node::constant(2.0), arg(0, &args)),
+        (Target::R, "cbrt") => {
+            let x = arg(0, &args);
+            let magnitude = Node::binary(
+                Operator::Pow,
+                Node::call("abs", vec![x.clone()]),
+                Node::binary(Operator::Div, Node::constant(1.0), Node::constant(3.0)),
+            );
+            Node::binary(Operator::Mult, Node::call("sign", vec![x]), magnitude)
+        }
+        (Target::R, "hypot") => {
+            let square = |x| Node::binary(Operator::Pow, x, Node::constant(2.0));
+            let sum = Node::binary(Operator::Plus, square(arg(0, &args)), square(arg(1, &args)));
+            Node::call("sqrt", vec![sum])
+        }
+        (Target::C, "abs") => Node::call("fabs", args),
+        (Target::C, "round") => Node::call("nearbyint", args),
+        (Target::C, "sign") => {
+            let x = arg(0, &args);
+            Node::binary(
+                Operator::Minus,
+                Node::binary(Operator::Gt, x.clone(), Node::constant(0.0)),
+                Node::binary(Operator::Lt, x, Node::constant(0.0)),
+            )
+        }
+        // `fmin` and `fmax` only take two arguments
+        (Target::C, "min" | "max") if !args.is_empty() => {
+            let function = format!("f{name}");
+            let first = args.remove(0);
+            args.into_iter().fold(first, |folded, arg| {
+                Node::call(&function, vec![folded, arg])
+            })
+        }
+
+        (_, name) => match name.strip_prefix("arc") {
+            Some(name) => Node::call(&format!("a{name}"), args),
+            None => Node::call(name, args),
+        },
+    }
+}
+
+/// Writes `node` operator by operator, once its builtins are translated.
+fn print(node: &Node, target: Target) -> String {
+    let operand = |node: &Node, parenthesized: bool| {
+        if parenthesized {
+            format!("({})", print(node, target))
+        } else {
+            print(node, target)
+        }
+    };
+
+    match node {
+        Node::Leaf(leaf) => match leaf.node_type {
+            // Integers are divided as integers in C
+            NodeType::Constant if target == Target::C => format!("{:?}", leaf.value),
+            NodeType::Constant => leaf.value.to_string(),
+            NodeType::Var => leaf.name.clone(),
+            NodeType::Function => {
+                let args: Vec<_> = leaf.args.iter().map(|arg| print(arg, target)).collect();
+                format!("{}({})", leaf.name, args.join(", "))
+            }
+        },
+        Node::UnaryExpr { op, expr } => {
+            let sign = if let Operator::Minus = op { "-" } else { "" };
+            // `--x` decrements `x` in C
+            let nested = expr.precedence() == Precedence::Negation && !sign.is_empty();
+            let expr = operand(expr, expr.precedence() < Precedence::Negation || nested);
+            format!("{sign}{expr}")
+        }
+        Node::BinaryExpr {
+            op: Operator::Pow,
+            left_expr,
+            right_expr,
+        } if target == Target::C => format!(
+            "pow({}, {})",
+            print(left_expr, target),
+            print(right_expr, target)
+        ),
+        Node::BinaryExpr {
+            op,
+            left_expr,
+            right_expr,
+        } => {
+            let (left, right) = Node::parenthesize_operands(*op, left_expr, right_expr);
+            let symbol = match (op, target) {
+                (Operator::Pow, Target::Python) => "**",
+                (op, _) => op.symbol(),
+            };
+            format!(
+                "{} {symbol} {}",
+                operand(left_expr, left),
+                operand(right_expr, right)
+            )
+        }
+        Node::Conditional {
+            condition,
+            if_true,
+            if_false,
+        } => {
+            let if_true = operand(if_true, if_true.precedence() <= Precedence::Conditional);
+            let if_false = print(if_false, target);
+            match target {
+                Target::Python => format!(
+                    "{if_true} if {} else {if_false}",
+                    operand(condition, condition.precedence() <= Precedence::Conditional)
+                ),
+                // Conditions are numbers, which are only booleans in C and R
+                Target::Julia => {
+                    format!(
+                        "!iszero({}) ? {if_true} : {if_false}",
+                        print(condition, target)
+                    )
+                }
+                Target::R => format!(
+                    "if ({}) {if_true} else {if_false}",
+                    print(condition, target)
+                ),
+                Target::C | Target::Native => format!(
+                    "{} ? {if_true} : {if_false}",
+                    operand(condition, condition.precedence() <= Precedence::Conditional)
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expr_evaluator::expr::Expression;
+
+    use super::*;
+
+    fn parse(text: &str) -> Box<Node> {
+        let mut expr = Expression::new();
+        expr.parse_expr(text.into()).unwrap();
+        expr.ast.unwrap()
+    }
+
+    fn written(text: &str) -> [String; 4] {
+        let node = parse(text);
+        [Target::Python, Target::Julia, Target::R, Target::C].map(|target| write(&node, target))
+    }
+
+    #[test]
+    fn builtins_are_translated() {
+        assert_eq!(
+            written("ln(x) + asin(x) ^ 2"),
+            [
+                "np.log(x) + np.arcsin(x) ** 2",
+                "log(x) + asin(x) ^ 2",
+                "log(x) + asin(x) ^ 2",
+                "log(x) + pow(asin(x), 2.0)",
+            ]
+        );
+        assert_eq!(
+            written("max(x, y, 1) / hypot(x, y)"),
+            [
+                "max(x, y, 1) / np.hypot(x, y)",
+                "max(x, y, 1) / hypot(x, y)",
+                "max(x, y, 1) / sqrt(x ^ 2 + y ^ 2)",
+                "fmax(fmax(x, y), 1.0) / hypot(x, y)",
+            ]
+        );
+        assert_eq!(
+            written("2 * sign(x) + power(x, cbrt(y))"),
+            [
+                "2 * np.sign(x) + np.power(x, np.cbrt(y))",
+                "2 * sign(x) + x ^ cbrt(y)",
+                "2 * sign(x) + x ^ (sign(y) * abs(y) ^ (1 / 3))",
+                "2.0 * ((x > 0.0) - (x < 0.0)) + pow(x, cbrt(y))",
+            ]
+        );
+    }
+
+    #[test]
+    fn conditionals_are_translated() {
+        assert_eq!(
+            written("x > 0 ? hill(x, 2) : -(-x)"),
+            [
+                "hill(x, 2) if x > 0 else -(-x)",
+                "!iszero(x > 0) ? hill(x, 2) : -(-x)",
+                "if (x > 0) hill(x, 2) else -(-x)",
+                "x > 0.0 ? hill(x, 2.0) : -(-x)",
+            ]
+        );
+    }
+}
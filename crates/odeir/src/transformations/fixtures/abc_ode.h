@@ -0,0 +1,92 @@
+/* Generated by ODE-Designer. */
+#ifndef ODE_ABC_H
+#define ODE_ABC_H
+
+#include <math.h>
+
+#define ODE_N_POPULATIONS 2
+#define ODE_N_PARAMS 1
+
+/* Indices of each population in the state vector */
+enum {
+    POP_A = 0,
+    POP_B = 1,
+};
+
+/* Indices of each parameter in the parameter vector */
+enum {
+    PARAM_k = 0,
+};
+
+static const char *const ode_population_names[ODE_N_POPULATIONS] = {
+    "A",
+    "B",
+};
+
+static const double ode_initial_state[ODE_N_POPULATIONS] = {
+    10.0, /* A */
+    20.0, /* B */
+};
+
+static const char *const ode_param_names[ODE_N_PARAMS] = {
+    "k",
+};
+
+static const double ode_default_params[ODE_N_PARAMS] = {
+    0.5, /* k */
+};
+
+static const double ode_start_time = 0.0;
+static const double ode_end_time = 10.0;
+static const double ode_delta_time = 0.1;
+
+static inline void rhs(double t, const double* y, double* dydt, const double* params)
+{
+    const double A = y[POP_A];
+    const double B = y[POP_B];
+    const double k = params[PARAM_k];
+    (void)t;
+    (void)params;
+
+    dydt[POP_A] = A + B;
+    dydt[POP_B] = -((A + B) * - k);
+}
+
+/* Advances `y` from `t` to `t + h` with the classic fourth-order Runge-Kutta
+ * method. */
+static inline void ode_rk4_step(double t, double h, double* y, const double* params)
+{
+    double k1[ODE_N_POPULATIONS], k2[ODE_N_POPULATIONS];
+    double k3[ODE_N_POPULATIONS], k4[ODE_N_POPULATIONS];
+    double tmp[ODE_N_POPULATIONS];
+    int i;
+
+    rhs(t, y, k1, params);
+    for (i = 0; i < ODE_N_POPULATIONS; i++) tmp[i] = y[i] + h / 2 * k1[i];
+    rhs(t + h / 2, tmp, k2, params);
+    for (i = 0; i < ODE_N_POPULATIONS; i++) tmp[i] = y[i] + h / 2 * k2[i];
+    rhs(t + h / 2, tmp, k3, params);
+    for (i = 0; i < ODE_N_POPULATIONS; i++) tmp[i] = y[i] + h * k3[i];
+    rhs(t + h, tmp, k4, params);
+
+    for (i = 0; i < ODE_N_POPULATIONS; i++)
+        y[i] += h / 6 * (k1[i] + 2 * k2[i] + 2 * k3[i] + k4[i]);
+}
+
+/* Integrates `y` in place from `t0` to `tf` with a fixed step `h`. If given,
+ * `observer` is called with the initial state and after every step. */
+static inline void ode_integrate(
+    double t0, double tf, double h, double* y, const double* params,
+    void (*observer)(double t, const double* y, void* data), void* data)
+{
+    long steps = (long)((tf - t0) / h + 0.5);
+    long n;
+
+    if (observer) observer(t0, y, data);
+    for (n = 0; n < steps; n++) {
+        ode_rk4_step(t0 + n * h, h, y, params);
+        if (observer) observer(t0 + (n + 1) * h, y, data);
+    }
+}
+
+#endif /* ODE_ABC_H */
\ No newline at end of file
@@ -0,0 +1,32 @@
+using DifferentialEquations
+
+const variable_names = ["A", "B"]
+
+initial_values = (
+    A = 10.0,
+    B = 20.0,
+)
+
+parameters = (
+    k = 0.5,
+)
+
+function f!(du, u, p, t)
+    A, B, = u
+    (; k) = p
+
+    du[1] = A + B
+    du[2] = -((A + B) * - k)
+    return nothing
+end
+
+u0 = collect(Float64, initial_values)
+tspan = (0.0, 10.0)
+
+prob = ODEProblem(f!, u0, tspan, parameters)
+sol = solve(prob, Tsit5(), saveat = 0.1)
+
+println("t,", join(variable_names, ","))
+for (t, u) in zip(sol.t, sol.u)
+    println(t, ",", join(u, ","))
+end
\ No newline at end of file
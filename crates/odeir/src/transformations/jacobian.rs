@@ -0,0 +1,243 @@
+//! Analytic Jacobian of a model: the derivative of the rate of every
+//! population with respect to every population, which implicit solvers use
+//! instead of approximating it.
+//!
+//! Rates are read the way the generated code writes them, from the one
+//! equation of each population. The functions of the model are replaced by
+//! their bodies, while extension functions are written in Python, so models
+//! relying on them have no Jacobian.
+
+use std::collections::BTreeSet;
+
+use expr_evaluator::expr::{Node, Operator, Precedence};
+
+use crate::{
+    models::{ode::OdeModel, Argument, CompositionStyle},
+    Map,
+};
+
+#[derive(Debug, Clone)]
+pub struct Jacobian {
+    /// Populations, in the order of both the rows and the columns
+    pub populations: Vec<String>,
+    /// `entries[i][j]` is the derivative of the rate of `populations[i]` with
+    /// respect to `populations[j]`
+    pub entries: Vec<Vec<Box<Node>>>,
+}
+
+impl Jacobian {
+    /// Entries that aren't always zero, as `(row, column, entry)`.
+    pub fn nonzero(&self) -> impl Iterator<Item = (usize, usize, &Node)> {
+        self.entries.iter().enumerate().flat_map(|(row, entries)| {
+            entries
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| !entry.is_constant(0.0))
+                .map(move |(column, entry)| (row, column, &**entry))
+        })
+    }
+}
+
+/// The Jacobian of the model, or `None` if one of its rates can't be
+/// differentiated.
+pub fn jacobian(model: &OdeModel) -> Option<Jacobian> {
+    let populations: Vec<String> = model
+        .get_populations()
+        .map(|population| population.name().to_owned())
+        .collect();
+    let equations = model
+        .equations
+        .iter()
+        .filter_map(|eq| Some((eq.operates_on.as_deref()?, eq)))
+        .collect::<Map<_, _>>();
+
+    let entries = populations
+        .iter()
+        .map(|population| {
+            let rate = match equations.get(population.as_str()) {
+                Some(equation) => {
+                    let rate = expression(model, &equation.argument, &mut BTreeSet::new())?;
+                    let rate = model.inline_functions(&rate).ok()?;
+                    if equation.contribution == '-' {
+                        Node::negated(rate)
+                    } else {
+                        rate
+                    }
+                }
+                None => Node::constant(0.0),
+            };
+
+            populations
+                .iter()
+                .map(|var| rate.derivative(var).ok())
+                .collect::<Option<Vec<_>>>()
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(Jacobian {
+        populations,
+        entries,
+    })
+}
+
+/// The argument `name` as an expression. Values, delays and names that don't
+/// exist in the model are variables. `visiting` holds the composites being
+/// expanded, so cycles give `None` instead of recursing forever.
+fn expression<'a>(
+    model: &'a OdeModel,
+    name: &'a str,
+    visiting: &mut BTreeSet<&'a str>,
+) -> Option<Box<Node>> {
+    let Some(Argument::Composite {
+        operation,
+        style,
+        composition,
+        ..
+    }) = model.arguments.get(name)
+    else {
+        return Some(Node::var(name));
+    };
+    let is_function = model.get_function(operation).is_some();
+    if (*style == CompositionStyle::Prefixed && !is_function) || !visiting.insert(name) {
+        return None;
+    }
+
+    let mut operands = Vec::with_capacity(composition.len());
+    for component in composition {
+        let operand = expression(model, &component.name, visiting)?;
+        operands.push(if component.contribution == '-' {
+            Node::negated(operand)
+        } else {
+            operand
+        });
+    }
+    visiting.remove(name);
+
+    if *style == CompositionStyle::Prefixed {
+        return Some(Node::call(operation, operands));
+    }
+
+    let op = match operation.as_str() {
+        "+" => Operator::Plus,
+        "-" => Operator::Minus,
+        "*" => Operator::Mult,
+        "/" => Operator::Div,
+        "==" => Operator::Eq,
+        "!=" => Operator::Ne,
+        "<" => Operator::Lt,
+        ">" => Operator::Gt,
+        "<=" => Operator::Le,
+        ">=" => Operator::Ge,
+        _ => return None,
+    };
+
+    let mut operands = operands.into_iter();
+    let first = operands.next()?;
+    if op.precedence() > Precedence::Comparison {
+        return Some(operands.fold(first, |left, right| Node::binary(op, left, right)));
+    }
+
+    // Comparisons chain as in Python: `a < b < c` is `a < b and b < c`
+    let mut left = first;
+    let mut chain: Option<Box<Node>> = None;
+    for right in operands {
+        let comparison = Node::binary(op, left, right.clone());
+        chain = Some(match chain {
+            Some(previous) => Node::binary(Operator::Mult, previous, comparison),
+            None => comparison,
+        });
+        left = right;
+    }
+    Some(chain.unwrap_or(left))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::ode::Metadata;
+    use crate::models::Function;
+    use crate::transformations::test_support::*;
+
+    use super::*;
+
+    /// `dS/dt = -beta * S * I`, `dI/dt = beta * S * I - gamma * I`
+    fn sir() -> OdeModel {
+        let mut model = OdeModel::new("SIR".into(), Metadata::default());
+        for (name, initial) in [("S", 990.0), ("I", 10.0), ("beta", 0.001), ("gamma", 0.1)] {
+            model.insert_argument(value(name, initial));
+        }
+        model.insert_argument(composite(
+            "infection",
+            "*",
+            [arg("beta"), arg("S"), arg("I")],
+        ));
+        model.insert_argument(composite("recovery", "*", [arg("gamma"), arg("I")]));
+        model.insert_argument(composite(
+            "infected",
+            "-",
+            [arg("infection"), arg("recovery")],
+        ));
+        model.insert_equation(equation("dSdt", "S", argument("infection", '-')));
+        model.insert_equation(equation("dIdt", "I", arg("infected")));
+        model
+    }
+
+    #[test]
+    fn jacobian_of_sir() {
+        let jacobian = jacobian(&sir()).unwrap();
+
+        assert_eq!(jacobian.populations, ["I", "S"]);
+        let entries: Vec<_> = jacobian
+            .nonzero()
+            .map(|(row, column, entry)| (row, column, entry.to_string()))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                (0, 0, String::from("beta * S - gamma")),
+                (0, 1, String::from("beta * I")),
+                (1, 0, String::from("-(beta * S)")),
+                (1, 1, String::from("-(beta * I)")),
+            ]
+        );
+    }
+
+    #[test]
+    fn extension_functions_have_no_jacobian() {
+        let mut model = sir();
+        model.insert_argument(Argument::Composite {
+            name: "infection".into(),
+            operation: "hill".into(),
+            style: CompositionStyle::Prefixed,
+            composition: vec![arg("S")],
+        });
+
+        assert!(jacobian(&model).is_none());
+    }
+
+    #[test]
+    fn functions_are_inlined() {
+        let mut model = sir();
+        model.insert_argument(Argument::Composite {
+            name: "recovery".into(),
+            operation: "decay".into(),
+            style: CompositionStyle::Prefixed,
+            composition: vec![arg("gamma"), arg("I")],
+        });
+        model.functions.push(Function {
+            name: "decay".into(),
+            parameters: vec!["k".into(), "x".into()],
+            body: "k * x".into(),
+        });
+
+        let jacobian = jacobian(&model).unwrap();
+        assert_eq!(jacobian.entries[0][0].to_string(), "beta * S - gamma");
+    }
+
+    #[test]
+    fn cycles_have_no_jacobian() {
+        let mut model = sir();
+        model.insert_argument(composite("recovery", "*", [arg("gamma"), arg("infected")]));
+
+        assert!(jacobian(&model).is_none());
+    }
+}
@@ -0,0 +1,84 @@
+use minijinja::{context, Environment};
+
+use crate::{models::ode::OdeModel, validation::Target, Map};
+
+use super::expression;
+
+const JULIA_TEMPLATE: &str = include_str!("../../templates/ode.jl.jinja");
+
+/// Renders the model as a Julia script that solves it with
+/// DifferentialEquations.jl over the model's time span and prints the results
+/// as CSV.
+pub fn render_julia(model: &OdeModel) -> String {
+    let env = Environment::new();
+
+    let populations = model.get_populations().collect::<Vec<_>>();
+    let constants = model.get_constants().collect::<Vec<_>>();
+    let equations = model
+        .equations
+        .iter()
+        .cloned()
+        .filter_map(|eq| Some((eq.operates_on.clone()?, eq)))
+        .collect::<Map<_, _>>();
+
+    // Extensions are Python code, so they can't be embedded in the script
+    let has_prefixed = expression::uses_extensions(model);
+
+    let mut ctx = context! {
+        model => model,
+        metadata => model.metadata,
+        equations => equations,
+        populations => populations,
+        constants => constants,
+        has_prefixed => has_prefixed,
+        functions => expression::definitions(model, Target::Julia),
+    };
+
+    env.render_str(JULIA_TEMPLATE, &mut ctx).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::ode::Metadata;
+    use crate::transformations::test_support::*;
+
+    use super::*;
+
+    #[test]
+    fn render_simple() {
+        let mut model = OdeModel::new(
+            "_".into(),
+            Metadata {
+                start_time: 0.0,
+                delta_time: 0.1,
+                end_time: 10.0,
+                time_unit: None,
+            },
+        );
+
+        model.insert_argument(value("A", 10_f64));
+        model.insert_argument(value("B", 20_f64));
+        model.insert_argument(value("k", 0.5));
+
+        model.insert_argument(composite("A+B", "+", [arg("A"), arg("B")]));
+        model.insert_equation(equation("dA/dt", "A", arg("A+B")));
+
+        model.insert_argument(composite("(A+B)*k", "*", [arg("A+B"), argument("k", '-')]));
+        model.insert_equation(equation("dB/dt", "B", argument("(A+B)*k", '-')));
+
+        let julia = render_julia(&model);
+
+        const EXPECTED: &str = include_str!("fixtures/abc_ode.jl");
+
+        assert_eq!(julia, EXPECTED);
+    }
+
+    #[test]
+    fn render_functions() {
+        let julia = render_julia(&hill_growth());
+
+        assert!(julia.contains("hill(x, k) = x ^ 2 / (k ^ 2 + x ^ 2)"));
+        assert!(julia.contains("du[1] = hill(X, K)"));
+        assert!(!julia.contains("extension functions"));
+    }
+}
@@ -1,7 +1,16 @@
 use crate::models::Argument;
 
+pub mod c;
+pub mod expression;
+pub mod jacobian;
+pub mod julia;
 pub mod ode;
+pub mod r;
 pub mod r4k;
+pub mod sbml;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod typeset;
 
 impl crate::models::CoreModel {
     pub fn get_arguments_where<'a>(
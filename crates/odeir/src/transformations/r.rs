@@ -0,0 +1,84 @@
+use minijinja::{context, Environment};
+
+use crate::{models::ode::OdeModel, validation::Target, Map};
+
+use super::expression;
+
+const R_TEMPLATE: &str = include_str!("../../templates/ode.R.jinja");
+
+/// Renders the model as an R script that solves it with `deSolve::ode`. Like
+/// the Python code, the script plots the results to a PDF unless `--csv` is
+/// given, and accepts `--st`, `--tf`, `--dt` and `--params` overrides.
+pub fn render_r(model: &OdeModel) -> String {
+    let env = Environment::new();
+
+    let populations = model.get_populations().collect::<Vec<_>>();
+    let constants = model.get_constants().collect::<Vec<_>>();
+    let equations = model
+        .equations
+        .iter()
+        .cloned()
+        .filter_map(|eq| Some((eq.operates_on.clone()?, eq)))
+        .collect::<Map<_, _>>();
+
+    // Extensions are Python code, so they can't be embedded in the script
+    let has_prefixed = expression::uses_extensions(model);
+
+    let mut ctx = context! {
+        model => model,
+        metadata => model.metadata,
+        equations => equations,
+        populations => populations,
+        constants => constants,
+        has_prefixed => has_prefixed,
+        functions => expression::definitions(model, Target::R),
+    };
+
+    env.render_str(R_TEMPLATE, &mut ctx).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::ode::Metadata;
+    use crate::transformations::test_support::*;
+
+    use super::*;
+
+    #[test]
+    fn render_simple() {
+        let mut model = OdeModel::new(
+            "_".into(),
+            Metadata {
+                start_time: 0.0,
+                delta_time: 0.1,
+                end_time: 10.0,
+                time_unit: None,
+            },
+        );
+
+        model.insert_argument(value("A", 10_f64));
+        model.insert_argument(value("B", 20_f64));
+        model.insert_argument(value("k", 0.5));
+
+        model.insert_argument(composite("A+B", "+", [arg("A"), arg("B")]));
+        model.insert_equation(equation("dA/dt", "A", arg("A+B")));
+
+        model.insert_argument(composite("(A+B)*k", "*", [arg("A+B"), argument("k", '-')]));
+        model.insert_equation(equation("dB/dt", "B", argument("(A+B)*k", '-')));
+
+        let r = render_r(&model);
+
+        const EXPECTED: &str = include_str!("fixtures/abc_ode.R");
+
+        assert_eq!(r, EXPECTED);
+    }
+
+    #[test]
+    fn render_functions() {
+        let r = render_r(&hill_growth());
+
+        assert!(r.contains("hill <- function(x, k) x ^ 2 / (k ^ 2 + x ^ 2)"));
+        assert!(r.contains("dX_dt <- hill(X, K)"));
+        assert!(!r.contains("extension functions"));
+    }
+}
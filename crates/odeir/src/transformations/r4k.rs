@@ -2,9 +2,11 @@ use std::path::PathBuf;
 
 use minijinja::{context, Environment};
 
-use crate::{models::ode::OdeModel, Map};
+use crate::{models::ode::OdeModel, validation::Target, Map};
 
-const ODE_TEMPLATE: &str = concat!{ 
+use super::{expression, jacobian::jacobian};
+
+const ODE_TEMPLATE: &str = concat! {
     include_str!("../../templates/ode.py.jinja"),
     include_str!("../../templates/ode-support.py")
 };
@@ -20,6 +22,21 @@ pub fn render_ode(model: &OdeModel, extension_lookup_paths: &[&PathBuf]) -> Stri
         .cloned()
         .filter_map(|eq| Some((eq.operates_on.clone()?, eq)))
         .collect::<Map<_, _>>();
+    // Delays look their population up in the state by index
+    let delays: Vec<_> = model
+        .delays
+        .iter()
+        .filter_map(|delay| {
+            let index = populations
+                .iter()
+                .position(|population| population.name() == delay.population)?;
+            Some(context! {
+                name => delay.name,
+                tau => delay.tau,
+                index => index,
+            })
+        })
+        .collect();
 
     let extensions: Vec<String> = model
         .extension_files
@@ -36,12 +53,33 @@ pub fn render_ode(model: &OdeModel, extension_lookup_paths: &[&PathBuf]) -> Stri
         })
         .collect();
 
+    // Systems with delays aren't integrated by `solve_ivp`, which is the only
+    // one to use the Jacobian
+    let jacobian = jacobian(model)
+        .filter(|_| delays.is_empty())
+        .map(|jacobian| {
+            let entries: Vec<_> = jacobian
+                .nonzero()
+                .map(|(row, column, entry)| {
+                    context! {
+                        row => row,
+                        column => column,
+                        value => expression::write(entry, Target::Python),
+                    }
+                })
+                .collect();
+            context! { entries => entries }
+        });
+
     let mut ctx = context! {
         model => model,
+        functions => expression::definitions(model, Target::Python),
         equations => equations,
         populations => populations,
         constants => constants,
+        delays => delays,
         extensions => extensions,
+        jacobian => jacobian,
     };
 
     env.render_str(ODE_TEMPLATE, &mut ctx).unwrap()
@@ -50,56 +88,10 @@ pub fn render_ode(model: &OdeModel, extension_lookup_paths: &[&PathBuf]) -> Stri
 #[cfg(test)]
 mod tests {
     use crate::models::ode::Metadata;
-    use crate::models::{Argument, Component};
-    use crate::Equation;
+    use crate::transformations::test_support::*;
 
     use super::*;
 
-    fn value(name: impl Into<String>, value: f64) -> Argument {
-        Argument::Value {
-            name: name.into(),
-            value,
-        }
-    }
-
-    fn composite(
-        name: impl Into<String>,
-        operation: impl Into<String>,
-        composition: impl IntoIterator<Item = Component>,
-    ) -> Argument {
-        Argument::Composite {
-            name: name.into(),
-            operation: operation.into(),
-            style: Default::default(),
-            composition: composition.into_iter().collect(),
-        }
-    }
-
-    fn argument(name: impl Into<String>, contribution: char) -> Component {
-        Component {
-            name: name.into(),
-            contribution,
-        }
-    }
-
-    /// Shorthand for positive arguments
-    fn arg(name: impl Into<String>) -> Component {
-        argument(name, '+')
-    }
-
-    fn equation(
-        name: impl Into<String>,
-        operates_on: impl Into<String>,
-        composition: Component,
-    ) -> Equation {
-        Equation {
-            name: name.into(),
-            operates_on: Some(operates_on.into()),
-            argument: composition.name,
-            contribution: composition.contribution,
-        }
-    }
-
     #[test]
     fn render_simple() {
         let mut model = OdeModel::new(
@@ -126,4 +118,49 @@ mod tests {
 
         assert_eq!(ode, EXPECTED);
     }
+
+    #[test]
+    fn render_jacobian() {
+        let mut model = OdeModel::new("_".into(), Metadata::default());
+        model.insert_argument(value("X", 1.0));
+        model.insert_argument(value("k", 0.5));
+        model.insert_argument(composite("kX", "*", [arg("k"), arg("X")]));
+        model.insert_argument(composite("decay", "/", [arg("kX"), arg("X")]));
+        model.insert_argument(composite("rate", "-", [arg("kX"), arg("decay")]));
+        model.insert_equation(equation("dX/dt", "X", argument("rate", '-')));
+
+        let ode = render_ode(&model, &[]);
+
+        assert!(
+            ode.contains("def jacobian(t: np.float64, y: np.ndarray, *constants) -> np.ndarray:")
+        );
+        assert!(ode.contains("jac[0, 0] = -k\n"));
+        assert!(
+            ode.contains(r#"options = {"jac": jacobian} if method in IMPLICIT_METHODS else {}"#)
+        );
+    }
+
+    #[test]
+    fn render_delays() {
+        const DELAYED: &str = include_str!("../../../../examples/delayed-immune-response.json");
+        let crate::Model::ODE(model) = serde_json::from_str(DELAYED).unwrap() else {
+            panic!("The example is an ODE model");
+        };
+
+        let ode = render_ode(&model, &[]);
+
+        assert!(ode.contains("def system(t: np.float64, y: np.ndarray, history, *constants)"));
+        assert!(ode.contains("V_lag = history(t - 3.0)[1]"));
+        assert!(ode.contains("dT_dt =  (s*V_lag )"));
+        assert!(ode.contains("simulation_output = solve_dde("));
+        assert!(!ode.contains("def jacobian("));
+    }
+
+    #[test]
+    fn render_functions() {
+        let ode = render_ode(&hill_growth(), &[]);
+
+        assert!(ode.contains("def hill(x, k):\n    return x ** 2 / (k ** 2 + x ** 2)"));
+        assert!(ode.contains("jac[0, 0] = "));
+    }
 }
@@ -0,0 +1,597 @@
+//! SBML (Level 3 Version 2) export of ODE models.
+//!
+//! Populations become species and constants become parameters. As the model
+//! has no notion of reactions, each equation is written as a rate rule whose
+//! MathML is the composite tree it points to. The functions of the model
+//! become function definitions, which prefixed composites apply. The
+//! simulation time span is kept in the model's annotation as a SED-ML
+//! `uniformTimeCourse`.
+
+use std::fmt::Write;
+
+use expr_evaluator::expr::{Node, NodeType, Operator};
+
+use crate::{
+    models::{ode::OdeModel, CompositionStyle, Function},
+    Argument, Equation, Map,
+};
+
+use super::expression;
+
+pub const SBML_NS: &str = "http://www.sbml.org/sbml/level3/version2/core";
+pub const MATHML_NS: &str = "http://www.w3.org/1998/Math/MathML";
+pub const SEDML_NS: &str = "http://sed-ml.org/sed-ml/level1/version4";
+
+const COMPARTMENT: &str = "default_compartment";
+
+#[derive(Debug)]
+pub enum SbmlExportError {
+    /// Custom nodes are implemented by Python functions, which can't be
+    /// written as MathML.
+    ExtensionFunction {
+        argument: String,
+        function: String,
+    },
+    UnknownOperation {
+        argument: String,
+        operation: String,
+    },
+    MissingArgument(String),
+    /// A function of the model whose body doesn't parse, or calls a builtin
+    /// MathML has no element for.
+    InvalidFunction {
+        function: String,
+        reason: String,
+    },
+    /// Each population has a single rate rule, so, as with the other
+    /// generators, only one equation may operate on it.
+    DuplicateEquation {
+        equation: String,
+        population: String,
+        first: String,
+    },
+}
+
+impl std::fmt::Display for SbmlExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SbmlExportError::ExtensionFunction { argument, function } => write!(
+                f,
+                "`{argument}` uses the extension function `{function}`, which can't be exported to SBML"
+            ),
+            SbmlExportError::UnknownOperation {
+                argument,
+                operation,
+            } => write!(f, "`{argument}` uses the unknown operation `{operation}`"),
+            SbmlExportError::MissingArgument(name) => {
+                write!(f, "`{name}` is referenced but does not exist")
+            }
+            SbmlExportError::InvalidFunction { function, reason } => {
+                write!(f, "`{function}` can't be exported to SBML: {reason}")
+            }
+            SbmlExportError::DuplicateEquation {
+                equation,
+                population,
+                first,
+            } => write!(
+                f,
+                "`{equation}` operates on `{population}`, which is already defined by `{first}`"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SbmlExportError {}
+
+pub fn render_sbml(model: &OdeModel) -> Result<String, SbmlExportError> {
+    let mut out = String::new();
+    let metadata = &model.metadata;
+
+    let number_of_steps = if metadata.delta_time > 0.0 {
+        ((metadata.end_time - metadata.start_time) / metadata.delta_time).round() as u64
+    } else {
+        0
+    };
+
+    // Writing to a String never fails
+    let _ = writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(out, r#"<sbml xmlns="{SBML_NS}" level="3" version="2">"#);
+    let _ = writeln!(
+        out,
+        r#"  <model id="{}" name="{}">"#,
+        sanitize_id(&model.name),
+        escape(&model.name)
+    );
+    let _ = writeln!(out, "    <annotation>");
+    let _ = writeln!(
+        out,
+        r#"      <sedml:uniformTimeCourse xmlns:sedml="{SEDML_NS}" id="simulation" initialTime="{start}" outputStartTime="{start}" outputEndTime="{end}" numberOfSteps="{number_of_steps}"/>"#,
+        start = metadata.start_time,
+        end = metadata.end_time,
+    );
+    let _ = writeln!(out, "    </annotation>");
+
+    if !model.functions.is_empty() {
+        let _ = writeln!(out, "    <listOfFunctionDefinitions>");
+        for function in &model.functions {
+            write_function(&mut out, function)?;
+        }
+        let _ = writeln!(out, "    </listOfFunctionDefinitions>");
+    }
+
+    let _ = writeln!(out, "    <listOfCompartments>");
+    let _ = writeln!(
+        out,
+        r#"      <compartment id="{COMPARTMENT}" spatialDimensions="3" size="1" constant="true"/>"#
+    );
+    let _ = writeln!(out, "    </listOfCompartments>");
+
+    let populations: Vec<_> = model.get_populations().collect();
+    if !populations.is_empty() {
+        let _ = writeln!(out, "    <listOfSpecies>");
+        for population in populations {
+            let Argument::Value { name, value, .. } = population else {
+                continue;
+            };
+            let _ = writeln!(
+                out,
+                r#"      <species id="{name}" compartment="{COMPARTMENT}" initialConcentration="{value}" hasOnlySubstanceUnits="false" boundaryCondition="false" constant="false"/>"#
+            );
+        }
+        let _ = writeln!(out, "    </listOfSpecies>");
+    }
+
+    let constants: Vec<_> = model.get_constants().collect();
+    if !constants.is_empty() {
+        let _ = writeln!(out, "    <listOfParameters>");
+        for constant in constants {
+            let Argument::Value { name, value, .. } = constant else {
+                continue;
+            };
+            let _ = writeln!(
+                out,
+                r#"      <parameter id="{name}" value="{value}" constant="true"/>"#
+            );
+        }
+        let _ = writeln!(out, "    </listOfParameters>");
+    }
+
+    let mut equations: Map<&str, &Equation> = Map::new();
+    for equation in &model.equations {
+        let Some(population) = &equation.operates_on else {
+            continue;
+        };
+        if let Some(first) = equations.insert(population, equation) {
+            return Err(SbmlExportError::DuplicateEquation {
+                equation: equation.name.clone(),
+                population: population.clone(),
+                first: first.name.clone(),
+            });
+        }
+    }
+
+    if !equations.is_empty() {
+        let _ = writeln!(out, "    <listOfRules>");
+        for (population, equation) in equations {
+            let term = (equation.argument.as_str(), equation.contribution);
+            let _ = writeln!(out, r#"      <rateRule variable="{population}">"#);
+            let _ = writeln!(out, r#"        <math xmlns="{MATHML_NS}">"#);
+            write_apply(model, &mut out, "+", &[term], population, 5)?;
+            let _ = writeln!(out, "        </math>");
+            let _ = writeln!(out, "      </rateRule>");
+        }
+        let _ = writeln!(out, "    </listOfRules>");
+    }
+
+    let _ = writeln!(out, "  </model>");
+    let _ = writeln!(out, "</sbml>");
+
+    Ok(out)
+}
+
+/// Writes `operation` applied over `components`, each of them negated if its
+/// contribution is `-`. Binary MathML operators are applied from left to
+/// right when there are more than two components.
+fn write_apply(
+    model: &OdeModel,
+    out: &mut String,
+    operation: &str,
+    components: &[(&str, char)],
+    composite_name: &str,
+    depth: usize,
+) -> Result<(), SbmlExportError> {
+    let operator = match operation {
+        "+" => "plus",
+        "-" => "minus",
+        "*" => "times",
+        "/" => "divide",
+        _ => {
+            return Err(SbmlExportError::UnknownOperation {
+                argument: composite_name.to_owned(),
+                operation: operation.to_owned(),
+            })
+        }
+    };
+    let indent = "  ".repeat(depth);
+
+    match components {
+        [] => {
+            let _ = writeln!(out, "{indent}<cn> 0 </cn>");
+        }
+        [single] => write_component(model, out, *single, depth)?,
+        [rest @ .., last] if rest.len() > 1 && matches!(operator, "minus" | "divide") => {
+            let _ = writeln!(out, "{indent}<apply>");
+            let _ = writeln!(out, "{indent}  <{operator}/>");
+            write_apply(model, out, operation, rest, composite_name, depth + 1)?;
+            write_component(model, out, *last, depth + 1)?;
+            let _ = writeln!(out, "{indent}</apply>");
+        }
+        components => {
+            let _ = writeln!(out, "{indent}<apply>");
+            let _ = writeln!(out, "{indent}  <{operator}/>");
+            for &component in components {
+                write_component(model, out, component, depth + 1)?;
+            }
+            let _ = writeln!(out, "{indent}</apply>");
+        }
+    }
+
+    Ok(())
+}
+
+fn write_component(
+    model: &OdeModel,
+    out: &mut String,
+    (name, contribution): (&str, char),
+    depth: usize,
+) -> Result<(), SbmlExportError> {
+    let indent = "  ".repeat(depth);
+
+    if contribution == '-' {
+        let _ = writeln!(out, "{indent}<apply>");
+        let _ = writeln!(out, "{indent}  <minus/>");
+        write_component(model, out, (name, '+'), depth + 1)?;
+        let _ = writeln!(out, "{indent}</apply>");
+        return Ok(());
+    }
+
+    match model.arguments.get(name) {
+        Some(Argument::Value { name, .. }) => {
+            let _ = writeln!(out, "{indent}<ci> {name} </ci>");
+        }
+        Some(Argument::Composite {
+            name,
+            operation,
+            style: CompositionStyle::Infixed,
+            composition,
+        }) => {
+            let components: Vec<_> = composition
+                .iter()
+                .map(|c| (c.name.as_str(), c.contribution))
+                .collect();
+            write_apply(model, out, operation, &components, name, depth)?;
+        }
+        Some(Argument::Composite {
+            operation,
+            style: CompositionStyle::Prefixed,
+            composition,
+            ..
+        }) if model.get_function(operation).is_some() => {
+            let _ = writeln!(out, "{indent}<apply>");
+            let _ = writeln!(out, "{indent}  <ci> {operation} </ci>");
+            for component in composition {
+                write_component(
+                    model,
+                    out,
+                    (&component.name, component.contribution),
+                    depth + 1,
+                )?;
+            }
+            let _ = writeln!(out, "{indent}</apply>");
+        }
+        Some(Argument::Composite {
+            name,
+            operation,
+            style: CompositionStyle::Prefixed,
+            ..
+        }) => {
+            return Err(SbmlExportError::ExtensionFunction {
+                argument: name.clone(),
+                function: operation.clone(),
+            })
+        }
+        None => return Err(SbmlExportError::MissingArgument(name.to_owned())),
+    }
+
+    Ok(())
+}
+
+/// Writes `function` as a lambda of its parameters.
+fn write_function(out: &mut String, function: &Function) -> Result<(), SbmlExportError> {
+    let body = function
+        .parse_body()
+        .map_err(|err| SbmlExportError::InvalidFunction {
+            function: function.name.clone(),
+            reason: err.to_string(),
+        })?;
+
+    let _ = writeln!(out, r#"      <functionDefinition id="{}">"#, function.name);
+    let _ = writeln!(out, r#"        <math xmlns="{MATHML_NS}">"#);
+    let _ = writeln!(out, "          <lambda>");
+    for parameter in &function.parameters {
+        let _ = writeln!(out, "            <bvar><ci> {parameter} </ci></bvar>");
+    }
+    write_node(out, &body, &function.name, 6)?;
+    let _ = writeln!(out, "          </lambda>");
+    let _ = writeln!(out, "        </math>");
+    let _ = writeln!(out, "      </functionDefinition>");
+
+    Ok(())
+}
+
+/// Writes the expression `node` of the body of `function`.
+fn write_node(
+    out: &mut String,
+    node: &Node,
+    function: &str,
+    depth: usize,
+) -> Result<(), SbmlExportError> {
+    let indent = "  ".repeat(depth);
+
+    let (head, operands): (String, Vec<&Node>) = match node {
+        Node::Leaf(leaf) => match leaf.node_type {
+            NodeType::Constant => {
+                let _ = writeln!(out, "{indent}<cn> {} </cn>", leaf.value);
+                return Ok(());
+            }
+            NodeType::Var => {
+                let _ = writeln!(out, "{indent}<ci> {} </ci>", leaf.name);
+                return Ok(());
+            }
+            NodeType::Function => {
+                let head = match expression::builtin(&leaf.name) {
+                    // Other functions of the model
+                    None => format!("<ci> {} </ci>", leaf.name),
+                    Some("sqrt") => "<root/>".into(),
+                    Some("cbrt") => "<root/><degree><cn> 3 </cn></degree>".into(),
+                    Some("log") => "<ln/>".into(),
+                    Some("log10") => "<log/>".into(),
+                    Some("log2") => "<log/><logbase><cn> 2 </cn></logbase>".into(),
+                    Some("ceil") => "<ceiling/>".into(),
+                    Some("pow") => "<power/>".into(),
+                    Some(
+                        builtin @ ("exp" | "sin" | "cos" | "tan" | "sinh" | "cosh" | "tanh"
+                        | "arcsin" | "arccos" | "arctan" | "arcsinh" | "arccosh"
+                        | "arctanh" | "abs" | "floor" | "min" | "max"),
+                    ) => format!("<{builtin}/>"),
+                    Some(builtin) => {
+                        return Err(SbmlExportError::InvalidFunction {
+                            function: function.to_owned(),
+                            reason: format!("MathML has no equivalent of `{builtin}`"),
+                        })
+                    }
+                };
+                (head, leaf.args.iter().map(|arg| &**arg).collect())
+            }
+        },
+        Node::UnaryExpr {
+            op: Operator::Minus,
+            expr,
+        } => ("<minus/>".into(), vec![&**expr]),
+        Node::UnaryExpr { expr, .. } => return write_node(out, expr, function, depth),
+        Node::BinaryExpr {
+            op,
+            left_expr,
+            right_expr,
+        } => {
+            let operator = match op {
+                Operator::Plus => "plus",
+                Operator::Minus => "minus",
+                Operator::Mult => "times",
+                Operator::Div => "divide",
+                Operator::Pow => "power",
+                Operator::Eq => "eq",
+                Operator::Ne => "neq",
+                Operator::Lt => "lt",
+                Operator::Gt => "gt",
+                Operator::Le => "leq",
+                Operator::Ge => "geq",
+            };
+            (format!("<{operator}/>"), vec![&**left_expr, &**right_expr])
+        }
+        Node::Conditional {
+            condition,
+            if_true,
+            if_false,
+        } => {
+            let _ = writeln!(out, "{indent}<piecewise>");
+            let _ = writeln!(out, "{indent}  <piece>");
+            write_node(out, if_true, function, depth + 2)?;
+            write_node(out, condition, function, depth + 2)?;
+            let _ = writeln!(out, "{indent}  </piece>");
+            let _ = writeln!(out, "{indent}  <otherwise>");
+            write_node(out, if_false, function, depth + 2)?;
+            let _ = writeln!(out, "{indent}  </otherwise>");
+            let _ = writeln!(out, "{indent}</piecewise>");
+            return Ok(());
+        }
+    };
+
+    let _ = writeln!(out, "{indent}<apply>");
+    let _ = writeln!(out, "{indent}  {head}");
+    for operand in operands {
+        write_node(out, operand, function, depth + 1)?;
+    }
+    let _ = writeln!(out, "{indent}</apply>");
+
+    Ok(())
+}
+
+/// SBML ids must start with a letter or underscore, followed by letters,
+/// digits or underscores.
+fn sanitize_id(name: &str) -> String {
+    let mut id: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !id.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        id.insert(0, '_');
+    }
+    id
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        import::sbml::{import_sbml, SbmlImport},
+        models::{ode::Metadata, CompositionStyle},
+        transformations::test_support::*,
+    };
+
+    fn predator_prey() -> OdeModel {
+        let mut model = OdeModel::new(
+            "Predator & prey".into(),
+            Metadata {
+                start_time: 0.0,
+                delta_time: 0.5,
+                end_time: 20.0,
+                time_unit: None,
+            },
+        );
+
+        model.insert_argument(value("x", 10.0));
+        model.insert_argument(value("y", 5.0));
+        model.insert_argument(value("alpha", 1.1));
+        model.insert_argument(value("beta", 0.4));
+        model.insert_argument(value("gamma", 0.4));
+
+        model.insert_argument(composite("alpha_x", "*", [arg("alpha"), arg("x")]));
+        model.insert_argument(composite("beta_xy", "*", [arg("beta"), arg("x"), arg("y")]));
+        model.insert_argument(composite("gamma_y", "*", [arg("gamma"), arg("y")]));
+        model.insert_argument(composite("dx", "-", [arg("alpha_x"), arg("beta_xy")]));
+        model.insert_argument(composite(
+            "dy",
+            "+",
+            [argument("gamma_y", '-'), arg("beta_xy")],
+        ));
+
+        model.insert_equation(equation("dxdt", "x", arg("dx")));
+        model.insert_equation(equation("dydt", "y", arg("dy")));
+
+        model
+    }
+
+    #[test]
+    fn render_predator_prey() {
+        let sbml = render_sbml(&predator_prey()).unwrap();
+
+        assert!(sbml.contains(r#"<model id="Predator___prey" name="Predator &amp; prey">"#));
+        assert!(sbml.contains(r#"outputEndTime="20" numberOfSteps="40"/>"#));
+        assert!(sbml.contains(
+            r#"<species id="x" compartment="default_compartment" initialConcentration="10""#
+        ));
+        assert!(sbml.contains(r#"<parameter id="alpha" value="1.1" constant="true"/>"#));
+        assert!(sbml.contains(r#"<rateRule variable="y">"#));
+    }
+
+    #[test]
+    fn round_trip_through_import() {
+        let SbmlImport { model, diagnostics } =
+            import_sbml(&render_sbml(&predator_prey()).unwrap()).unwrap();
+
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        assert_eq!(model.metadata.start_time, 0.0);
+        assert_eq!(model.metadata.delta_time, 0.5);
+        assert_eq!(model.metadata.end_time, 20.0);
+
+        let names =
+            |args: Vec<&Argument>| args.iter().map(|a| a.name().to_owned()).collect::<Vec<_>>();
+        assert_eq!(names(model.get_populations().collect()), ["x", "y"]);
+        assert_eq!(
+            names(model.get_constants().collect()),
+            ["alpha", "beta", "gamma"]
+        );
+
+        let Argument::Composite { composition, .. } = &model.arguments["y_eq"] else {
+            panic!("`y_eq` should be a composite");
+        };
+        assert_eq!(composition[0].contribution, '-');
+    }
+
+    #[test]
+    fn functions_are_defined() {
+        let mut model = predator_prey();
+        model.insert_argument(Argument::Composite {
+            name: "gamma_y".into(),
+            operation: "decay".into(),
+            style: CompositionStyle::Prefixed,
+            composition: vec![arg("gamma"), arg("y")],
+        });
+        model.functions.push(Function {
+            name: "decay".into(),
+            parameters: vec!["k".into(), "x".into()],
+            body: "k * sqrt(x)".into(),
+        });
+
+        let sbml = render_sbml(&model).unwrap();
+
+        assert!(sbml.contains(
+            "<lambda>
+            <bvar><ci> k </ci></bvar>
+            <bvar><ci> x </ci></bvar>
+            <apply>
+              <times/>
+              <ci> k </ci>
+              <apply>
+                <root/>
+                <ci> x </ci>
+              </apply>
+            </apply>
+          </lambda>"
+        ));
+        assert!(sbml.contains("<apply>\n                <ci> decay </ci>\n"));
+
+        model.functions[0].body = "k * sign(x)".into();
+        assert!(matches!(
+            render_sbml(&model),
+            Err(SbmlExportError::InvalidFunction { function, .. }) if function == "decay"
+        ));
+    }
+
+    #[test]
+    fn extension_functions_are_rejected() {
+        let mut model = predator_prey();
+        model.insert_argument(Argument::Composite {
+            name: "dx".into(),
+            operation: "sine".into(),
+            style: CompositionStyle::Prefixed,
+            composition: vec![arg("x")],
+        });
+
+        assert!(matches!(
+            render_sbml(&model),
+            Err(SbmlExportError::ExtensionFunction { function, .. }) if function == "sine"
+        ));
+    }
+
+    #[test]
+    fn duplicate_equations_are_rejected() {
+        let mut model = predator_prey();
+        model.insert_equation(equation("dx2dt", "x", arg("alpha_x")));
+
+        assert!(matches!(
+            render_sbml(&model),
+            Err(SbmlExportError::DuplicateEquation { equation, population, first })
+                if equation == "dx2dt" && population == "x" && first == "dxdt"
+        ));
+    }
+}
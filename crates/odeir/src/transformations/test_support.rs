@@ -0,0 +1,73 @@
+//! Shorthands for building the models the tests of the transformations and
+//! of the validation work on.
+
+use crate::models::ode::{Metadata, OdeModel};
+use crate::models::{Argument, Component, CompositionStyle, Function};
+use crate::Equation;
+
+pub(crate) fn value(name: impl Into<String>, value: f64) -> Argument {
+    Argument::Value {
+        name: name.into(),
+        value,
+        unit: None,
+        info: None,
+    }
+}
+
+pub(crate) fn composite(
+    name: impl Into<String>,
+    operation: impl Into<String>,
+    composition: impl IntoIterator<Item = Component>,
+) -> Argument {
+    Argument::Composite {
+        name: name.into(),
+        operation: operation.into(),
+        style: Default::default(),
+        composition: composition.into_iter().collect(),
+    }
+}
+
+pub(crate) fn argument(name: impl Into<String>, contribution: char) -> Component {
+    Component {
+        name: name.into(),
+        contribution,
+    }
+}
+
+/// Shorthand for positive arguments
+pub(crate) fn arg(name: impl Into<String>) -> Component {
+    argument(name, '+')
+}
+
+pub(crate) fn equation(
+    name: impl Into<String>,
+    operates_on: impl Into<String>,
+    composition: Component,
+) -> Equation {
+    Equation {
+        name: name.into(),
+        operates_on: Some(operates_on.into()),
+        argument: composition.name,
+        contribution: composition.contribution,
+    }
+}
+
+/// `dX/dt = hill(X, K)`, with `hill` declared by the model.
+pub(crate) fn hill_growth() -> OdeModel {
+    let mut model = OdeModel::new("hill".into(), Metadata::default());
+    model.insert_argument(value("X", 1.0));
+    model.insert_argument(value("K", 2.0));
+    model.insert_argument(Argument::Composite {
+        name: "growth".into(),
+        operation: "hill".into(),
+        style: CompositionStyle::Prefixed,
+        composition: vec![arg("X"), arg("K")],
+    });
+    model.insert_equation(equation("dX/dt", "X", arg("growth")));
+    model.functions.push(Function {
+        name: "hill".into(),
+        parameters: vec!["x".into(), "k".into()],
+        body: "x ^ 2 / (k ^ 2 + x ^ 2)".into(),
+    });
+    model
+}
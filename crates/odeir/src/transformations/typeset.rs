@@ -0,0 +1,738 @@
+//! Typesetting of the model's equations as LaTeX and MathML, for papers and
+//! web pages.
+//!
+//! Prefixed composites come from extension functions, whose notation is only
+//! known by the application, so the renderers take a `format_prefixed`
+//! callback. It receives the function name and its already typeset arguments,
+//! and returns `None` to fall back to the usual `f(x, y)` notation. The
+//! functions declared by the model are typeset after the equations.
+
+use std::fmt::Write;
+
+use expr_evaluator::expr::{Node, NodeType, Operator};
+
+use super::jacobian::jacobian;
+use crate::{
+    models::{ode::OdeModel, Argument, CompositionStyle},
+    Map,
+};
+
+const GREEK_LETTERS: &[&str] = &[
+    "alpha", "beta", "gamma", "delta", "epsilon", "zeta", "eta", "theta", "iota", "kappa",
+    "lambda", "mu", "nu", "xi", "pi", "rho", "sigma", "tau", "upsilon", "phi", "chi", "psi",
+    "omega", "Gamma", "Delta", "Theta", "Lambda", "Xi", "Pi", "Sigma", "Upsilon", "Phi", "Psi",
+    "Omega",
+];
+
+const GREEK_SYMBOLS: &[char] = &[
+    'α', 'β', 'γ', 'δ', 'ε', 'ζ', 'η', 'θ', 'ι', 'κ', 'λ', 'μ', 'ν', 'ξ', 'π', 'ρ', 'σ', 'τ', 'υ',
+    'φ', 'χ', 'ψ', 'ω', 'Γ', 'Δ', 'Θ', 'Λ', 'Ξ', 'Π', 'Σ', 'Υ', 'Φ', 'Ψ', 'Ω',
+];
+
+pub fn render_latex(
+    model: &OdeModel,
+    format_prefixed: impl Fn(&str, &[String]) -> Option<String>,
+) -> String {
+    let mut out = String::new();
+    let typesetter = Typesetter {
+        model,
+        notation: &Latex,
+        format_prefixed: &format_prefixed,
+    };
+
+    let _ = writeln!(out, r"\begin{{align}}");
+    let equations = typesetter.equations();
+    for (idx, (population, rhs)) in equations.iter().enumerate() {
+        let separator = if idx + 1 < equations.len() {
+            r" \\"
+        } else {
+            ""
+        };
+        let _ = writeln!(
+            out,
+            r"  \frac{{d{}}}{{dt}} &= {rhs}{separator}",
+            Latex.symbol(population)
+        );
+    }
+    let _ = writeln!(out, r"\end{{align}}");
+
+    let definitions = typesetter.definitions();
+    if !definitions.is_empty() {
+        let _ = writeln!(out);
+        let _ = writeln!(out, r"\begin{{align}}");
+        for (idx, (function, body)) in definitions.iter().enumerate() {
+            let separator = if idx + 1 < definitions.len() {
+                r" \\"
+            } else {
+                ""
+            };
+            let _ = writeln!(out, r"  {function} &= {body}{separator}");
+        }
+        let _ = writeln!(out, r"\end{{align}}");
+    }
+
+    let partials: Vec<_> = jacobian(model)
+        .map(|jacobian| {
+            jacobian
+                .nonzero()
+                .map(|(row, column, entry)| {
+                    let (partial, _) = typesetter.node(entry);
+                    (
+                        jacobian.populations[row].clone(),
+                        jacobian.populations[column].clone(),
+                        partial,
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    if !partials.is_empty() {
+        let _ = writeln!(out);
+        let _ = writeln!(out, r"\begin{{align}}");
+        for (idx, (population, var, partial)) in partials.iter().enumerate() {
+            let separator = if idx + 1 < partials.len() { r" \\" } else { "" };
+            let _ = writeln!(
+                out,
+                r"  \frac{{\partial \dot{{{}}}}}{{\partial {}}} &= {partial}{separator}",
+                Latex.symbol(population),
+                Latex.symbol(var)
+            );
+        }
+        let _ = writeln!(out, r"\end{{align}}");
+    }
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, r"\begin{{tabular}}{{lr}}");
+    let _ = writeln!(out, r"  \hline");
+    let _ = writeln!(out, r"  Parameter & Value \\");
+    let _ = writeln!(out, r"  \hline");
+    for (name, value) in table_rows(model, &Latex) {
+        let _ = writeln!(out, r"  ${name}$ & ${value}$ \\");
+    }
+    let _ = writeln!(out, r"  \hline");
+    let _ = writeln!(out, r"\end{{tabular}}");
+
+    out
+}
+
+pub fn render_mathml(
+    model: &OdeModel,
+    format_prefixed: impl Fn(&str, &[String]) -> Option<String>,
+) -> String {
+    let mut out = String::new();
+    let typesetter = Typesetter {
+        model,
+        notation: &MathMl,
+        format_prefixed: &format_prefixed,
+    };
+
+    let _ = writeln!(
+        out,
+        r#"<math xmlns="http://www.w3.org/1998/Math/MathML" display="block">"#
+    );
+    let _ = writeln!(out, r#"  <mtable columnalign="right left">"#);
+    for (population, rhs) in typesetter.equations() {
+        let _ = writeln!(out, "    <mtr>");
+        let _ = writeln!(
+            out,
+            "      <mtd><mfrac><mrow><mi>d</mi>{}</mrow><mrow><mi>d</mi><mi>t</mi></mrow></mfrac></mtd>",
+            MathMl.symbol(&population)
+        );
+        let _ = writeln!(out, "      <mtd><mo>=</mo>{rhs}</mtd>");
+        let _ = writeln!(out, "    </mtr>");
+    }
+    for (function, body) in typesetter.definitions() {
+        let _ = writeln!(out, "    <mtr>");
+        let _ = writeln!(out, "      <mtd>{function}</mtd>");
+        let _ = writeln!(out, "      <mtd><mo>=</mo>{body}</mtd>");
+        let _ = writeln!(out, "    </mtr>");
+    }
+    let _ = writeln!(out, "  </mtable>");
+    let _ = writeln!(out, "</math>");
+
+    let _ = writeln!(out, "<table>");
+    let _ = writeln!(out, "  <tr><th>Parameter</th><th>Value</th></tr>");
+    for (name, value) in table_rows(model, &MathMl) {
+        let _ = writeln!(
+            out,
+            "  <tr><td><math>{name}</math></td><td><math>{value}</math></td></tr>"
+        );
+    }
+    let _ = writeln!(out, "</table>");
+
+    out
+}
+
+/// Constants followed by the initial values of the populations.
+fn table_rows(model: &OdeModel, notation: &dyn Notation) -> Vec<(String, String)> {
+    let value_rows = |args: Vec<&Argument>, initial: bool| {
+        args.into_iter()
+            .filter_map(|arg| match arg {
+                Argument::Value { name, value, .. } => Some((
+                    if initial {
+                        notation.initial_value(name)
+                    } else {
+                        notation.symbol(name)
+                    },
+                    notation.number(*value),
+                )),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut rows = value_rows(model.get_constants().collect(), false);
+    rows.extend(value_rows(model.get_populations().collect(), true));
+    rows
+}
+
+/// Binding strength of a typeset expression, used to decide where
+/// parentheses are needed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    Sum,
+    Negation,
+    Product,
+    Atom,
+}
+
+trait Notation {
+    fn symbol(&self, name: &str) -> String;
+    fn number(&self, value: f64) -> String;
+    fn initial_value(&self, name: &str) -> String;
+    fn parenthesized(&self, inner: &str) -> String;
+    /// Joins already typeset operands with an infix operator.
+    fn infix(&self, operator: &str, operands: &[String]) -> String;
+    fn negated(&self, inner: &str) -> String;
+    fn fraction(&self, numerator: &str, denominator: &str) -> String;
+    fn power(&self, base: &str, exponent: &str) -> String;
+    fn call(&self, function: &str, args: &[String]) -> String;
+}
+
+struct Typesetter<'a> {
+    model: &'a OdeModel,
+    notation: &'a dyn Notation,
+    format_prefixed: &'a dyn Fn(&str, &[String]) -> Option<String>,
+}
+
+impl Typesetter<'_> {
+    /// The typeset right-hand side of each population's equation.
+    fn equations(&self) -> Vec<(String, String)> {
+        let mut equations: Map<&str, Vec<(String, Precedence)>> = Map::new();
+
+        for equation in &self.model.equations {
+            let Some(population) = &equation.operates_on else {
+                continue;
+            };
+            let term = self.argument(&equation.argument);
+            let term = if equation.contribution == '-' {
+                self.negate(term)
+            } else {
+                term
+            };
+            equations.entry(population).or_default().push(term);
+        }
+
+        equations
+            .into_iter()
+            .map(|(population, terms)| {
+                let (rhs, _) = self.sum(terms);
+                (population.to_owned(), rhs)
+            })
+            .collect()
+    }
+
+    /// Each function of the model applied to its parameters, with its body.
+    /// Functions whose body doesn't parse are left out.
+    fn definitions(&self) -> Vec<(String, String)> {
+        self.model
+            .functions
+            .iter()
+            .filter_map(|function| {
+                let body = function.parse_body().ok()?;
+                let parameters: Vec<_> = function
+                    .parameters
+                    .iter()
+                    .map(|parameter| self.notation.symbol(parameter))
+                    .collect();
+                Some((
+                    self.notation.call(&function.name, &parameters),
+                    self.node(&body).0,
+                ))
+            })
+            .collect()
+    }
+
+    fn argument(&self, name: &str) -> (String, Precedence) {
+        match self.model.arguments.get(name) {
+            Some(Argument::Composite {
+                operation,
+                style,
+                composition,
+                ..
+            }) => {
+                let operands: Vec<_> = composition
+                    .iter()
+                    .map(|component| {
+                        let operand = self.argument(&component.name);
+                        if component.contribution == '-' {
+                            self.negate(operand)
+                        } else {
+                            operand
+                        }
+                    })
+                    .collect();
+
+                match style {
+                    CompositionStyle::Infixed => self.infixed(operation, operands),
+                    CompositionStyle::Prefixed => self.prefixed(operation, operands),
+                }
+            }
+            // Values, or names that don't exist in the model
+            _ => (self.notation.symbol(name), Precedence::Atom),
+        }
+    }
+
+    fn infixed(
+        &self,
+        operation: &str,
+        mut operands: Vec<(String, Precedence)>,
+    ) -> (String, Precedence) {
+        if operands.len() == 1 {
+            return operands.remove(0);
+        }
+
+        match operation {
+            "+" => self.sum(operands),
+            "-" => {
+                let mut operands = operands.into_iter();
+                let first = operands.next().map(|(s, _)| s).unwrap_or_default();
+                let rest: Vec<_> = operands
+                    .map(|operand| self.wrap_at_most(operand, Precedence::Negation))
+                    .collect();
+                let all: Vec<_> = std::iter::once(first).chain(rest).collect();
+                (self.notation.infix("-", &all), Precedence::Sum)
+            }
+            "*" => {
+                let factors: Vec<_> = operands
+                    .into_iter()
+                    .map(|operand| self.wrap_below(operand, Precedence::Product))
+                    .collect();
+                (self.notation.infix("*", &factors), Precedence::Product)
+            }
+            "/" => {
+                let mut operands = operands.into_iter().map(|(s, _)| s);
+                let first = operands.next().unwrap_or_default();
+                let fraction = operands.fold(first, |numerator, denominator| {
+                    self.notation.fraction(&numerator, &denominator)
+                });
+                (fraction, Precedence::Atom)
+            }
+            other => {
+                let operands: Vec<_> = operands
+                    .into_iter()
+                    .map(|operand| self.wrap_below(operand, Precedence::Atom))
+                    .collect();
+                (self.notation.infix(other, &operands), Precedence::Sum)
+            }
+        }
+    }
+
+    /// Expressions from `expr_evaluator`, such as the entries of the
+    /// Jacobian. Conditionals are written as `piecewise` calls.
+    fn node(&self, node: &Node) -> (String, Precedence) {
+        match node {
+            Node::Leaf(leaf) => match leaf.node_type {
+                NodeType::Constant if leaf.value < 0.0 => {
+                    self.negate((self.notation.number(-leaf.value), Precedence::Atom))
+                }
+                NodeType::Constant => (self.notation.number(leaf.value), Precedence::Atom),
+                NodeType::Var => (self.notation.symbol(&leaf.name), Precedence::Atom),
+                NodeType::Function => {
+                    let args: Vec<_> = leaf.args.iter().map(|arg| self.node(arg).0).collect();
+                    (self.notation.call(&leaf.name, &args), Precedence::Atom)
+                }
+            },
+            Node::UnaryExpr { expr, .. } => self.negate(self.node(expr)),
+            Node::BinaryExpr {
+                op: Operator::Pow,
+                left_expr,
+                right_expr,
+            } => {
+                let base = self.wrap_below(self.node(left_expr), Precedence::Atom);
+                let (exponent, _) = self.node(right_expr);
+                (self.notation.power(&base, &exponent), Precedence::Atom)
+            }
+            Node::BinaryExpr {
+                op,
+                left_expr,
+                right_expr,
+            } => self.infixed(
+                op.symbol(),
+                vec![self.node(left_expr), self.node(right_expr)],
+            ),
+            Node::Conditional {
+                condition,
+                if_true,
+                if_false,
+            } => {
+                let args = [if_true, condition, if_false].map(|arg| self.node(arg).0);
+                (self.notation.call("piecewise", &args), Precedence::Atom)
+            }
+        }
+    }
+
+    fn prefixed(&self, function: &str, args: Vec<(String, Precedence)>) -> (String, Precedence) {
+        let bare_args: Vec<_> = args.iter().map(|(s, _)| s.clone()).collect();
+        let wrapped_args: Vec<_> = args
+            .into_iter()
+            .map(|arg| self.wrap_below(arg, Precedence::Atom))
+            .collect();
+
+        match (self.format_prefixed)(function, &wrapped_args) {
+            // Nothing is known about custom formats, so they are treated as
+            // loosely as possible
+            Some(formatted) => (formatted, Precedence::Sum),
+            None => (self.notation.call(function, &bare_args), Precedence::Atom),
+        }
+    }
+
+    /// Terms with a leading minus sign are written as subtractions.
+    fn sum(&self, terms: Vec<(String, Precedence)>) -> (String, Precedence) {
+        let mut terms = terms.into_iter();
+        let Some((first, _)) = terms.next() else {
+            return (self.notation.number(0.0), Precedence::Atom);
+        };
+
+        let mut out = first;
+        for (term, _) in terms {
+            out = self.notation.infix("+", &[out, term]);
+        }
+        (out, Precedence::Sum)
+    }
+
+    fn negate(&self, operand: (String, Precedence)) -> (String, Precedence) {
+        let inner = self.wrap_below(operand, Precedence::Product);
+        (self.notation.negated(&inner), Precedence::Negation)
+    }
+
+    fn wrap_below(&self, (s, precedence): (String, Precedence), min: Precedence) -> String {
+        if precedence < min {
+            self.notation.parenthesized(&s)
+        } else {
+            s
+        }
+    }
+
+    fn wrap_at_most(&self, (s, precedence): (String, Precedence), max: Precedence) -> String {
+        if precedence <= max {
+            self.notation.parenthesized(&s)
+        } else {
+            s
+        }
+    }
+}
+
+/// Splits names such as `k_on` into a base and a subscript, and finds out if
+/// the base is the name of a greek letter.
+fn split_name(name: &str) -> (&str, Option<usize>, Option<&str>) {
+    let (base, subscript) = match name.split_once('_') {
+        Some((base, subscript)) if !base.is_empty() && !subscript.is_empty() => {
+            (base, Some(subscript))
+        }
+        _ => (name, None),
+    };
+    let greek = GREEK_LETTERS.iter().position(|letter| *letter == base);
+    (base, greek, subscript)
+}
+
+struct Latex;
+
+impl Notation for Latex {
+    fn symbol(&self, name: &str) -> String {
+        let (base, greek, subscript) = split_name(name);
+
+        let base = match greek {
+            Some(_) => format!(r"\{base}"),
+            None if base.chars().count() == 1 => base.to_owned(),
+            None => format!(r"\mathit{{{}}}", base.replace('_', r"\_")),
+        };
+
+        match subscript {
+            Some(subscript) => format!(r"{base}_{{\mathrm{{{}}}}}", subscript.replace('_', r"\_")),
+            None => base,
+        }
+    }
+
+    fn number(&self, value: f64) -> String {
+        value.to_string()
+    }
+
+    fn initial_value(&self, name: &str) -> String {
+        format!("{}(t_0)", self.symbol(name))
+    }
+
+    fn parenthesized(&self, inner: &str) -> String {
+        format!(r"\left({inner}\right)")
+    }
+
+    fn infix(&self, operator: &str, operands: &[String]) -> String {
+        let operator = match operator {
+            "*" => r" \cdot ",
+            "+" => " + ",
+            "-" => " - ",
+            other => return operands.join(&format!(" {other} ")),
+        };
+
+        // `a + -b` reads better as `a - b`
+        operands
+            .iter()
+            .enumerate()
+            .fold(String::new(), |mut out, (idx, operand)| {
+                if idx > 0 {
+                    match operand.strip_prefix('-') {
+                        Some(negated) if operator == " + " => {
+                            out.push_str(" - ");
+                            out.push_str(negated);
+                            return out;
+                        }
+                        _ => out.push_str(operator),
+                    }
+                }
+                out.push_str(operand);
+                out
+            })
+    }
+
+    fn negated(&self, inner: &str) -> String {
+        format!("-{inner}")
+    }
+
+    fn fraction(&self, numerator: &str, denominator: &str) -> String {
+        format!(r"\frac{{{numerator}}}{{{denominator}}}")
+    }
+
+    fn power(&self, base: &str, exponent: &str) -> String {
+        format!("{base}^{{{exponent}}}")
+    }
+
+    fn call(&self, function: &str, args: &[String]) -> String {
+        format!(
+            r"\operatorname{{{}}}\left({}\right)",
+            function.replace('_', r"\_"),
+            args.join(", ")
+        )
+    }
+}
+
+struct MathMl;
+
+const MINUS: &str = "<mo>&#x2212;</mo>";
+
+impl Notation for MathMl {
+    fn symbol(&self, name: &str) -> String {
+        let (base, greek, subscript) = split_name(name);
+
+        let base = match greek {
+            Some(idx) => format!("<mi>{}</mi>", GREEK_SYMBOLS[idx]),
+            None => format!("<mi>{base}</mi>"),
+        };
+
+        match subscript {
+            Some(subscript) => format!("<msub>{base}<mi>{subscript}</mi></msub>"),
+            None => base,
+        }
+    }
+
+    fn number(&self, value: f64) -> String {
+        format!("<mn>{value}</mn>")
+    }
+
+    fn initial_value(&self, name: &str) -> String {
+        format!(
+            "<mrow>{}<mo>(</mo><msub><mi>t</mi><mn>0</mn></msub><mo>)</mo></mrow>",
+            self.symbol(name)
+        )
+    }
+
+    fn parenthesized(&self, inner: &str) -> String {
+        format!("<mrow><mo>(</mo>{inner}<mo>)</mo></mrow>")
+    }
+
+    fn infix(&self, operator: &str, operands: &[String]) -> String {
+        let operator = match operator {
+            "*" => "<mo>&#x22C5;</mo>".to_owned(),
+            "+" => "<mo>+</mo>".to_owned(),
+            "-" => MINUS.to_owned(),
+            other => format!("<mo>{other}</mo>"),
+        };
+
+        let mut out = String::from("<mrow>");
+        for (idx, operand) in operands.iter().enumerate() {
+            if idx > 0 {
+                // `a + -b` reads better as `a - b`
+                let negated = operand
+                    .strip_prefix("<mrow>")
+                    .and_then(|rest| rest.strip_prefix(MINUS))
+                    .and_then(|rest| rest.strip_suffix("</mrow>"));
+                match negated {
+                    Some(negated) if operator == "<mo>+</mo>" => {
+                        out.push_str(MINUS);
+                        out.push_str(negated);
+                        continue;
+                    }
+                    _ => out.push_str(&operator),
+                }
+            }
+            out.push_str(operand);
+        }
+        out.push_str("</mrow>");
+        out
+    }
+
+    fn negated(&self, inner: &str) -> String {
+        format!("<mrow>{MINUS}{inner}</mrow>")
+    }
+
+    fn fraction(&self, numerator: &str, denominator: &str) -> String {
+        format!("<mfrac><mrow>{numerator}</mrow><mrow>{denominator}</mrow></mfrac>")
+    }
+
+    fn power(&self, base: &str, exponent: &str) -> String {
+        format!("<msup><mrow>{base}</mrow><mrow>{exponent}</mrow></msup>")
+    }
+
+    fn call(&self, function: &str, args: &[String]) -> String {
+        format!(
+            "<mrow><mi>{function}</mi><mo>&#x2061;</mo><mrow><mo>(</mo>{}<mo>)</mo></mrow></mrow>",
+            args.join("<mo>,</mo>")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::ode::Metadata;
+    use crate::models::{Argument, Function};
+    use crate::transformations::test_support::*;
+
+    use super::*;
+
+    fn abc_model() -> OdeModel {
+        let mut model = OdeModel::new(
+            "_".into(),
+            Metadata {
+                start_time: 0.0,
+                delta_time: 0.1,
+                end_time: 10.0,
+                time_unit: None,
+            },
+        );
+
+        model.insert_argument(value("A", 10_f64));
+        model.insert_argument(value("B", 20_f64));
+        model.insert_argument(value("k", 0.5));
+
+        model.insert_argument(composite("A+B", "+", [arg("A"), arg("B")]));
+        model.insert_equation(equation("dA/dt", "A", arg("A+B")));
+
+        model.insert_argument(composite("(A+B)*k", "*", [arg("A+B"), argument("k", '-')]));
+        model.insert_equation(equation("dB/dt", "B", argument("(A+B)*k", '-')));
+
+        model
+    }
+
+    #[test]
+    fn render_simple() {
+        let model = abc_model();
+
+        const EXPECTED_LATEX: &str = include_str!("fixtures/abc_ode.tex");
+        const EXPECTED_MATHML: &str = include_str!("fixtures/abc_ode.html");
+
+        assert_eq!(render_latex(&model, |_, _| None), EXPECTED_LATEX);
+        assert_eq!(render_mathml(&model, |_, _| None), EXPECTED_MATHML);
+    }
+
+    #[test]
+    fn greek_letters_and_subscripts() {
+        assert_eq!(Latex.symbol("beta"), r"\beta");
+        assert_eq!(Latex.symbol("k_on"), r"k_{\mathrm{on}}");
+        assert_eq!(Latex.symbol("gamma_1"), r"\gamma_{\mathrm{1}}");
+        assert_eq!(Latex.symbol("Tcell"), r"\mathit{Tcell}");
+        assert_eq!(MathMl.symbol("beta"), "<mi>β</mi>");
+        assert_eq!(MathMl.symbol("k_on"), "<msub><mi>k</mi><mi>on</mi></msub>");
+    }
+
+    #[test]
+    fn prefixed_composites() {
+        let mut model = abc_model();
+        model.insert_argument(value("C", 1_f64));
+        model.insert_argument(Argument::Composite {
+            name: "hill".into(),
+            operation: "hill".into(),
+            style: CompositionStyle::Prefixed,
+            composition: vec![arg("A+B"), arg("k")],
+        });
+        model.insert_argument(composite("C/hill", "/", [arg("C"), arg("hill")]));
+        model.insert_equation(equation("dC/dt", "C", arg("C/hill")));
+
+        let latex = render_latex(&model, |_, _| None);
+        assert!(
+            latex.contains(r"\frac{dC}{dt} &= \frac{C}{\operatorname{hill}\left(A + B, k\right)}")
+        );
+
+        let latex = render_latex(&model, |function, args| {
+            (function == "hill").then(|| format!("{} ^ {}", args[0], args[1]))
+        });
+        assert!(latex.contains(r"\frac{C}{\left(A + B\right) ^ k}"));
+        assert!(!latex.contains(r"\partial"));
+    }
+
+    #[test]
+    fn functions_are_defined() {
+        let mut model = abc_model();
+        model.insert_argument(Argument::Composite {
+            name: "hill".into(),
+            operation: "hill".into(),
+            style: CompositionStyle::Prefixed,
+            composition: vec![arg("A"), arg("k")],
+        });
+        model.insert_equation(equation("dB/dt", "B", arg("hill")));
+        model.functions.push(Function {
+            name: "hill".into(),
+            parameters: vec!["x".into(), "k".into()],
+            body: "x / (k + x)".into(),
+        });
+
+        let latex = render_latex(&model, |_, _| None);
+        assert!(latex.contains(r"\operatorname{hill}\left(x, k\right) &= \frac{x}{k + x}"));
+        // Functions are inlined in the Jacobian
+        assert!(latex.contains(r"&= \frac{k}{\left(k + A\right)^{2}}"));
+
+        let mathml = render_mathml(&model, |_, _| None);
+        assert!(mathml.contains("<mtd><mrow><mi>hill</mi>"));
+    }
+
+    #[test]
+    fn expressions() {
+        let typesetter = Typesetter {
+            model: &abc_model(),
+            notation: &Latex,
+            format_prefixed: &|_, _| None,
+        };
+        let node = |text: &str| {
+            let mut expr = expr_evaluator::expr::Expression::new();
+            expr.parse_expr(text.to_owned()).unwrap();
+            typesetter.node(&expr.ast.unwrap()).0
+        };
+
+        assert_eq!(
+            node("(A + B) ^ 2 * -k"),
+            r"\left(A + B\right)^{2} \cdot \left(-k\right)"
+        );
+        assert_eq!(
+            node("A < B ? sqrt(A) : 0"),
+            r"\operatorname{piecewise}\left(\operatorname{sqrt}\left(A\right), A < B, 0\right)"
+        );
+    }
+}
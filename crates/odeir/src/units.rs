@@ -0,0 +1,324 @@
+//! Physical units attached to values, such as `cells/mL` or `1/day`.
+//!
+//! Units are kept symbolic: each name is its own dimension, so `day` and `h`
+//! are considered different units and are never converted into each other.
+//! That is enough to catch rates measured per day being mixed with rates
+//! measured per hour.
+
+use std::{fmt::Display, str::FromStr};
+
+use crate::Map;
+
+/// A product of unit names raised to integer powers. The dimensionless unit
+/// has no names at all.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Unit {
+    exponents: Map<String, i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseUnitError {
+    /// Byte offset of the problem within the unit.
+    pub position: usize,
+    pub reason: &'static str,
+}
+
+impl Display for ParseUnitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.reason, self.position)
+    }
+}
+
+impl std::error::Error for ParseUnitError {}
+
+impl Unit {
+    pub fn dimensionless() -> Self {
+        Self::default()
+    }
+
+    pub fn is_dimensionless(&self) -> bool {
+        self.exponents.is_empty()
+    }
+
+    fn named(name: &str) -> Self {
+        Self {
+            exponents: Map::from([(name.to_owned(), 1)]),
+        }
+    }
+
+    pub fn powi(&self, exponent: i32) -> Self {
+        let mut unit = Self::dimensionless();
+        if exponent != 0 {
+            for (name, power) in &self.exponents {
+                unit.exponents.insert(name.clone(), power * exponent);
+            }
+        }
+        unit
+    }
+
+    pub fn recip(&self) -> Self {
+        self.powi(-1)
+    }
+
+    fn combine(mut self, other: &Self, sign: i32) -> Self {
+        for (name, power) in &other.exponents {
+            let entry = self.exponents.entry(name.clone()).or_default();
+            *entry += sign * power;
+            if *entry == 0 {
+                self.exponents.remove(name);
+            }
+        }
+        self
+    }
+}
+
+impl std::ops::Mul<&Unit> for Unit {
+    type Output = Unit;
+
+    fn mul(self, rhs: &Unit) -> Self::Output {
+        self.combine(rhs, 1)
+    }
+}
+
+impl std::ops::Div<&Unit> for Unit {
+    type Output = Unit;
+
+    fn div(self, rhs: &Unit) -> Self::Output {
+        self.combine(rhs, -1)
+    }
+}
+
+/// Writes names with positive powers over names with negative ones, as in
+/// `cells/(mL*day)`.
+impl Display for Unit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn product(factors: &[(&String, i32)]) -> String {
+            factors
+                .iter()
+                .map(|(name, power)| match power {
+                    1 => (*name).clone(),
+                    _ => format!("{name}^{power}"),
+                })
+                .collect::<Vec<_>>()
+                .join("*")
+        }
+
+        let (numerator, denominator): (Vec<_>, Vec<_>) = self
+            .exponents
+            .iter()
+            .map(|(name, &power)| (name, power))
+            .partition(|(_, power)| *power > 0);
+        let denominator: Vec<_> = denominator
+            .into_iter()
+            .map(|(name, power)| (name, -power))
+            .collect();
+
+        match (numerator.is_empty(), denominator.len()) {
+            (true, 0) => f.write_str("1"),
+            (false, 0) => f.write_str(&product(&numerator)),
+            (numerator_is_empty, len) => {
+                let numerator = if numerator_is_empty {
+                    "1".to_owned()
+                } else {
+                    product(&numerator)
+                };
+                if len == 1 {
+                    write!(f, "{numerator}/{}", product(&denominator))
+                } else {
+                    write!(f, "{numerator}/({})", product(&denominator))
+                }
+            }
+        }
+    }
+}
+
+/// Parses units made of names, `1`, `*`, `/`, integer powers written with
+/// `^` and parentheses. Multiplication and division associate to the left,
+/// so `1/day*mL` is `mL/day`.
+impl FromStr for Unit {
+    type Err = ParseUnitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { input: s, pos: 0 };
+        let unit = parser.product()?;
+
+        parser.skip_whitespace();
+        if parser.pos < s.len() {
+            return Err(parser.error("expected `*`, `/` or `^`"));
+        }
+
+        Ok(unit)
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, reason: &'static str) -> ParseUnitError {
+        ParseUnitError {
+            position: self.pos,
+            reason,
+        }
+    }
+
+    fn rest(&self) -> &str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn product(&mut self) -> Result<Unit, ParseUnitError> {
+        let mut unit = self.power()?;
+        loop {
+            if self.eat('*') {
+                unit = unit * &self.power()?;
+            } else if self.eat('/') {
+                unit = unit / &self.power()?;
+            } else {
+                return Ok(unit);
+            }
+        }
+    }
+
+    fn power(&mut self) -> Result<Unit, ParseUnitError> {
+        let base = self.atom()?;
+        if !self.eat('^') {
+            return Ok(base);
+        }
+
+        self.skip_whitespace();
+        let negative = self.eat('-');
+        let digits = self.rest().len()
+            - self
+                .rest()
+                .trim_start_matches(|c: char| c.is_ascii_digit())
+                .len();
+        let exponent: i32 = self.rest()[..digits]
+            .parse()
+            .map_err(|_| self.error("expected an integer power"))?;
+        self.pos += digits;
+
+        Ok(base.powi(if negative { -exponent } else { exponent }))
+    }
+
+    fn atom(&mut self) -> Result<Unit, ParseUnitError> {
+        if self.eat('(') {
+            let unit = self.product()?;
+            if !self.eat(')') {
+                return Err(self.error("expected `)`"));
+            }
+            return Ok(unit);
+        }
+
+        self.skip_whitespace();
+        let input = self.input;
+        let rest = &input[self.pos..];
+        let len = rest.len()
+            - rest
+                .trim_start_matches(|c: char| c.is_alphanumeric() || c == '_' || c == '%')
+                .len();
+
+        match &rest[..len] {
+            "" => Err(self.error("expected a unit")),
+            "1" => {
+                self.pos += len;
+                Ok(Unit::dimensionless())
+            }
+            name if name.starts_with(|c: char| c.is_ascii_digit()) => {
+                Err(self.error("scale factors are not supported"))
+            }
+            name => {
+                self.pos += len;
+                Ok(Unit::named(name))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(s: &str) -> Unit {
+        s.parse().unwrap_or_else(|err| panic!("{s}: {err}"))
+    }
+
+    #[test]
+    fn parse_and_display() {
+        assert_eq!(unit("cells/mL").to_string(), "cells/mL");
+        assert_eq!(unit("1/day").to_string(), "1/day");
+        assert_eq!(unit(" 1 / ( mL * day ) ").to_string(), "1/(day*mL)");
+        assert_eq!(unit("mL^-1*cells").to_string(), "cells/mL");
+        assert_eq!(unit("m^2/s^2").to_string(), "m^2/s^2");
+        assert_eq!(unit("cells/cells").to_string(), "1");
+        assert!(unit("1").is_dimensionless());
+    }
+
+    #[test]
+    fn arithmetic() {
+        let concentration = unit("cells/mL");
+        let rate = unit("1/day");
+
+        assert_eq!(concentration.clone() * &rate, unit("cells/(mL*day)"));
+        assert_eq!(
+            concentration.clone() / &concentration,
+            Unit::dimensionless()
+        );
+        assert_eq!(rate.recip(), unit("day"));
+        assert_eq!(concentration.powi(2), unit("cells^2/mL^2"));
+    }
+
+    #[test]
+    fn invalid_units() {
+        let errors: Vec<_> = ["", "cells/", "(mL", "1000*mL", "mL^x", "cells mL"]
+            .into_iter()
+            .map(|s| s.parse::<Unit>().unwrap_err())
+            .collect();
+
+        assert_eq!(
+            errors,
+            [
+                ParseUnitError {
+                    position: 0,
+                    reason: "expected a unit"
+                },
+                ParseUnitError {
+                    position: 6,
+                    reason: "expected a unit"
+                },
+                ParseUnitError {
+                    position: 3,
+                    reason: "expected `)`"
+                },
+                ParseUnitError {
+                    position: 0,
+                    reason: "scale factors are not supported"
+                },
+                ParseUnitError {
+                    position: 3,
+                    reason: "expected an integer power"
+                },
+                ParseUnitError {
+                    position: 6,
+                    reason: "expected `*`, `/` or `^`"
+                },
+            ]
+        );
+    }
+}
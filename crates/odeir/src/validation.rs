@@ -0,0 +1,959 @@
+//! Static checks on a [`CoreModel`], so mistakes are reported before the
+//! code generators render broken code or panic on a dangling name.
+//!
+//! Values may also carry units, which are checked by a dimensional analysis
+//! of the composites and equations. As units are optional, anything that
+//! depends on a value without one is left unchecked.
+
+use std::fmt::Display;
+
+use crate::{
+    models::{ode::OdeModel, parameter::Bounds, Argument, CompositionStyle, CoreModel},
+    units::{ParseUnitError, Unit},
+    Map,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// The language a model is going to be generated in, which decides what is
+/// a valid identifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Target {
+    #[default]
+    Python,
+    Julia,
+    R,
+    C,
+    /// The simulators of the editor, which evaluate expressions themselves
+    /// and so have no words of their own.
+    Native,
+}
+
+impl Target {
+    /// Keywords of the language, along with the names the generated code
+    /// already uses for its own variables, separated by whitespace.
+    fn reserved_words(self) -> &'static str {
+        match self {
+            Target::Python => {
+                "\
+                False None True and as assert async await break class continue def del elif else \
+                except finally for from global if import in is lambda nonlocal not or pass raise \
+                return try while with yield np scipy sys os argparse contextlib t y constants history"
+            }
+            Target::Julia => {
+                "\
+                baremodule begin break catch const continue do else elseif end export false \
+                finally for function global if import let local macro module quote return struct \
+                true try using while du u p t"
+            }
+            Target::R => {
+                "\
+                if else repeat while function for next break in TRUE FALSE NULL Inf NaN NA \
+                NA_integer_ NA_real_ NA_character_ t state parms"
+            }
+            Target::C => {
+                "\
+                auto break case char const continue default do double else enum extern float for \
+                goto if inline int long register restrict return short signed sizeof static \
+                struct switch typedef union unsigned void volatile while bool catch class delete \
+                false namespace new operator private protected public template this throw true \
+                try using virtual t y dydt params"
+            }
+            Target::Native => "",
+        }
+    }
+
+    pub fn is_valid_identifier(self, name: &str) -> bool {
+        let mut chars = name.chars();
+        let valid_start = match chars.next() {
+            // R names can't start with an underscore
+            Some('_') => self != Target::R,
+            Some(c) => c.is_ascii_alphabetic(),
+            None => false,
+        };
+
+        valid_start
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !self
+                .reserved_words()
+                .split_whitespace()
+                .any(|word| word == name)
+    }
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Target::Python => "Python",
+            Target::Julia => "Julia",
+            Target::R => "R",
+            Target::C => "C",
+            Target::Native => "the native simulator",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiagnosticKind {
+    /// A composite or equation refers to an argument that doesn't exist.
+    UndefinedReference {
+        name: String,
+    },
+    /// Composites that end up depending on themselves. The path starts and
+    /// ends on the same composite.
+    Cycle {
+        path: Vec<String>,
+    },
+    /// Only one equation per population is used by the generated code.
+    DuplicateEquation {
+        population: String,
+        first: String,
+    },
+    /// An equation operates on a composite instead of a value.
+    NotAPopulation {
+        name: String,
+    },
+    /// An equation that doesn't operate on any population.
+    Unassigned,
+    EmptyComposition,
+    InvalidIdentifier {
+        target: Target,
+    },
+    UnusedConstant,
+    InvalidUnit {
+        unit: String,
+        reason: ParseUnitError,
+    },
+    /// A sum or difference of operands with different units.
+    MismatchedOperands {
+        first: Unit,
+        other: Unit,
+    },
+    /// The rate of change of a population isn't measured in the units of the
+    /// population per time unit.
+    UnitMismatch {
+        expected: Unit,
+        found: Unit,
+    },
+    /// Bounds whose minimum is above their maximum, or aren't finite.
+    InvalidBounds {
+        bounds: Bounds,
+    },
+    /// A value outside of the bounds declared for it.
+    OutOfBounds {
+        bounds: Bounds,
+    },
+    /// A prior distribution that can't be sampled from, such as a normal
+    /// distribution with no deviation.
+    InvalidPrior,
+    /// A submodel whose model file couldn't be loaded.
+    InvalidSubmodel {
+        path: String,
+        reason: String,
+    },
+    /// An argument of a submodel that, once inlined, has the same name as an
+    /// argument of the model.
+    NameClash {
+        submodel: String,
+    },
+    /// A reaction that can't be turned into equations.
+    InvalidReaction {
+        reason: String,
+    },
+    /// A delay whose value can't be looked up.
+    InvalidDelay {
+        reason: String,
+    },
+    /// A function of the model that can't be defined.
+    InvalidFunction {
+        reason: String,
+    },
+    /// A composite that calls a function of the model with the wrong number
+    /// of arguments.
+    ArgumentCount {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// Name of the argument or equation the diagnostic is about.
+    pub node: String,
+    pub kind: DiagnosticKind,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(node: impl Into<String>, kind: DiagnosticKind) -> Self {
+        Self {
+            node: node.into(),
+            kind,
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self.kind {
+            DiagnosticKind::Unassigned
+            | DiagnosticKind::UnusedConstant
+            | DiagnosticKind::InvalidUnit { .. }
+            | DiagnosticKind::MismatchedOperands { .. }
+            | DiagnosticKind::UnitMismatch { .. }
+            | DiagnosticKind::InvalidBounds { .. }
+            | DiagnosticKind::OutOfBounds { .. }
+            | DiagnosticKind::InvalidPrior => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity() == Severity::Error
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            DiagnosticKind::UndefinedReference { name } => {
+                write!(
+                    f,
+                    "`{}` refers to `{name}`, which does not exist",
+                    self.node
+                )
+            }
+            DiagnosticKind::Cycle { path } => {
+                write!(
+                    f,
+                    "`{}` depends on itself: {}",
+                    self.node,
+                    path.join(" -> ")
+                )
+            }
+            DiagnosticKind::DuplicateEquation { population, first } => write!(
+                f,
+                "`{}` operates on `{population}`, which is already defined by `{first}`",
+                self.node
+            ),
+            DiagnosticKind::NotAPopulation { name } => write!(
+                f,
+                "`{}` operates on `{name}`, which is an expression instead of a population",
+                self.node
+            ),
+            DiagnosticKind::Unassigned => {
+                write!(f, "`{}` does not operate on any population", self.node)
+            }
+            DiagnosticKind::EmptyComposition => write!(f, "`{}` has no inputs", self.node),
+            DiagnosticKind::InvalidIdentifier { target } => {
+                write!(f, "`{}` is not a valid name in {target}", self.node)
+            }
+            DiagnosticKind::UnusedConstant => write!(f, "`{}` is never used", self.node),
+            DiagnosticKind::InvalidUnit { unit, reason } => {
+                write!(f, "`{}` has an invalid unit `{unit}`: {reason}", self.node)
+            }
+            DiagnosticKind::MismatchedOperands { first, other } => write!(
+                f,
+                "`{}` adds up terms in `{first}` and in `{other}`",
+                self.node
+            ),
+            DiagnosticKind::UnitMismatch { expected, found } => write!(
+                f,
+                "`{}` should be in `{expected}`, but is in `{found}`",
+                self.node
+            ),
+            DiagnosticKind::InvalidBounds { bounds } => write!(
+                f,
+                "`{}` has invalid bounds [{}, {}]",
+                self.node, bounds.min, bounds.max
+            ),
+            DiagnosticKind::OutOfBounds { bounds } => write!(
+                f,
+                "`{}` is outside of its bounds [{}, {}]",
+                self.node, bounds.min, bounds.max
+            ),
+            DiagnosticKind::InvalidPrior => {
+                write!(f, "`{}` has a prior that can't be sampled", self.node)
+            }
+            DiagnosticKind::InvalidSubmodel { path, reason } => write!(
+                f,
+                "`{}` refers to `{path}`, which could not be loaded: {reason}",
+                self.node
+            ),
+            DiagnosticKind::NameClash { submodel } => write!(
+                f,
+                "`{}` is defined both by the model and by its submodel `{submodel}`",
+                self.node
+            ),
+            DiagnosticKind::InvalidReaction { reason } => {
+                write!(f, "`{}` is not a valid reaction: {reason}", self.node)
+            }
+            DiagnosticKind::InvalidDelay { reason } => {
+                write!(f, "`{}` is not a valid delay: {reason}", self.node)
+            }
+            DiagnosticKind::InvalidFunction { reason } => {
+                write!(f, "`{}` is not a valid function: {reason}", self.node)
+            }
+            DiagnosticKind::ArgumentCount {
+                function,
+                expected,
+                found,
+            } => write!(
+                f,
+                "`{}` calls `{function}` with {found} argument(s), but it takes {expected}",
+                self.node
+            ),
+        }
+    }
+}
+
+impl CoreModel {
+    /// Checks the model for mistakes that would make the code generated for
+    /// `target` fail or misbehave. Errors come before warnings.
+    pub fn validate(&self, target: Target) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        self.validate_arguments(target, &mut diagnostics);
+        self.validate_equations(&mut diagnostics);
+        self.validate_delays(target, &mut diagnostics);
+        self.validate_functions(target, &mut diagnostics);
+        self.find_cycles(&mut diagnostics);
+
+        diagnostics.sort_by_key(|diagnostic| std::cmp::Reverse(diagnostic.severity()));
+        diagnostics
+    }
+
+    fn validate_arguments(&self, target: Target, diagnostics: &mut Vec<Diagnostic>) {
+        // Names of the functions called by prefixed composites, which are
+        // reported once even if several composites call them
+        let mut operations = Vec::new();
+
+        for arg in self.arguments.values() {
+            match arg {
+                Argument::Value {
+                    name, value, info, ..
+                } => {
+                    if !target.is_valid_identifier(name) {
+                        diagnostics.push(Diagnostic::new(
+                            name,
+                            DiagnosticKind::InvalidIdentifier { target },
+                        ));
+                    }
+
+                    let Some(info) = info else {
+                        continue;
+                    };
+
+                    match info.bounds {
+                        Some(bounds) if !bounds.is_valid() => diagnostics.push(Diagnostic::new(
+                            name,
+                            DiagnosticKind::InvalidBounds { bounds },
+                        )),
+                        Some(bounds) if !bounds.contains(*value) => diagnostics.push(
+                            Diagnostic::new(name, DiagnosticKind::OutOfBounds { bounds }),
+                        ),
+                        _ => {}
+                    }
+
+                    if info.prior.is_some_and(|prior| !prior.is_valid()) {
+                        diagnostics.push(Diagnostic::new(name, DiagnosticKind::InvalidPrior));
+                    }
+                }
+                Argument::Composite {
+                    name,
+                    operation,
+                    style,
+                    composition,
+                } => {
+                    // Composites are written inline, but prefixed ones call
+                    // their operation by name. Functions of the model are
+                    // checked along with their definition
+                    if *style == CompositionStyle::Prefixed
+                        && self.get_function(operation).is_none()
+                        && !target.is_valid_identifier(operation)
+                        && !operations.contains(&operation)
+                    {
+                        operations.push(operation);
+                        diagnostics.push(Diagnostic::new(
+                            operation,
+                            DiagnosticKind::InvalidIdentifier { target },
+                        ));
+                    }
+
+                    if composition.is_empty() {
+                        diagnostics.push(Diagnostic::new(name, DiagnosticKind::EmptyComposition));
+                    }
+
+                    for component in composition {
+                        if !self.is_defined(&component.name) {
+                            diagnostics.push(Diagnostic::new(
+                                name,
+                                DiagnosticKind::UndefinedReference {
+                                    name: component.name.clone(),
+                                },
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        for constant in self.get_constants() {
+            let name = constant.name();
+            let is_used = self.equations.iter().any(|eq| eq.argument == name)
+                || self.arguments.values().any(|arg| match arg {
+                    Argument::Composite { composition, .. } => {
+                        composition.iter().any(|component| component.name == name)
+                    }
+                    Argument::Value { .. } => false,
+                });
+
+            if !is_used {
+                diagnostics.push(Diagnostic::new(name, DiagnosticKind::UnusedConstant));
+            }
+        }
+    }
+
+    fn validate_equations(&self, diagnostics: &mut Vec<Diagnostic>) {
+        let mut defined_by: Map<&str, &str> = Map::new();
+
+        for equation in &self.equations {
+            if !self.is_defined(&equation.argument) {
+                diagnostics.push(Diagnostic::new(
+                    &equation.name,
+                    DiagnosticKind::UndefinedReference {
+                        name: equation.argument.clone(),
+                    },
+                ));
+            }
+
+            let Some(population) = &equation.operates_on else {
+                diagnostics.push(Diagnostic::new(&equation.name, DiagnosticKind::Unassigned));
+                continue;
+            };
+
+            match self.arguments.get(population) {
+                None => diagnostics.push(Diagnostic::new(
+                    &equation.name,
+                    DiagnosticKind::UndefinedReference {
+                        name: population.clone(),
+                    },
+                )),
+                Some(Argument::Composite { .. }) => diagnostics.push(Diagnostic::new(
+                    &equation.name,
+                    DiagnosticKind::NotAPopulation {
+                        name: population.clone(),
+                    },
+                )),
+                Some(Argument::Value { .. }) => {}
+            }
+
+            if let Some(first) = defined_by.get(population.as_str()) {
+                diagnostics.push(Diagnostic::new(
+                    &equation.name,
+                    DiagnosticKind::DuplicateEquation {
+                        population: population.clone(),
+                        first: (*first).to_owned(),
+                    },
+                ));
+            } else {
+                defined_by.insert(population, &equation.name);
+            }
+        }
+    }
+
+    /// Dimensional analysis of the composites, and of each equation against
+    /// the units of its population per `time_unit`.
+    pub fn check_units(&self, time_unit: Option<&str>) -> Vec<Diagnostic> {
+        let mut checker = UnitChecker {
+            model: self,
+            units: Map::new(),
+            diagnostics: Vec::new(),
+        };
+
+        for arg in self.arguments.values() {
+            if let Argument::Value {
+                name,
+                unit: Some(unit),
+                ..
+            } = arg
+            {
+                let unit = checker.parse(name, unit);
+                checker.units.insert(name, unit);
+            }
+        }
+
+        // Time is named after the variable the generated code uses for it
+        let time_unit = time_unit.and_then(|unit| checker.parse("t", unit));
+
+        for name in self.arguments.keys() {
+            checker.unit_of(name);
+        }
+
+        for equation in &self.equations {
+            let (Some(population), Some(time_unit)) = (&equation.operates_on, &time_unit) else {
+                continue;
+            };
+            let (Some(population), Some(found)) = (
+                checker.unit_of(population),
+                checker.unit_of(&equation.argument),
+            ) else {
+                continue;
+            };
+
+            let expected = population / time_unit;
+            if found != expected {
+                checker.diagnostics.push(Diagnostic::new(
+                    &equation.name,
+                    DiagnosticKind::UnitMismatch { expected, found },
+                ));
+            }
+        }
+
+        checker.diagnostics
+    }
+
+    /// Depth-first search over the composites, reporting every edge that
+    /// leads back into the current path.
+    fn find_cycles(&self, diagnostics: &mut Vec<Diagnostic>) {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Visit {
+            InProgress,
+            Done,
+        }
+
+        fn visit<'a>(
+            model: &'a CoreModel,
+            name: &'a str,
+            visits: &mut Map<&'a str, Visit>,
+            path: &mut Vec<&'a str>,
+            diagnostics: &mut Vec<Diagnostic>,
+        ) {
+            let Some(Argument::Composite { composition, .. }) = model.arguments.get(name) else {
+                return;
+            };
+
+            visits.insert(name, Visit::InProgress);
+            path.push(name);
+
+            for component in composition {
+                match visits.get(component.name.as_str()) {
+                    Some(Visit::InProgress) => {
+                        let start = path
+                            .iter()
+                            .position(|&node| node == component.name)
+                            .unwrap_or_default();
+                        let mut cycle: Vec<String> =
+                            path[start..].iter().map(|&node| node.to_owned()).collect();
+                        cycle.push(component.name.clone());
+
+                        diagnostics.push(Diagnostic::new(
+                            &component.name,
+                            DiagnosticKind::Cycle { path: cycle },
+                        ));
+                    }
+                    Some(Visit::Done) => {}
+                    None => visit(model, &component.name, visits, path, diagnostics),
+                }
+            }
+
+            path.pop();
+            visits.insert(name, Visit::Done);
+        }
+
+        let mut visits = Map::new();
+        for name in self.arguments.keys() {
+            if !visits.contains_key(name.as_str()) {
+                visit(self, name, &mut visits, &mut Vec::new(), diagnostics);
+            }
+        }
+    }
+}
+
+impl OdeModel {
+    /// Validates the model like [`CoreModel::validate`], checking its units
+    /// against the time unit in its metadata as well.
+    pub fn validate(&self, target: Target) -> Vec<Diagnostic> {
+        let mut diagnostics = self.core.validate(target);
+        diagnostics.extend(self.check_units(self.metadata.time_unit.as_deref()));
+
+        diagnostics.sort_by_key(|diagnostic| std::cmp::Reverse(diagnostic.severity()));
+        diagnostics
+    }
+}
+
+struct UnitChecker<'a> {
+    model: &'a CoreModel,
+    /// Unit of every argument looked at so far, `None` when it is unknown.
+    units: Map<&'a str, Option<Unit>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> UnitChecker<'a> {
+    fn parse(&mut self, node: &str, unit: &str) -> Option<Unit> {
+        unit.parse()
+            .map_err(|reason| {
+                self.diagnostics.push(Diagnostic::new(
+                    node,
+                    DiagnosticKind::InvalidUnit {
+                        unit: unit.to_owned(),
+                        reason,
+                    },
+                ))
+            })
+            .ok()
+    }
+
+    fn unit_of(&mut self, name: &'a str) -> Option<Unit> {
+        if let Some(unit) = self.units.get(name) {
+            return unit.clone();
+        }
+
+        // Cycles are reported by `find_cycles`, here they are just unknown
+        self.units.insert(name, None);
+
+        let unit = match self.model.arguments.get(name) {
+            Some(Argument::Composite {
+                operation,
+                style: CompositionStyle::Infixed,
+                composition,
+                ..
+            }) if !composition.is_empty() => {
+                let mut operands = composition
+                    .iter()
+                    .map(|component| self.unit_of(&component.name))
+                    .collect::<Vec<_>>()
+                    .into_iter();
+
+                match operation.as_str() {
+                    "+" | "-" => {
+                        let mut known = operands.flatten();
+                        let first = known.next();
+                        if let Some(first) = &first {
+                            for other in known.filter(|other| other != first) {
+                                self.diagnostics.push(Diagnostic::new(
+                                    name,
+                                    DiagnosticKind::MismatchedOperands {
+                                        first: first.clone(),
+                                        other,
+                                    },
+                                ));
+                            }
+                        }
+                        first
+                    }
+                    "*" => operands.try_fold(Unit::dimensionless(), |product, unit| {
+                        Some(product * &unit?)
+                    }),
+                    "/" => {
+                        let numerator = operands.next().flatten();
+                        operands.try_fold(numerator?, |quotient, unit| Some(quotient / &unit?))
+                    }
+                    _ => None,
+                }
+            }
+            // A delay is in the unit of its population
+            None => match self.model.get_delay(name) {
+                Some(delay) => self.unit_of(&delay.population),
+                None => None,
+            },
+            // Values without a unit, extension functions and dangling names
+            _ => None,
+        };
+
+        self.units.insert(name, unit.clone());
+        unit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{Argument, Function};
+    use crate::transformations::test_support::*;
+
+    use super::*;
+
+    fn value_in(name: impl Into<String>, value: f64, unit: &str) -> Argument {
+        Argument::Value {
+            name: name.into(),
+            value,
+            unit: Some(unit.into()),
+            info: None,
+        }
+    }
+
+    fn unit(unit: &str) -> Unit {
+        unit.parse().unwrap()
+    }
+
+    fn kinds(model: &CoreModel, target: Target) -> Vec<(String, DiagnosticKind)> {
+        model
+            .validate(target)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.node, diagnostic.kind))
+            .collect()
+    }
+
+    #[test]
+    fn valid_model() {
+        let mut model = CoreModel::new();
+        model.insert_argument(value("A", 10_f64));
+        model.insert_argument(value("k", 0.5));
+        model.insert_argument(composite("A*k", "*", [arg("A"), arg("k")]));
+        model.insert_equation(equation("dA/dt", "A", arg("A*k")));
+
+        assert_eq!(model.validate(Target::Python), vec![]);
+    }
+
+    #[test]
+    fn dangling_names() {
+        let mut model = CoreModel::new();
+        model.insert_argument(value("A", 10_f64));
+        model.insert_argument(composite("A*k", "*", [arg("A"), arg("k")]));
+        model.insert_argument(composite("empty", "+", []));
+        model.insert_equation(equation("dA/dt", "A", arg("A*k")));
+        model.insert_equation(equation("dB/dt", "B", arg("empty")));
+        model.insert_equation(equation("dC/dt", "A*k", arg("A")));
+
+        assert_eq!(
+            kinds(&model, Target::Python),
+            vec![
+                (
+                    "A*k".to_owned(),
+                    DiagnosticKind::UndefinedReference { name: "k".into() }
+                ),
+                ("empty".to_owned(), DiagnosticKind::EmptyComposition),
+                (
+                    "dB/dt".to_owned(),
+                    DiagnosticKind::UndefinedReference { name: "B".into() }
+                ),
+                (
+                    "dC/dt".to_owned(),
+                    DiagnosticKind::NotAPopulation { name: "A*k".into() }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn cycles_and_duplicates() {
+        let mut model = CoreModel::new();
+        model.insert_argument(value("A", 10_f64));
+        model.insert_argument(value("unused", 1_f64));
+        model.insert_argument(composite("x", "+", [arg("A"), arg("y")]));
+        model.insert_argument(composite("y", "*", [arg("A"), arg("x")]));
+        model.insert_equation(equation("first", "A", arg("x")));
+        model.insert_equation(equation("second", "A", arg("y")));
+
+        let diagnostics = model.validate(Target::Python);
+
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diagnostic| (diagnostic.node.as_str(), &diagnostic.kind))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "second",
+                    &DiagnosticKind::DuplicateEquation {
+                        population: "A".into(),
+                        first: "first".into()
+                    }
+                ),
+                (
+                    "x",
+                    &DiagnosticKind::Cycle {
+                        path: vec!["x".into(), "y".into(), "x".into()]
+                    }
+                ),
+                ("unused", &DiagnosticKind::UnusedConstant),
+            ]
+        );
+        assert_eq!(diagnostics[2].severity(), Severity::Warning);
+    }
+
+    #[test]
+    fn identifiers_depend_on_the_target() {
+        assert!(Target::Python.is_valid_identifier("k_on"));
+        assert!(!Target::Python.is_valid_identifier("lambda"));
+        assert!(Target::Julia.is_valid_identifier("lambda"));
+        assert!(!Target::Julia.is_valid_identifier("end"));
+        assert!(Target::Python.is_valid_identifier("_k"));
+        assert!(!Target::R.is_valid_identifier("_k"));
+        assert!(!Target::C.is_valid_identifier("double"));
+        assert!(!Target::Python.is_valid_identifier("2x"));
+        assert!(!Target::Python.is_valid_identifier("k-1"));
+        assert!(Target::Native.is_valid_identifier("lambda"));
+        assert!(!Target::Native.is_valid_identifier("a*k"));
+    }
+
+    #[test]
+    fn called_names_are_identifiers() {
+        let mut model = CoreModel::new();
+        model.insert_argument(value("A", 10_f64));
+        model.insert_argument(Argument::Composite {
+            name: "end(A)".into(),
+            operation: "end".into(),
+            style: CompositionStyle::Prefixed,
+            composition: vec![arg("A")],
+        });
+        model.insert_argument(Argument::Composite {
+            name: "double(A)".into(),
+            operation: "double".into(),
+            style: CompositionStyle::Prefixed,
+            composition: vec![arg("A")],
+        });
+        model.insert_argument(composite("sum", "+", [arg("end(A)"), arg("double(A)")]));
+        model.insert_equation(equation("dA/dt", "A", arg("sum")));
+        model.functions.push(Function {
+            name: "double".into(),
+            parameters: vec!["lambda".into()],
+            body: "2 * lambda".into(),
+        });
+
+        let invalid = |name: &str, target| {
+            (
+                name.to_owned(),
+                DiagnosticKind::InvalidIdentifier { target },
+            )
+        };
+        assert_eq!(
+            kinds(&model, Target::Python),
+            [invalid("lambda", Target::Python)]
+        );
+        assert_eq!(
+            kinds(&model, Target::Julia),
+            [invalid("end", Target::Julia)]
+        );
+        assert_eq!(kinds(&model, Target::R), []);
+        assert_eq!(kinds(&model, Target::C), [invalid("double", Target::C)]);
+        assert_eq!(kinds(&model, Target::Native), []);
+    }
+
+    #[test]
+    fn rates_are_measured_per_time_unit() {
+        let mut model = CoreModel::new();
+        model.insert_argument(value_in("A", 10_f64, "cells/mL"));
+        model.insert_argument(value_in("B", 5_f64, "cells/mL"));
+        model.insert_argument(value_in("k", 0.5, "1/day"));
+        model.insert_argument(value_in("h", 0.1, "1/h"));
+        model.insert_argument(value("c", 2_f64));
+        model.insert_argument(composite("A*k", "*", [arg("A"), arg("k")]));
+        model.insert_argument(composite("B*h", "*", [arg("B"), arg("h")]));
+        model.insert_argument(composite("B*c", "*", [arg("B"), arg("c")]));
+        model.insert_argument(composite("A*k+B*h", "+", [arg("A*k"), arg("B*h")]));
+        model.insert_equation(equation("dA/dt", "A", arg("A*k")));
+        model.insert_equation(equation("dB/dt", "B", arg("B*h")));
+        // Depends on `c`, which has no unit, so it can't be checked
+        model.insert_equation(equation("dB/dt'", "B", arg("B*c")));
+
+        assert_eq!(
+            model
+                .check_units(Some("day"))
+                .into_iter()
+                .map(|diagnostic| (diagnostic.node, diagnostic.kind))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "A*k+B*h".to_owned(),
+                    DiagnosticKind::MismatchedOperands {
+                        first: unit("cells/(mL*day)"),
+                        other: unit("cells/(mL*h)"),
+                    }
+                ),
+                (
+                    "dB/dt".to_owned(),
+                    DiagnosticKind::UnitMismatch {
+                        expected: unit("cells/(mL*day)"),
+                        found: unit("cells/(mL*h)"),
+                    }
+                ),
+            ]
+        );
+
+        // Without a time unit only the composites are checked
+        assert_eq!(model.check_units(None).len(), 1);
+    }
+
+    #[test]
+    fn invalid_units_are_reported() {
+        let mut model = CoreModel::new();
+        model.insert_argument(value_in("A", 10_f64, "cells/"));
+        model.insert_argument(composite("A+A", "+", [arg("A"), arg("A")]));
+        model.insert_equation(equation("dA/dt", "A", arg("A+A")));
+
+        let diagnostics = model.check_units(Some("1000*s"));
+
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.node.as_str())
+                .collect::<Vec<_>>(),
+            ["A", "t"]
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "`A` has an invalid unit `cells/`: expected a unit at position 6"
+        );
+        assert_eq!(diagnostics[0].severity(), Severity::Warning);
+    }
+
+    #[test]
+    fn bounds_and_priors() {
+        use crate::models::parameter::{Distribution, ParameterInfo};
+
+        let with_info = |name: &str, value: f64, info: ParameterInfo| Argument::Value {
+            name: name.into(),
+            value,
+            unit: None,
+            info: Some(info),
+        };
+
+        let mut model = CoreModel::new();
+        model.insert_argument(value("A", 10_f64));
+        model.insert_argument(with_info(
+            "a",
+            2_f64,
+            ParameterInfo {
+                bounds: Some(Bounds { min: 0.0, max: 1.0 }),
+                ..Default::default()
+            },
+        ));
+        model.insert_argument(with_info(
+            "b",
+            0.5,
+            ParameterInfo {
+                bounds: Some(Bounds { min: 1.0, max: 0.0 }),
+                prior: Some(Distribution::Normal {
+                    mean: 0.5,
+                    std_dev: -1.0,
+                }),
+                ..Default::default()
+            },
+        ));
+        model.insert_argument(composite("A*a*b", "*", [arg("A"), arg("a"), arg("b")]));
+        model.insert_equation(equation("dA/dt", "A", arg("A*a*b")));
+
+        assert_eq!(
+            kinds(&model, Target::Python),
+            vec![
+                (
+                    "a".to_owned(),
+                    DiagnosticKind::OutOfBounds {
+                        bounds: Bounds { min: 0.0, max: 1.0 }
+                    }
+                ),
+                (
+                    "b".to_owned(),
+                    DiagnosticKind::InvalidBounds {
+                        bounds: Bounds { min: 1.0, max: 0.0 }
+                    }
+                ),
+                ("b".to_owned(), DiagnosticKind::InvalidPrior),
+            ]
+        );
+    }
+}
@@ -76,18 +76,38 @@ def update_constants_with_params(constants, params):
 
 
 
-def simulate(filename, st=0, tf=50, dt=0.1, plot=False, x_label="time (days)", y_label="conc/ml", params={}):
+# Methods of `solve_ivp` that use the Jacobian
+IMPLICIT_METHODS = ("Radau", "BDF", "LSODA")
+
+def simulate(filename, st=0, tf=50, dt=0.1, plot=False, x_label="time (days)", y_label="conc/ml", params={}, method="RK45"):
     sim_steps = np.arange(st, tf + dt, dt)
 
     constants_values = [value for _, value in update_constants_with_params(constants_with_names(), params)]
 
+    {% if delays -%}
+    simulation_output = solve_dde(
+        fun=system,
+        t_span=(st, tf + dt * 2),
+        y0=initial_values(),
+        args=tuple(constants_values),
+        t_eval=sim_steps,
+    )
+    {%- else -%}
+    {% if jacobian -%}
+    options = {"jac": jacobian} if method in IMPLICIT_METHODS else {}
+    {% else -%}
+    options = {}
+    {% endif -%}
     simulation_output = scipy.integrate.solve_ivp(
         fun=system,
         t_span=(st, tf + dt * 2),
         y0=initial_values(),
         args=tuple(constants_values),
         t_eval=sim_steps,
+        method=method,
+        **options,
     )
+    {%- endif %}
 
     if plot:
         plot_simulation(sim_steps, simulation_output, filename, x_label, y_label)
@@ -105,6 +125,7 @@ if __name__ == "__main__":
     parser.add_argument("--xlabel", type=str, default="time (days)")
     parser.add_argument("--ylabel", type=str, default="conc/ml")
     parser.add_argument("--params", type=str, default="")
+    parser.add_argument("--method", type=str, default="RK45")
 
     args = parser.parse_args()
 
@@ -121,5 +142,6 @@ if __name__ == "__main__":
         dt=args.dt,
         x_label=args.xlabel,
         y_label=args.ylabel,
-        params=params
+        params=params,
+        method=args.method,
     )
\ No newline at end of file
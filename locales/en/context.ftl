@@ -9,3 +9,11 @@ extensions-title = { -extensions-window(label: "Extensions") }
 extensions-origin = { -extensions-origin-column(label: "Origin") }
 extensions-nodes = { -extensions-nodes-column(label: "Implements nodes") }
 extensions-load = { -extensions-load-btn(label: "Load Extension") }
+
+diagnostics-title = { -diagnostics-window(label: "Diagnostics") }
+diagnostics-severity = { -diagnostics-severity-column(label: "Severity") }
+diagnostics-node = { -diagnostics-node-column(label: "Node") }
+diagnostics-message = { -diagnostics-message-column(label: "Message") }
+diagnostics-none = No problems found.
+diagnostics-error = Error
+diagnostics-warning = Warning
@@ -2,4 +2,13 @@ error-csv-read = Failed to read CSV file { $file }
 error-model-load = Failed to load model
 error-pdf-export = Failed to export PDF due to an error in the simulation
 error-python-exec = Failed to execute simulation: { $reason }
+error-native-sim = Failed to simulate the model: { $reason }
+error-stochastic-sim = Failed to simulate the model stochastically: { $reason }
+error-automaton-sim = Failed to simulate the automaton: { $reason }
 error-invalid-term-name = Invalid node name "{ $ident_name }" 
+error-export = Failed to export the model: { $reason }
+error-sbml-export = Failed to export SBML: { $reason }
+error-sbml-import = Failed to import SBML: { $reason }
+error-submodel-import = Failed to import submodel: { $reason }
+warning-sbml-import = Skipped `{ $element }` while importing: { $reason }
+error-invalid-model = The model has errors, see the diagnostics window
@@ -7,11 +7,32 @@ file-new = { -file-new-btn(label: "New") }
 file-load = { -file-load-btn(label: "Load") }
 file-save = { -file-save-btn(label: "Save") }
 file-plot = { -file-plot-btn(label: "Plot CSV") }
+file-import-sbml = { -file-import-sbml-btn(label: "Import SBML") }
+file-import-submodel = { -file-import-submodel-btn(label: "Import Submodel") }
 
 export-code = { -export-code-btn(label: "Generate Code") }
+export-r-code = { -export-r-code-btn(label: "Generate R Code") }
+export-sbml = { -export-sbml-btn(label: "Export SBML") }
+export-latex = { -export-latex-btn(label: "LaTeX") }
+export-mathml = { -export-mathml-btn(label: "MathML") }
+export-save-file = { -export-save-file-btn(label: "Save to File") }
+export-copy = { -export-copy-btn(label: "Copy to Clipboard") }
 export-pdf = { -export-pdf-btn(label: "Plot to PDF") }
 
 language = { -language-btn(label: "Language") }
 dark-theme = Dark Theme
 
 parameter-estimation = { -parameter-estimation-btn(label: "Parameter Estimation") }
+
+simulation-backend = Simulation backend
+backend-native = Native
+backend-python = Python (scipy)
+backend-stochastic = Stochastic
+stochastic-method = Method
+stochastic-ssa = Exact (Gillespie)
+stochastic-tau-leaping = Tau-leaping
+stochastic-tau = Leap
+stochastic-runs = Runs
+stochastic-seed = Seed
+solver = Solver
+validate-model = { -validate-model-btn(label: "Validate Model") }
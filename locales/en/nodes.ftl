@@ -1,10 +1,35 @@
 Term = { -term-icon } Term
 Expression = { -expression-icon } Expression
 Assigner = { -assigner-icon } Assigner
+Reaction = { -reaction-icon } Reaction
+Delay = { -delay-icon } Delay
 Custom = { -custom-icon } { $name }
 
 nothing-yet = Nothing yet!
 term-initial-value = Initial Value: 
+term-unit = Unit: 
+term-details = Details
+term-description = Description
+term-reference = Reference
+term-bounds = Bounds
+term-prior = Prior
+term-prior-none = none
+
+submodel-ports = Ports
+
+reaction-law = Rate law: 
+reaction-mass-action = Mass action
+reaction-michaelis-menten = Michaelis-Menten
+reaction-hill = Hill
+reaction-hill-coefficient = Hill coefficient: 
+reaction-stoichiometry = Stoichiometry: 
+reaction-reactant = Reactant { $idx }
+reaction-product = Product { $idx }
+reaction-add-reactant = + Reactant
+reaction-add-product = + Product
+
+delay-population = Population
+delay-tau = Delay (τ): 
 
 assigner-variable = Variable: 
 assigner-change = { -assigner-change-btn(label: "Change" ) }
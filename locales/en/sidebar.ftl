@@ -2,3 +2,12 @@ node-name-hint = Node name
 start-time = Start Time
 delta-time = Delta Time
 end-time = End Time
+time-unit = Time Unit
+model-ode = ODE
+model-cellular-automata = Cellular Automaton
+grid-size = Grid Size
+steps = Steps
+neighbourhood = Neighbourhood
+neighbourhood-moore = Moore (8 cells)
+neighbourhood-von-neumann = Von Neumann (4 cells)
+seed = Seed
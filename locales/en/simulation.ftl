@@ -4,3 +4,7 @@ default-y-label = Concentration (unit / ml)
 tab-model = { -model-tab(label: "Model") }
 tab-all-plots = { -all-plots-tab(label: "All") }
 tab-idx = Tab { $idx }###tab-{ $idx }
+tab-automaton = { -automaton-tab(label: "Grid") }
+automaton-play = Play
+automaton-pause = Pause
+automaton-generation = Generation
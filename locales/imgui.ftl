@@ -6,12 +6,21 @@
 -file-load-btn =  { $label }###file-load
 -file-save-btn =  { $label }###file-save
 -file-plot-btn =  { $label }###file-plot
+-file-import-sbml-btn = 󰈝 { $label }###file-import-sbml
+-file-import-submodel-btn = 󰘸 { $label }###file-import-submodel
 
 -export-btn =  { $label }###export
 -export-code-btn = 󰯂 { $label }###export-code
+-export-r-code-btn = 󰟔 { $label }###export-r-code
+-export-sbml-btn = 󰗀 { $label }###export-sbml
+-export-latex-btn = 󰘫 { $label }###export-latex
+-export-mathml-btn = 󰌝 { $label }###export-mathml
+-export-save-file-btn = 󰆓 { $label }###export-save-file
+-export-copy-btn = 󰆏 { $label }###export-copy
 -export-pdf-btn =  { $label }###export-pdf
 
 -run-btn =  { $label }###run
+-validate-model-btn = 󰄳 { $label }###validate-model
 
 -extensions-btn = 󱓙 { $label }###extensions
 
@@ -25,9 +34,15 @@
 -extensions-nodes-column = { $label }###nodes-column
 -extensions-load-btn = { $label }###extensions-load
 
+-diagnostics-window = { $label }###diagnostics-window
+-diagnostics-severity-column = { $label }###severity-column
+-diagnostics-node-column = { $label }###node-column
+-diagnostics-message-column = { $label }###message-column
+
 -model-tab = { $label }###model-tab
 -all-plots-tab = { $label }###all-plots-tab
 -parameter-estimation-tab = { $label }###est-param-tab
+-automaton-tab = { $label }###automaton-tab
 
 -parameter-estimation-dnd = { $label }###est-param-dnd
 
@@ -39,4 +54,6 @@
 -term-icon = 󰫧
 -expression-icon = 
 -assigner-icon = 󰉲
+-reaction-icon = 󰂓
+-delay-icon = 󰋚
 -custom-icon = 󰯂
@@ -9,3 +9,11 @@ extensions-title = { -extensions-window(label: "Extensões") }
 extensions-origin = { -extensions-origin-column(label: "Origem") }
 extensions-nodes = { -extensions-nodes-column(label: "Implementa os nós") }
 extensions-load = { -extensions-load-btn(label: "Carregar Extensão") }
+
+diagnostics-title = { -diagnostics-window(label: "Diagnósticos") }
+diagnostics-severity = { -diagnostics-severity-column(label: "Gravidade") }
+diagnostics-node = { -diagnostics-node-column(label: "Nó") }
+diagnostics-message = { -diagnostics-message-column(label: "Mensagem") }
+diagnostics-none = Nenhum problema encontrado.
+diagnostics-error = Erro
+diagnostics-warning = Aviso
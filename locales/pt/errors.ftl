@@ -2,5 +2,13 @@ error-csv-read = Falha ao abrir arquivo CSV { $file }
 error-model-load = Falha ao carregar modelo
 error-pdf-export = Falha ao exportar PDF devido à um erro na simulação
 error-python-exec = Falha ao executar simulação: { $reason }
+error-native-sim = Falha ao simular o modelo: { $reason }
+error-stochastic-sim = Falha ao simular o modelo estocasticamente: { $reason }
+error-automaton-sim = Falha ao simular o autômato: { $reason }
 error-invalid-term-name = Nome do nó inválido "{ $ident_name }" 
-    
\ No newline at end of file
+error-export = Falha ao exportar o modelo: { $reason }
+error-sbml-export = Falha ao exportar SBML: { $reason }
+error-sbml-import = Falha ao importar SBML: { $reason }
+error-submodel-import = Falha ao importar submodelo: { $reason }
+warning-sbml-import = `{ $element }` ignorado ao importar: { $reason }
+error-invalid-model = O modelo possui erros, veja a janela de diagnósticos
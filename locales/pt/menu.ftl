@@ -7,11 +7,32 @@ file-new = { -file-new-btn(label: "Novo") }
 file-load = { -file-load-btn(label: "Abrir") }
 file-save = { -file-save-btn(label: "Salvar") }
 file-plot = { -file-plot-btn(label: "Plotar CSV") }
+file-import-sbml = { -file-import-sbml-btn(label: "Importar SBML") }
+file-import-submodel = { -file-import-submodel-btn(label: "Importar Submodelo") }
 
 export-code = { -export-code-btn(label: "Gerar Código") }
+export-r-code = { -export-r-code-btn(label: "Gerar Código R") }
+export-sbml = { -export-sbml-btn(label: "Exportar SBML") }
+export-latex = { -export-latex-btn(label: "LaTeX") }
+export-mathml = { -export-mathml-btn(label: "MathML") }
+export-save-file = { -export-save-file-btn(label: "Salvar em Arquivo") }
+export-copy = { -export-copy-btn(label: "Copiar para a Área de Transferência") }
 export-pdf = { -export-pdf-btn(label: "Plotar em PDF") }
 
 language = { -language-btn(label: "Idioma") }
 dark-theme = Tema escuro
 
 parameter-estimation = { -parameter-estimation-btn(label: "Estimativa de Parâmetros") }
+
+simulation-backend = Backend de simulação
+backend-native = Nativo
+backend-python = Python (scipy)
+backend-stochastic = Estocástico
+stochastic-method = Método
+stochastic-ssa = Exato (Gillespie)
+stochastic-tau-leaping = Tau-leaping
+stochastic-tau = Passo
+stochastic-runs = Execuções
+stochastic-seed = Semente
+solver = Método numérico
+validate-model = { -validate-model-btn(label: "Validar Modelo") }
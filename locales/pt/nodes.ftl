@@ -1,10 +1,35 @@
 Term = { -term-icon } Termo
 Expression = { -expression-icon } Expressão
 Assigner = { -assigner-icon } Equação
+Reaction = { -reaction-icon } Reação
+Delay = { -delay-icon } Atraso
 Custom = { -custom-icon } { $name }
 
 nothing-yet = Ainda nada!
 term-initial-value = Valor Inicial: 
+term-unit = Unidade: 
+term-details = Detalhes
+term-description = Descrição
+term-reference = Referência
+term-bounds = Limites
+term-prior = Distribuição a priori
+term-prior-none = nenhuma
+
+submodel-ports = Portas
+
+reaction-law = Lei de velocidade: 
+reaction-mass-action = Ação das massas
+reaction-michaelis-menten = Michaelis-Menten
+reaction-hill = Hill
+reaction-hill-coefficient = Coeficiente de Hill: 
+reaction-stoichiometry = Estequiometria: 
+reaction-reactant = Reagente { $idx }
+reaction-product = Produto { $idx }
+reaction-add-reactant = + Reagente
+reaction-add-product = + Produto
+
+delay-population = População
+delay-tau = Atraso (τ): 
 
 assigner-variable = Variável: 
 assigner-change = { -assigner-change-btn(label: "Mudar" ) }
@@ -2,3 +2,12 @@ node-name-hint = Nome do nó
 start-time = Tempo Inicial
 delta-time = Delta-tempo
 end-time = Tempo Final
+time-unit = Unidade de Tempo
+model-ode = EDO
+model-cellular-automata = Autômato Celular
+grid-size = Tamanho da Grade
+steps = Passos
+neighbourhood = Vizinhança
+neighbourhood-moore = Moore (8 células)
+neighbourhood-von-neumann = Von Neumann (4 células)
+seed = Semente
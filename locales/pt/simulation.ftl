@@ -4,3 +4,7 @@ default-y-label = Concentração (unidade / ml)
 tab-model = { -model-tab(label: "Modelo") }
 tab-all-plots = { -all-plots-tab(label: "Todos") }
 tab-idx = Aba { $idx }###tab-{ $idx }
+tab-automaton = { -automaton-tab(label: "Grade") }
+automaton-play = Reproduzir
+automaton-pause = Pausar
+automaton-generation = Geração
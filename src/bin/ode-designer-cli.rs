@@ -0,0 +1,493 @@
+//! Headless front-end for ODE-Designer. Loads an `odeir` model and simulates,
+//! exports or calibrates it without ever opening a window, so it can be used
+//! from scripts and CI.
+
+use std::{
+    fs::File,
+    io::{BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{WrapErr, bail, eyre};
+use ode_designer_rs::ode::{
+    ParameterEstimation,
+    csvdata::CSVData,
+    ga_json::{Bound, ConfigData, GAArgument},
+    odesystem::{self, Solver, ode_system_from_model},
+    stochastic::{self, BAND_PERCENTILES, StochasticSettings},
+};
+use odeir::{
+    models::{cellular_automata::CaModel, ode::OdeModel, submodel::Flattened},
+    validation::{Severity, Target},
+};
+
+const USAGE: &str = "\
+Usage: ode-designer-cli <command> <model.json> [options]
+
+Commands:
+  simulate           Simulate the model and write the results as CSV. For
+                     cellular automata, write how many cells are in each
+                     state at every generation
+  simulate-stochastic
+                     Simulate the model stochastically, reading its terms as
+                     reactions, and write the mean and the 5th and 95th
+                     percentiles of the runs as CSV
+  export-python      Write the generated Python code
+  export-equations   Write the model's equations as plain text
+  export-sbml        Write the model as SBML
+  export-julia       Write a Julia script using DifferentialEquations.jl
+  export-r           Write an R script using deSolve
+  export-c           Write a C/C++ header with the model's `rhs` function
+  export-latex       Write the model's equations and parameters as LaTeX
+  export-mathml      Write the model's equations and parameters as MathML
+  estimate           Estimate parameters against observed data
+  validate           List mistakes in the model, failing if there are errors
+
+Options:
+  -o, --output <file>   Write to <file> instead of stdout
+  --st <time>           Override the model's start time
+  --tf <time>           Override the model's end time
+  --dt <time>           Override the model's time step
+  --solver <name>       (simulate) dop853 (default), dopri5, rk4 or rosenbrock23
+  --method <name>       (simulate-stochastic) ssa (default) or tau-leaping
+  --runs <count>        (simulate-stochastic) Number of runs, 50 by default
+  --seed <number>       (simulate-stochastic) Seed of the first run
+  --tau <time>          (simulate-stochastic) Leap of tau-leaping
+  --driver              (export-c) Include a fixed-step RK4 integrator
+  --target <language>   (validate) python (default), julia, r, c or native
+  --config <file>       (estimate) JSON file with the GA `metadata` and `bounds`,
+                        which default to the bounds of the model's parameters
+  --data <file>         (estimate) CSV file with the observed data
+";
+
+#[derive(Debug, Default)]
+struct Options {
+    output: Option<PathBuf>,
+    start_time: Option<f64>,
+    end_time: Option<f64>,
+    delta_time: Option<f64>,
+    solver: Solver,
+    stochastic: StochasticSettings,
+    driver: bool,
+    target: Target,
+    config: Option<PathBuf>,
+    data: Option<PathBuf>,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> color_eyre::Result<Self> {
+        let mut options = Self::default();
+
+        while let Some(flag) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| eyre!("Missing value for `{flag}`"))
+            };
+
+            match flag.as_str() {
+                "-o" | "--output" => options.output = Some(value()?.into()),
+                "--st" => options.start_time = Some(value()?.parse()?),
+                "--tf" => options.end_time = Some(value()?.parse()?),
+                "--dt" => options.delta_time = Some(value()?.parse()?),
+                "--solver" => options.solver = value()?.parse()?,
+                "--method" => options.stochastic.method = value()?.parse()?,
+                "--runs" => options.stochastic.runs = value()?.parse()?,
+                "--seed" => options.stochastic.seed = value()?.parse()?,
+                "--tau" => options.stochastic.tau = value()?.parse()?,
+                "--driver" => options.driver = true,
+                "--target" => {
+                    options.target = match value()?.as_str() {
+                        "python" => Target::Python,
+                        "julia" => Target::Julia,
+                        "r" => Target::R,
+                        "c" => Target::C,
+                        "native" => Target::Native,
+                        other => bail!("Unknown target `{other}`"),
+                    }
+                }
+                "--config" => options.config = Some(value()?.into()),
+                "--data" => options.data = Some(value()?.into()),
+                other => bail!("Unknown option `{other}`\n\n{USAGE}"),
+            }
+        }
+
+        Ok(options)
+    }
+
+    fn writer(&self) -> color_eyre::Result<Box<dyn Write>> {
+        Ok(match &self.output {
+            Some(path) => Box::new(
+                File::create(path)
+                    .wrap_err_with(|| format!("Could not create {}", path.display()))?,
+            ),
+            None => Box::new(std::io::stdout().lock()),
+        })
+    }
+}
+
+fn load_model(path: &Path) -> color_eyre::Result<odeir::Model> {
+    let file = File::open(path).wrap_err_with(|| format!("Could not open {}", path.display()))?;
+
+    Ok(serde_json::from_reader(BufReader::new(file))?)
+}
+
+/// Extension files are stored relative to the model file, just like when the
+/// editor loads a model.
+fn extension_paths(model: &OdeModel, model_path: &Path) -> Vec<PathBuf> {
+    let base_dir = model_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+
+    model
+        .extension_files
+        .iter()
+        .map(|file| base_dir.join(file))
+        .collect()
+}
+
+fn simulate(
+    model: &OdeModel,
+    extensions: &[&PathBuf],
+    options: &Options,
+) -> color_eyre::Result<()> {
+    let ode_system = ode_system_from_model(model, extensions)?;
+    let initial_state = ode_system.initial_state();
+    let names: Vec<String> = ode_system.population_names().map(str::to_owned).collect();
+
+    let (times, states) = odesystem::integrate(
+        ode_system,
+        options.solver,
+        &initial_state,
+        options.start_time.unwrap_or(model.metadata.start_time),
+        options.end_time.unwrap_or(model.metadata.end_time),
+        options.delta_time.unwrap_or(model.metadata.delta_time),
+    )?;
+
+    odesystem::write_csv(
+        options.writer()?,
+        names.iter().map(String::as_str),
+        &times,
+        &states,
+    )?;
+
+    Ok(())
+}
+
+fn simulate_stochastically(model: &OdeModel, options: &Options) -> color_eyre::Result<()> {
+    let ensemble = stochastic::simulate(
+        model,
+        &options.stochastic,
+        options.start_time.unwrap_or(model.metadata.start_time),
+        options.end_time.unwrap_or(model.metadata.end_time),
+        options.delta_time.unwrap_or(model.metadata.delta_time),
+    )?;
+
+    let (lower, upper) = BAND_PERCENTILES;
+    let lines = [
+        ensemble.mean(),
+        ensemble.percentile(lower),
+        ensemble.percentile(upper),
+    ];
+
+    let mut writer = options.writer()?;
+    write!(writer, "t")?;
+    for label in &ensemble.labels {
+        write!(writer, ",{label}_mean,{label}_p{lower},{label}_p{upper}")?;
+    }
+    writeln!(writer)?;
+
+    for (sample, time) in ensemble.time.iter().enumerate() {
+        write!(writer, "{time}")?;
+        for population in 0..ensemble.labels.len() {
+            for statistic in &lines {
+                write!(writer, ",{}", statistic[population][sample])?;
+            }
+        }
+        writeln!(writer)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn simulate_automaton(model: &CaModel, options: &Options) -> color_eyre::Result<()> {
+    let simulation = model.simulate()?;
+
+    let mut writer = options.writer()?;
+    writeln!(writer, "generation,{}", simulation.states.join(","))?;
+    for (generation, grid) in simulation.generations.iter().enumerate() {
+        write!(writer, "{generation}")?;
+        for count in grid.count(simulation.states.len()) {
+            write!(writer, ",{count}")?;
+        }
+        writeln!(writer)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn estimate(
+    model: &OdeModel,
+    extensions: &[&PathBuf],
+    options: &Options,
+) -> color_eyre::Result<()> {
+    let (Some(config_path), Some(data_path)) = (&options.config, &options.data) else {
+        bail!("`estimate` requires both `--config` and `--data`");
+    };
+
+    let mut config: ConfigData = serde_json::from_reader(BufReader::new(
+        File::open(config_path)
+            .wrap_err_with(|| format!("Could not open {}", config_path.display()))?,
+    ))?;
+
+    let csv_data = CSVData::load_data(
+        File::open(data_path)
+            .wrap_err_with(|| format!("Could not open {}", data_path.display()))?,
+    )?;
+
+    // Arguments are kept sorted by name, matching the order of the system's
+    // equations.
+    config.arguments = model
+        .arguments
+        .values()
+        .filter_map(|arg| match arg {
+            odeir::Argument::Value { name, value, .. } => {
+                Some(GAArgument::new(name.clone(), *value))
+            }
+            _ => None,
+        })
+        .collect();
+
+    // Without bounds in the config, every parameter with bounds in the model
+    // is estimated within them
+    if config.bounds.is_empty() {
+        config.bounds = model
+            .arguments
+            .values()
+            .filter_map(|arg| {
+                let bounds = arg.info()?.bounds.filter(|bounds| bounds.is_valid())?;
+                Some(Bound::new(arg.name().to_owned(), bounds.min, bounds.max))
+            })
+            .collect();
+    }
+
+    if config.bounds.is_empty() {
+        bail!("No bounds were given in the config, and no parameter in the model has bounds");
+    }
+
+    let selected_params = config
+        .bounds
+        .iter()
+        .map(|bound| {
+            config
+                .arguments
+                .iter()
+                .find(|arg| arg.name == bound.name)
+                .cloned()
+                .ok_or_else(|| eyre!("Bound `{}` does not name a model argument", bound.name))
+        })
+        .collect::<color_eyre::Result<Vec<_>>>()?;
+
+    let mut estimator = ParameterEstimation::new();
+    estimator.config_data = config.clone();
+    estimator.estimate_parameters(
+        csv_data,
+        config.arguments,
+        selected_params,
+        ode_system_from_model(model, extensions)?,
+    );
+
+    if estimator.best_solution.is_empty() {
+        bail!("The optimization did not produce a solution");
+    }
+
+    let estimated: odeir::Map<&str, f64> = config
+        .bounds
+        .iter()
+        .map(|bound| bound.name.as_str())
+        .zip(estimator.best_solution.iter().copied())
+        .collect();
+
+    let mut writer = options.writer()?;
+    serde_json::to_writer_pretty(&mut writer, &estimated)?;
+    writeln!(writer)?;
+
+    Ok(())
+}
+
+fn validate(model: &Flattened, options: &Options) -> color_eyre::Result<()> {
+    let diagnostics = model.validate(options.target);
+
+    let mut writer = options.writer()?;
+    for diagnostic in &diagnostics {
+        let severity = match diagnostic.severity() {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        writeln!(writer, "{severity}: {diagnostic}")?;
+    }
+
+    let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+    if errors > 0 {
+        bail!("The model has {errors} error(s)");
+    }
+
+    Ok(())
+}
+
+fn main() -> color_eyre::Result<()> {
+    color_eyre::install()?;
+
+    let mut args = std::env::args().skip(1);
+
+    let (Some(command), Some(model_path)) = (args.next(), args.next()) else {
+        eprint!("{USAGE}");
+        std::process::exit(2);
+    };
+
+    run(&command, Path::new(&model_path), &Options::parse(args)?)
+}
+
+/// Runs `command` on the model at `model_path`.
+fn run(command: &str, model_path: &Path, options: &Options) -> color_eyre::Result<()> {
+    // Submodels are inlined relative to the model file
+    let flattened = match load_model(model_path)? {
+        odeir::Model::ODE(model) => model.flatten(model_path.parent().unwrap_or(Path::new(""))),
+        odeir::Model::CellularAutomata(model) if command == "simulate" => {
+            return simulate_automaton(&model, options);
+        }
+        odeir::Model::CellularAutomata(_) => {
+            bail!("Cellular automata can only be simulated")
+        }
+    };
+    let model = &flattened.model;
+
+    // Only `validate` can cope with submodels that couldn't be inlined
+    if command != "validate"
+        && let Some(error) = flattened.diagnostics.iter().find(|d| d.is_error())
+    {
+        bail!("Could not inline the submodels: {error}");
+    }
+
+    let extension_paths = extension_paths(model, model_path);
+    let extensions: Vec<&PathBuf> = extension_paths.iter().collect();
+
+    match command {
+        "simulate" => simulate(model, &extensions, options),
+        "simulate-stochastic" => simulate_stochastically(model, options),
+        "export-python" => {
+            let code = odeir::transformations::r4k::render_ode(model, &extensions);
+            options.writer()?.write_all(code.as_bytes())?;
+            Ok(())
+        }
+        "export-equations" => {
+            let equations =
+                odeir::transformations::ode::render_txt_with_equations(model, &extensions);
+            options.writer()?.write_all(equations.as_bytes())?;
+            Ok(())
+        }
+        "export-sbml" => {
+            let sbml = odeir::transformations::sbml::render_sbml(model)?;
+            options.writer()?.write_all(sbml.as_bytes())?;
+            Ok(())
+        }
+        "export-julia" => {
+            let code = odeir::transformations::julia::render_julia(model);
+            options.writer()?.write_all(code.as_bytes())?;
+            Ok(())
+        }
+        "export-r" => {
+            let code = odeir::transformations::r::render_r(model);
+            options.writer()?.write_all(code.as_bytes())?;
+            Ok(())
+        }
+        "export-c" => {
+            let header = odeir::transformations::c::render_c_header(model, options.driver);
+            options.writer()?.write_all(header.as_bytes())?;
+            Ok(())
+        }
+        "export-latex" => {
+            let latex = odeir::transformations::typeset::render_latex(model, |_, _| None);
+            options.writer()?.write_all(latex.as_bytes())?;
+            Ok(())
+        }
+        "export-mathml" => {
+            let mathml = odeir::transformations::typeset::render_mathml(model, |_, _| None);
+            options.writer()?.write_all(mathml.as_bytes())?;
+            Ok(())
+        }
+        "estimate" => estimate(model, &extensions, options),
+        "validate" => validate(&flattened, options),
+        other => bail!("Unknown command `{other}`\n\n{USAGE}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|&arg| arg.to_owned())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Runs `command` on the example `model`, returning what it wrote.
+    fn output_of(command: &str, model: &str, flags: &[&str]) -> String {
+        let model_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("examples")
+            .join(model);
+        let output = std::env::temp_dir().join(format!("ode-designer-cli-{command}-{model}"));
+
+        let mut options = Options::parse(args(flags)).unwrap();
+        options.output = Some(output.clone());
+        run(command, &model_path, &options).unwrap();
+
+        std::fs::read_to_string(output).unwrap()
+    }
+
+    #[test]
+    fn options_are_parsed() {
+        let options = Options::parse(args(&[
+            "--tf", "5", "--dt", "0.5", "--solver", "rk4", "-o", "out.csv", "--target", "julia",
+            "--driver",
+        ]))
+        .unwrap();
+
+        assert_eq!(options.start_time, None);
+        assert_eq!(options.end_time, Some(5.0));
+        assert_eq!(options.delta_time, Some(0.5));
+        assert_eq!(options.solver, Solver::Rk4);
+        assert_eq!(options.output, Some(PathBuf::from("out.csv")));
+        assert_eq!(options.target, Target::Julia);
+        assert!(options.driver);
+
+        assert!(Options::parse(args(&["--dt"])).is_err());
+        assert!(Options::parse(args(&["--dt", "soon"])).is_err());
+        assert!(Options::parse(args(&["--target", "cobol"])).is_err());
+        assert!(Options::parse(args(&["--verbose"])).is_err());
+    }
+
+    #[test]
+    fn simulate_writes_csv() {
+        let csv = output_of("simulate", "logistic.json", &["--tf", "1", "--dt", "0.5"]);
+        let lines: Vec<_> = csv.lines().collect();
+
+        assert_eq!(lines[0], "t,N");
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].starts_with("0,10"));
+        assert!(lines[3].starts_with("1,"));
+    }
+
+    #[test]
+    fn exports_write_the_generated_code() {
+        let python = output_of("export-python", "logistic.json", &[]);
+        assert!(python.contains("def system(t: np.float64, y: np.ndarray, *constants)"));
+
+        let sbml = output_of("export-sbml", "logistic.json", &[]);
+        assert!(sbml.contains(r#"<species id="N""#));
+    }
+}
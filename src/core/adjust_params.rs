@@ -26,9 +26,14 @@ pub struct Parameter {
 impl Parameter {
     pub fn new(term: Term) -> Self {
         let node_id: i32 = term.id().into();
+        // Bounds recorded in the model are used as the search range
+        let range = match term.info.bounds {
+            Some(bounds) if bounds.is_valid() => bounds.min as f32..bounds.max as f32,
+            _ => 0.01..1.0,
+        };
         Self {
             term,
-            range: 0.01..1.0,
+            range,
             selected: false,
             min_label: format!("##min-{node_id}"),
             max_label: format!("##max-{node_id}"),
@@ -9,35 +9,43 @@ use fluent_bundle::FluentValue;
 use imnodes::{InputPinId, LinkId, NodeId, OutputPinId};
 
 use implot::{ImVec4, PlotFlags, PlotUi};
+use odeir::models::cellular_automata::Simulation;
 use odeir::models::ode::OdeModel;
+use odeir::models::submodel::Flattened;
+use odeir::validation::{Diagnostic, Severity, Target};
 use rfd::FileDialog;
 use strum::{VariantArray, VariantNames};
 use unic_langid::LanguageIdentifier;
 
 use crate::core::GeneratesId;
-use crate::errors::{InvalidNodeReason, InvalidNodeReference, NotCorrectModel};
+use crate::errors::{
+    ExportError, InvalidNodeReason, InvalidNodeReference, NotCorrectModel, SimulationError,
+};
 use crate::exprtree::Sign;
-use crate::extensions::Extension;
+use crate::extensions::{Extension, ModelFunction, format::Format};
 use crate::locale::Locale;
 use crate::message::{Message, MessageQueue, SendData, TaggedMessage};
 use crate::nodes::{
     LinkEvent, Node, NodeImpl, NodeTypeRepresentation, NodeVariant, PendingOperation,
-    PendingOperations, Term,
+    PendingOperations, SubmodelNode, Term,
 };
 use crate::ode::csvdata;
-use crate::ode::odesystem::create_ode_system;
+use crate::ode::odesystem::{Solver, create_ode_system, integrate, ode_system_from_model};
+use crate::ode::stochastic::{self, Ensemble, StochasticSettings};
 use crate::pins::Pin;
 use crate::utils::{ModelFragment, VecConversion, localized_error};
 
 use imgui::{Key, StyleVar, TabItem, Ui};
 
+use crate::core::plot::EnsemblePlot;
 use crate::core::plot::PlotInfo;
 use crate::core::plot::PlotLayout;
 
 use super::adjust_params::ParameterEstimationState;
+use super::automaton::AutomatonState;
 use super::plot::CSVData;
 use super::python::execute_python_code;
-use super::side_bar::SideBarState;
+use super::side_bar::{ModelKind, SideBarState};
 use super::widgets;
 
 #[derive(Debug, Clone)]
@@ -59,7 +67,7 @@ impl Link {
     }
 }
 
-const COLORS: &[ImVec4] = &[
+pub(crate) const COLORS: &[ImVec4] = &[
     ImVec4::new(0.98, 0.027, 0.027, 1.0), //vermelha
     ImVec4::new(0.09, 0.027, 0.98, 1.0),
     ImVec4::new(0.027, 0.98, 0.12, 1.0), //verde claro
@@ -84,6 +92,8 @@ pub struct SimulationState {
     pub colors: Vec<ImVec4>,
     pub set_focus_to_tab: bool,
     real_data: Option<csvdata::CSVData>,
+    /// Spread of the runs, when the data is the mean of a stochastic simulation
+    ensemble: Option<EnsemblePlot>,
     tab_locale_parameter: HashMap<&'static str, FluentValue<'static>>,
 }
 
@@ -98,10 +108,27 @@ pub struct TextFields {
     pub y_label: String,
 }
 
+/// Where the simulation triggered by the *Run* menu is computed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SimulationBackend {
+    /// Integrates the model in-process, with the selected [`Solver`].
+    #[default]
+    Native,
+    /// Runs the generated code with `python3`, which requires scipy.
+    Python,
+    /// Simulates many runs with whole populations changing at random, reading
+    /// the terms of the model as reactions.
+    Stochastic,
+}
+
 impl SimulationState {
     pub fn from_csv(csv_content: String, locale: &Locale) -> Self {
         let csv_data = CSVData::load_data(csv_content.as_bytes()).unwrap();
 
+        Self::from_data(csv_data, locale)
+    }
+
+    pub fn from_data(csv_data: CSVData, locale: &Locale) -> Self {
         let pane_count = csv_data.population_count().div_ceil(4);
 
         Self {
@@ -110,10 +137,26 @@ impl SimulationState {
             colors: COLORS.to_owned(),
             set_focus_to_tab: true,
             real_data: Default::default(),
+            ensemble: None,
             tab_locale_parameter: HashMap::from([("idx", 0.into())]),
         }
     }
 
+    /// Plots the mean of the runs, along with their spread.
+    pub fn from_ensemble(ensemble: Ensemble, locale: &Locale) -> Self {
+        let ensemble_plot = EnsemblePlot::new(&ensemble);
+        let data = CSVData {
+            lines: ensemble.mean(),
+            labels: ensemble.labels,
+            time: ensemble.time,
+        };
+
+        Self {
+            ensemble: Some(ensemble_plot),
+            ..Self::from_data(data, locale)
+        }
+    }
+
     pub fn draw_tabs(
         &mut self,
         ui: &Ui,
@@ -174,7 +217,12 @@ impl SimulationState {
                             .iter()
                             .zip(&self.plot.data.labels)
                             .zip(self.colors.iter().cycle())
-                            .for_each(|((line, label), color)| {
+                            .enumerate()
+                            .for_each(|(idx, ((line, label), color))| {
+                                if let Some(ensemble) = &self.ensemble {
+                                    ensemble.plot(idx, label, &self.plot.data.time, *color);
+                                }
+
                                 let ImVec4 { x, y, z, w } = *color;
                                 let color_token = implot::push_style_color(
                                     &implot::PlotColorElement::Line,
@@ -226,8 +274,18 @@ impl SimulationState {
                                 .x_label(&self.plot.xlabel)
                                 .y_label(&self.plot.ylabel)
                                 .build(plot_ui, || {
-                                    let ImVec4 { x, y, z, w } = self.colors
-                                        [(tab_idx * populations_per_tab + idx) % self.colors.len()];
+                                    let population = tab_idx * populations_per_tab + idx;
+                                    let color = self.colors[population % self.colors.len()];
+                                    if let Some(ensemble) = &self.ensemble {
+                                        ensemble.plot(
+                                            population,
+                                            label,
+                                            &self.plot.data.time,
+                                            color,
+                                        );
+                                    }
+
+                                    let ImVec4 { x, y, z, w } = color;
                                     let color_token = implot::push_style_color(
                                         &implot::PlotColorElement::Line,
                                         x,
@@ -284,11 +342,23 @@ pub struct App {
     queue: MessageQueue,
     received_messages: HashMap<NodeId, HashSet<usize>>,
     pub(crate) simulation_state: Option<SimulationState>,
+    pub(crate) automaton_state: Option<AutomatonState>,
     pub sidebar_state: SideBarState,
     pub extensions: Vec<Extension>,
+    /// Functions declared by the model, which custom nodes may call
+    pub model_functions: Vec<ModelFunction>,
     pub text_fields: TextFields,
     pub parameter_estimation_state: Option<ParameterEstimationState>,
     pub dark_theme: bool,
+    pub simulation_backend: SimulationBackend,
+    pub solver: Solver,
+    pub stochastic: StochasticSettings,
+    /// Node the editor should be moved to in the next frame
+    focused_node: Option<NodeId>,
+    /// Name stored in the model file, kept so it survives a load/save cycle
+    model_name: String,
+    /// Directory of the model file, which submodel paths are relative to
+    model_dir: PathBuf,
 }
 
 pub enum AppState {
@@ -302,6 +372,9 @@ pub enum AppState {
         search_query: String,
     },
     ManagingExtensions,
+    ReviewingDiagnostics {
+        diagnostics: Vec<Diagnostic>,
+    },
 }
 
 enum StateAction {
@@ -454,6 +527,55 @@ impl AppState {
                     StateAction::Clear
                 }
             }
+            AppState::ReviewingDiagnostics { diagnostics } => {
+                let mut user_kept_open = true;
+                ui.window(locale.get("diagnostics-title"))
+                    .collapsible(false)
+                    .opened(&mut user_kept_open)
+                    .build(|| {
+                        if diagnostics.is_empty() {
+                            ui.text(locale.get("diagnostics-none"));
+                            return;
+                        }
+
+                        if let Some(_t) = ui.begin_table("##Diagnostics Table", 3) {
+                            ui.table_setup_column(locale.get("diagnostics-severity"));
+                            ui.table_setup_column(locale.get("diagnostics-node"));
+                            ui.table_setup_column(locale.get("diagnostics-message"));
+                            ui.table_headers_row();
+
+                            for (idx, diagnostic) in diagnostics.iter().enumerate() {
+                                ui.table_next_row();
+                                ui.table_next_column();
+                                match diagnostic.severity() {
+                                    Severity::Error => ui.text_colored(
+                                        [1.0, 0.1, 0.1, 1.0],
+                                        locale.get("diagnostics-error"),
+                                    ),
+                                    Severity::Warning => ui.text_colored(
+                                        [0.7, 0.7, 0.2, 1.0],
+                                        locale.get("diagnostics-warning"),
+                                    ),
+                                }
+
+                                ui.table_next_column();
+                                let label = format!("{}##diagnostic-{idx}", diagnostic.node);
+                                if ui.selectable(label) {
+                                    app.focus_node(&diagnostic.node);
+                                }
+
+                                ui.table_next_column();
+                                ui.text_wrapped(diagnostic.to_string());
+                            }
+                        }
+                    });
+
+                if user_kept_open {
+                    StateAction::Keep
+                } else {
+                    StateAction::Clear
+                }
+            }
         }
     }
 }
@@ -481,9 +603,20 @@ impl App {
     pub fn get_all_population_ids(&self) -> HashSet<&NodeId> {
         self.nodes
             .iter()
-            .filter_map(|(_id, node)| match node {
-                Node::Assigner(assigner) => assigner.operates_on.as_ref().map(|(id, _)| id),
-                _ => None,
+            .flat_map(|(_id, node)| match node {
+                Node::Assigner(assigner) => assigner
+                    .operates_on
+                    .as_ref()
+                    .map(|(id, _)| id)
+                    .into_iter()
+                    .collect(),
+                // Species of reactions get an equation when the model is flattened
+                Node::Reaction(reaction) => reaction
+                    .species_pins()
+                    .iter()
+                    .filter_map(|pin| self.output_pins.get(pin.linked_to.as_ref()?))
+                    .collect(),
+                _ => Vec::new(),
             })
             .collect()
     }
@@ -582,6 +715,10 @@ impl App {
             self.draw_editor(ui, &mut editor, locale)
         });
 
+        if let Some(node_id) = self.focused_node.take() {
+            context.move_to_node(node_id);
+        }
+
         if let Some(link) = scope.links_created() {
             self.add_link(link.start_pin, link.end_pin);
         } else if let Some(link_id) = scope.get_dropped_link() {
@@ -620,7 +757,13 @@ impl App {
             .collect::<Vec<String>>()
             .join(" ");
 
-        let py_code = self.generate_code();
+        let py_code = match self.generate_code() {
+            Ok(py_code) => py_code,
+            Err(err) => {
+                localized_error!(locale, "error-export", "reason" => err.to_string());
+                return;
+            }
+        };
 
         let mut command = Command::new("python3");
         command
@@ -704,6 +847,12 @@ impl App {
                         }
                     }
 
+                    if let Some(automaton_state) = &mut self.automaton_state {
+                        if automaton_state.draw_tab(ui, locale) == TabAction::Close {
+                            self.automaton_state = None;
+                        }
+                    }
+
                     let mut opened = false;
                     if self.parameter_estimation_state.is_some() {
                         opened = true;
@@ -754,7 +903,9 @@ impl App {
                 .iter()
                 .copied()
                 .zip(NodeVariant::VARIANTS)
-                .filter(|(_, variant)| variant != &&NodeVariant::Custom)
+                .filter(|(_, variant)| {
+                    !matches!(variant, NodeVariant::Custom | NodeVariant::Submodel)
+                })
                 .map(|(name, variant)| {
                     NodeTypeRepresentation::new(locale.get(name), *variant, None)
                 })
@@ -788,6 +939,18 @@ impl App {
                     return None;
                 }
             }
+            Node::Reaction(node) => {
+                let name = node.name().trim();
+                if name.is_empty() {
+                    return None;
+                }
+            }
+            Node::Delay(node) => {
+                let name = node.name().trim();
+                if name.is_empty() {
+                    return None;
+                }
+            }
             _ => (),
         }
 
@@ -1077,6 +1240,14 @@ impl App {
                 self.input_pins.remove(&pin_id);
                 None
             }
+            Message::RegisterOutputPin(node_id, pin_id) => {
+                self.output_pins.insert(pin_id, node_id);
+                None
+            }
+            Message::UnregisterOutputPin(pin_id) => {
+                self.output_pins.remove(&pin_id);
+                None
+            }
             Message::SetInitialValue(node_id, value) => {
                 if let Some(param_state) = &mut self.parameter_estimation_state {
                     param_state.set_initial_value(&node_id, value);
@@ -1117,6 +1288,9 @@ impl App {
     fn create_json(&self) -> odeir::Json {
         let mut arguments = Vec::new();
         let mut equations = Vec::new();
+        let mut submodels = Vec::new();
+        let mut reactions = Vec::new();
+        let mut delays = Vec::new();
         let mut positions = odeir::Map::new();
 
         self.nodes
@@ -1137,12 +1311,21 @@ impl App {
             .for_each(|frag| match frag {
                 ModelFragment::Argument(arg) => arguments.push(arg),
                 ModelFragment::Equation(eq) => equations.push(eq),
+                ModelFragment::Submodel(submodel) => submodels.push(submodel),
+                ModelFragment::Reaction(reaction) => reactions.push(reaction),
+                ModelFragment::Delay(delay) => delays.push(delay),
             });
 
         odeir::Json {
             metadata: odeir::Metadata {
-                name: "TODO".to_string(),
-                model_metadata: odeir::ModelMetadata::ODE(self.sidebar_state.get_metadata()),
+                format_version: odeir::json::CURRENT_FORMAT_VERSION,
+                name: self.model_name.clone(),
+                model_metadata: match self.sidebar_state.kind() {
+                    ModelKind::Ode => odeir::ModelMetadata::ODE(self.sidebar_state.get_metadata()),
+                    ModelKind::CellularAutomata => odeir::ModelMetadata::CellularAutomata(
+                        self.sidebar_state.get_automaton_metadata(),
+                    ),
+                },
                 positions,
                 extension_files: self
                     .extensions
@@ -1152,42 +1335,275 @@ impl App {
             },
             arguments,
             equations,
+            submodels,
+            reactions,
+            delays,
+            functions: self
+                .model_functions
+                .iter()
+                .map(|model_function| model_function.function.clone())
+                .collect(),
         }
     }
 
-    pub fn generate_code(&self) -> String {
-        let model: odeir::Model = self.create_json().into();
+    pub fn model_dir(&self) -> &Path {
+        &self.model_dir
+    }
 
-        let odeir::Model::ODE(ode_model) = model else {
-            unreachable!("This program can only produce ODE models for now");
-        };
+    /// The model with its submodels inlined, as the code generators and the
+    /// simulator expect it. Cellular automata have no submodels, nor code
+    /// generators, so only ODE models can be flattened.
+    fn flat_model(&self) -> Result<Flattened, NotCorrectModel> {
+        match odeir::Model::from(self.create_json()) {
+            odeir::Model::ODE(ode_model) => Ok(self.flatten(&ode_model)),
+            odeir::Model::CellularAutomata(_) => Err(NotCorrectModel::NotODE),
+        }
+    }
 
-        let extension_lookup_paths: Vec<_> =
-            self.extensions.iter().map(|ext| &ext.file_path).collect();
+    /// Inlines the submodels of `model`, which were loaded by their nodes.
+    /// Those that couldn't be are reported in the diagnostics.
+    fn flatten(&self, model: &OdeModel) -> Flattened {
+        model.flatten_with(|submodel| {
+            self.nodes
+                .values()
+                .find_map(|node| match node {
+                    Node::Submodel(node) if node.name == submodel.name => Some(node.model.clone()),
+                    _ => None,
+                })
+                .unwrap_or_else(|| Err(format!("`{}` is not in the editor", submodel.name)))
+        })
+    }
 
-        odeir::transformations::r4k::render_ode(&ode_model, &extension_lookup_paths)
+    /// Where to find the extensions used by `model`, including the ones
+    /// brought in by its submodels.
+    fn extension_lookup_paths(&self, model: &OdeModel) -> Vec<PathBuf> {
+        self.extensions
+            .iter()
+            .map(|ext| ext.file_path.clone())
+            .chain(
+                model
+                    .extension_files
+                    .iter()
+                    .map(|file| self.model_dir.join(file)),
+            )
+            .collect()
+    }
+
+    pub fn generate_code(&self) -> Result<String, ExportError> {
+        let ode_model = self.flat_model()?.model;
+
+        let extension_lookup_paths = self.extension_lookup_paths(&ode_model);
+        let extension_lookup_paths: Vec<_> = extension_lookup_paths.iter().collect();
+
+        Ok(odeir::transformations::r4k::render_ode(
+            &ode_model,
+            &extension_lookup_paths,
+        ))
+    }
+
+    pub fn generate_r_code(&self) -> Result<String, ExportError> {
+        let ode_model = self.flat_model()?.model;
+
+        Ok(odeir::transformations::r::render_r(&ode_model))
+    }
+
+    pub fn generate_sbml(&self) -> Result<String, ExportError> {
+        let ode_model = self.flat_model()?.model;
+
+        Ok(odeir::transformations::sbml::render_sbml(&ode_model)?)
+    }
+
+    pub fn validate_model(&self, target: Target) -> Vec<Diagnostic> {
+        match odeir::Model::from(self.create_json()) {
+            odeir::Model::ODE(ode_model) => self.flatten(&ode_model).validate(target),
+            odeir::Model::CellularAutomata(ca_model) => ca_model.validate(target),
+        }
+    }
+
+    /// What "Run" needs the model to be valid for: the generated Python code,
+    /// or the simulators of the editor, which cellular automata always use.
+    pub fn run_target(&self) -> Target {
+        match (self.sidebar_state.kind(), self.simulation_backend) {
+            (ModelKind::Ode, SimulationBackend::Python) => Target::Python,
+            _ => Target::Native,
+        }
+    }
+
+    /// Validates the model before generating code for `target`. If there are
+    /// errors, they are listed in the diagnostics window and `false` is
+    /// returned.
+    pub fn check_model(&mut self, target: Target, locale: &Locale) -> bool {
+        let diagnostics = self.validate_model(target);
+
+        if diagnostics.iter().any(Diagnostic::is_error) {
+            localized_error!(locale, "error-invalid-model");
+            self.state = Some(AppState::ReviewingDiagnostics { diagnostics });
+            false
+        } else {
+            true
+        }
+    }
+
+    /// Moves the editor to the node named `name`, if there is one.
+    pub fn focus_node(&mut self, name: &str) {
+        self.focused_node = self
+            .nodes
+            .iter()
+            .find(|(_, node)| node.name() == name)
+            .map(|(id, _)| *id);
+    }
+
+    /// The [`Format`] of the extension node implementing `function`, used to
+    /// typeset prefixed composites the same way the editor displays them.
+    fn custom_node_format(&self, function: &str) -> Option<&Format> {
+        self.custom_node_spec(function).map(|spec| &spec.format)
+    }
+
+    pub fn generate_latex(&self) -> Result<String, ExportError> {
+        let ode_model = self.flat_model()?.model;
+
+        Ok(odeir::transformations::typeset::render_latex(
+            &ode_model,
+            |function, args| {
+                self.custom_node_format(function)
+                    .map(|format| format.format_args(args.to_vec()))
+            },
+        ))
+    }
+
+    pub fn generate_mathml(&self) -> Result<String, ExportError> {
+        let ode_model = self.flat_model()?.model;
+
+        let mathml =
+            odeir::transformations::typeset::render_mathml(&ode_model, |function, args| {
+                self.custom_node_format(function).map(|format| {
+                    format.format_args_with(args.to_vec(), |part| {
+                        let escaped = part
+                            .replace('&', "&amp;")
+                            .replace('<', "&lt;")
+                            .replace('>', "&gt;");
+                        format!("<mo>{escaped}</mo>")
+                    })
+                })
+            });
+        Ok(mathml)
+    }
+
+    /// Whether custom nodes backed by Python extensions, rather than by the
+    /// functions of the model, are used by the model or its submodels.
+    fn uses_extensions(&self) -> bool {
+        self.nodes.values().any(|node| match node {
+            Node::Custom(node) => !self.is_model_function(node.function_name()),
+            Node::Submodel(submodel) => submodel
+                .model
+                .as_ref()
+                .is_ok_and(|flattened| !flattened.model.extension_files.is_empty()),
+            _ => false,
+        })
+    }
+
+    /// Simulates the current model with the selected [`Solver`], without
+    /// depending on Python. Custom nodes are backed by Python extensions, so
+    /// models using them can't be simulated this way.
+    pub fn simulate_natively(&self) -> Result<CSVData, SimulationError> {
+        if self.uses_extensions() {
+            return Err(SimulationError::UsesExtensions);
+        }
+        if self.sidebar_state.kind() != ModelKind::Ode {
+            return Err(NotCorrectModel::NotODE.into());
+        }
+
+        let ode_model = self.flat_model()?.model;
+
+        let ode_system = ode_system_from_model(&ode_model, &[])?;
+        let initial_state = ode_system.initial_state();
+        let labels: Vec<String> = ode_system.population_names().map(str::to_owned).collect();
+
+        let (time, states) = integrate(
+            ode_system,
+            self.solver,
+            &initial_state,
+            ode_model.metadata.start_time,
+            ode_model.metadata.end_time,
+            ode_model.metadata.delta_time,
+        )?;
+
+        let lines = (0..labels.len())
+            .map(|idx| states.iter().map(|state| state[idx]).collect())
+            .collect();
+
+        Ok(CSVData {
+            labels,
+            lines,
+            time,
+        })
+    }
+
+    /// Simulates many runs of the current model with the stochastic settings,
+    /// reading its terms as reactions. Like [`App::simulate_natively`], it
+    /// can't simulate models using extensions.
+    pub fn simulate_stochastically(&self) -> Result<Ensemble, SimulationError> {
+        if self.uses_extensions() {
+            return Err(SimulationError::UsesExtensions);
+        }
+        if self.sidebar_state.kind() != ModelKind::Ode {
+            return Err(NotCorrectModel::NotODE.into());
+        }
+
+        let ode_model = self.flat_model()?.model;
+
+        Ok(stochastic::simulate(
+            &ode_model,
+            &self.stochastic,
+            ode_model.metadata.start_time,
+            ode_model.metadata.end_time,
+            ode_model.metadata.delta_time,
+        )?)
+    }
+
+    /// Simulates the current model as a cellular automaton, on the grid set
+    /// up in the side bar.
+    pub fn simulate_automaton(&self) -> Result<Simulation, SimulationError> {
+        let model = odeir::Model::from(self.create_json());
+        let odeir::Model::CellularAutomata(ca_model) = model else {
+            return Err(NotCorrectModel::NotCellularAutomata.into());
+        };
+
+        Ok(ca_model.simulate()?)
     }
 
     pub fn generate_equations(&mut self, all_constants: Vec<Term>) {
         if self.is_model_valid() {
-            let model: odeir::Model = self.create_json().into();
-            let Some(param_state) = &mut self.parameter_estimation_state else {
-                return;
+            let ode_model = match self.flat_model() {
+                Ok(flattened) => flattened.model,
+                Err(err) => {
+                    log::error!("Could not build the system of equations: {err}");
+                    return;
+                }
             };
+            let extension_lookup_paths = self.extension_lookup_paths(&ode_model);
+            let extension_lookup_paths: Vec<_> = extension_lookup_paths.iter().collect();
 
-            let odeir::Model::ODE(ode_model) = model else {
-                unreachable!("This program can only produce ODE models for now");
+            let Some(param_state) = &mut self.parameter_estimation_state else {
+                return;
             };
-            let extension_lookup_paths: Vec<_> =
-                self.extensions.iter().map(|ext| &ext.file_path).collect();
 
-            param_state.ode_system = create_ode_system(
+            match create_ode_system(
                 odeir::transformations::ode::render_txt_with_equations(
                     &ode_model,
                     &extension_lookup_paths,
                 ),
-                all_constants,
-            );
+                all_constants
+                    .into_iter()
+                    .map(|term| (term.leaf.symbol, term.initial_value)),
+            )
+            .and_then(|mut ode_system| {
+                ode_system.define_functions(&ode_model)?;
+                Ok(ode_system)
+            }) {
+                Ok(ode_system) => param_state.ode_system = ode_system,
+                Err(err) => log::error!("Could not build the system of equations: {err}"),
+            }
         }
         //else Error
     }
@@ -1204,35 +1620,57 @@ impl App {
             .add_filter("json", &["json"])
             .save_file()?;
 
-        let file = File::create(file_path).ok()?;
+        let file = File::create(&file_path).ok()?;
 
-        let json = self.create_json();
+        let mut json = self.create_json();
+
+        // New models are named after the file they're first saved to
+        if json.metadata.name.is_empty() {
+            if let Some(stem) = file_path.file_stem() {
+                json.metadata.name = stem.to_string_lossy().into_owned();
+            }
+        }
 
         serde_json::to_writer_pretty(file, &json).ok()
     }
 
-    fn try_read_model(&mut self, model: OdeModel, path: PathBuf) -> color_eyre::Result<()> {
+    fn try_read_model(&mut self, model: odeir::Model, path: PathBuf) -> color_eyre::Result<()> {
+        let (name, core, extension_files) = match model {
+            odeir::Model::ODE(model) => {
+                self.sidebar_state.set_metadata(model.metadata);
+                (model.name, model.core, model.extension_files)
+            }
+            odeir::Model::CellularAutomata(model) => {
+                self.sidebar_state.set_automaton_metadata(model.metadata);
+                (model.name, model.core, model.extension_files)
+            }
+        };
         let odeir::CoreModel {
             equations,
             arguments,
             positions,
-        } = model.core;
+            submodels,
+            reactions,
+            delays,
+            functions,
+        } = core;
 
-        self.sidebar_state.set_metadata(model.metadata);
+        self.model_name = name;
+        self.model_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
 
-        model.extension_files.into_iter().try_for_each(|file| {
-            self.load_extension_from_path(
-                path.parent()
-                    .map(Path::to_path_buf)
-                    .unwrap_or_default()
-                    .join(file),
-            )
-        })?;
+        let model_dir = self.model_dir.clone();
+        extension_files
+            .into_iter()
+            .try_for_each(|file| self.load_extension_from_path(model_dir.join(file)))?;
+        self.load_model_functions(functions);
 
         let nodes_and_ops: Vec<(Node, Option<PendingOperations>)> = arguments
             .into_values()
             .map(Into::<ModelFragment>::into)
             .chain(equations.into_iter().map(Into::<ModelFragment>::into))
+            .chain(submodels.into_iter().map(Into::<ModelFragment>::into))
+            .chain(reactions.into_iter().map(Into::<ModelFragment>::into))
+            .chain(delays.into_iter().map(Into::<ModelFragment>::into))
             .map(|frag| Node::build_from_fragment(frag, self))
             .collect::<Result<_, _>>()?;
 
@@ -1245,9 +1683,15 @@ impl App {
             .collect();
 
         let mut node_name_map = HashMap::new();
+        // Links may refer to one of the outputs of a node, such as the port
+        // of a submodel
+        let mut output_name_map = HashMap::new();
 
         self.nodes.iter().for_each(|(_node_id, node)| {
             node_name_map.insert(node.name(), node);
+            for output in node.outputs().unwrap_or_default() {
+                output_name_map.insert(node.output_name(output.id()), node);
+            }
         });
 
         for PendingOperations {
@@ -1273,14 +1717,13 @@ impl App {
                         };
 
                         let output_pin_id = {
-                            let node = node_name_map
-                                .get(&node_name as &str)
+                            let node = output_name_map
+                                .get(&node_name)
+                                .or_else(|| node_name_map.get(&node_name as &str))
                                 .ok_or_else(|| node_error(InvalidNodeReason::NodeDoesNotExist))?;
 
                             let output_node = node
-                                .outputs()
-                                .ok_or_else(|| node_error(InvalidNodeReason::NoOutputPin))?
-                                .first()
+                                .output_named(&node_name)
                                 .ok_or_else(|| node_error(InvalidNodeReason::NoOutputPin))?;
 
                             *output_node.id()
@@ -1342,13 +1785,64 @@ impl App {
 
         let reader = BufReader::new(file);
 
-        let odeir::Model::ODE(model) = serde_json::from_reader(reader)? else {
-            Err(NotCorrectModel::NotODE)?
-        };
+        let model = serde_json::from_reader(reader)?;
         self.clear_state();
         self.try_read_model(model, file_path)
     }
 
+    /// Opens an SBML file as a new model. Whatever could not be imported is
+    /// returned, so it can be reported to the user.
+    pub fn import_sbml(&mut self) -> color_eyre::Result<Vec<odeir::import::sbml::Diagnostic>> {
+        let file_path = FileDialog::new()
+            .add_filter("sbml", &["xml", "sbml"])
+            .pick_file()
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "Could not open file")
+            })?;
+
+        let content = std::fs::read_to_string(&file_path)?;
+        let odeir::import::sbml::SbmlImport { model, diagnostics } =
+            odeir::import::sbml::import_sbml(&content)?;
+
+        self.clear_state();
+        self.try_read_model(odeir::Model::ODE(model), file_path)?;
+
+        Ok(diagnostics)
+    }
+
+    /// Embeds another model file as a submodel, named after the file. Its
+    /// path is stored relative to the model's directory when possible.
+    pub fn import_submodel(&mut self) -> color_eyre::Result<()> {
+        let file_path = FileDialog::new()
+            .add_filter("json", &["json"])
+            .pick_file()
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "Could not open file")
+            })?;
+
+        let name = file_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().replace(|c: char| !c.is_alphanumeric(), "_"))
+            .unwrap_or_else(|| String::from("submodel"));
+        let path = file_path
+            .strip_prefix(&self.model_dir)
+            .unwrap_or(&file_path)
+            .to_string_lossy()
+            .into_owned();
+
+        let submodel = odeir::models::Submodel {
+            name,
+            path,
+            ports: Vec::new(),
+        };
+        let model = submodel.load(&self.model_dir)?;
+
+        self.add_node(
+            SubmodelNode::new(NodeId::generate(), submodel.name, submodel.path, Ok(model)).into(),
+        );
+        Ok(())
+    }
+
     pub fn clear_state(&mut self) {
         self.nodes.clear();
 
@@ -1368,8 +1862,12 @@ impl App {
         self.queue = Default::default();
         self.received_messages.clear();
         self.simulation_state = None;
+        self.automaton_state = None;
         self.sidebar_state.clear_state();
         self.parameter_estimation_state.take();
+        self.model_name.clear();
+        self.model_dir = PathBuf::new();
+        self.unload_model_functions();
     }
 
     pub fn update_locale(&mut self, locale: &mut Locale, lang: LanguageIdentifier) {
@@ -1612,7 +2110,8 @@ mod tests {
 
         init_id_gen();
 
-        let app = app_with_nodes_abk();
+        let mut app = app_with_nodes_abk();
+        app.model_name = "ABK".to_string();
 
         // When - The user requests a JSON to be created, for saving purposes
 
@@ -1652,7 +2151,7 @@ mod tests {
             panic!("Unable to extract ODE Model from ABK JSON");
         };
 
-        let result = app.try_read_model(ode_model, PathBuf::default());
+        let result = app.try_read_model(odeir::Model::ODE(ode_model), PathBuf::default());
 
         let expected_positions: HashMap<_, _> = [
             ("A", [-355.0, 469.0]),
@@ -1685,6 +2184,7 @@ mod tests {
         });
 
         assert_eq!(actual_positions, expected_positions);
+        assert_eq!(app.model_name, "ABK");
 
         app.update(); // Runs possible pending operations!
 
@@ -0,0 +1,148 @@
+use imgui::{TabItem, TabItemFlags, Ui};
+use implot::ImVec4;
+use odeir::models::cellular_automata::{Grid, Simulation};
+
+use crate::locale::Locale;
+
+use super::app::{COLORS, TabAction};
+
+/// How many generations are shown per second while playing.
+const GENERATIONS_PER_SECOND: f32 = 10.0;
+
+/// The result of simulating a cellular automaton, shown as an animated grid.
+pub struct AutomatonState {
+    simulation: Simulation,
+    generation: usize,
+    playing: bool,
+    /// Time since the last generation was shown, in seconds
+    elapsed: f32,
+    pub set_focus_to_tab: bool,
+}
+
+fn state_color(state: usize) -> [f32; 4] {
+    let ImVec4 { x, y, z, w } = COLORS[state % COLORS.len()];
+    [x, y, z, w]
+}
+
+impl AutomatonState {
+    pub fn new(simulation: Simulation) -> Self {
+        Self {
+            simulation,
+            generation: 0,
+            playing: false,
+            elapsed: 0.0,
+            set_focus_to_tab: true,
+        }
+    }
+
+    fn last_generation(&self) -> usize {
+        self.simulation.generations.len().saturating_sub(1)
+    }
+
+    fn advance(&mut self, delta_time: f32) {
+        if !self.playing {
+            return;
+        }
+
+        self.elapsed += delta_time;
+        let generations = (self.elapsed * GENERATIONS_PER_SECOND) as usize;
+        self.elapsed -= generations as f32 / GENERATIONS_PER_SECOND;
+
+        self.generation = (self.generation + generations).min(self.last_generation());
+        if self.generation == self.last_generation() {
+            self.playing = false;
+        }
+    }
+
+    pub fn draw_tab(&mut self, ui: &Ui, locale: &Locale) -> TabAction {
+        let mut opened = true;
+
+        let mut flags = TabItemFlags::empty();
+        if self.set_focus_to_tab {
+            flags.set(TabItemFlags::SET_SELECTED, true);
+            self.set_focus_to_tab = false;
+        }
+
+        TabItem::new(locale.get("tab-automaton"))
+            .opened(&mut opened)
+            .flags(flags)
+            .build(ui, || {
+                self.advance(ui.io().delta_time);
+
+                let label = if self.playing {
+                    locale.get("automaton-pause")
+                } else {
+                    locale.get("automaton-play")
+                };
+                if ui.button(label) {
+                    // Playing from the end starts over
+                    if self.generation == self.last_generation() {
+                        self.generation = 0;
+                    }
+                    self.playing = !self.playing;
+                    self.elapsed = 0.0;
+                }
+                ui.same_line();
+                let last_generation = self.last_generation();
+                ui.slider(
+                    locale.get("automaton-generation"),
+                    0,
+                    last_generation,
+                    &mut self.generation,
+                );
+
+                let Some(grid) = self.simulation.generations.get(self.generation) else {
+                    return;
+                };
+                self.draw_legend(ui, grid);
+                draw_grid(ui, grid);
+            });
+
+        if opened {
+            TabAction::Open
+        } else {
+            TabAction::Close
+        }
+    }
+
+    fn draw_legend(&self, ui: &Ui, grid: &Grid) {
+        let counts = grid.count(self.simulation.states.len());
+        for (idx, (state, count)) in self.simulation.states.iter().zip(counts).enumerate() {
+            if idx > 0 {
+                ui.same_line();
+            }
+            ui.color_button(format!("##{state}"), state_color(idx));
+            ui.same_line();
+            ui.text(format!("{state}: {count}"));
+        }
+    }
+}
+
+/// Draws one square per cell, as large as the available region allows.
+fn draw_grid(ui: &Ui, grid: &Grid) {
+    let [available_width, available_height] = ui.content_region_avail();
+    let cell_size = (available_width / grid.width as f32)
+        .min(available_height / grid.height as f32)
+        .max(1.0);
+
+    let [left, top] = ui.cursor_screen_pos();
+    let draw_list = ui.get_window_draw_list();
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            let corner = [left + x as f32 * cell_size, top + y as f32 * cell_size];
+            draw_list
+                .add_rect(
+                    corner,
+                    [corner[0] + cell_size, corner[1] + cell_size],
+                    state_color(grid.get(x, y)),
+                )
+                .filled(true)
+                .build();
+        }
+    }
+
+    ui.dummy([
+        grid.width as f32 * cell_size,
+        grid.height as f32 * cell_size,
+    ]);
+}
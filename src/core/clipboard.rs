@@ -0,0 +1,21 @@
+use copypasta::{ClipboardContext, ClipboardProvider};
+use imgui::ClipboardBackend;
+
+/// Connects imgui to the system clipboard, which is otherwise only shared
+/// within the application.
+pub struct ClipboardSupport(pub ClipboardContext);
+
+pub fn init() -> Option<ClipboardSupport> {
+    ClipboardContext::new().ok().map(ClipboardSupport)
+}
+
+impl ClipboardBackend for ClipboardSupport {
+    fn get(&mut self) -> Option<String> {
+        self.0.get_contents().ok()
+    }
+
+    fn set(&mut self, text: &str) {
+        // Failing to copy is not worth interrupting the user over
+        let _ = self.0.set_contents(text.to_owned());
+    }
+}
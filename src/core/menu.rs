@@ -1,8 +1,12 @@
 use imgui::{Ui, StyleVar};
+use odeir::validation::Target;
+use strum::VariantArray;
 
 use crate::{
+    errors::ExportError,
     locale::{Locale, LANGUAGES},
-    utils::localized_error,
+    ode::{odesystem::Solver, stochastic::StochasticMethod},
+    utils::{fluent_map, localized_error},
     App,
 };
 use rfd::FileDialog;
@@ -11,11 +15,38 @@ use std::{fs::read_to_string, process::Command};
 
 use super::{
     adjust_params::ParameterEstimationState,
-    app::{AppState, SimulationState},
+    app::{AppState, SimulationBackend, SimulationState},
+    automaton::AutomatonState,
+    plot::CSVData,
     python::execute_python_code,
+    side_bar::ModelKind,
+    widgets::input_num,
 };
 
 impl App {
+    /// Saves what a code generator produced, or reports why it couldn't.
+    fn save_export(
+        &self,
+        generated: Result<String, ExportError>,
+        ext: &str,
+        error_key: &'static str,
+        locale: &Locale,
+    ) {
+        match generated {
+            Ok(content) => {
+                self.save_to_file(content, ext);
+            }
+            Err(err) => localized_error!(locale, error_key, "reason" => err.to_string()),
+        }
+    }
+
+    fn copy_export(&self, ui: &Ui, generated: Result<String, ExportError>, locale: &Locale) {
+        match generated {
+            Ok(content) => ui.set_clipboard_text(content),
+            Err(err) => localized_error!(locale, "error-export", "reason" => err.to_string()),
+        }
+    }
+
     fn draw_menu_load_csv(&mut self, ui: &Ui, locale: &Locale) {
         if ui.menu_item(locale.get("file-plot")) {
             let file = FileDialog::new()
@@ -33,6 +64,93 @@ impl App {
         }
     }
 
+    fn draw_menu_simulation_settings(&mut self, ui: &Ui, locale: &Locale) {
+        ui.text(locale.get("simulation-backend"));
+        ui.radio_button(
+            locale.get("backend-native"),
+            &mut self.simulation_backend,
+            SimulationBackend::Native,
+        );
+        ui.radio_button(
+            locale.get("backend-python"),
+            &mut self.simulation_backend,
+            SimulationBackend::Python,
+        );
+        ui.radio_button(
+            locale.get("backend-stochastic"),
+            &mut self.simulation_backend,
+            SimulationBackend::Stochastic,
+        );
+
+        match self.simulation_backend {
+            SimulationBackend::Native => {
+                ui.text(locale.get("solver"));
+                for &solver in Solver::VARIANTS {
+                    ui.radio_button(solver.to_string(), &mut self.solver, solver);
+                }
+            }
+            SimulationBackend::Python => {}
+            SimulationBackend::Stochastic => self.draw_menu_stochastic_settings(ui, locale),
+        }
+    }
+
+    fn draw_menu_stochastic_settings(&mut self, ui: &Ui, locale: &Locale) {
+        ui.text(locale.get("stochastic-method"));
+        ui.radio_button(
+            locale.get("stochastic-ssa"),
+            &mut self.stochastic.method,
+            StochasticMethod::Ssa,
+        );
+        ui.radio_button(
+            locale.get("stochastic-tau-leaping"),
+            &mut self.stochastic.method,
+            StochasticMethod::TauLeaping,
+        );
+
+        if self.stochastic.method == StochasticMethod::TauLeaping {
+            input_num(ui, locale.get("stochastic-tau"), &mut self.stochastic.tau);
+        }
+
+        let _width = ui.push_item_width(72.0);
+        ui.input_scalar(locale.get("stochastic-runs"), &mut self.stochastic.runs)
+            .build();
+        ui.input_scalar(locale.get("stochastic-seed"), &mut self.stochastic.seed)
+            .build();
+    }
+
+    /// Simulates the cellular automaton, showing its grid and plotting how
+    /// many cells are in each state over the generations.
+    fn run_automaton(&mut self, locale: &Locale) {
+        match self.simulate_automaton() {
+            Ok(simulation) => {
+                let data = CSVData {
+                    labels: simulation.states.clone(),
+                    lines: simulation
+                        .counts()
+                        .into_iter()
+                        .map(|counts| counts.into_iter().map(|count| count as f64).collect())
+                        .collect(),
+                    time: (0..simulation.generations.len())
+                        .map(|generation| generation as f64)
+                        .collect(),
+                };
+
+                let mut simulation_state = SimulationState::from_data(data, locale);
+                simulation_state.plot.xlabel = locale.get("automaton-generation").to_owned();
+                simulation_state.plot.bg_color = self.dark_theme;
+                // The grid is more interesting, so it gets the focus
+                simulation_state.set_focus_to_tab = false;
+
+                self.simulation_state = Some(simulation_state);
+                self.automaton_state = Some(AutomatonState::new(simulation));
+            }
+            Err(err) => {
+                localized_error!(locale, "error-automaton-sim", "reason" => err.to_string());
+                eprintln!("{err}")
+            }
+        }
+    }
+
     pub fn draw_input_label(&mut self, ui: &Ui) {
         ui.input_text("X Label", &mut self.text_fields.x_label)
             .hint("time (days)")
@@ -43,6 +161,10 @@ impl App {
     }
 
     pub fn draw_menu(&mut self, ui: &Ui, locale: &mut Locale) {
+        // Code generation, exports and parameter estimation only make sense
+        // for ODE models
+        let is_ode = self.sidebar_state.kind() == ModelKind::Ode;
+
         ui.menu_bar(|| {
             ui.menu(locale.get("file"), || {
                 if ui
@@ -69,21 +191,84 @@ impl App {
                     self.save_state();
                 }
 
+                if ui.menu_item(locale.get("file-import-sbml")) {
+                    match self.import_sbml() {
+                        Ok(diagnostics) => diagnostics.iter().for_each(|diagnostic| {
+                            log::warn!(
+                                "{}",
+                                &*locale.fmt(
+                                    "warning-sbml-import",
+                                    &fluent_map! {
+                                        "element" => diagnostic.element.clone(),
+                                        "reason" => diagnostic.message.clone(),
+                                    }
+                                )
+                            )
+                        }),
+                        Err(err) => {
+                            localized_error!(locale, "error-sbml-import", "reason" => err.to_string())
+                        }
+                    }
+                }
+
+                if ui.menu_item(locale.get("file-import-submodel")) {
+                    if let Err(err) = self.import_submodel() {
+                        localized_error!(locale, "error-submodel-import", "reason" => err.to_string())
+                    }
+                }
+
                 self.draw_menu_load_csv(ui, locale);
             });
 
-            ui.menu(locale.get("export"), || {
+            ui.menu_with_enabled(locale.get("export"), is_ode, || {
                 self.draw_input_label(ui);
-                if ui.menu_item(locale.get("export-code")) {
-                    let py_code = self.generate_code();
-                    self.save_to_file(py_code, "py");
+                if ui.menu_item(locale.get("export-code"))
+                    && self.check_model(Target::Python, locale)
+                {
+                    self.save_export(self.generate_code(), "py", "error-export", locale);
+                }
+
+                if ui.menu_item(locale.get("export-r-code"))
+                    && self.check_model(Target::R, locale)
+                {
+                    self.save_export(self.generate_r_code(), "R", "error-export", locale);
                 }
 
-                if ui.menu_item(locale.get("export-pdf")) {
+                if ui.menu_item(locale.get("export-sbml")) {
+                    self.save_export(self.generate_sbml(), "xml", "error-sbml-export", locale);
+                }
+
+                ui.menu(locale.get("export-latex"), || {
+                    if ui.menu_item(locale.get("export-save-file")) {
+                        self.save_export(self.generate_latex(), "tex", "error-export", locale);
+                    }
+                    if ui.menu_item(locale.get("export-copy")) {
+                        self.copy_export(ui, self.generate_latex(), locale);
+                    }
+                });
+
+                ui.menu(locale.get("export-mathml"), || {
+                    if ui.menu_item(locale.get("export-save-file")) {
+                        self.save_export(self.generate_mathml(), "html", "error-export", locale);
+                    }
+                    if ui.menu_item(locale.get("export-copy")) {
+                        self.copy_export(ui, self.generate_mathml(), locale);
+                    }
+                });
+
+                if ui.menu_item(locale.get("export-pdf"))
+                    && self.check_model(Target::Python, locale)
+                {
                     if let Some(file_path) =
                         FileDialog::new().add_filter("pdf", &["pdf"]).save_file()
                     {
-                        let py_code = self.generate_code();
+                        let py_code = match self.generate_code() {
+                            Ok(py_code) => py_code,
+                            Err(err) => {
+                                localized_error!(locale, "error-export", "reason" => err.to_string());
+                                return;
+                            }
+                        };
 
                         let mut command = Command::new("python3");
                         command
@@ -139,46 +324,81 @@ impl App {
 
                 ui.separator();
 
-                if ui.menu_item(locale.get("run")) {
-                    let py_code = self.generate_code();
-
-                    let mut command = Command::new("python3");
-                    command
-                        .arg("-c")
-                        .arg(&py_code)
-                        .arg("--csv")
-                        .args(self.sidebar_state.time_flags());
-
-                    match execute_python_code(&mut command) {
-                        Ok(output) => {
-                            
-                            self.simulation_state = Some(SimulationState::from_csv(output, locale));
-                            if let Some(mut simulation_state) = self.simulation_state.clone() {
-                                if !self.text_fields.x_label.is_empty() {
-                                    simulation_state.plot.xlabel =
-                                        self.text_fields.x_label.to_string();
-                                }
-                                if !self.text_fields.y_label.is_empty() {
-                                    simulation_state.plot.ylabel =
-                                        self.text_fields.y_label.to_string();
-                                }
-
-                                simulation_state.plot.bg_color = self.dark_theme;
-                                //if self.dark_theme {
-                                    
-                                //}
-                                self.simulation_state = Some(simulation_state);
+                if is_ode {
+                    self.draw_menu_simulation_settings(ui, locale);
+
+                    ui.separator();
+                }
+
+                if ui.menu_item(locale.get("validate-model")) {
+                    let diagnostics = self.validate_model(self.run_target());
+                    self.state = Some(AppState::ReviewingDiagnostics { diagnostics });
+                }
+
+                if ui.menu_item(locale.get("run"))
+                    && self.check_model(self.run_target(), locale)
+                {
+                    if !is_ode {
+                        self.run_automaton(locale);
+                        return;
+                    }
+
+                    let (simulation, error_key) = match self.simulation_backend {
+                        SimulationBackend::Native => (
+                            self.simulate_natively()
+                                .map(|data| SimulationState::from_data(data, locale))
+                                .map_err(|err| err.to_string()),
+                            "error-native-sim",
+                        ),
+                        SimulationBackend::Stochastic => (
+                            self.simulate_stochastically()
+                                .map(|ensemble| SimulationState::from_ensemble(ensemble, locale))
+                                .map_err(|err| err.to_string()),
+                            "error-stochastic-sim",
+                        ),
+                        SimulationBackend::Python => (
+                            self.generate_code()
+                                .map_err(|err| err.to_string())
+                                .and_then(|py_code| {
+                                    let mut command = Command::new("python3");
+                                    command
+                                        .arg("-c")
+                                        .arg(&py_code)
+                                        .arg("--csv")
+                                        .args(self.sidebar_state.time_flags());
+
+                                    execute_python_code(&mut command)
+                                        .map(|output| SimulationState::from_csv(output, locale))
+                                        .map_err(|err| err.to_string())
+                                }),
+                            "error-python-exec",
+                        ),
+                    };
+
+                    match simulation {
+                        Ok(mut simulation_state) => {
+                            if !self.text_fields.x_label.is_empty() {
+                                simulation_state.plot.xlabel = self.text_fields.x_label.to_string();
+                            }
+                            if !self.text_fields.y_label.is_empty() {
+                                simulation_state.plot.ylabel = self.text_fields.y_label.to_string();
                             }
+
+                            simulation_state.plot.bg_color = self.dark_theme;
+                            self.simulation_state = Some(simulation_state);
                         }
                         Err(err) => {
-                            localized_error!(locale, "error-python-exec", "reason" => err.to_string());
+                            localized_error!(locale, error_key, "reason" => err.clone());
                             eprintln!("{err}")
                         }
                     }
                 }
             });
 
-            if ui.menu_item(locale.get("parameter-estimation"))
+            if ui
+                .menu_item_config(locale.get("parameter-estimation"))
+                .enabled(is_ode)
+                .build()
                 && self.parameter_estimation_state.is_none()
             {
                 let all_population_ids = self.get_all_population_ids();
@@ -14,6 +14,8 @@ pub use id_gen::{initialize_id_generator, GeneratesId};
 
 pub mod adjust_params;
 pub mod app;
+pub mod automaton;
+mod clipboard;
 mod id_gen;
 pub mod menu;
 pub mod notification;
@@ -92,6 +94,12 @@ impl System {
         imgui.io_mut().config_flags |= imgui::ConfigFlags::DOCKING_ENABLE;
         imgui.set_ini_filename(None);
 
+        if let Some(backend) = clipboard::init() {
+            imgui.set_clipboard_backend(backend);
+        } else {
+            eprintln!("Failed to initialize the system clipboard");
+        }
+
         let mut platform = WinitPlatform::new(&mut imgui);
         platform.attach_window(imgui.io_mut(), &window, HiDpiMode::Default);
 
@@ -1,6 +1,14 @@
 use std::io::Read;
 
-use crate::locale::Locale;
+use implot::ImVec4;
+
+use crate::{
+    locale::Locale,
+    ode::stochastic::{BAND_PERCENTILES, Ensemble},
+};
+
+/// How many runs of a stochastic simulation are drawn behind their mean.
+const RUNS_SHOWN: usize = 20;
 
 #[derive(Default, Debug, Clone)]
 pub struct CSVData {
@@ -18,6 +26,17 @@ pub struct PlotInfo {
     pub bg_color: bool,
 }
 
+/// The spread of the runs of a stochastic simulation, whose mean is the
+/// plotted data.
+#[derive(Default, Clone)]
+pub struct EnsemblePlot {
+    /// Lower and upper bounds of the band, one line per population
+    lower: Vec<Vec<f64>>,
+    upper: Vec<Vec<f64>>,
+    /// The first few runs, one line per population
+    runs: Vec<Vec<Vec<f64>>>,
+}
+
 #[derive(Default, Clone)]
 pub struct PlotLayout {
     pub rows: u32,
@@ -95,3 +114,38 @@ impl PlotInfo {
         self.ylabel = locale.get("default-y-label").to_owned();
     }
 }
+
+impl EnsemblePlot {
+    pub fn new(ensemble: &Ensemble) -> Self {
+        let (lower, upper) = BAND_PERCENTILES;
+
+        Self {
+            lower: ensemble.percentile(lower),
+            upper: ensemble.percentile(upper),
+            runs: ensemble.runs.iter().take(RUNS_SHOWN).cloned().collect(),
+        }
+    }
+
+    /// Plots the runs and the band of `population`, in fainter shades of
+    /// `color` than its mean.
+    pub fn plot(&self, population: usize, label: &str, time: &[f64], color: ImVec4) {
+        let ImVec4 { x, y, z, .. } = color;
+        let line_weight = implot::push_style_var_f32(&implot::StyleVar::LineWeight, 1.0);
+
+        let color_token = implot::push_style_color(&implot::PlotColorElement::Line, x, y, z, 0.15);
+        for (idx, run) in self.runs.iter().enumerate() {
+            // Runs are left out of the legend
+            implot::PlotLine::new(&format!("##{label} run {idx}")).plot(time, &run[population]);
+        }
+        color_token.pop();
+
+        let (lower, upper) = BAND_PERCENTILES;
+        let band_label = format!("{label} ({lower}-{upper}%)");
+        let color_token = implot::push_style_color(&implot::PlotColorElement::Line, x, y, z, 0.5);
+        implot::PlotLine::new(&band_label).plot(time, &self.lower[population]);
+        implot::PlotLine::new(&band_label).plot(time, &self.upper[population]);
+        color_token.pop();
+
+        line_weight.pop();
+    }
+}
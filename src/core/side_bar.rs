@@ -5,12 +5,26 @@ use crate::{
     nodes::{Node, NodeTypeRepresentation},
 };
 
-use odeir::models::ode;
+use odeir::models::{
+    cellular_automata::{self, Neighbourhood},
+    ode,
+};
+
+/// The kind of model being edited, which decides how it is simulated.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ModelKind {
+    #[default]
+    Ode,
+    CellularAutomata,
+}
 
 #[derive(Debug, Default, Clone)]
 pub struct SideBarState {
     node_name: String,
+    kind: ModelKind,
     sim_times: Times,
+    time_unit: String,
+    automaton: cellular_automata::Metadata,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -56,7 +70,7 @@ impl SideBarState {
         }
 
         const MAGIC_BUTTON_HEIGHT: f32 = 31.0;
-        const MAGIC_SPACING: f32 = 300.0;
+        const MAGIC_SPACING: f32 = 400.0;
 
         let [_, height] = ui.window_size();
         ui.dummy([
@@ -66,15 +80,31 @@ impl SideBarState {
 
         {
             let _width = ui.push_item_width(WIDTH);
-            ui.text(locale.get("start-time"));
-            ui.input_scalar("##StartTime", &mut self.sim_times.start)
-                .build();
-            ui.text(locale.get("delta-time"));
-            ui.input_scalar("##DeltaTime", &mut self.sim_times.delta)
-                .build();
-            ui.text(locale.get("end-time"));
-            ui.input_scalar("##EndTime", &mut self.sim_times.end)
-                .build();
+            ui.radio_button(locale.get("model-ode"), &mut self.kind, ModelKind::Ode);
+            ui.radio_button(
+                locale.get("model-cellular-automata"),
+                &mut self.kind,
+                ModelKind::CellularAutomata,
+            );
+
+            match self.kind {
+                ModelKind::Ode => {
+                    ui.text(locale.get("start-time"));
+                    ui.input_scalar("##StartTime", &mut self.sim_times.start)
+                        .build();
+                    ui.text(locale.get("delta-time"));
+                    ui.input_scalar("##DeltaTime", &mut self.sim_times.delta)
+                        .build();
+                    ui.text(locale.get("end-time"));
+                    ui.input_scalar("##EndTime", &mut self.sim_times.end)
+                        .build();
+                    ui.text(locale.get("time-unit"));
+                    ui.input_text("##TimeUnit", &mut self.time_unit)
+                        .hint("day")
+                        .build();
+                }
+                ModelKind::CellularAutomata => self.draw_automaton_settings(ui, locale),
+            }
         }
 
         table_group.end();
@@ -86,14 +116,65 @@ impl SideBarState {
         })
     }
 
+    fn draw_automaton_settings(&mut self, ui: &Ui, locale: &Locale) {
+        let automaton = &mut self.automaton;
+
+        ui.text(locale.get("grid-size"));
+        let mut size = [automaton.width, automaton.height];
+        if ui.input_scalar_n("##GridSize", &mut size).build() {
+            [automaton.width, automaton.height] = size;
+        }
+        ui.text(locale.get("steps"));
+        ui.input_scalar("##Steps", &mut automaton.steps).build();
+        ui.text(locale.get("neighbourhood"));
+        let mut selected = Neighbourhood::VARIANTS
+            .iter()
+            .position(|&neighbourhood| neighbourhood == automaton.neighbourhood)
+            .unwrap_or_default();
+        if ui.combo(
+            "##Neighbourhood",
+            &mut selected,
+            Neighbourhood::VARIANTS,
+            |neighbourhood| {
+                let name = match neighbourhood {
+                    Neighbourhood::Moore => locale.get("neighbourhood-moore"),
+                    Neighbourhood::VonNeumann => locale.get("neighbourhood-von-neumann"),
+                };
+                name.to_owned().into()
+            },
+        ) {
+            automaton.neighbourhood = Neighbourhood::VARIANTS[selected];
+        }
+        ui.text(locale.get("seed"));
+        ui.input_scalar("##Seed", &mut automaton.seed).build();
+    }
+
+    pub fn kind(&self) -> ModelKind {
+        self.kind
+    }
+
     pub fn get_metadata(&self) -> ode::Metadata {
-        self.sim_times.into()
+        ode::Metadata {
+            time_unit: (!self.time_unit.is_empty()).then(|| self.time_unit.clone()),
+            ..self.sim_times.into()
+        }
     }
 
     pub fn set_metadata(&mut self, metadata: ode::Metadata) {
+        self.kind = ModelKind::Ode;
+        self.time_unit = metadata.time_unit.clone().unwrap_or_default();
         self.sim_times = metadata.into();
     }
 
+    pub fn get_automaton_metadata(&self) -> cellular_automata::Metadata {
+        self.automaton.clone()
+    }
+
+    pub fn set_automaton_metadata(&mut self, metadata: cellular_automata::Metadata) {
+        self.kind = ModelKind::CellularAutomata;
+        self.automaton = metadata;
+    }
+
     pub fn time_flags(&self) -> [String; 6] {
         [
             "--st".to_owned(),
@@ -106,7 +187,10 @@ impl SideBarState {
     }
 
     pub fn clear_state(&mut self) {
+        self.kind = ModelKind::default();
         self.sim_times = Times::default();
+        self.time_unit.clear();
+        self.automaton = Default::default();
     }
 }
 
@@ -117,6 +201,7 @@ impl From<Times> for ode::Metadata {
             start_time: start,
             delta_time: delta,
             end_time: end,
+            time_unit: None,
         }
     }
 }
@@ -127,6 +212,7 @@ impl From<ode::Metadata> for Times {
             start_time,
             delta_time,
             end_time,
+            ..
         } = value;
 
         Self {
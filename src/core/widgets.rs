@@ -11,6 +11,11 @@ pub fn input_num(ui: &Ui, label: &str, value: &mut f64) -> bool {
         .build()
 }
 
+pub fn input_unit(ui: &Ui, label: &str, unit: &mut String) -> bool {
+    let _width = ui.push_item_width(72.0);
+    ui.input_text(label, unit).hint("1/day").build()
+}
+
 pub fn search_bar(ui: &Ui, buf: &mut String) {
     let _k = ui.push_style_var(StyleVar::ItemSpacing([0.2, 0.0]));
     // Magnifying glass icon
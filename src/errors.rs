@@ -22,3 +22,30 @@ pub enum InvalidNodeReason {
     NodeDoesNotExist,
     NoOutputPin,
 }
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error(transparent)]
+    WrongModel(#[from] NotCorrectModel),
+
+    #[error(transparent)]
+    Sbml(#[from] odeir::transformations::sbml::SbmlExportError),
+}
+
+#[derive(Error, Debug)]
+pub enum SimulationError {
+    #[error("Models using extensions can only be simulated through Python")]
+    UsesExtensions,
+
+    #[error(transparent)]
+    Integration(#[from] crate::ode::odesystem::OdeError),
+
+    #[error(transparent)]
+    Stochastic(#[from] crate::ode::stochastic::StochasticError),
+
+    #[error(transparent)]
+    Automaton(#[from] odeir::models::cellular_automata::CaError),
+
+    #[error(transparent)]
+    WrongModel(#[from] NotCorrectModel),
+}
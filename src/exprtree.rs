@@ -1,4 +1,4 @@
-use std::{collections::BTreeMap, fmt::Write, hash::Hash};
+use std::{collections::BTreeMap, hash::Hash};
 
 use strum::EnumString;
 
@@ -169,6 +169,36 @@ pub enum Operation {
     #[default]
     #[strum(serialize = "*", serialize = "×")]
     Mul,
+    #[strum(serialize = "==")]
+    Eq,
+    #[strum(serialize = "!=")]
+    Ne,
+    #[strum(serialize = "<")]
+    Lt,
+    #[strum(serialize = ">")]
+    Gt,
+    #[strum(serialize = "<=")]
+    Le,
+    #[strum(serialize = ">=")]
+    Ge,
+}
+
+impl Operation {
+    /// How the operation is written in equations and model files.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Operation::Add => "+",
+            Operation::Sub => "-",
+            Operation::Div => "/",
+            Operation::Mul => "*",
+            Operation::Eq => "==",
+            Operation::Ne => "!=",
+            Operation::Lt => "<",
+            Operation::Gt => ">",
+            Operation::Le => "<=",
+            Operation::Ge => ">=",
+        }
+    }
 }
 
 pub struct NotAnOperationChar;
@@ -182,31 +212,21 @@ impl TryFrom<char> for Operation {
             '-' => Ok(Self::Sub),
             '/' => Ok(Self::Div),
             '*' => Ok(Self::Mul),
+            '<' => Ok(Self::Lt),
+            '>' => Ok(Self::Gt),
             _ => Err(NotAnOperationChar),
         }
     }
 }
 
-impl From<Operation> for char {
-    fn from(value: Operation) -> Self {
-        match value {
-            Operation::Add => '+',
-            Operation::Sub => '-',
-            Operation::Div => '/',
-            Operation::Mul => '*',
-        }
-    }
-}
-
 impl std::fmt::Display for Operation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let c: char = (*self).into();
-        f.write_char(c)
+        f.write_str(self.symbol())
     }
 }
 
 impl From<Operation> for Format {
     fn from(value: Operation) -> Self {
-        format!("$@{value}").parse().unwrap()
+        Format::joined_by(value.symbol())
     }
 }
@@ -20,19 +20,37 @@ impl Format {
         Self(vec![
             FormatPart::Static(fn_name.to_owned()),
             FormatPart::Static("(".to_owned()),
-            FormatPart::Dynamic(ArgumentSpecifier::All { separator: ',' }),
+            FormatPart::Dynamic(ArgumentSpecifier::All {
+                separator: ",".to_owned(),
+            }),
             FormatPart::Static(")".to_owned()),
         ])
     }
 
+    /// Every argument, joined by `separator`.
+    pub fn joined_by(separator: &str) -> Self {
+        Self(vec![FormatPart::Dynamic(ArgumentSpecifier::All {
+            separator: separator.to_owned(),
+        })])
+    }
+
     pub fn format_args<T: Display>(&self, args: Vec<T>) -> String {
-        let mut str_buf = [0; 4];
+        self.format_args_with(args, str::to_owned)
+    }
+
+    /// Like [`Format::format_args`], but passes the static parts of the format
+    /// through `map_static`, so they can be escaped or marked up.
+    pub fn format_args_with<T: Display>(
+        &self,
+        args: Vec<T>,
+        map_static: impl Fn(&str) -> String,
+    ) -> String {
         let display_args: Vec<_> = args.into_iter().map(|arg| arg.to_string()).collect();
 
         self.0
             .iter()
             .map(|part| match part {
-                FormatPart::Static(s) => s.clone(),
+                FormatPart::Static(s) => map_static(s),
                 FormatPart::Dynamic(arg_spec) => match arg_spec {
                     ArgumentSpecifier::Indexed(idx) => display_args
                         .get(*idx - 1)
@@ -42,7 +60,8 @@ impl Format {
                         todo!("Named parameters are still not supported")
                     }
                     ArgumentSpecifier::All { separator } => {
-                        display_args.join(separator.encode_utf8(&mut str_buf))
+                        let separator = map_static(separator);
+                        display_args.join(separator.as_str())
                     }
                 },
             })
@@ -71,7 +90,7 @@ pub enum FormatPart {
 pub enum ArgumentSpecifier {
     Indexed(usize),
     Named(String),
-    All { separator: char },
+    All { separator: String },
 }
 
 fn parse_ident(input: &str) -> IResult<&str, &str> {
@@ -90,7 +109,9 @@ fn parse_dollar_preceded_element(input: &str) -> IResult<&str, FormatPart> {
             FormatPart::Dynamic(ArgumentSpecifier::Named(ident.to_owned()))
         }),
         map(pair(tag("@"), anychar), |(_at, c)| {
-            FormatPart::Dynamic(ArgumentSpecifier::All { separator: c })
+            FormatPart::Dynamic(ArgumentSpecifier::All {
+                separator: c.to_string(),
+            })
         }),
         map(many0(is_not("$ ")), |vc| {
             let mut s = String::from("$");
@@ -147,7 +168,9 @@ mod tests {
         &[
             S("Sum".to_string()),
             S("=".to_string()),
-            D(All { separator: '+' }),
+            D(All {
+                separator: "+".to_string()
+            }),
         ]
     )]
     #[case(
@@ -206,7 +229,9 @@ mod tests {
         Format(vec![
             S("Sum".to_string()),
             S("=".to_string()),
-            D(All { separator: '+' }),
+            D(All {
+                separator: "+".to_string()
+            }),
         ]),
         &['A', 'B', 'C', 'D'],
         "Sum = A+B+C+D",
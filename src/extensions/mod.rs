@@ -19,6 +19,13 @@ pub struct Extension {
     pub nodes: Vec<Rc<CustomNodeSpecification>>,
 }
 
+/// A function declared by the model, offered as a custom node. Its body is an
+/// expression, so unlike extension nodes it doesn't need Python to simulate.
+pub struct ModelFunction {
+    pub function: odeir::Function,
+    pub spec: Rc<CustomNodeSpecification>,
+}
+
 #[derive(Debug)]
 pub struct CustomNodeSpecification {
     pub function: NodeFunction,
@@ -102,4 +109,51 @@ impl App {
 
         Ok(())
     }
+
+    /// Registers the functions declared by a model, so custom nodes can call
+    /// them.
+    pub fn load_model_functions(&mut self, functions: Vec<odeir::Function>) {
+        for function in functions {
+            let spec = Rc::new(CustomNodeSpecification::from(NodeFunction {
+                name: function.name.clone(),
+                required_arguments: function.parameters.clone(),
+                features_variadic: false,
+                format: None,
+            }));
+            self.node_types.push(NodeTypeRepresentation::new(
+                format!("󰡱 {}", function.name),
+                NodeVariant::Custom,
+                Some(Rc::clone(&spec)),
+            ));
+            self.model_functions.push(ModelFunction { function, spec });
+        }
+    }
+
+    /// Forgets the functions of the model, along with their node types.
+    pub fn unload_model_functions(&mut self) {
+        let functions = std::mem::take(&mut self.model_functions);
+        self.node_types.retain(|node_type| {
+            node_type.custom_node_spec.as_ref().is_none_or(|spec| {
+                !functions
+                    .iter()
+                    .any(|function| Rc::ptr_eq(spec, &function.spec))
+            })
+        });
+    }
+
+    /// The specification of the custom node calling `function`, which is
+    /// either a function of the model or one of an extension.
+    pub fn custom_node_spec(&self, function: &str) -> Option<&Rc<CustomNodeSpecification>> {
+        self.model_functions
+            .iter()
+            .map(|model_function| &model_function.spec)
+            .chain(self.extensions.iter().flat_map(|ext| &ext.nodes))
+            .find(|spec| spec.function.name == function)
+    }
+
+    pub fn is_model_function(&self, function: &str) -> bool {
+        self.model_functions
+            .iter()
+            .any(|model_function| model_function.function.name == function)
+    }
 }
@@ -0,0 +1,5 @@
+//! The parts of ODE-Designer that need no window: simulating models and
+//! estimating their parameters. Both the editor and `ode-designer-cli` are
+//! built on it.
+
+pub mod ode;
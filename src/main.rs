@@ -13,10 +13,11 @@ pub mod exprtree;
 pub mod extensions;
 pub mod locale;
 pub mod nodes;
-pub mod ode;
 pub mod pins;
 pub mod utils;
 
+pub use ode_designer_rs::ode;
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     color_eyre::install().unwrap();
 
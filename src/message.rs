@@ -26,6 +26,8 @@ pub enum Message {
     },
     RegisterPin(NodeId, InputPinId),
     UnregisterPin(InputPinId),
+    RegisterOutputPin(NodeId, OutputPinId),
+    UnregisterOutputPin(OutputPinId),
     RemoveNode(NodeId),
     RenameNode(NodeId, String),
     SetInitialValue(NodeId, f64),
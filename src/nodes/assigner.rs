@@ -115,16 +115,18 @@ impl NodeImpl for Assigner {
     }
 
     fn to_model_fragment(&self, app: &crate::core::App) -> Option<ModelFragment> {
+        let linked_pin_id = self.input.linked_to?;
+
         let node_id = app
             .output_pins
-            .get(&self.input.linked_to?)
+            .get(&linked_pin_id)
             .expect("The node must exist, otherwise this should have been unlinked");
 
         let node = app
             .get_node(*node_id)
             .expect("The node must exist, otherwise this should have been unlinked");
 
-        let argument = node.name().to_owned();
+        let argument = node.output_name(&linked_pin_id);
         let contribution = self.input.sign.into();
 
         Some(
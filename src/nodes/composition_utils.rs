@@ -36,7 +36,7 @@ pub fn build_composition(
             .expect("The node must exist, otherwise this should have been unlinked");
 
         composition.push(odeir::models::Component {
-            name: node.name().to_owned(),
+            name: node.output_name(&linked_pin_id),
             contribution: input_pin.sign.into(),
         });
     }
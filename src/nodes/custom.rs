@@ -48,6 +48,11 @@ impl CustomFunctionNode {
             spec,
         }
     }
+
+    /// Name of the function the node calls.
+    pub fn function_name(&self) -> &str {
+        &self.spec.function.name
+    }
 }
 
 impl NodeImpl for CustomFunctionNode {
@@ -152,11 +157,7 @@ impl NodeImpl for CustomFunctionNode {
             return None;
         };
 
-        let spec = app
-            .extensions
-            .iter()
-            .flat_map(|ext| ext.nodes.iter())
-            .find(|node_spec| &node_spec.function.name == operation)?;
+        let spec = app.custom_node_spec(operation)?;
 
         build_from_composition(node_id, frag, |name, _composition, _expr_wrapper| {
             Self::from_spec(node_id, name.to_owned(), Rc::clone(spec))
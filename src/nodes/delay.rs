@@ -0,0 +1,125 @@
+use imgui::{ImColor32, Ui};
+use imnodes::{InputPinId, NodeId};
+use odeir::models::Delay;
+
+use crate::{
+    core::{App, widgets::input_num},
+    exprtree::{ExpressionNode, Leaf, Sign},
+    locale::Locale,
+    pins::{InputPin, OutputPin, Pin},
+    utils::ModelFragment,
+};
+
+use super::{NodeImpl, PendingOperation, PendingOperations, SimpleNodeBuilder, linked_name};
+
+/// The value the population linked to it had `tau` units of time earlier,
+/// which is sent through the output. Before the start of the simulation, it's
+/// the initial value of the population.
+#[derive(Debug)]
+pub struct DelayNode {
+    pub id: NodeId,
+    pub name: String,
+    pub tau: f64,
+    pub input: InputPin,
+    pub output: OutputPin,
+}
+
+impl SimpleNodeBuilder for DelayNode {
+    fn new(node_id: NodeId, name: String) -> Self {
+        let mut input = InputPin::new(node_id);
+        input.remove_sign();
+
+        Self {
+            id: node_id,
+            name,
+            tau: 1.0,
+            input,
+            output: Pin::new(node_id),
+        }
+    }
+}
+
+impl NodeImpl for DelayNode {
+    fn id(&self) -> NodeId {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn name_mut(&mut self) -> &mut String {
+        &mut self.name
+    }
+
+    fn color(&self) -> ImColor32 {
+        ImColor32::from_rgb(92, 84, 150)
+    }
+
+    fn selected_color(&self) -> ImColor32 {
+        ImColor32::from_rgb(121, 111, 191)
+    }
+
+    fn send_data(&self) -> ExpressionNode<InputPinId> {
+        ExpressionNode::Leaf(Leaf {
+            symbol: self.name.clone(),
+            unary_op: Sign::Positive,
+        })
+    }
+
+    fn draw(&mut self, ui: &Ui, locale: &Locale) -> bool {
+        self.input.set_label(locale.get("delay-population"));
+
+        ui.text(locale.get("delay-tau"));
+        ui.same_line();
+        input_num(ui, "##tau", &mut self.tau)
+    }
+
+    fn inputs(&self) -> Option<&[InputPin]> {
+        Some(std::slice::from_ref(&self.input))
+    }
+
+    fn outputs(&self) -> Option<&[OutputPin]> {
+        Some(std::slice::from_ref(&self.output))
+    }
+
+    fn inputs_mut(&mut self) -> Option<&mut [InputPin]> {
+        Some(std::slice::from_mut(&mut self.input))
+    }
+
+    fn outputs_mut(&mut self) -> Option<&mut [OutputPin]> {
+        Some(std::slice::from_mut(&mut self.output))
+    }
+
+    fn to_model_fragment(&self, app: &App) -> Option<ModelFragment> {
+        Some(ModelFragment::Delay(Delay {
+            name: self.name.clone(),
+            population: linked_name(app, &self.input).unwrap_or_default(),
+            tau: self.tau,
+        }))
+    }
+
+    fn try_from_model_fragment(
+        node_id: NodeId,
+        frag: &ModelFragment,
+        _app: &App,
+    ) -> Option<(Self, Option<PendingOperations>)> {
+        let ModelFragment::Delay(delay) = frag else {
+            return None;
+        };
+
+        let mut node = Self::new(node_id, delay.name.clone());
+        node.tau = delay.tau;
+
+        let pending_ops = (!delay.population.is_empty()).then(|| PendingOperations {
+            node_id,
+            operations: vec![PendingOperation::LinkWith {
+                node_name: delay.population.clone(),
+                via_pin_id: node.input.id,
+                sign: Sign::Positive,
+            }],
+        });
+
+        Some((node, pending_ops))
+    }
+}
@@ -188,7 +188,7 @@ impl NodeImpl for Expression {
         build_composition(
             &self.name,
             &self.inputs,
-            Into::<char>::into(self.expr_wrapper.join_op()).into(),
+            self.expr_wrapper.join_op().to_string(),
             CompositionStyle::Infixed,
             app,
         )
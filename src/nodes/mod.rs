@@ -1,8 +1,11 @@
 mod assigner;
 pub mod composition_utils;
 pub mod custom;
+mod delay;
 pub mod errors;
 pub mod expression;
+mod reaction;
+mod submodel;
 pub mod term;
 
 use std::{
@@ -11,9 +14,12 @@ use std::{
 };
 
 pub use assigner::Assigner;
+pub use delay::DelayNode;
 use enutil::EnumDeref;
 pub use expression::Expression;
+pub use reaction::ReactionNode;
 use strum::{EnumDiscriminants, FromRepr, VariantArray, VariantNames};
+pub use submodel::SubmodelNode;
 pub use term::Term;
 
 use imgui::{ImColor32, Ui};
@@ -75,7 +81,10 @@ pub enum Node {
     Term(Term),
     Expression(Expression),
     Assigner(Assigner),
+    Reaction(ReactionNode),
+    Delay(DelayNode),
     Custom(CustomFunctionNode),
+    Submodel(SubmodelNode),
 }
 
 impl Node {
@@ -93,6 +102,8 @@ impl Node {
             (NodeVariant::Term, None) => Term::new(node_id, name).into(),
             (NodeVariant::Expression, None) => Expression::new(node_id, name).into(),
             (NodeVariant::Assigner, None) => Assigner::new(node_id, name).into(),
+            (NodeVariant::Reaction, None) => ReactionNode::new(node_id, name).into(),
+            (NodeVariant::Delay, None) => DelayNode::new(node_id, name).into(),
             (NodeVariant::Custom, Some(node_spec)) => {
                 CustomFunctionNode::from_spec(node_id, name, Rc::clone(node_spec)).into()
             }
@@ -117,23 +128,39 @@ impl Node {
                             .or_else(|| {
                                 CustomFunctionNode::try_from_model_fragment(node_id, &frag, app)
                                     .map(|(node_impl, ops)| (node_impl.into(), ops))
+                                    .or_else(|| {
+                                        SubmodelNode::try_from_model_fragment(node_id, &frag, app)
+                                            .map(|(node_impl, ops)| (node_impl.into(), ops))
+                                            .or_else(|| {
+                                                ReactionNode::try_from_model_fragment(
+                                                    node_id, &frag, app,
+                                                )
+                                                .map(|(node_impl, ops)| (node_impl.into(), ops))
+                                            })
+                                            .or_else(|| {
+                                                DelayNode::try_from_model_fragment(
+                                                    node_id, &frag, app,
+                                                )
+                                                .map(|(node_impl, ops)| (node_impl.into(), ops))
+                                            })
+                                    })
                             })
                     })
             })
             .ok_or(NotANode(frag))
     }
 
-    /// Broadcasts messages from a node. Essentially, relays all messages sent by [`NodeImpl::send_data`] to the output pins.
+    /// Broadcasts messages from a node. Essentially, relays all messages sent by [`NodeImpl::send_data_from`] to the output pins.
     pub fn broadcast_data(&self) -> Vec<Message> {
         let Some(outputs) = self.outputs() else {
             //log::warn!("Tried broadcasting data to node without any output pins");
             return vec![];
         };
-        let data = self.send_data();
         outputs
             .iter()
             .flat_map(|output| {
-                output.linked_to.iter().copied().map(|to_input| SendData {
+                let data = self.send_data_from(&output.id);
+                output.linked_to.iter().copied().map(move |to_input| SendData {
                     data: data.clone(),
                     from_output: output.id,
                     to_input,
@@ -197,7 +224,11 @@ impl Node {
         for output in self.outputs_mut().unwrap_or_default() {
             let shape = output.get_shape();
             let id = *output.id();
-            ui_node.add_output(id, shape, || {});
+            ui_node.add_output(id, shape, || {
+                if let Some(label) = output.get_label() {
+                    ui.text(label);
+                }
+            });
         }
 
         let inner_content_changed = self.draw(ui, locale);
@@ -217,10 +248,10 @@ impl Node {
         }
 
         if inner_content_changed {
-            match self.as_term() {
-                Some(term) => {
+            match self {
+                Node::Term(term) => {
                     let node_set_value_msg =
-                        Message::SetInitialValue(self.id(), term.initial_value);
+                        Message::SetInitialValue(term.id(), term.initial_value);
 
                     if let Some(ref mut msg) = messages {
                         msg.push(node_set_value_msg);
@@ -228,7 +259,25 @@ impl Node {
                         messages = Some(vec![node_set_value_msg]);
                     }
                 }
-                None => {
+                Node::Reaction(reaction) => {
+                    let pin_change_msgs = reaction.take_pin_changes();
+
+                    if let Some(ref mut msg) = messages {
+                        msg.extend(pin_change_msgs);
+                    } else {
+                        messages = Some(pin_change_msgs);
+                    }
+                }
+                Node::Submodel(submodel) => {
+                    let pin_change_msgs = submodel.take_pin_changes();
+
+                    if let Some(ref mut msg) = messages {
+                        msg.extend(pin_change_msgs);
+                    } else {
+                        messages = Some(pin_change_msgs);
+                    }
+                }
+                _ => {
                     // TODO
                 }
             }
@@ -274,6 +323,28 @@ pub trait NodeImpl {
 
     fn send_data(&self) -> ExpressionNode<InputPinId>;
 
+    /// The data sent through a specific output pin. Most nodes send the same
+    /// data through all of them.
+    fn send_data_from(&self, output_pin_id: &OutputPinId) -> ExpressionNode<InputPinId> {
+        let _ = output_pin_id;
+        self.send_data()
+    }
+
+    /// The name the model refers to whatever is sent through `output_pin_id`
+    /// by, which is the node's own name unless it has several outputs.
+    fn output_name(&self, output_pin_id: &OutputPinId) -> String {
+        let _ = output_pin_id;
+        self.name().to_owned()
+    }
+
+    /// The output pin sending `name`, as given by [`NodeImpl::output_name`].
+    fn output_named(&self, name: &str) -> Option<&OutputPin> {
+        self.outputs()
+            .unwrap_or_default()
+            .iter()
+            .find(|pin| self.output_name(pin.id()) == name)
+    }
+
     fn trigger_app_state_change(&self) -> Option<AppState> {
         None
     }
@@ -437,3 +508,18 @@ pub enum PendingOperation {
         target_node_name: String,
     },
 }
+
+/// Name of whatever is linked to `pin`, if anything.
+fn linked_name(app: &App, pin: &InputPin) -> Option<String> {
+    let output_pin_id = pin.linked_to?;
+    let node_id = app
+        .output_pins
+        .get(&output_pin_id)
+        .expect("The node must exist, otherwise this should have been unlinked");
+
+    Some(
+        app.get_node(*node_id)
+            .expect("The node must exist, otherwise this should have been unlinked")
+            .output_name(&output_pin_id),
+    )
+}
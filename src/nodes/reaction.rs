@@ -0,0 +1,380 @@
+use std::mem::discriminant;
+
+use imgui::{ImColor32, Ui};
+use imnodes::{InputPinId, NodeId};
+use odeir::models::reaction::{RateLaw, Reaction, Species};
+
+use crate::{
+    core::App,
+    exprtree::{ExpressionNode, Leaf, Sign},
+    locale::Locale,
+    message::Message,
+    pins::{InputPin, OutputPin, Pin},
+    utils::{ModelFragment, fluent_map},
+};
+
+use super::{NodeImpl, PendingOperation, PendingOperations, SimpleNodeBuilder, linked_name};
+
+/// Locale keys of the rate laws, in the same order as [`rate_laws`].
+const RATE_LAW_NAMES: [&str; 3] = [
+    "reaction-mass-action",
+    "reaction-michaelis-menten",
+    "reaction-hill",
+];
+
+/// Every rate law, with its parameters yet to be linked.
+fn rate_laws(hill_coefficient: u32) -> [RateLaw; 3] {
+    [
+        RateLaw::MassAction { k: String::new() },
+        RateLaw::MichaelisMenten {
+            vmax: String::new(),
+            km: String::new(),
+        },
+        RateLaw::Hill {
+            vmax: String::new(),
+            k: String::new(),
+            n: hill_coefficient,
+        },
+    ]
+}
+
+/// A reaction between populations, which the model turns into terms of their
+/// equations. The parameters of the rate law and the species are linked to
+/// it, and its rate is sent through the output.
+#[derive(Debug)]
+pub struct ReactionNode {
+    pub id: NodeId,
+    pub name: String,
+    /// The rate law, whose parameters are taken from the links when saving
+    pub rate: RateLaw,
+    /// The parameters of the rate law, then the reactants, then the products
+    pub inputs: Vec<InputPin>,
+    /// Stoichiometry of the reactants and products, in the same order as their pins
+    pub stoichiometry: Vec<u32>,
+    pub reactant_count: usize,
+    pub output: OutputPin,
+    /// Pins added or removed since the last frame, which the app must know of
+    pin_changes: Vec<Message>,
+}
+
+impl SimpleNodeBuilder for ReactionNode {
+    fn new(node_id: NodeId, name: String) -> Self {
+        let mut node = Self {
+            id: node_id,
+            name,
+            rate: RateLaw::MassAction { k: String::new() },
+            inputs: Vec::new(),
+            stoichiometry: Vec::new(),
+            reactant_count: 0,
+            output: Pin::new(node_id),
+            pin_changes: Vec::new(),
+        };
+        node.insert_pin(0, "k");
+        // The app registers the pins of the nodes it's given
+        node.pin_changes.clear();
+
+        node
+    }
+}
+
+impl ReactionNode {
+    fn parameter_count(&self) -> usize {
+        self.rate.parameter_names().len()
+    }
+
+    fn insert_pin(&mut self, idx: usize, label: &str) {
+        let mut input = InputPin::new(self.id);
+        input.remove_sign().set_label(label);
+
+        self.pin_changes
+            .push(Message::RegisterPin(self.id, input.id));
+        self.inputs.insert(idx, input);
+    }
+
+    fn remove_pin(&mut self, idx: usize) {
+        let input = self.inputs.remove(idx);
+        self.pin_changes.push(Message::UnregisterPin(input.id));
+    }
+
+    /// Switches to `rate`, adding or removing parameter pins as needed.
+    fn set_rate(&mut self, rate: RateLaw) {
+        let old_count = self.parameter_count();
+        let names = rate.parameter_names();
+
+        for idx in (names.len()..old_count).rev() {
+            self.remove_pin(idx);
+        }
+        for (idx, name) in names.iter().enumerate() {
+            if idx < old_count {
+                self.inputs[idx].set_label(name);
+            } else {
+                self.insert_pin(idx, name);
+            }
+        }
+
+        self.rate = rate;
+    }
+
+    fn add_species(&mut self, is_product: bool, stoichiometry: u32) {
+        let species_idx = if is_product {
+            self.stoichiometry.len()
+        } else {
+            self.reactant_count += 1;
+            self.reactant_count - 1
+        };
+
+        self.insert_pin(self.parameter_count() + species_idx, "");
+        self.stoichiometry.insert(species_idx, stoichiometry);
+    }
+
+    fn remove_species(&mut self, species_idx: usize) {
+        if species_idx < self.reactant_count {
+            self.reactant_count -= 1;
+        }
+
+        self.remove_pin(self.parameter_count() + species_idx);
+        self.stoichiometry.remove(species_idx);
+    }
+
+    /// Messages registering the pins added or removed while drawing.
+    pub fn take_pin_changes(&mut self) -> Vec<Message> {
+        std::mem::take(&mut self.pin_changes)
+    }
+
+    /// The pins of the reactants and products.
+    pub fn species_pins(&self) -> &[InputPin] {
+        &self.inputs[self.parameter_count()..]
+    }
+
+    /// Lets the rate law be chosen, as long as it doesn't drop linked parameters.
+    fn draw_rate_law(&mut self, ui: &Ui, locale: &Locale) -> bool {
+        let hill_coefficient = match self.rate {
+            RateLaw::Hill { n, .. } => n,
+            _ => 1,
+        };
+        let laws = rate_laws(hill_coefficient);
+        let current = laws
+            .iter()
+            .position(|law| discriminant(law) == discriminant(&self.rate))
+            .expect("Every rate law is listed");
+
+        let mut changed = false;
+
+        ui.text(locale.get("reaction-law"));
+        if let Some(_combo) = ui.begin_combo("##rate law", locale.get(RATE_LAW_NAMES[current])) {
+            for (idx, (law, name)) in laws.into_iter().zip(RATE_LAW_NAMES).enumerate() {
+                let drops_linked = self.inputs[..self.parameter_count()]
+                    .iter()
+                    .skip(law.parameter_names().len())
+                    .any(|pin| pin.linked_to.is_some());
+
+                if ui
+                    .selectable_config(locale.get(name))
+                    .selected(idx == current)
+                    .disabled(drops_linked)
+                    .build()
+                    && idx != current
+                {
+                    self.set_rate(law);
+                    changed = true;
+                }
+            }
+        }
+
+        if let RateLaw::Hill { n, .. } = &mut self.rate {
+            ui.text(locale.get("reaction-hill-coefficient"));
+            changed |= ui.input_scalar("##hill coefficient", n).build();
+            *n = (*n).max(1);
+        }
+
+        changed
+    }
+
+    /// Lets the stoichiometry of each species be changed, and species be added
+    /// or removed. Linked species can't be removed.
+    fn draw_species(&mut self, ui: &Ui, locale: &Locale) -> bool {
+        let parameter_count = self.parameter_count();
+        let mut changed = false;
+        let mut removed = None;
+
+        ui.text(locale.get("reaction-stoichiometry"));
+        for species_idx in 0..self.stoichiometry.len() {
+            let (key, number) = if species_idx < self.reactant_count {
+                ("reaction-reactant", species_idx + 1)
+            } else {
+                ("reaction-product", species_idx - self.reactant_count + 1)
+            };
+            let label = String::from(&*locale.fmt(key, &fluent_map!("idx" => number)));
+            let pin = &mut self.inputs[parameter_count + species_idx];
+            pin.set_label(&label);
+            let linked = pin.linked_to.is_some();
+
+            let _width = ui.push_item_width(80.0);
+            changed |= ui
+                .input_scalar(
+                    format!("{label}##stoichiometry {species_idx}"),
+                    &mut self.stoichiometry[species_idx],
+                )
+                .build();
+            self.stoichiometry[species_idx] = self.stoichiometry[species_idx].max(1);
+
+            ui.same_line();
+            let _disabled = ui.begin_disabled(linked);
+            if ui.button(format!(" × ##remove species {species_idx}")) {
+                removed = Some(species_idx);
+            }
+        }
+
+        if let Some(species_idx) = removed {
+            self.remove_species(species_idx);
+            changed = true;
+        }
+
+        if ui.button(locale.get("reaction-add-reactant")) {
+            self.add_species(false, 1);
+            changed = true;
+        }
+        ui.same_line();
+        if ui.button(locale.get("reaction-add-product")) {
+            self.add_species(true, 1);
+            changed = true;
+        }
+
+        changed
+    }
+}
+
+impl NodeImpl for ReactionNode {
+    fn id(&self) -> NodeId {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn name_mut(&mut self) -> &mut String {
+        &mut self.name
+    }
+
+    fn color(&self) -> ImColor32 {
+        ImColor32::from_rgb(46, 125, 110)
+    }
+
+    fn selected_color(&self) -> ImColor32 {
+        ImColor32::from_rgb(67, 163, 145)
+    }
+
+    fn send_data(&self) -> ExpressionNode<InputPinId> {
+        ExpressionNode::Leaf(Leaf {
+            symbol: self.name.clone(),
+            unary_op: Sign::Positive,
+        })
+    }
+
+    fn draw(&mut self, ui: &Ui, locale: &Locale) -> bool {
+        let _width = ui.push_item_width(150.0);
+
+        let rate_changed = self.draw_rate_law(ui, locale);
+        let species_changed = self.draw_species(ui, locale);
+
+        rate_changed || species_changed
+    }
+
+    fn inputs(&self) -> Option<&[InputPin]> {
+        Some(&self.inputs)
+    }
+
+    fn outputs(&self) -> Option<&[OutputPin]> {
+        Some(std::slice::from_ref(&self.output))
+    }
+
+    fn inputs_mut(&mut self) -> Option<&mut [InputPin]> {
+        Some(&mut self.inputs)
+    }
+
+    fn outputs_mut(&mut self) -> Option<&mut [OutputPin]> {
+        Some(std::slice::from_mut(&mut self.output))
+    }
+
+    fn to_model_fragment(&self, app: &App) -> Option<ModelFragment> {
+        let mut rate = self.rate.clone();
+        for (parameter, pin) in rate.parameters_mut().into_iter().zip(&self.inputs) {
+            *parameter = linked_name(app, pin).unwrap_or_default();
+        }
+
+        let mut reactants = Vec::new();
+        let mut products = Vec::new();
+        for (species_idx, (pin, &stoichiometry)) in self
+            .species_pins()
+            .iter()
+            .zip(&self.stoichiometry)
+            .enumerate()
+        {
+            let Some(name) = linked_name(app, pin) else {
+                continue;
+            };
+            let species = Species {
+                name,
+                stoichiometry,
+            };
+
+            if species_idx < self.reactant_count {
+                reactants.push(species);
+            } else {
+                products.push(species);
+            }
+        }
+
+        Some(ModelFragment::Reaction(Reaction {
+            name: self.name.clone(),
+            reactants,
+            products,
+            rate,
+        }))
+    }
+
+    fn try_from_model_fragment(
+        node_id: NodeId,
+        frag: &ModelFragment,
+        _app: &App,
+    ) -> Option<(Self, Option<PendingOperations>)> {
+        let ModelFragment::Reaction(reaction) = frag else {
+            return None;
+        };
+
+        let mut node = Self::new(node_id, reaction.name.clone());
+        node.set_rate(reaction.rate.clone());
+        for species in &reaction.reactants {
+            node.add_species(false, species.stoichiometry);
+        }
+        for species in &reaction.products {
+            node.add_species(true, species.stoichiometry);
+        }
+        // The app registers the pins of the nodes it's given
+        node.pin_changes.clear();
+
+        let linked_names = reaction.rate.parameters().into_iter().chain(
+            reaction
+                .reactants
+                .iter()
+                .chain(&reaction.products)
+                .map(|species| &species.name),
+        );
+
+        let pending_ops = PendingOperations {
+            node_id,
+            operations: linked_names
+                .zip(&node.inputs)
+                .filter(|(name, _)| !name.is_empty())
+                .map(|(name, input)| PendingOperation::LinkWith {
+                    node_name: name.clone(),
+                    via_pin_id: input.id,
+                    sign: Sign::Positive,
+                })
+                .collect(),
+        };
+
+        Some((node, Some(pending_ops)))
+    }
+}
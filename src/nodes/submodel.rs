@@ -0,0 +1,255 @@
+use imgui::{ImColor32, Ui};
+use imnodes::{InputPinId, NodeId, OutputPinId};
+use odeir::models::submodel::{Flattened, Port, qualified_name};
+
+use crate::{
+    core::App,
+    exprtree::{ExpressionNode, Leaf, Sign},
+    locale::Locale,
+    message::Message,
+    pins::{InputPin, OutputPin, Pin},
+    utils::ModelFragment,
+};
+
+use super::{NodeImpl, PendingOperation, PendingOperations};
+
+/// Another model file embedded in the model. Each port has an output pin,
+/// sending `namespace.port`, and an input pin, which replaces the port with
+/// whatever is linked to it.
+#[derive(Debug)]
+pub struct SubmodelNode {
+    pub id: NodeId,
+    /// Namespace the ports are referred to by
+    pub name: String,
+    /// Path of the model file, relative to the model's file
+    pub path: String,
+    /// The embedded model, flattened, or why it couldn't be loaded
+    pub model: Result<Flattened, String>,
+    /// Names of the exposed arguments, in the same order as the pins
+    pub ports: Vec<String>,
+    pub inputs: Vec<InputPin>,
+    pub outputs: Vec<OutputPin>,
+    /// Pins added or removed since the last frame, which the app must know of
+    pin_changes: Vec<Message>,
+}
+
+impl SubmodelNode {
+    pub fn new(
+        node_id: NodeId,
+        name: String,
+        path: String,
+        model: Result<Flattened, String>,
+    ) -> Self {
+        Self {
+            id: node_id,
+            name,
+            path,
+            model,
+            ports: Vec::new(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            pin_changes: Vec::new(),
+        }
+    }
+
+    fn add_port(&mut self, name: String) {
+        let mut input = InputPin::new(self.id);
+        input.remove_sign().set_label(&name);
+        let mut output = OutputPin::new(self.id);
+        output.set_label(&name);
+
+        self.pin_changes
+            .push(Message::RegisterPin(self.id, input.id));
+        self.pin_changes
+            .push(Message::RegisterOutputPin(self.id, output.id));
+
+        self.ports.push(name);
+        self.inputs.push(input);
+        self.outputs.push(output);
+    }
+
+    fn remove_port(&mut self, idx: usize) {
+        self.ports.remove(idx);
+        let input = self.inputs.remove(idx);
+        let output = self.outputs.remove(idx);
+
+        self.pin_changes.push(Message::UnregisterPin(input.id));
+        self.pin_changes
+            .push(Message::UnregisterOutputPin(output.id));
+    }
+
+    /// Messages registering the pins added or removed while drawing.
+    pub fn take_pin_changes(&mut self) -> Vec<Message> {
+        std::mem::take(&mut self.pin_changes)
+    }
+
+    fn port_of(&self, output_pin_id: &OutputPinId) -> Option<&str> {
+        self.outputs
+            .iter()
+            .position(|pin| pin.id == *output_pin_id)
+            .map(|idx| self.ports[idx].as_str())
+    }
+}
+
+impl NodeImpl for SubmodelNode {
+    fn id(&self) -> NodeId {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn name_mut(&mut self) -> &mut String {
+        &mut self.name
+    }
+
+    fn color(&self) -> ImColor32 {
+        ImColor32::from_rgb(106, 76, 147)
+    }
+
+    fn selected_color(&self) -> ImColor32 {
+        ImColor32::from_rgb(142, 108, 189)
+    }
+
+    fn send_data(&self) -> ExpressionNode<InputPinId> {
+        ExpressionNode::Leaf(Leaf {
+            symbol: self.name.clone(),
+            unary_op: Sign::Positive,
+        })
+    }
+
+    fn send_data_from(&self, output_pin_id: &OutputPinId) -> ExpressionNode<InputPinId> {
+        ExpressionNode::Leaf(Leaf {
+            symbol: self.output_name(output_pin_id),
+            unary_op: Sign::Positive,
+        })
+    }
+
+    fn output_name(&self, output_pin_id: &OutputPinId) -> String {
+        match self.port_of(output_pin_id) {
+            Some(port) => qualified_name(&self.name, port),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Lists the arguments of the embedded model, so the ones to expose as
+    /// ports can be chosen. Ports in use can't be removed.
+    fn draw(&mut self, ui: &Ui, locale: &Locale) -> bool {
+        let Some(_ports) = ui.tree_node(locale.get("submodel-ports")) else {
+            return false;
+        };
+
+        ui.text_disabled(&self.path);
+
+        let arguments: Vec<String> = match &self.model {
+            Ok(flattened) => flattened.model.arguments.keys().cloned().collect(),
+            Err(reason) => {
+                ui.text_colored([1.0, 0.1, 0.1, 1.0], reason);
+                return false;
+            }
+        };
+
+        let mut changed = false;
+
+        for argument in arguments {
+            let idx = self.ports.iter().position(|port| *port == argument);
+            let in_use = idx.is_some_and(|idx| {
+                self.inputs[idx].linked_to.is_some() || !self.outputs[idx].linked_to.is_empty()
+            });
+
+            let _disabled = ui.begin_disabled(in_use);
+            let mut exposed = idx.is_some();
+            if ui.checkbox(&argument, &mut exposed) {
+                match idx {
+                    Some(idx) => self.remove_port(idx),
+                    None => self.add_port(argument),
+                }
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    fn inputs(&self) -> Option<&[InputPin]> {
+        Some(&self.inputs)
+    }
+
+    fn outputs(&self) -> Option<&[OutputPin]> {
+        Some(&self.outputs)
+    }
+
+    fn inputs_mut(&mut self) -> Option<&mut [InputPin]> {
+        Some(&mut self.inputs)
+    }
+
+    fn outputs_mut(&mut self) -> Option<&mut [OutputPin]> {
+        Some(&mut self.outputs)
+    }
+
+    fn to_model_fragment(&self, app: &App) -> Option<ModelFragment> {
+        let ports =
+            self.ports
+                .iter()
+                .zip(&self.inputs)
+                .map(|(name, input)| Port {
+                    name: name.clone(),
+                    connects_to: input.linked_to.map(|output_pin_id| {
+                        let node_id = app.output_pins.get(&output_pin_id).expect(
+                            "The node must exist, otherwise this should have been unlinked",
+                        );
+
+                        app.get_node(*node_id)
+                            .expect("The node must exist, otherwise this should have been unlinked")
+                            .output_name(&output_pin_id)
+                    }),
+                })
+                .collect();
+
+        Some(ModelFragment::Submodel(odeir::models::Submodel {
+            name: self.name.clone(),
+            path: self.path.clone(),
+            ports,
+        }))
+    }
+
+    fn try_from_model_fragment(
+        node_id: NodeId,
+        frag: &ModelFragment,
+        app: &App,
+    ) -> Option<(Self, Option<PendingOperations>)> {
+        let ModelFragment::Submodel(submodel) = frag else {
+            return None;
+        };
+
+        let model = submodel
+            .load(app.model_dir())
+            .map_err(|err| err.to_string());
+        let mut node = Self::new(node_id, submodel.name.clone(), submodel.path.clone(), model);
+
+        for port in &submodel.ports {
+            node.add_port(port.name.clone());
+        }
+        // The app registers the pins of the nodes it's given
+        node.pin_changes.clear();
+
+        let pending_ops = PendingOperations {
+            node_id,
+            operations: submodel
+                .ports
+                .iter()
+                .zip(&node.inputs)
+                .filter_map(|(port, input)| {
+                    Some(PendingOperation::LinkWith {
+                        node_name: port.connects_to.clone()?,
+                        via_pin_id: input.id,
+                        sign: Sign::Positive,
+                    })
+                })
+                .collect(),
+        };
+
+        Some((node, Some(pending_ops)))
+    }
+}
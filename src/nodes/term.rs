@@ -1,8 +1,15 @@
 use imgui::{ImColor32, Ui};
 use imnodes::{InputPinId, NodeId};
+use odeir::models::{
+    parameter::{Bounds, Distribution},
+    ParameterInfo,
+};
 
 use crate::{
-    core::{widgets::input_num, App},
+    core::{
+        widgets::{input_num, input_unit},
+        App,
+    },
     exprtree::{ExpressionNode, Leaf, Sign},
     locale::Locale,
     pins::{OutputPin, Pin},
@@ -16,6 +23,9 @@ pub struct Term {
     pub id: NodeId,
     pub leaf: Leaf,
     pub initial_value: f64,
+    /// Physical unit of the value, empty when it has none
+    pub unit: String,
+    pub info: ParameterInfo,
     pub output: OutputPin,
 }
 
@@ -28,11 +38,75 @@ impl SimpleNodeBuilder for Term {
                 unary_op: Sign::Positive,
             },
             initial_value: 0.00,
+            unit: String::new(),
+            info: ParameterInfo::default(),
             output: Pin::new(node_id),
         }
     }
 }
 
+impl Term {
+    /// Collapsible description, provenance and uncertainty of the value.
+    fn draw_info(&mut self, ui: &Ui, locale: &Locale) -> bool {
+        let Some(_details) = ui.tree_node(locale.get("term-details")) else {
+            return false;
+        };
+        let _width = ui.push_item_width(150.0);
+        let mut changed = false;
+
+        ui.text(locale.get("term-description"));
+        changed |= ui
+            .input_text("##description", &mut self.info.description)
+            .build();
+
+        ui.text(locale.get("term-reference"));
+        changed |= ui
+            .input_text("##reference", &mut self.info.reference)
+            .hint("doi:10.")
+            .build();
+
+        let mut has_bounds = self.info.bounds.is_some();
+        if ui.checkbox(locale.get("term-bounds"), &mut has_bounds) {
+            self.info.bounds = has_bounds.then(|| Bounds {
+                min: 0.0,
+                max: 2.0 * self.initial_value.abs().max(0.5),
+            });
+            changed = true;
+        }
+        if let Some(bounds) = &mut self.info.bounds {
+            changed |= input_num(ui, "##bounds min", &mut bounds.min);
+            ui.same_line();
+            changed |= input_num(ui, "##bounds max", &mut bounds.max);
+        }
+
+        let names: Vec<&str> = std::iter::once(locale.get("term-prior-none"))
+            .chain(Distribution::VARIANTS.iter().map(Distribution::name))
+            .collect();
+        let mut selected = self.info.prior.map_or(0, |prior| {
+            Distribution::VARIANTS
+                .iter()
+                .position(|variant| variant.name() == prior.name())
+                .map_or(0, |idx| idx + 1)
+        });
+
+        ui.text(locale.get("term-prior"));
+        if ui.combo_simple_string("##prior", &mut selected, &names) {
+            self.info.prior = selected
+                .checked_sub(1)
+                .map(|idx| Distribution::VARIANTS[idx]);
+            changed = true;
+        }
+        if let Some(prior) = &mut self.info.prior {
+            let [first, second] = prior.params_mut();
+            changed |= input_num(ui, "##prior first", first);
+            ui.same_line();
+            changed |= input_num(ui, "##prior second", second);
+        }
+
+        changed
+    }
+}
+
 impl NodeImpl for Term {
     fn id(&self) -> NodeId {
         self.id
@@ -66,7 +140,15 @@ impl NodeImpl for Term {
     fn draw(&mut self, ui: &Ui, locale: &Locale) -> bool {
         ui.text(locale.get("term-initial-value"));
         ui.same_line();
-        input_num(ui, "##population initial value", &mut self.initial_value)
+        let value_changed = input_num(ui, "##population initial value", &mut self.initial_value);
+
+        ui.text(locale.get("term-unit"));
+        ui.same_line();
+        let unit_changed = input_unit(ui, "##population unit", &mut self.unit);
+
+        let info_changed = self.draw_info(ui, locale);
+
+        value_changed || unit_changed || info_changed
     }
 
     fn outputs(&self) -> Option<&[OutputPin]> {
@@ -82,6 +164,8 @@ impl NodeImpl for Term {
             odeir::Argument::Value {
                 name: self.name().to_owned(),
                 value: self.initial_value,
+                unit: (!self.unit.is_empty()).then(|| self.unit.clone()),
+                info: (!self.info.is_empty()).then(|| self.info.clone()),
             }
             .into(),
         )
@@ -92,7 +176,13 @@ impl NodeImpl for Term {
         frag: &ModelFragment,
         _app: &App,
     ) -> Option<(Self, Option<PendingOperations>)> {
-        let ModelFragment::Argument(odeir::Argument::Value { name, value }) = frag else {
+        let ModelFragment::Argument(odeir::Argument::Value {
+            name,
+            value,
+            unit,
+            info,
+        }) = frag
+        else {
             return None;
         };
 
@@ -103,6 +193,8 @@ impl NodeImpl for Term {
                 unary_op: Sign::Positive,
             },
             initial_value: *value,
+            unit: unit.clone().unwrap_or_default(),
+            info: info.clone().unwrap_or_default(),
             output: Pin::new(node_id),
         };
 
@@ -236,16 +236,17 @@ impl GA {
             }
 
             solutions.push(best.to_string());
-            println!("current best is {:?}", best);
+            log::debug!("current best is {:?}", best);
 
             i += 1;
         }
 
-        GA::to_disk(
+        if let Err(err) = GA::to_disk(
             Path::new(&String::from("./src/ode/result/ga_iterations.txt")),
             solutions,
-        )
-        .unwrap();
+        ) {
+            log::warn!("Could not save the GA iterations: {err}");
+        }
 
         Ok(best)
     }
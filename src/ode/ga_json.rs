@@ -1,7 +1,10 @@
 #![allow(dead_code)] // A lot of stuff is WIP here
 
-#[derive(Debug, Clone, Default)]
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct GAMetadata {
+    #[serde(default)]
     pub name: String,
     pub start_time: f64,
     pub delta_time: f64,
@@ -13,7 +16,7 @@ pub struct GAMetadata {
 }
 
 //initial condition
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct GAArgument {
     pub name: String,
     pub value: f64,
@@ -26,7 +29,7 @@ impl GAArgument {
 }
 
 //parameters to be adjusted
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct Bound {
     pub name: String,
     pub min: f64,
@@ -39,9 +42,11 @@ impl Bound {
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct ConfigData {
     pub metadata: GAMetadata,
+    #[serde(default)]
     pub arguments: Vec<GAArgument>, //manter o vetor ordenado
+    #[serde(default)]
     pub bounds: Vec<Bound>,
 }
@@ -1,7 +1,8 @@
-pub(crate) mod csvdata;
+pub mod csvdata;
 mod ga;
 pub mod ga_json;
 pub mod odesystem;
+pub mod stochastic;
 use crate::ode::odesystem::solve;
 use ga_json::GAArgument;
 use ode_solvers::DVector;
@@ -69,6 +70,11 @@ impl ParameterEstimation {
         );
 
         ode_system.set_context(all_args);
+        // Once, rather than for every individual
+        if let Err(err) = ode_system.compile() {
+            log::error!("Could not compile the system of equations: {err}");
+            return;
+        }
 
         match self.ga.optimize(|values: Vec<f64>| {
             //ode_system.update_context(args_selected_params.clone(), values);
@@ -124,10 +130,10 @@ impl ParameterEstimation {
             }
         }) {
             Ok(c) => {
-                println!("The best individual is {:?}", c);
+                log::info!("The best individual is {:?}", c);
                 self.best_solution = c.get_values();
             }
-            Err(e) => println!("An error ocurred during the optimization: {:?}", e),
+            Err(e) => log::error!("An error ocurred during the optimization: {:?}", e),
         }
     }
 }
@@ -1,34 +1,179 @@
-use expr_evaluator::expr::{ExprContext, Expression};
+use expr_evaluator::{
+    compiled::{Compiled, Slots},
+    expr::{ExprContext, ExprError, Expression},
+};
+use ode_solvers::dop_shared::{IntegrationError, Stats};
 use ode_solvers::*;
+use odeir::models::ode::OdeModel;
 //use meval::{Context,Error,Expr};
 use std::collections::BTreeMap;
 use std::{
     fs::File,
     io::{BufWriter, Write},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
 use super::ga_json::GAArgument;
-use crate::nodes::Term;
 
 pub type State = DVector<f64>;
 
+/// Why an [`OdeSystem`] could not be built or integrated.
+#[derive(Debug, thiserror::Error)]
+pub enum OdeError {
+    #[error("Could not parse the equation of `{population}`: {error}")]
+    Parse {
+        population: String,
+        error: ExprError,
+    },
+
+    #[error("Could not compile the equation of `{population}`: {error}")]
+    Compile {
+        population: String,
+        error: ExprError,
+    },
+
+    #[error("Could not define the function `{function}`: {error}")]
+    Function { function: String, error: ExprError },
+
+    #[error(transparent)]
+    Equation(#[from] EquationError),
+
+    #[error(transparent)]
+    Integration(#[from] IntegrationError),
+}
+
+/// An equation that could not be evaluated during the integration.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Could not evaluate the equation of `{population}` at t = {t}: {error}")]
+pub struct EquationError {
+    pub population: String,
+    pub t: f64,
+    pub error: ExprError,
+}
+
 #[derive(Debug, Clone)]
 pub struct OdeSystem {
     pub equations: BTreeMap<String, Expression>,
     pub context: ExprContext,
+    /// Past values of populations the equations refer to, which make it a
+    /// system of delay differential equations
+    pub delays: Vec<DelayedValue>,
+    /// First equation that failed to evaluate, which stops the integration
+    pub error: Option<EquationError>,
+    compiled: Option<CompiledSystem>,
+}
+
+/// The equations of an [`OdeSystem`] compiled against a buffer holding the
+/// state first, then every other variable.
+#[derive(Debug, Clone)]
+struct CompiledSystem {
+    /// In the same order as [`OdeSystem::equations`]
+    equations: Vec<Compiled>,
+    slots: Slots,
+    buffer: Vec<f64>,
+    stack: Vec<f64>,
+    /// Slot of each of [`OdeSystem::delays`]
+    delays: Vec<usize>,
+    /// Entries of the Jacobian that aren't always zero, as `(row, column,
+    /// entry)`, or `None` if an equation can't be differentiated
+    jacobian: Option<Vec<(usize, usize, Compiled)>>,
+}
+
+/// A variable of the context holding the value a population had `tau` units
+/// of time earlier.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DelayedValue {
+    pub name: String,
+    /// Index of the population in the state
+    pub population: usize,
+    pub tau: f64,
 }
 
 impl OdeSystem {
     pub fn set_context(&mut self, args: Vec<GAArgument>) {
         args.iter()
-            .for_each(|arg| self.context.set_var(arg.name.clone(), arg.value));
+            .for_each(|arg| self.set_var(&arg.name, arg.value));
     }
 
     pub fn update_context(&mut self, args: Vec<GAArgument>, values: Vec<f64>) {
         args.iter()
             .zip(values)
-            .for_each(|(arg, value)| self.context.set_var(arg.name.clone(), value));
+            .for_each(|(arg, value)| self.set_var(&arg.name, value));
+    }
+
+    /// Sets a variable of the context, along with its slot if the system was
+    /// compiled.
+    fn set_var(&mut self, name: &str, value: f64) {
+        if let Some(compiled) = &mut self.compiled
+            && let Some(slot) = compiled.slots.get(name)
+        {
+            compiled.buffer[slot] = value;
+        }
+        self.context.set_var(name.to_owned(), value);
+    }
+
+    /// Compiles the equations, so they're evaluated from a buffer of values
+    /// instead of looking every variable up in the context. Once compiled, the
+    /// context must only be changed through [`OdeSystem::set_context`] and
+    /// [`OdeSystem::update_context`].
+    pub fn compile(&mut self) -> Result<(), OdeError> {
+        let mut slots = Slots::new();
+        let names = self
+            .equations
+            .keys()
+            .chain(self.context.vars.keys())
+            .chain(self.delays.iter().map(|delay| &delay.name));
+        for name in names {
+            slots.insert(name);
+        }
+
+        let equations = self
+            .equations
+            .iter()
+            .map(|(population, equation)| {
+                equation
+                    .ast
+                    .as_deref()
+                    .ok_or(ExprError::UndefinedAST)
+                    .and_then(|ast| Compiled::new(ast, &slots, &self.context))
+                    .map_err(|error| OdeError::Compile {
+                        population: population.clone(),
+                        error,
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+
+        self.compiled = Some(CompiledSystem {
+            jacobian: self.compile_jacobian(&slots),
+            equations,
+            buffer: slots.buffer(&self.context),
+            stack: Vec::new(),
+            delays: self
+                .delays
+                .iter()
+                .filter_map(|delay| slots.get(&delay.name))
+                .collect(),
+            slots,
+        });
+
+        Ok(())
+    }
+
+    /// Differentiates every equation with respect to every population. The
+    /// solvers approximate the Jacobian instead when this fails.
+    fn compile_jacobian(&self, slots: &Slots) -> Option<Vec<(usize, usize, Compiled)>> {
+        let mut entries = Vec::new();
+        for (row, equation) in self.equations.values().enumerate() {
+            let ast = equation.ast.as_deref()?;
+            for (column, population) in self.equations.keys().enumerate() {
+                let entry = ast.derivative(population).ok()?;
+                if !entry.is_constant(0.0) {
+                    let entry = Compiled::new(&entry, slots, &self.context).ok()?;
+                    entries.push((row, column, entry));
+                }
+            }
+        }
+        Some(entries)
     }
 
     pub fn update_context_with_state(&mut self, y: &State) {
@@ -46,21 +191,107 @@ impl Default for OdeSystem {
         Self {
             equations: BTreeMap::new(),
             context: ExprContext::new(),
+            delays: Vec::new(),
+            error: None,
+            compiled: None,
         }
     }
 }
 
 impl ode_solvers::System<f64, State> for OdeSystem {
-    fn system(&mut self, _t: f64, y: &State, dydt: &mut State) {
-        self.update_context_with_state(y);
+    fn system(&mut self, t: f64, y: &State, dydt: &mut State) {
+        match &mut self.compiled {
+            Some(CompiledSystem {
+                equations,
+                buffer,
+                stack,
+                ..
+            }) => {
+                buffer[..y.len()].copy_from_slice(y.as_slice());
+
+                let populations = self.equations.keys();
+                for (i, (population, equation)) in populations.zip(equations).enumerate() {
+                    dydt[i] = equation
+                        .eval(buffer, stack)
+                        .unwrap_or_else(|error| failed(&mut self.error, population, t, error));
+                }
+            }
+            None => {
+                self.update_context_with_state(y);
 
-        for (i, equation) in self.equations.values_mut().enumerate() {
-            equation.set_context(self.context.clone());
+                for (i, (population, equation)) in self.equations.iter_mut().enumerate() {
+                    equation.set_context(self.context.clone());
+
+                    dydt[i] = equation
+                        .eval()
+                        .unwrap_or_else(|error| failed(&mut self.error, population, t, error));
+                }
+            }
+        }
+    }
+
+    fn solout(&mut self, _t: f64, _y: &State, _dydt: &State) -> bool {
+        self.error.is_some()
+    }
 
-            if let Ok(value) = equation.eval() {
-                dydt[i] = value;
+    fn jacobian(&mut self, _t: f64, y: &State, jac: &mut DMatrix<f64>) -> bool {
+        let Some(CompiledSystem {
+            jacobian: Some(entries),
+            buffer,
+            stack,
+            ..
+        }) = &mut self.compiled
+        else {
+            return false;
+        };
+        buffer[..y.len()].copy_from_slice(y.as_slice());
+
+        jac.fill(0.0);
+        for (row, column, entry) in entries.iter() {
+            // Errors are reported by `system`, evaluated at the same point
+            match entry.eval(buffer, stack) {
+                Ok(value) => jac[(*row, *column)] = value,
+                Err(_) => return false,
+            }
+        }
+        true
+    }
+}
+
+/// Keeps the first error of the integration, returning a derivative that
+/// can't be mistaken for a valid one.
+fn failed(first: &mut Option<EquationError>, population: &str, t: f64, error: ExprError) -> f64 {
+    first.get_or_insert_with(|| EquationError {
+        population: population.to_owned(),
+        t,
+        error,
+    });
+    f64::NAN
+}
+
+impl ode_solvers::DelaySystem<f64, State> for OdeSystem {
+    fn delays(&self) -> Vec<f64> {
+        self.delays.iter().map(|delay| delay.tau).collect()
+    }
+
+    fn system(&mut self, t: f64, y: &State, lagged: &[State], dydt: &mut State) {
+        match &mut self.compiled {
+            Some(compiled) => {
+                for ((delay, past_state), &slot) in
+                    self.delays.iter().zip(lagged).zip(&compiled.delays)
+                {
+                    compiled.buffer[slot] = past_state[delay.population];
+                }
+            }
+            None => {
+                for (delay, past_state) in self.delays.iter().zip(lagged) {
+                    self.context
+                        .set_var(delay.name.clone(), past_state[delay.population]);
+                }
             }
         }
+
+        ode_solvers::System::system(self, t, y, dydt);
     }
 }
 
@@ -75,10 +306,8 @@ pub fn solve(
 ) -> Vec<State> {
     ode_system.update_context(args, values);
 
-    let mut solver = Dop853::new(ode_system, t_ini, t_final, dt, y.clone(), 1.0e-8, 1.0e-8);
-
-    match solver.integrate() {
-        Ok(_stats) => solver.y_out().to_vec(),
+    match integrate(ode_system, Solver::Dop853, y, t_ini, t_final, dt) {
+        Ok((_times, states)) => states,
         Err(err) => {
             eprintln!("Error integrating system: {err}");
             vec![]
@@ -86,13 +315,107 @@ pub fn solve(
     }
 }
 
-pub fn create_ode_system(input: String, terms: impl IntoIterator<Item = Term>) -> OdeSystem {
+/// Numerical method used to integrate an [`OdeSystem`].
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    strum::Display,
+    strum::EnumString,
+    strum::VariantArray,
+)]
+#[strum(ascii_case_insensitive)]
+pub enum Solver {
+    /// Explicit Runge-Kutta method of order 8(5,3), with adaptive step size.
+    #[default]
+    Dop853,
+    /// Explicit Runge-Kutta method of order 5(4), with adaptive step size.
+    Dopri5,
+    /// Classic fixed step Runge-Kutta method of order 4, stepping by `dt`.
+    Rk4,
+    /// Linearly implicit Rosenbrock method of order 2(3), for stiff models.
+    Rosenbrock23,
+}
+
+/// Integrates the system with the given [`Solver`], returning the output
+/// sampled every `dt` from `t_ini` to `t_final`. Systems with delays are
+/// always integrated by the method of steps of [`Dde`], whichever solver is
+/// chosen. The equations are compiled beforehand, unless they already were,
+/// and the integration stops at the first one that can't be evaluated, which
+/// is returned as the error.
+pub fn integrate(
+    mut ode_system: OdeSystem,
+    solver: Solver,
+    y: &State,
+    t_ini: f64,
+    t_final: f64,
+    dt: f64,
+) -> Result<(Vec<f64>, Vec<State>), OdeError> {
+    const RTOL: f64 = 1.0e-8;
+    const ATOL: f64 = 1.0e-8;
+
+    if ode_system.compiled.is_none() {
+        ode_system.compile()?;
+    }
+
+    if !ode_system.delays.is_empty() {
+        let mut stepper = Dde::new(ode_system, t_ini, y.clone(), t_final, dt);
+        let stats = stepper.integrate();
+        return output(stepper.system(), stats, stepper.x_out(), stepper.y_out());
+    }
+
+    match solver {
+        Solver::Dop853 => {
+            let mut stepper = Dop853::new(ode_system, t_ini, t_final, dt, y.clone(), RTOL, ATOL);
+            let stats = stepper.integrate();
+            output(stepper.system(), stats, stepper.x_out(), stepper.y_out())
+        }
+        Solver::Dopri5 => {
+            let mut stepper = Dopri5::new(ode_system, t_ini, t_final, dt, y.clone(), RTOL, ATOL);
+            let stats = stepper.integrate();
+            output(stepper.system(), stats, stepper.x_out(), stepper.y_out())
+        }
+        Solver::Rosenbrock23 => {
+            let mut stepper =
+                Rosenbrock23::new(ode_system, t_ini, t_final, dt, y.clone(), RTOL, ATOL);
+            let stats = stepper.integrate();
+            output(stepper.system(), stats, stepper.x_out(), stepper.y_out())
+        }
+        Solver::Rk4 => {
+            let mut stepper = Rk4::new(ode_system, t_ini, y.clone(), t_final, dt);
+            let stats = stepper.integrate();
+            output(stepper.system(), stats, stepper.x_out(), stepper.y_out())
+        }
+    }
+}
+
+/// Output of a finished stepper. An equation that failed to evaluate takes
+/// precedence over the error of the stepper, which it likely caused.
+fn output(
+    ode_system: &OdeSystem,
+    stats: Result<Stats, IntegrationError>,
+    times: &[f64],
+    states: &[State],
+) -> Result<(Vec<f64>, Vec<State>), OdeError> {
+    if let Some(err) = &ode_system.error {
+        return Err(err.clone().into());
+    }
+    stats?;
+
+    Ok((times.to_vec(), states.to_vec()))
+}
+
+pub fn create_ode_system(
+    input: String,
+    values: impl IntoIterator<Item = (String, f64)>,
+) -> Result<OdeSystem, OdeError> {
     let mut ode_system = OdeSystem::default();
 
-    for term in terms.into_iter() {
-        ode_system
-            .context
-            .set_var(term.leaf.symbol.trim().to_string(), term.initial_value);
+    for (name, value) in values.into_iter() {
+        ode_system.context.set_var(name.trim().to_string(), value);
     }
 
     let lines = input.split("\n").collect::<Vec<_>>();
@@ -107,11 +430,101 @@ pub fn create_ode_system(input: String, terms: impl IntoIterator<Item = Term>) -
         if new_line.len() == 2 {
             let population = new_line[0].trim().to_string();
             let mut ode_rhs: Expression = Expression::new();
-            ode_rhs.parse_expr(new_line[1].trim().to_string()).unwrap();
+            ode_rhs
+                .parse_expr(new_line[1].trim().to_string())
+                .map_err(|error| OdeError::Parse {
+                    population: population.clone(),
+                    error,
+                })?;
             ode_system.equations.insert(population.clone(), ode_rhs);
         }
     }
-    ode_system
+    Ok(ode_system)
+}
+
+/// Builds an [`OdeSystem`] straight from a model, without going through the
+/// node editor. Every `Argument::Value` is registered in the context with its
+/// initial value, as are the functions of the model, and delays of populations
+/// without an equation are left out.
+pub fn ode_system_from_model(
+    model: &OdeModel,
+    extension_lookup_paths: &[&PathBuf],
+) -> Result<OdeSystem, OdeError> {
+    let equations =
+        odeir::transformations::ode::render_txt_with_equations(model, extension_lookup_paths);
+
+    let values = model.arguments.values().filter_map(|arg| match arg {
+        odeir::Argument::Value { name, value, .. } => Some((name.clone(), *value)),
+        _ => None,
+    });
+
+    let mut ode_system = create_ode_system(equations, values)?;
+    ode_system.define_functions(model)?;
+    ode_system.delays = model
+        .delays
+        .iter()
+        .filter_map(|delay| {
+            Some(DelayedValue {
+                name: delay.name.clone(),
+                population: ode_system
+                    .equations
+                    .keys()
+                    .position(|name| *name == delay.population)?,
+                tau: delay.tau,
+            })
+        })
+        .collect();
+
+    Ok(ode_system)
+}
+
+impl OdeSystem {
+    /// Initial state vector, ordered the same way as [`OdeSystem::equations`].
+    pub fn initial_state(&self) -> State {
+        State::from_iterator(
+            self.equations.len(),
+            self.equations
+                .keys()
+                .map(|name| self.context.vars.get(name).copied().unwrap_or_default()),
+        )
+    }
+
+    pub fn population_names(&self) -> impl Iterator<Item = &str> {
+        self.equations.keys().map(String::as_str)
+    }
+
+    /// Registers the functions of `model` in the context, so the equations
+    /// can call them. Must be done before [`OdeSystem::compile`].
+    pub fn define_functions(&mut self, model: &OdeModel) -> Result<(), OdeError> {
+        model
+            .define_functions(&mut self.context)
+            .map_err(|(function, error)| OdeError::Function { function, error })
+    }
+}
+
+/// Writes the simulation as CSV, with the same `t,<populations...>` header the
+/// generated Python code emits with `--csv`.
+pub fn write_csv<'a>(
+    mut writer: impl Write,
+    names: impl IntoIterator<Item = &'a str>,
+    times: &[f64],
+    states: &[State],
+) -> std::io::Result<()> {
+    write!(writer, "t")?;
+    for name in names {
+        write!(writer, ",{name}")?;
+    }
+    writeln!(writer)?;
+
+    for (time, state) in times.iter().zip(states) {
+        write!(writer, "{time}")?;
+        for val in state.iter() {
+            write!(writer, ",{val}")?;
+        }
+        writeln!(writer)?;
+    }
+
+    writer.flush()
 }
 
 pub fn save(times: &[f64], states: &[State], filename: &Path) {
@@ -0,0 +1,349 @@
+//! Stochastic simulation of a model split into reaction channels (see
+//! [`odeir::models::stochastic`]). Populations are whole numbers, changed by
+//! the channels firing at random, which matters when they're small.
+//!
+//! The rate of each channel is used as its propensity, so rates are read as
+//! events per unit of time. Populations never drop below zero.
+
+use expr_evaluator::expr::{ExprContext, ExprError, Expression, Node};
+use odeir::models::{ode::OdeModel, stochastic::Channel};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use rand_distr::{Distribution, Poisson};
+use rayon::prelude::*;
+
+/// Percentiles bounding the band of runs plotted around their mean.
+pub const BAND_PERCENTILES: (f64, f64) = (5.0, 95.0);
+
+/// Algorithm used to simulate each run.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    strum::Display,
+    strum::EnumString,
+    strum::VariantArray,
+)]
+#[strum(serialize_all = "kebab-case", ascii_case_insensitive)]
+pub enum StochasticMethod {
+    /// Gillespie's direct method, which is exact but fires one channel at a
+    /// time.
+    #[default]
+    Ssa,
+    /// Fires every channel a Poisson distributed number of times per leap,
+    /// which is much faster when populations are large.
+    TauLeaping,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StochasticSettings {
+    pub method: StochasticMethod,
+    /// How many runs are simulated
+    pub runs: usize,
+    /// Seed of the first run, the others using the following ones
+    pub seed: u64,
+    /// Length of each leap of [`StochasticMethod::TauLeaping`]
+    pub tau: f64,
+}
+
+impl Default for StochasticSettings {
+    fn default() -> Self {
+        Self {
+            method: StochasticMethod::default(),
+            runs: 50,
+            seed: 0,
+            tau: 0.01,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StochasticError {
+    #[error("The model has no terms that can be read as reactions")]
+    NoChannels,
+
+    #[error("The leap of tau-leaping must be a positive number")]
+    InvalidTau,
+
+    #[error("The time between samples must be a positive number")]
+    InvalidStep,
+
+    #[error("Could not evaluate the rate of `{0}`")]
+    Rate(String),
+
+    #[error("Could not define the function `{function}`: {error}")]
+    Function { function: String, error: ExprError },
+}
+
+/// Every run of a stochastic simulation, sampled at the same times.
+#[derive(Debug, Default, Clone)]
+pub struct Ensemble {
+    pub labels: Vec<String>,
+    pub time: Vec<f64>,
+    /// One line per population, for each run
+    pub runs: Vec<Vec<Vec<f64>>>,
+}
+
+impl Ensemble {
+    /// Applies `statistic` to the values of every run, for each population at
+    /// each time. Without runs there is nothing to summarize, so every value
+    /// is NaN.
+    fn across_runs(&self, statistic: impl Fn(&mut [f64]) -> f64) -> Vec<Vec<f64>> {
+        if self.runs.is_empty() {
+            return vec![vec![f64::NAN; self.time.len()]; self.labels.len()];
+        }
+
+        let mut values = vec![0.0; self.runs.len()];
+
+        (0..self.labels.len())
+            .map(|population| {
+                (0..self.time.len())
+                    .map(|sample| {
+                        for (value, run) in values.iter_mut().zip(&self.runs) {
+                            *value = run[population][sample];
+                        }
+                        statistic(&mut values)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Mean of the runs, one line per population.
+    pub fn mean(&self) -> Vec<Vec<f64>> {
+        self.across_runs(|values| values.iter().sum::<f64>() / values.len() as f64)
+    }
+
+    /// The `percentile`th percentile of the runs, between 0 and 100, one line
+    /// per population.
+    pub fn percentile(&self, percentile: f64) -> Vec<Vec<f64>> {
+        self.across_runs(|values| {
+            values.sort_by(f64::total_cmp);
+            let rank = (percentile / 100.0 * (values.len() - 1) as f64).round() as usize;
+            values[rank.min(values.len() - 1)]
+        })
+    }
+}
+
+/// The channels of a model, ready to be evaluated.
+struct StochasticSystem {
+    populations: Vec<String>,
+    context: ExprContext,
+    channels: Vec<(String, Box<Node>)>,
+    /// Index of each population a channel changes, and by how much
+    changes: Vec<Vec<(usize, i64)>>,
+}
+
+impl StochasticSystem {
+    fn new(model: &OdeModel) -> Result<Self, StochasticError> {
+        let channels: Vec<Channel> = model.channels();
+        if channels.is_empty() {
+            return Err(StochasticError::NoChannels);
+        }
+
+        let mut populations: Vec<String> = model
+            .equations
+            .iter()
+            .filter_map(|eq| eq.operates_on.clone())
+            .collect();
+        populations.sort();
+        populations.dedup();
+
+        let mut context = ExprContext::new();
+        model
+            .define_functions(&mut context)
+            .map_err(|(function, error)| StochasticError::Function { function, error })?;
+        for arg in model.arguments.values() {
+            if let odeir::Argument::Value { name, value, .. } = arg {
+                let value = if populations.contains(name) {
+                    value.round().max(0.0)
+                } else {
+                    *value
+                };
+                context.set_var(name.clone(), value);
+            }
+        }
+
+        let changes = channels
+            .iter()
+            .map(|channel| {
+                channel
+                    .changes
+                    .iter()
+                    .filter_map(|(population, change)| {
+                        let idx = populations.iter().position(|name| name == population)?;
+                        Some((idx, *change))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let channels = channels
+            .into_iter()
+            .map(|channel| {
+                let mut rate = Expression::new();
+                rate.parse_expr(channel.rate)
+                    .map_err(|_| StochasticError::Rate(channel.name.clone()))?;
+                let ast = rate
+                    .ast
+                    .ok_or_else(|| StochasticError::Rate(channel.name.clone()))?;
+                Ok((channel.name, ast))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            populations,
+            context,
+            channels,
+            changes,
+        })
+    }
+
+    fn initial_state(&self) -> Vec<f64> {
+        self.populations
+            .iter()
+            .map(|name| self.context.vars.get(name).copied().unwrap_or_default())
+            .collect()
+    }
+
+    /// Rate of every channel in `state`, written to `rates`.
+    fn rates(
+        &self,
+        context: &mut ExprContext,
+        state: &[f64],
+        rates: &mut [f64],
+    ) -> Result<(), StochasticError> {
+        for (name, value) in self.populations.iter().zip(state) {
+            if let Some(var) = context.vars.get_mut(name) {
+                *var = *value;
+            }
+        }
+
+        for (rate, (name, ast)) in rates.iter_mut().zip(&self.channels) {
+            *rate = ast
+                .eval(context)
+                .ok()
+                .filter(|rate| rate.is_finite())
+                .ok_or_else(|| StochasticError::Rate(name.clone()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Fires `channel` `times` times, backwards if its rate is negative.
+    fn fire(&self, state: &mut [f64], channel: usize, times: f64, rate: f64) {
+        let times = times.copysign(rate);
+        for &(population, change) in &self.changes[channel] {
+            state[population] = (state[population] + change as f64 * times).max(0.0);
+        }
+    }
+
+    /// One run, sampled at every time in `times`.
+    fn run(
+        &self,
+        method: StochasticMethod,
+        tau: f64,
+        times: &[f64],
+        seed: u64,
+    ) -> Result<Vec<Vec<f64>>, StochasticError> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut context = self.context.clone();
+        let mut state = self.initial_state();
+        let mut rates = vec![0.0; self.channels.len()];
+        let mut lines = vec![Vec::with_capacity(times.len()); state.len()];
+
+        let mut t = times.first().copied().unwrap_or_default();
+        let mut sample = 0;
+
+        while sample < times.len() {
+            self.rates(&mut context, &state, &mut rates)?;
+            let total: f64 = rates.iter().map(|rate| rate.abs()).sum();
+
+            let next_t = match method {
+                StochasticMethod::Ssa if total > 0.0 => t - (1.0 - rng.r#gen::<f64>()).ln() / total,
+                // Nothing can happen anymore
+                StochasticMethod::Ssa => f64::INFINITY,
+                StochasticMethod::TauLeaping => t + tau,
+            };
+
+            // The state holds until the next event
+            while sample < times.len() && times[sample] < next_t {
+                for (line, value) in lines.iter_mut().zip(&state) {
+                    line.push(*value);
+                }
+                sample += 1;
+            }
+
+            match method {
+                StochasticMethod::Ssa if total > 0.0 => {
+                    let mut threshold = rng.r#gen::<f64>() * total;
+                    let channel = rates
+                        .iter()
+                        .position(|rate| {
+                            threshold -= rate.abs();
+                            threshold < 0.0
+                        })
+                        .unwrap_or(rates.len() - 1);
+                    self.fire(&mut state, channel, 1.0, rates[channel]);
+                }
+                StochasticMethod::Ssa => {}
+                StochasticMethod::TauLeaping => {
+                    for (channel, &rate) in rates.iter().enumerate() {
+                        let Ok(poisson) = Poisson::new(rate.abs() * tau) else {
+                            continue;
+                        };
+                        self.fire(&mut state, channel, poisson.sample(&mut rng), rate);
+                    }
+                }
+            }
+
+            t = next_t;
+        }
+
+        Ok(lines)
+    }
+}
+
+/// Simulates `settings.runs` runs of the model in parallel, sampled every
+/// `dt` from `t_ini` to `t_final`.
+pub fn simulate(
+    model: &OdeModel,
+    settings: &StochasticSettings,
+    t_ini: f64,
+    t_final: f64,
+    dt: f64,
+) -> Result<Ensemble, StochasticError> {
+    if settings.method == StochasticMethod::TauLeaping
+        && (!settings.tau.is_finite() || settings.tau <= 0.0)
+    {
+        return Err(StochasticError::InvalidTau);
+    }
+    if !dt.is_finite() || dt <= 0.0 {
+        return Err(StochasticError::InvalidStep);
+    }
+
+    let system = StochasticSystem::new(model)?;
+
+    let samples = ((t_final - t_ini) / dt).round().max(0.0) as usize;
+    let time: Vec<f64> = (0..=samples).map(|idx| t_ini + idx as f64 * dt).collect();
+
+    let runs = (0..settings.runs.max(1) as u64)
+        .into_par_iter()
+        .map(|run| {
+            system.run(
+                settings.method,
+                settings.tau,
+                &time,
+                settings.seed.wrapping_add(run),
+            )
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(Ensemble {
+        labels: system.populations,
+        time,
+        runs,
+    })
+}
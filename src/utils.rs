@@ -24,6 +24,9 @@ pub(crate) use localized_error;
 pub enum ModelFragment {
     Argument(odeir::Argument),
     Equation(odeir::Equation),
+    Submodel(odeir::models::Submodel),
+    Reaction(odeir::models::Reaction),
+    Delay(odeir::models::Delay),
 }
 
 impl From<odeir::Argument> for ModelFragment {
@@ -38,6 +41,24 @@ impl From<odeir::Equation> for ModelFragment {
     }
 }
 
+impl From<odeir::models::Submodel> for ModelFragment {
+    fn from(value: odeir::models::Submodel) -> Self {
+        ModelFragment::Submodel(value)
+    }
+}
+
+impl From<odeir::models::Reaction> for ModelFragment {
+    fn from(value: odeir::models::Reaction) -> Self {
+        ModelFragment::Reaction(value)
+    }
+}
+
+impl From<odeir::models::Delay> for ModelFragment {
+    fn from(value: odeir::models::Delay) -> Self {
+        ModelFragment::Delay(value)
+    }
+}
+
 pub trait VecConversion<To> {
     fn convert(self) -> To;
 }